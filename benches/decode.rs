@@ -0,0 +1,91 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Decoding and validation benchmarks over synthetic modules of representative
+//! shapes (see `wafer::bench_support`).
+//!
+//! Run with `just bench`; `just bench-save <name>` and `just bench-compare
+//! <name>` record and compare against a named baseline.
+
+use std::hint::black_box;
+use std::io::Cursor;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use wafer::Module;
+use wafer::bench_support::{ModuleShape, generate};
+use wafer::core_compat::alloc::Global;
+use wafer::decode::{DecodeOptions, NoCustomSectionVisitor};
+use wafer::storage::BufferedStream;
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, shape) in ModuleShape::ALL {
+        let bytes = generate(&shape);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("buffer", name), &bytes, |b, bytes| {
+            b.iter(|| {
+                // Decodes from an in-memory Buffer.
+                Module::decode_bytes(
+                    black_box(&bytes[..]),
+                    &mut NoCustomSectionVisitor {},
+                    Global,
+                )
+                .unwrap()
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("std-io", name), &bytes, |b, bytes| {
+            b.iter(|| {
+                Module::decode(
+                    Cursor::new(black_box(&bytes[..])),
+                    &mut NoCustomSectionVisitor {},
+                    Global,
+                )
+                .unwrap()
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("buffered", name), &bytes, |b, bytes| {
+            b.iter(|| {
+                let stream = BufferedStream::new(Cursor::new(black_box(&bytes[..]))).unwrap();
+                Module::decode(stream, &mut NoCustomSectionVisitor {}, Global).unwrap()
+            });
+        });
+
+        let packed = DecodeOptions {
+            packed_expressions: true,
+            ..DecodeOptions::default()
+        };
+        group.bench_with_input(BenchmarkId::new("packed", name), &bytes, |b, bytes| {
+            b.iter(|| {
+                Module::decode_bytes_with_options(
+                    black_box(&bytes[..]),
+                    &mut NoCustomSectionVisitor {},
+                    Global,
+                    &packed,
+                )
+                .unwrap()
+            });
+        });
+    }
+    group.finish();
+}
+
+fn validate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("validate");
+    for (name, shape) in ModuleShape::ALL {
+        let bytes = generate(&shape);
+        let module = Module::decode_bytes(&bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &module, |b, module| {
+            b.iter(|| black_box(module).validate().unwrap());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode, validate);
+criterion_main!(benches);
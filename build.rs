@@ -4,11 +4,17 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT
 
+use std::env;
+
 use rustc_version::{Channel, Result, version_meta};
 
+// The core allocator APIs are only used if asked for with the `nightly`
+// feature, so that the public API does not otherwise vary with the toolchain.
+// Asking for them off of a nightly toolchain is not an error, to keep
+// `--all-features` builds working on stable.
 fn main() -> Result<()> {
-    let meta = version_meta()?;
-    if meta.channel == Channel::Nightly {
+    if env::var_os("CARGO_FEATURE_NIGHTLY").is_some() && version_meta()?.channel == Channel::Nightly
+    {
         println!("cargo:rustc-cfg=nightly");
     }
     Ok(())
@@ -0,0 +1,11 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|bytes: &[u8]| wafer::fuzz_entry(bytes));
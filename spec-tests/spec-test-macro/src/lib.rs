@@ -14,21 +14,50 @@ use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::quote;
 use syn::Ident;
 
-use wast2json::{AssertMalformedCommand, Command, ModuleCommand, ModuleType};
+use wast2json::{
+    Action, ActionCommand, AssertExhaustionCommand, AssertInvalidCommand, AssertMalformedCommand,
+    AssertReturnCommand, AssertTrapCommand, Command, ModuleCommand, ModuleType, Value,
+};
 
 // TODO: Burn this list down!
 const DISABLED_TESTS: &[&str] = &[
-    "align_109_assert_malformed_892",
-    "align_110_assert_malformed_911",
-    "align_111_assert_malformed_930",
-    "align_112_assert_malformed_949",
-    "align_113_assert_malformed_968",
     "binary_66_assert_malformed_494",
     "binary_67_assert_malformed_517",
     "custom_5_assert_malformed_77",
     "custom_6_assert_malformed_85",
 ];
 
+// The expected errors of `assert_invalid` commands that validation is able to
+// detect; tests expecting any other are generated but ignored. Notably absent
+// are the type errors within function bodies (e.g., "type mismatch"), as
+// operand types are not yet checked.
+const VALIDATED_ERRORS: &[&str] = &[
+    "Alignment",
+    "AlignmentMustNotBeLargerThanNatural",
+    "DuplicateExportName",
+    "InvalidResultArity",
+    "MemorySizeMustBeAtMost65536Pages",
+    "MultipleMemories",
+    "SizeMinimumMustNotBeGreaterThanMaximum",
+    "StartFunction",
+    "UndeclaredFunctionReference",
+    "UnknownDataSegment",
+    "UnknownDataSegment1",
+    "UnknownElemSegment0",
+    "UnknownElemSegment4",
+    "UnknownFunction",
+    "UnknownFunction7",
+    "UnknownGlobal",
+    "UnknownGlobal0",
+    "UnknownGlobal1",
+    "UnknownMemory",
+    "UnknownMemory0",
+    "UnknownMemory1",
+    "UnknownTable",
+    "UnknownTable0",
+    "UnknownType",
+];
+
 fn load_spec_tests() -> Vec<Command> {
     let out_dir = env::var("OUT_DIR").unwrap();
     let spec_tests_file = PathBuf::from(out_dir).join("spec-tests.json");
@@ -92,6 +121,15 @@ fn generate_tests() -> Vec<TokenStream2> {
                 let test_case = assert_malformed_test_case(&name, malformed, is_disabled);
                 all_test_functions.push(test_case);
             }
+            Command::AssertInvalid(invalid) => {
+                generate_module_test_case!();
+
+                let name = test_name(&invalid.filename);
+                let name = format!("{name}_assert_invalid_{}", invalid.line);
+                let is_disabled = disabled_tests.remove(&name);
+                let test_case = assert_invalid_test_case(&name, invalid, is_disabled);
+                all_test_functions.push(test_case);
+            }
             _ => {
                 trailing_commands.push(command);
             }
@@ -145,10 +183,50 @@ fn assert_malformed_test_case(
     }
 }
 
+fn assert_invalid_test_case(
+    name: &str,
+    invalid: &AssertInvalidCommand,
+    is_disabled: bool,
+) -> TokenStream2 {
+    if invalid.module_type != ModuleType::Binary {
+        return quote! {};
+    }
+
+    // Convert relative path to absolute path
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let wasm_file = PathBuf::from(out_dir).join(&invalid.filename);
+    let wasm_file = wasm_file.to_string_lossy();
+
+    let name = Ident::new(name, Span::call_site());
+
+    let wasm_file = wasm_file.as_ref();
+    let variant = format!("{:?}", invalid.text);
+    let ignore_attr = if is_disabled {
+        quote! { #[ignore] }
+    } else if !VALIDATED_ERRORS.contains(&variant.as_str()) {
+        quote! { #[ignore = "error not yet detected by validation"] }
+    } else {
+        quote! {}
+    };
+    let error_variant = {
+        let variant = format!("wast2json::Error::{variant}");
+        let tokens: TokenStream2 = variant.parse().expect("Failed to parse error variant");
+        tokens
+    };
+
+    quote! {
+        #[test]
+        #ignore_attr
+        fn #name() {
+            assert_invalid(#wasm_file, &#error_variant);
+        }
+    }
+}
+
 fn module_test_case(
     name: &str,
     module: &ModuleCommand,
-    _commands: &[&Command],
+    commands: &[&Command],
     is_disabled: bool,
 ) -> TokenStream2 {
     // Convert relative path to absolute path
@@ -164,13 +242,41 @@ fn module_test_case(
         quote! {}
     };
 
-    // For now, just load the module - later we can process the commands vector
-    // to generate additional test logic for assert_return, assert_trap, etc.
+    // The actions on the module (those naming no module, or this one) are
+    // checked against the signatures of its exports. They are passed along
+    // in their JSON encoding.
+    let actions: Vec<(&Action, &[Value])> = commands
+        .iter()
+        .filter_map(|command| match command {
+            Command::Action(ActionCommand {
+                action, expected, ..
+            })
+            | Command::AssertReturn(AssertReturnCommand {
+                action, expected, ..
+            })
+            | Command::AssertTrap(AssertTrapCommand {
+                action, expected, ..
+            })
+            | Command::AssertExhaustion(AssertExhaustionCommand {
+                action, expected, ..
+            }) => Some((action, expected.as_slice())),
+            _ => None,
+        })
+        .filter(|(action, _)| {
+            let target = match action {
+                Action::Invoke(invoke) => &invoke.module,
+                Action::Get(get) => &get.module,
+            };
+            target.is_none() || *target == module.name
+        })
+        .collect();
+    let actions = serde_json::to_string(&actions).expect("Failed to serialize actions");
+
     quote! {
         #ignore_attr
         #[test]
         fn #name() {
-            check_module(#wasm_file_str);
+            check_module(#wasm_file_str, #actions);
         }
     }
 }
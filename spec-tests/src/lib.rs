@@ -9,17 +9,31 @@ use std::fs;
 use spec_test_macro::wasm_spec_tests;
 use wafer::Module;
 use wafer::core_compat::alloc;
+use wafer::decode::test::assert_no_panic_on_prefixes;
 use wafer::decode::{self, NoCustomSectionVisitor};
 use wafer::storage::MemoryEof;
+use wafer::types::{ExternType, SectionId, ValType};
 use wafer::validate;
+use wast2json::{Action, ActionError, TypedValue, Value, ValueType};
 
+// Checks that a module decodes and validates, and that the given actions on
+// it (with their expected results), in their JSON encoding, agree with the
+// signatures of its exports.
 #[allow(unused)]
-fn check_module(wasm: &str) {
+fn check_module(wasm: &str, actions: &str) {
     let bytes = fs::read(wasm).unwrap();
+    assert_no_panic_on_prefixes(&bytes);
     let module =
         Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, alloc::Global).unwrap();
 
     module.validate().unwrap();
+
+    let actions: Vec<(Action, Vec<Value>)> = serde_json::from_str(actions).unwrap();
+    for (action, expected) in &actions {
+        if let Err(err) = check_action(&module, action, expected) {
+            panic!("{err}");
+        }
+    }
 }
 
 #[allow(unused)]
@@ -46,6 +60,7 @@ fn assert_malformed(wasm: &str, expected: &wast2json::Error) {
 
         // Very much best-effort.
         match expected {
+            DuplicateExportName => error_matches!(decode::Error::DuplicateExportName(_)),
             EndOpcodeExpected => error_matches!(
                 decode::Error::Storage(MemoryEof {})
                     | decode::Error::InvalidFunctionLength {
@@ -63,7 +78,9 @@ fn assert_malformed(wasm: &str, expected: &wast2json::Error) {
                 error_matches!(decode::Error::InvalidLeb128 | decode::Error::InvalidToken(_));
             }
             LengthOutOfBounds => {
-                error_matches!(decode::Error::Storage(MemoryEof {}));
+                error_matches!(
+                    decode::Error::Storage(MemoryEof {}) | decode::Error::LengthOutOfBounds(_)
+                );
             }
             MagicHeaderNotDetected => error_matches!(decode::Error::InvalidMagic(_)),
             MalformedUtf8Encoding => error_is!(decode::Error::InvalidUtf8),
@@ -89,7 +106,9 @@ fn assert_malformed(wasm: &str, expected: &wast2json::Error) {
             UnexpectedEnd | UnexpectedEndOfSectionOrFunction => {
                 error_is!(decode::Error::Storage(MemoryEof {}));
             }
-            UnknownBinaryVersion => error_matches!(decode::Error::UnknownVersion(_)),
+            UnknownBinaryVersion => error_matches!(
+                decode::Error::UnknownVersion(_) | decode::Error::UnexpectedComponent(_)
+            ),
             _ => todo!(
                 "Handle wast2json::Error::{:?} -> wafer::decode::Error mapping",
                 expected
@@ -106,32 +125,79 @@ fn assert_malformed(wasm: &str, expected: &wast2json::Error) {
         panic!("Success!? Expected decoding or validation error: {expected:?}")
     };
 
+    check_validation_error(error, expected);
+}
+
+#[allow(unused)]
+fn assert_invalid(wasm: &str, expected: &wast2json::Error) {
+    let bytes = fs::read(wasm).unwrap();
+    assert_no_panic_on_prefixes(&bytes);
+
+    // Invalid modules are nonetheless well-formed.
+    let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, alloc::Global)
+        .unwrap_or_else(|error| panic!("Expected validation error {expected:?}: {error:?}"));
+    let Err(error) = module.validate() else {
+        panic!("Success!? Expected validation error: {expected:?}")
+    };
+    check_validation_error(error, expected);
+}
+
+fn check_validation_error(error: validate::Error, expected: &wast2json::Error) {
+    use wast2json::Error::*;
+
     macro_rules! error_matches {
         ($pattern:pat) => {
             assert!(matches!(error, $pattern), "Unexpected error: {error:?}")
         };
     }
 
-    macro_rules! error_is {
-        ($value:expr) => {
-            assert_eq!(error, $value, "Unexpected error: {error:?}")
+    macro_rules! unknown_index {
+        ($id:ident) => {
+            error_matches!(validate::Error::IndexOutOfBounds {
+                id: SectionId::$id,
+                ..
+            })
         };
     }
 
     // Also very much best-effort.
     match expected {
+        Alignment | AlignmentMustNotBeLargerThanNatural | MalformedMemopFlags => {
+            error_matches!(validate::Error::InvalidAlignment {
+                opcode: _,
+                align: _
+            });
+        }
         DataCountAndDataSectionHaveInconsistentLengths => {
             error_matches!(validate::Error::DataCountMismatch {
                 expected: _,
                 actual: _,
             });
         }
+        DuplicateExportName => error_matches!(validate::Error::DuplicateExportName { .. }),
         FunctionAndCodeSectionHaveInconsistentLengths => {
             error_matches!(validate::Error::FunctionAndCodeSectionMismatch {
                 funcsec_size: _,
                 codesec_size: _
             });
         }
+        InvalidResultArity => error_matches!(validate::Error::InvalidResultArity(_)),
+        MemorySizeMustBeAtMost65536Pages => error_matches!(validate::Error::InvalidMemType(_)),
+        MultipleMemories => error_matches!(validate::Error::MultipleMemories),
+        SizeMinimumMustNotBeGreaterThanMaximum => error_matches!(
+            validate::Error::InvalidMemType(_) | validate::Error::InvalidTableLimits(_)
+        ),
+        StartFunction => error_matches!(validate::Error::InvalidStartFunction(_)),
+        UndeclaredFunctionReference => {
+            error_matches!(validate::Error::UndeclaredFunctionReference(_));
+        }
+        UnknownDataSegment | UnknownDataSegment1 => unknown_index!(Data),
+        UnknownElemSegment0 | UnknownElemSegment4 => unknown_index!(Element),
+        UnknownFunction | UnknownFunction7 => unknown_index!(Function),
+        UnknownGlobal | UnknownGlobal0 | UnknownGlobal1 => unknown_index!(Global),
+        UnknownMemory | UnknownMemory0 | UnknownMemory1 => unknown_index!(Memory),
+        UnknownTable | UnknownTable0 => unknown_index!(Table),
+        UnknownType => unknown_index!(Type),
         _ => todo!(
             "Handle wast2json::Error::{:?} -> wafer::validate::Error mapping",
             expected
@@ -139,4 +205,55 @@ fn assert_malformed(wasm: &str, expected: &wast2json::Error) {
     }
 }
 
+// Checks an action and its expected results against the signature of the
+// export it targets, returning the parsed arguments of an invocation.
+fn check_action(
+    module: &Module<alloc::Global>,
+    action: &Action,
+    expected: &[Value],
+) -> Result<Vec<TypedValue>, String> {
+    let field = match action {
+        Action::Invoke(invoke) => &invoke.field,
+        Action::Get(get) => &get.field,
+    };
+    let Some((_, ty)) = module.export_types().find(|(name, _)| name == field) else {
+        return Err(format!("no export named {field:?}"));
+    };
+    let value_types = |types: &[ValType]| {
+        types
+            .iter()
+            .map(|&ty| value_type(ty).ok_or_else(|| format!("{field:?}: {ty:?} is unsupported")))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let (params, results) = match (action, ty) {
+        (Action::Invoke(_), ExternType::Func(ty)) => {
+            (value_types(&ty.parameters)?, value_types(&ty.results)?)
+        }
+        (Action::Get(_), ExternType::Global(ty)) => (vec![], value_types(&[ty.value])?),
+        (_, ty) => return Err(format!("{field:?} is not of the expected kind: {ty:?}")),
+    };
+
+    let context = |err: ActionError| format!("{field:?}: {err}");
+    wast2json::check_expected(expected, &results).map_err(context)?;
+    match action {
+        Action::Invoke(invoke) => invoke.typed_args(&params).map_err(context),
+        Action::Get(_) => Ok(vec![]),
+    }
+}
+
+// The wast2json type of a value type, if any: v128 values are not yet
+// supported.
+fn value_type(ty: ValType) -> Option<ValueType> {
+    let ty = match ty {
+        ValType::I32 => ValueType::I32,
+        ValType::I64 => ValueType::I64,
+        ValType::F32 => ValueType::F32,
+        ValType::F64 => ValueType::F64,
+        ValType::FuncRef => ValueType::Funcref,
+        ValType::ExternRef => ValueType::Externref,
+        ValType::Vec => return None,
+    };
+    Some(ty)
+}
+
 wasm_spec_tests!();
@@ -6,6 +6,8 @@
 
 //! Common types for WebAssembly specification test format (JSON output from wast2json)
 
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 /// Top-level structure of a wast2json output file
@@ -178,6 +180,138 @@ pub enum ValueType {
     Funcref,
 }
 
+/// A parsed WebAssembly value, with floats given by their bit patterns and
+/// references by their host-assigned integers (or None if null).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypedValue {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    Funcref(Option<u32>),
+    Externref(Option<u32>),
+}
+
+impl TypedValue {
+    pub fn value_type(self) -> ValueType {
+        match self {
+            Self::I32(_) => ValueType::I32,
+            Self::I64(_) => ValueType::I64,
+            Self::F32(_) => ValueType::F32,
+            Self::F64(_) => ValueType::F64,
+            Self::Funcref(_) => ValueType::Funcref,
+            Self::Externref(_) => ValueType::Externref,
+        }
+    }
+}
+
+impl Value {
+    /// Parses the value, as given in its wast2json encoding: integers and
+    /// float bit patterns in unsigned decimal, and references as "null" or
+    /// in decimal. Returns None if the value is missing or malformed, or is a
+    /// NaN pattern (e.g., "nan:canonical"), which only describes results.
+    pub fn parse(&self) -> Option<TypedValue> {
+        let value = self.value.as_deref()?;
+        let reference = || match value {
+            "null" => Some(None),
+            _ => value.parse().ok().map(Some),
+        };
+        Some(match self.value_type {
+            ValueType::I32 => TypedValue::I32(value.parse::<u32>().ok()?.cast_signed()),
+            ValueType::I64 => TypedValue::I64(value.parse::<u64>().ok()?.cast_signed()),
+            ValueType::F32 => TypedValue::F32(value.parse().ok()?),
+            ValueType::F64 => TypedValue::F64(value.parse().ok()?),
+            ValueType::Funcref => TypedValue::Funcref(reference()?),
+            ValueType::Externref => TypedValue::Externref(reference()?),
+        })
+    }
+}
+
+impl InvokeAction {
+    /// Parses the arguments of the invocation, checking them against the
+    /// parameter types of the function invoked.
+    pub fn typed_args(&self, params: &[ValueType]) -> Result<Vec<TypedValue>, ActionError> {
+        check_types(&self.args, params)?;
+        self.args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                arg.parse().ok_or_else(|| ActionError::InvalidValue {
+                    index,
+                    value: arg.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Checks the expected values of an action's results against the result
+/// types of the function invoked (or the type of the global gotten).
+pub fn check_expected(expected: &[Value], results: &[ValueType]) -> Result<(), ActionError> {
+    check_types(expected, results)
+}
+
+fn check_types(values: &[Value], types: &[ValueType]) -> Result<(), ActionError> {
+    if values.len() != types.len() {
+        return Err(ActionError::ArityMismatch {
+            expected: types.len(),
+            actual: values.len(),
+        });
+    }
+    for (index, (value, &ty)) in values.iter().zip(types).enumerate() {
+        if value.value_type != ty {
+            return Err(ActionError::TypeMismatch {
+                index,
+                expected: ty,
+                actual: value.value_type,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// A disagreement between the values of an action and the signature of its
+/// target.
+#[derive(Debug, Clone)]
+pub enum ActionError {
+    /// The target has a different number of parameters or results.
+    ArityMismatch { expected: usize, actual: usize },
+    /// A value is of a different type than the corresponding parameter or
+    /// result.
+    TypeMismatch {
+        index: usize,
+        expected: ValueType,
+        actual: ValueType,
+    },
+    /// A value could not be parsed.
+    InvalidValue { index: usize, value: Value },
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ArityMismatch { expected, actual } => {
+                write!(f, "expected {expected} values, but the action has {actual}")
+            }
+            Self::TypeMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "value #{index} is of type {actual:?}, but the signature has {expected:?}"
+            ),
+            Self::InvalidValue { index, value } => write!(
+                f,
+                "value #{index} is not a valid {:?}: {:?}",
+                value.value_type, value.value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
 /// Module representation type
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -373,3 +507,58 @@ pub enum Error {
     #[serde(rename = "zero byte expected")]
     ZeroByteExpected,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value_type: ValueType, value: &str) -> Value {
+        Value {
+            value_type,
+            value: Some(value.to_string()),
+        }
+    }
+
+    #[test]
+    fn typed_args() {
+        let action = InvokeAction {
+            field: "f".to_string(),
+            module: None,
+            args: vec![
+                value(ValueType::I32, "4294967295"),
+                value(ValueType::F32, "1065353216"),
+                value(ValueType::Externref, "null"),
+            ],
+        };
+        assert_eq!(
+            action
+                .typed_args(&[ValueType::I32, ValueType::F32, ValueType::Externref])
+                .unwrap(),
+            [
+                TypedValue::I32(-1),
+                TypedValue::F32(1.0f32.to_bits()),
+                TypedValue::Externref(None)
+            ]
+        );
+        assert!(matches!(
+            action.typed_args(&[ValueType::I32]),
+            Err(ActionError::ArityMismatch {
+                expected: 1,
+                actual: 3
+            })
+        ));
+        assert!(matches!(
+            action.typed_args(&[ValueType::I32, ValueType::F64, ValueType::Externref]),
+            Err(ActionError::TypeMismatch {
+                index: 1,
+                expected: ValueType::F64,
+                actual: ValueType::F32
+            })
+        ));
+
+        assert!(value(ValueType::F32, "nan:canonical").parse().is_none());
+        assert!(
+            check_expected(&[value(ValueType::F32, "nan:canonical")], &[ValueType::F32]).is_ok()
+        );
+    }
+}
@@ -0,0 +1,635 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Static analyses of decoded modules.
+
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::core_compat::vec::Vec;
+use crate::types::{
+    DataIdx, DataMode, ElemIdx, ElementInit, ElementMode, ExportDescriptor, ExprVisitor, FuncIdx,
+    GlobalIdx, Import, MemArg, MemIdx, Opcode, Operands, Operator, TableIdx, TypeIdx,
+};
+use crate::{Allocator, Module};
+
+/// The entities of a module reachable from its exports and start function (see
+/// [`reachability`]).
+pub struct ReachabilitySet<A: Allocator> {
+    functions: IndexSet<A>,
+    tables: IndexSet<A>,
+    memories: IndexSet<A>,
+    globals: IndexSet<A>,
+    elems: IndexSet<A>,
+    datas: IndexSet<A>,
+}
+
+impl<A: Allocator> ReachabilitySet<A> {
+    /// Whether the function, imported or defined, is reachable.
+    pub fn contains_function(&self, funcidx: FuncIdx) -> bool {
+        self.functions.contains(*funcidx)
+    }
+
+    /// Whether the table, imported or defined, is reachable.
+    pub fn contains_table(&self, tableidx: TableIdx) -> bool {
+        self.tables.contains(*tableidx)
+    }
+
+    /// Whether the memory, imported or defined, is reachable.
+    pub fn contains_memory(&self, memidx: MemIdx) -> bool {
+        self.memories.contains(*memidx)
+    }
+
+    /// Whether the global, imported or defined, is reachable.
+    pub fn contains_global(&self, globalidx: GlobalIdx) -> bool {
+        self.globals.contains(*globalidx)
+    }
+
+    /// Whether the element segment is reachable.
+    pub fn contains_elem(&self, elemidx: ElemIdx) -> bool {
+        self.elems.contains(*elemidx)
+    }
+
+    /// Whether the data segment is reachable.
+    pub fn contains_data(&self, dataidx: DataIdx) -> bool {
+        self.datas.contains(*dataidx)
+    }
+
+    /// Iterates over the reachable functions in index order.
+    pub fn functions(&self) -> impl Iterator<Item = FuncIdx> + '_ {
+        self.functions.iter().map(FuncIdx::new)
+    }
+
+    /// Iterates over the reachable tables in index order.
+    pub fn tables(&self) -> impl Iterator<Item = TableIdx> + '_ {
+        self.tables.iter().map(TableIdx::new)
+    }
+
+    /// Iterates over the reachable memories in index order.
+    pub fn memories(&self) -> impl Iterator<Item = MemIdx> + '_ {
+        self.memories.iter().map(MemIdx::new)
+    }
+
+    /// Iterates over the reachable globals in index order.
+    pub fn globals(&self) -> impl Iterator<Item = GlobalIdx> + '_ {
+        self.globals.iter().map(GlobalIdx::new)
+    }
+
+    /// Iterates over the reachable element segments in index order.
+    pub fn elems(&self) -> impl Iterator<Item = ElemIdx> + '_ {
+        self.elems.iter().map(ElemIdx::new)
+    }
+
+    /// Iterates over the reachable data segments in index order.
+    pub fn datas(&self) -> impl Iterator<Item = DataIdx> + '_ {
+        self.datas.iter().map(DataIdx::new)
+    }
+}
+
+/// Computes the entities of a module transitively reachable from its exports
+/// and start function, through the instructions of function bodies and of
+/// global, element, and data initializers.
+///
+/// Active element and data segments are applied at instantiation, with
+/// observable effects (if only by trapping), and so are treated as reachable
+/// along with their tables and memories. A declarative element segment is
+/// reachable once any function it declares is referenced by a reachable
+/// `ref.func`, which validation requires to be declared. Everything else is a
+/// candidate for removal by size-optimization tooling.
+///
+/// The module is expected to have been validated; out-of-range indices are
+/// ignored. The set is allocated with the allocator of the module's code
+/// section.
+pub fn reachability<A: Allocator>(
+    module: &Module<A>,
+) -> Result<ReachabilitySet<A>, TryReserveError> {
+    let imports = module.imports_by_kind();
+    let alloc = module.codesec.allocator();
+    let mut walker = Walker {
+        set: ReachabilitySet {
+            functions: IndexSet::new(imports.functions.len() + module.funcsec.len(), alloc)?,
+            tables: IndexSet::new(imports.tables.len() + module.tablesec.len(), alloc)?,
+            memories: IndexSet::new(imports.memories.len() + module.memsec.len(), alloc)?,
+            globals: IndexSet::new(imports.globals.len() + module.globalsec.len(), alloc)?,
+            elems: IndexSet::new(module.elemsec.len(), alloc)?,
+            datas: IndexSet::new(module.datasec.len(), alloc)?,
+        },
+        pending: Vec::new_in(alloc.clone()),
+        referenced: IndexSet::new(imports.functions.len() + module.funcsec.len(), alloc)?,
+    };
+
+    for export in module.exportsec.iter() {
+        match export.descriptor {
+            ExportDescriptor::Function(funcidx) => walker.function(*funcidx)?,
+            ExportDescriptor::Table(tableidx) => walker.table(*tableidx),
+            ExportDescriptor::Memory(memidx) => walker.memory(*memidx),
+            ExportDescriptor::Global(globalidx) => walker.global(*globalidx)?,
+        }
+    }
+    if let Some(startsec) = &module.startsec {
+        walker.function(***startsec)?;
+    }
+    for (idx, segment) in module.elemsec.iter().enumerate() {
+        if matches!(segment.mode, ElementMode::Active(_)) {
+            walker.elem(idx as u32)?;
+        }
+    }
+    for (idx, segment) in module.datasec.iter().enumerate() {
+        if matches!(segment.mode, DataMode::Active(_)) {
+            walker.data(idx as u32)?;
+        }
+    }
+
+    loop {
+        walker.drain(module)?;
+
+        // Keep the declarative segments that declare referenced functions,
+        // walking on from their own references if any are newly reached.
+        for (idx, segment) in module.elemsec.iter().enumerate() {
+            if !matches!(segment.mode, ElementMode::Declarative) {
+                continue;
+            }
+            let declared = |funcidx: u32| walker.referenced.contains(funcidx);
+            let declares_referenced = match &segment.init {
+                ElementInit::FunctionIndices(funcs) => funcs.iter().any(|idx| declared(**idx)),
+                ElementInit::Expressions(exprs) => exprs.iter().any(|expr| {
+                    expr.instructions().any(|instr| {
+                        matches!(
+                            (instr.op, instr.operands),
+                            (Operator::Basic(Opcode::RefFunc), Operands::Idx(idx)) if declared(idx)
+                        )
+                    })
+                }),
+            };
+            if declares_referenced {
+                walker.elem(idx as u32)?;
+            }
+        }
+        if walker.pending.is_empty() {
+            return Ok(walker.set);
+        }
+    }
+}
+
+// A reachable entity whose own references are yet to be walked. (Tables and
+// memories reference nothing.)
+#[derive(Clone, Copy)]
+enum Item {
+    Function(u32),
+    Global(u32),
+    Elem(u32),
+    Data(u32),
+}
+
+struct Walker<A: Allocator> {
+    set: ReachabilitySet<A>,
+    pending: Vec<Item, A>,
+    // The functions referenced by ref.func.
+    referenced: IndexSet<A>,
+}
+
+impl<A: Allocator> Walker<A> {
+    // Walks the references of the pending entities, and so on transitively.
+    fn drain(&mut self, module: &Module<A>) -> Result<(), TryReserveError> {
+        let imports = module.imports_by_kind();
+        while let Some(item) = self.pending.pop() {
+            match item {
+                Item::Function(idx) => {
+                    let body = (idx as usize)
+                        .checked_sub(imports.functions.len())
+                        .and_then(|idx| module.codesec.get(idx));
+                    if let Some(function) = body {
+                        function.code.visit(self)?;
+                    }
+                }
+                Item::Global(idx) => {
+                    let global = (idx as usize)
+                        .checked_sub(imports.globals.len())
+                        .and_then(|idx| module.globalsec.get(idx));
+                    if let Some(global) = global {
+                        global.init.visit(self)?;
+                    }
+                }
+                Item::Elem(idx) => {
+                    let Some(segment) = module.elemsec.get(idx as usize) else {
+                        continue;
+                    };
+                    match &segment.init {
+                        ElementInit::FunctionIndices(funcs) => {
+                            for funcidx in funcs {
+                                self.function(**funcidx)?;
+                            }
+                        }
+                        ElementInit::Expressions(exprs) => {
+                            for expr in exprs {
+                                expr.visit(self)?;
+                            }
+                        }
+                    }
+                    if let ElementMode::Active(active) = &segment.mode {
+                        self.table(*active.table);
+                        active.offset.visit(self)?;
+                    }
+                }
+                Item::Data(idx) => {
+                    let Some(segment) = module.datasec.get(idx as usize) else {
+                        continue;
+                    };
+                    if let DataMode::Active(active) = &segment.mode {
+                        self.memory(*active.memory);
+                        active.offset.visit(self)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, item: Item) -> Result<(), TryReserveError> {
+        self.pending.try_reserve(1)?;
+        self.pending.push(item);
+        Ok(())
+    }
+
+    fn function(&mut self, idx: u32) -> Result<(), TryReserveError> {
+        if self.set.functions.insert(idx) {
+            self.push(Item::Function(idx))?;
+        }
+        Ok(())
+    }
+
+    fn global(&mut self, idx: u32) -> Result<(), TryReserveError> {
+        if self.set.globals.insert(idx) {
+            self.push(Item::Global(idx))?;
+        }
+        Ok(())
+    }
+
+    fn elem(&mut self, idx: u32) -> Result<(), TryReserveError> {
+        if self.set.elems.insert(idx) {
+            self.push(Item::Elem(idx))?;
+        }
+        Ok(())
+    }
+
+    fn data(&mut self, idx: u32) -> Result<(), TryReserveError> {
+        if self.set.datas.insert(idx) {
+            self.push(Item::Data(idx))?;
+        }
+        Ok(())
+    }
+
+    fn table(&mut self, idx: u32) {
+        self.set.tables.insert(idx);
+    }
+
+    fn memory(&mut self, idx: u32) {
+        self.set.memories.insert(idx);
+    }
+}
+
+// Marks the entities referenced by the instructions of an expression.
+impl<A: Allocator> ExprVisitor for Walker<A> {
+    type Error = TryReserveError;
+
+    fn visit_call(&mut self, func: FuncIdx) -> Result<(), TryReserveError> {
+        self.function(*func)
+    }
+
+    fn visit_call_indirect(&mut self, _: TypeIdx, table: TableIdx) -> Result<(), TryReserveError> {
+        self.table(*table);
+        Ok(())
+    }
+
+    fn visit_ref_func(&mut self, func: FuncIdx) -> Result<(), TryReserveError> {
+        self.referenced.insert(*func);
+        self.function(*func)
+    }
+
+    fn visit_global(&mut self, _: Opcode, global: GlobalIdx) -> Result<(), TryReserveError> {
+        self.global(*global)
+    }
+
+    fn visit_memarg(&mut self, _: Operator, _: MemArg) -> Result<(), TryReserveError> {
+        self.memory(0);
+        Ok(())
+    }
+
+    fn visit_memory(&mut self, _: Operator, memory: MemIdx) -> Result<(), TryReserveError> {
+        self.memory(*memory);
+        Ok(())
+    }
+
+    fn visit_table(&mut self, _: Operator, table: TableIdx) -> Result<(), TryReserveError> {
+        self.table(*table);
+        Ok(())
+    }
+
+    fn visit_elem(&mut self, _: Operator, elem: ElemIdx) -> Result<(), TryReserveError> {
+        self.elem(*elem)
+    }
+
+    fn visit_data(&mut self, _: Operator, data: DataIdx) -> Result<(), TryReserveError> {
+        self.data(*data)
+    }
+}
+
+/// A version of WASI, as identified by the namespaces of its imports.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WasiVersion {
+    /// The precursor of preview 1, imported from `wasi_unstable`.
+    Unstable,
+    /// Preview 1, imported from `wasi_snapshot_preview1`.
+    Preview1,
+    /// Preview 2, imported from interfaces named like
+    /// `wasi:io/streams@0.2.0` (as in core modules adapted to components).
+    Preview2,
+}
+
+impl WasiVersion {
+    // Classifies the module name of an import.
+    fn of(module: &str) -> Option<Self> {
+        match module {
+            "wasi_unstable" => Some(Self::Unstable),
+            "wasi_snapshot_preview1" => Some(Self::Preview1),
+            _ => {
+                let path = module.strip_prefix("wasi:")?;
+                path.contains('/').then_some(Self::Preview2)
+            }
+        }
+    }
+}
+
+/// A WASI API imported by a module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WasiApi<'a> {
+    /// The version of WASI to which the API belongs, per the module name of
+    /// the import.
+    pub version: WasiVersion,
+    /// The module name of the import, which for preview 2 names the interface
+    /// along with its package and version.
+    pub interface: &'a str,
+    /// The name of the imported function (or other entity).
+    pub name: &'a str,
+}
+
+/// The imports of a module, classified as WASI APIs or not (see
+/// [`wasi_profile`]).
+pub struct WasiProfile<'a, A: Allocator> {
+    /// The WASI APIs imported, in import section order.
+    pub wasi: Vec<WasiApi<'a>, A>,
+    /// The imports that are not WASI APIs and so must be provided by the host
+    /// otherwise, in import section order.
+    pub host: Vec<&'a Import<A>, A>,
+}
+
+impl<A: Allocator> WasiProfile<'_, A> {
+    /// Whether any API of the given WASI version is imported.
+    pub fn uses(&self, version: WasiVersion) -> bool {
+        self.wasi.iter().any(|api| api.version == version)
+    }
+
+    /// Whether all imports are WASI APIs.
+    pub fn is_wasi_only(&self) -> bool {
+        self.host.is_empty()
+    }
+}
+
+/// Classifies the imports of a module against the known WASI namespaces,
+/// summarizing the WASI APIs it requires and the other imports that remain to
+/// be provided by the host.
+///
+/// Only names are considered: an import is not checked against the signature
+/// of the WASI API of its name, nor is the API known to exist. The profile is
+/// allocated with the allocator of the module's import section.
+pub fn wasi_profile<A: Allocator>(
+    module: &Module<A>,
+) -> Result<WasiProfile<'_, A>, TryReserveError> {
+    let alloc = module.importsec.allocator();
+    let mut profile = WasiProfile {
+        wasi: Vec::new_in(alloc.clone()),
+        host: Vec::new_in(alloc.clone()),
+    };
+    for import in module.imports_in_binary_order() {
+        if let Some(version) = WasiVersion::of(&import.module) {
+            profile.wasi.try_reserve(1)?;
+            profile.wasi.push(WasiApi {
+                version,
+                interface: &import.module,
+                name: &import.field,
+            });
+        } else {
+            profile.host.try_reserve(1)?;
+            profile.host.push(import);
+        }
+    }
+    Ok(profile)
+}
+
+// A set of the indices within an index space of fixed size.
+struct IndexSet<A: Allocator> {
+    words: Vec<u64, A>,
+}
+
+impl<A: Allocator> IndexSet<A> {
+    fn new(len: usize, alloc: &A) -> Result<Self, TryReserveError> {
+        let count = len.div_ceil(u64::BITS as usize);
+        let mut words = Vec::new_in(alloc.clone());
+        words.try_reserve_exact(count)?;
+        words.resize(count, 0);
+        Ok(Self { words })
+    }
+
+    // Inserts an index, returning whether it was newly inserted. Indices
+    // beyond the index space are ignored.
+    fn insert(&mut self, idx: u32) -> bool {
+        let bits = u64::BITS as usize;
+        let idx = idx as usize;
+        let Some(word) = self.words.get_mut(idx / bits) else {
+            return false;
+        };
+        let mask = 1 << (idx % bits);
+        let inserted = *word & mask == 0;
+        *word |= mask;
+        inserted
+    }
+
+    fn contains(&self, idx: u32) -> bool {
+        let bits = u64::BITS as usize;
+        let idx = idx as usize;
+        self.words
+            .get(idx / bits)
+            .is_some_and(|word| word & (1 << (idx % bits)) != 0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            (0..u64::BITS)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i as u32 * u64::BITS + bit)
+        })
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+    use crate::encoding::{name, section};
+
+    #[test]
+    fn reachable() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x12, 0x02, // importsec
+            0x03, b'e', b'n', b'v', 0x01, b'g', 0x03, 0x7f, 0x00, // (global i32)
+            0x03, b'e', b'n', b'v', 0x01, b'f', 0x00, 0x00, // (func (type 0))
+            0x03, 0x04, 0x03, 0x00, 0x00, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x06, 0x0b, 0x02, // globalsec
+            0x7f, 0x00, 0x23, 0x00, 0x0b, // (global i32 (global.get 0))
+            0x7f, 0x00, 0x41, 0x00, 0x0b, // (global i32 (i32.const 0))
+            0x07, 0x08, 0x01, 0x04, b'm', b'a', b'i', b'n', 0x00, 0x01, // exportsec
+            0x0c, 0x01, 0x02, // datacountsec
+            0x0a, 0x14, 0x03, // codesec
+            0x0a, 0x00, // func 1
+            0x10, 0x02, // call 2
+            0x23, 0x01, 0x1a, // (drop (global.get 1))
+            0xfc, 0x09, 0x00, // data.drop 0
+            0x0b, // end
+            0x02, 0x00, 0x0b, // func 2
+            0x04, 0x00, 0x10, 0x00, 0x0b, // func 3: call 0
+            0x0b, 0x05, 0x02, 0x01, 0x00, 0x01, 0x00, // datasec
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        #[cfg(feature = "validate")]
+        module.validate().unwrap();
+
+        let reachable = reachability(&module).unwrap();
+        let functions: std::vec::Vec<_> = reachable.functions().map(|idx| *idx).collect();
+        assert_eq!(functions, [1, 2]);
+        let globals: std::vec::Vec<_> = reachable.globals().map(|idx| *idx).collect();
+        assert_eq!(globals, [0, 1]);
+        assert!(reachable.contains_data(DataIdx::new(0)));
+        assert!(!reachable.contains_data(DataIdx::new(1)));
+        assert!(!reachable.contains_function(FuncIdx::new(0)));
+        assert!(!reachable.contains_function(FuncIdx::new(3)));
+        assert!(!reachable.contains_global(GlobalIdx::new(2)));
+        assert_eq!(reachable.memories().count(), 0);
+        assert_eq!(reachable.tables().count(), 0);
+        assert_eq!(reachable.elems().count(), 0);
+    }
+
+    #[test]
+    fn declared_functions() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x04, 0x03, 0x00, 0x00, 0x00, // funcsec
+            0x07, 0x08, 0x01, 0x04, b'm', b'a', b'i', b'n', 0x00, 0x00, // exportsec
+            0x09, 0x09, 0x02, // elemsec
+            0x03, 0x00, 0x01, 0x01, // (elem declare func 1)
+            0x03, 0x00, 0x01, 0x02, // (elem declare func 2)
+            0x0a, 0x0d, 0x03, // codesec
+            0x05, 0x00, 0xd2, 0x01, 0x1a, 0x0b, // func 0: (drop (ref.func 1))
+            0x02, 0x00, 0x0b, // func 1
+            0x02, 0x00, 0x0b, // func 2
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        #[cfg(feature = "validate")]
+        module.validate().unwrap();
+
+        // The segment declaring the referenced function must be kept for the
+        // reference to validate; the other need not be.
+        let reachable = reachability(&module).unwrap();
+        let functions: std::vec::Vec<_> = reachable.functions().map(|idx| *idx).collect();
+        assert_eq!(functions, [0, 1]);
+        let elems: std::vec::Vec<_> = reachable.elems().map(|idx| *idx).collect();
+        assert_eq!(elems, [0]);
+    }
+
+    #[test]
+    fn wasi() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x52, 0x04, // importsec
+            0x16, b'w', b'a', b's', b'i', b'_', b's', b'n', b'a', b'p', b's', b'h', b'o', b't',
+            b'_', b'p', b'r', b'e', b'v', b'i', b'e', b'w', b'1', // "wasi_snapshot_preview1"
+            0x08, b'f', b'd', b'_', b'w', b'r', b'i', b't', b'e', // "fd_write"
+            0x00, 0x00, // (func (type 0))
+            0x13, b'w', b'a', b's', b'i', b':', b'c', b'l', b'i', b'/', b'e', b'x', b'i', b't',
+            b'@', b'0', b'.', b'2', b'.', b'0', // "wasi:cli/exit@0.2.0"
+            0x04, b'e', b'x', b'i', b't', // "exit"
+            0x00, 0x00, // (func (type 0))
+            0x03, b'e', b'n', b'v', 0x03, b'l', b'o', b'g', // "env" "log"
+            0x00, 0x00, // (func (type 0))
+            0x05, b'w', b'a', b's', b'i', b':', 0x01, b'x', // "wasi:" "x"
+            0x00, 0x00, // (func (type 0))
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+
+        let profile = wasi_profile(&module).unwrap();
+        assert_eq!(
+            &profile.wasi[..],
+            [
+                WasiApi {
+                    version: WasiVersion::Preview1,
+                    interface: "wasi_snapshot_preview1",
+                    name: "fd_write",
+                },
+                WasiApi {
+                    version: WasiVersion::Preview2,
+                    interface: "wasi:cli/exit@0.2.0",
+                    name: "exit",
+                },
+            ]
+        );
+        assert!(profile.uses(WasiVersion::Preview2));
+        assert!(!profile.uses(WasiVersion::Unstable));
+        let host: std::vec::Vec<_> = profile
+            .host
+            .iter()
+            .map(|import| (&**import.module, &**import.field))
+            .collect();
+        assert_eq!(host, [("env", "log"), ("wasi:", "x")]);
+        assert!(!profile.is_wasi_only());
+    }
+    #[test]
+    fn wasi_interleaved_imports() {
+        // Memory imports ahead of function imports, which the import section
+        // groups after them.
+        let mut bytes = std::vec::Vec::from(*b"\0asm\x01\0\0\0");
+        section(&mut bytes, 1, |out| out.extend([0x01, 0x60, 0x00, 0x00]));
+        section(&mut bytes, 2, |out| {
+            out.push(0x04);
+            for (module, field, desc) in [
+                ("wasi_snapshot_preview1", "memory", &[0x02, 0x00, 0x01][..]), // (memory 1)
+                ("env", "memory", &[0x02, 0x00, 0x01]),                        // (memory 1)
+                ("env", "log", &[0x00, 0x00]),                                 // (func (type 0))
+                ("wasi_snapshot_preview1", "fd_write", &[0x00, 0x00]),         // (func (type 0))
+            ] {
+                name(out, module);
+                name(out, field);
+                out.extend(desc);
+            }
+        });
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+
+        let profile = wasi_profile(&module).unwrap();
+        let wasi: std::vec::Vec<_> = profile.wasi.iter().map(|api| api.name).collect();
+        assert_eq!(wasi, ["memory", "fd_write"]);
+        let host: std::vec::Vec<_> = profile
+            .host
+            .iter()
+            .map(|import| (&**import.module, &**import.field))
+            .collect();
+        assert_eq!(host, [("env", "memory"), ("env", "log")]);
+    }
+}
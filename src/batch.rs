@@ -0,0 +1,194 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Decoding of many modules in succession, sharing scratch space between them.
+//!
+//! Decoding a module on its own transcodes each expression into an allocation
+//! that grows exactly as the expression is written, which amounts to a
+//! reallocation per instruction. A [`BatchDecoder`] instead transcodes into a
+//! scratch buffer that is retained across expressions and across modules,
+//! copying each expression out into an allocation of its exact size once
+//! complete. For services decoding many small modules, this replaces most of
+//! the allocator traffic of decoding with a single allocation per expression.
+//!
+//! The scratch buffer is allocated with the global allocator; the decoded
+//! modules are allocated as usual with the allocator given.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::core_compat::alloc::Global;
+use crate::decode::{
+    self, DecodeOptions, ExpressionBuilder, NoCustomSectionVisitor, NoDataSegmentSink,
+    SectionAllocators,
+};
+use crate::storage::Stream;
+use crate::{Allocator, Module};
+
+/// The result of decoding a module in a batch.
+pub type BatchResult<A, StorageError> =
+    Result<Module<A>, decode::ErrorWithContext<StorageError, A>>;
+
+/// A decoder of modules that shares scratch space between them (see the
+/// [module documentation](self)).
+///
+/// Custom sections are ignored, as with [`Module::from_bytes`].
+#[derive(Debug)]
+pub struct BatchDecoder<'a> {
+    options: DecodeOptions<'a>,
+    expr_scratch: Option<ExpressionBuilder<Global>>,
+}
+
+impl<'a> BatchDecoder<'a> {
+    /// Creates a decoder of modules with the given options.
+    pub fn new(options: &DecodeOptions<'a>) -> Self {
+        Self {
+            options: *options,
+            expr_scratch: Some(ExpressionBuilder::new_scratch(Global)),
+        }
+    }
+
+    /// Decodes a module from streaming storage, as with
+    /// [`Module::decode_with_options`].
+    pub fn decode<Storage: Stream, A: Allocator>(
+        &mut self,
+        storage: Storage,
+        alloc: A,
+    ) -> BatchResult<A, Storage::Error> {
+        Module::decode_with_scratch(
+            storage,
+            &mut NoCustomSectionVisitor {},
+            &mut NoDataSegmentSink {},
+            &SectionAllocators::uniform(alloc),
+            &self.options,
+            &mut self.expr_scratch,
+        )
+    }
+}
+
+/// Lazily decodes each of a sequence of modules with a shared
+/// [`BatchDecoder`], yielding the result of each in turn.
+pub fn decode_all<I, Storage, A>(
+    inputs: I,
+    alloc: A,
+    options: &DecodeOptions,
+) -> impl Iterator<Item = BatchResult<A, Storage::Error>>
+where
+    I: IntoIterator<Item = Storage>,
+    Storage: Stream,
+    A: Allocator,
+{
+    let mut decoder = BatchDecoder::new(options);
+    inputs
+        .into_iter()
+        .map(move |storage| decoder.decode(storage, alloc.clone()))
+}
+
+/// Decodes each of a collection of modules concurrently on the rayon thread
+/// pool, returning the results in the order of the modules.
+///
+/// Each worker decodes its share of the modules with a [`BatchDecoder`] of
+/// its own.
+#[cfg(feature = "rayon")]
+pub fn decode_all_parallel<I, Storage, A>(
+    inputs: I,
+    alloc: A,
+    options: &DecodeOptions,
+) -> std::vec::Vec<BatchResult<A, Storage::Error>>
+where
+    I: IntoParallelIterator<Item = Storage>,
+    I::Iter: IndexedParallelIterator,
+    Storage: Stream + Send,
+    Storage::Error: Send,
+    A: Allocator + Send + Sync,
+{
+    inputs
+        .into_par_iter()
+        .map_init(
+            || BatchDecoder::new(options),
+            |decoder, storage| decoder.decode(storage, alloc.clone()),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefaultModule;
+    use crate::storage::{Buffer, MemoryEof};
+
+    type Results = std::vec::Vec<Result<DefaultModule, decode::ErrorWithContext<MemoryEof>>>;
+
+    const MODULES: [&[u8]; 3] = [
+        &[
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+            0x0a, 0x11, 0x02, // codesec
+            0x04, 0x00, 0x41, 0x2a, 0x0b, // (i32.const 42)
+            0x0a, 0x00, 0x02, 0x7f, 0x42, 0x01, 0x1a, 0x41, 0x00, 0x0b,
+            0x0b, // (block (result i32) (drop (i64.const 1)) (i32.const 0))
+        ],
+        &[
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x0a, 0x01, // truncated codesec
+        ],
+        &[
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+        ],
+    ];
+
+    // Batch decoding agrees with decoding each module on its own.
+    fn check(results: &Results) {
+        assert_eq!(results.len(), MODULES.len());
+        for (result, bytes) in results.iter().zip(MODULES) {
+            match (result, DefaultModule::from_bytes(bytes)) {
+                (Ok(module), Ok(expected)) => assert!(module.semantically_eq(&expected)),
+                (Err(err), Err(expected)) => assert_eq!(err.error(), expected.error()),
+                _ => panic!("batch decoding disagrees with decoding alone"),
+            }
+        }
+    }
+
+    #[test]
+    fn batch_decoding() {
+        let inputs = MODULES.map(Buffer::new);
+        check(&decode_all(inputs, Global, &DecodeOptions::default()).collect());
+
+        let options = DecodeOptions {
+            packed_expressions: true,
+            ..DecodeOptions::default()
+        };
+        let mut decoder = BatchDecoder::new(&options);
+        let module = decoder.decode(Buffer::new(MODULES[0]), Global).unwrap();
+        let expected = DefaultModule::decode_bytes_with_options(
+            MODULES[0],
+            &mut NoCustomSectionVisitor {},
+            Global,
+            &options,
+        )
+        .unwrap();
+        assert!(module.semantically_eq(&expected));
+        assert!(module.codesec[1].code.is_packed());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_batch_decoding() {
+        let inputs = std::vec::Vec::from(MODULES.map(Buffer::new));
+        check(&decode_all_parallel(
+            inputs,
+            Global,
+            &DecodeOptions::default(),
+        ));
+    }
+}
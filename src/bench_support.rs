@@ -0,0 +1,168 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Generation of synthetic modules for benchmarking (see `benches/`).
+//!
+//! The modules are valid and of a parameterized size and composition, so that
+//! the cost of decoding and validation can be measured as a function of the
+//! amount of code or data in a module.
+
+use std::vec::Vec;
+
+use crate::encoding::{leb128, name, section, sleb128};
+
+/// The composition of a synthetic module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ModuleShape {
+    /// The number of defined functions, each of which is exported.
+    pub functions: u32,
+    /// The (approximate) number of instructions in each function body.
+    pub instructions_per_function: u32,
+    /// The number of active data segments.
+    pub data_segments: u32,
+    /// The size in bytes of each data segment.
+    pub data_segment_size: u32,
+}
+
+impl ModuleShape {
+    /// A handful of small functions and a little data.
+    pub const SMALL: Self = Self {
+        functions: 4,
+        instructions_per_function: 16,
+        data_segments: 1,
+        data_segment_size: 64,
+    };
+
+    /// Many functions of moderate size, and no data.
+    pub const CODE_HEAVY: Self = Self {
+        functions: 1000,
+        instructions_per_function: 500,
+        data_segments: 0,
+        data_segment_size: 0,
+    };
+
+    /// A single function, and many large data segments.
+    pub const DATA_HEAVY: Self = Self {
+        functions: 1,
+        instructions_per_function: 16,
+        data_segments: 64,
+        data_segment_size: 16 * 1024,
+    };
+
+    /// The representative shapes, along with their names.
+    pub const ALL: [(&str, Self); 3] = [
+        ("small", Self::SMALL),
+        ("code-heavy", Self::CODE_HEAVY),
+        ("data-heavy", Self::DATA_HEAVY),
+    ];
+}
+
+const PAGE_SIZE: u64 = 0x1_0000;
+
+// Each function body repeats this sequence, of type [] -> [], within the
+// function type [i32] -> [i32]:
+//   local.get 0; i32.const 1; i32.add; local.set 0
+//   i32.const 0; i32.load offset=4; drop
+const BODY_UNIT: &[u8] = &[
+    0x20, 0x00, 0x41, 0x01, 0x6a, 0x21, 0x00, // local.get 0; ...; local.set 0
+    0x41, 0x00, 0x28, 0x02, 0x04, 0x1a, // i32.const 0; i32.load; drop
+];
+const BODY_UNIT_INSTRUCTIONS: u32 = 7;
+
+/// Generates the binary encoding of a valid module of the given shape.
+///
+/// Besides the defined functions and data, the module imports a function and
+/// defines a memory large enough to hold the data.
+pub fn generate(shape: &ModuleShape) -> Vec<u8> {
+    let mut bytes = Vec::from(*b"\0asm\x01\0\0\0");
+
+    // (type (func (param i32) (result i32)))
+    section(&mut bytes, 1, |out| {
+        out.extend([0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f]);
+    });
+
+    // (import "env" "f" (func (type 0)))
+    section(&mut bytes, 2, |out| {
+        out.push(0x01);
+        name(out, "env");
+        name(out, "f");
+        out.extend([0x00, 0x00]);
+    });
+
+    section(&mut bytes, 3, |out| {
+        leb128(out, u64::from(shape.functions));
+        out.extend((0..shape.functions).map(|_| 0x00));
+    });
+
+    let data_size = u64::from(shape.data_segments) * u64::from(shape.data_segment_size);
+    section(&mut bytes, 5, |out| {
+        out.extend([0x01, 0x00]);
+        leb128(out, data_size.div_ceil(PAGE_SIZE).max(1));
+    });
+
+    // The defined functions follow the import in the function index space.
+    section(&mut bytes, 7, |out| {
+        leb128(out, u64::from(shape.functions));
+        for i in 0..shape.functions {
+            name(out, &std::format!("f{i}"));
+            out.push(0x00);
+            leb128(out, u64::from(i) + 1);
+        }
+    });
+
+    let units = shape.instructions_per_function / BODY_UNIT_INSTRUCTIONS;
+    let mut body = std::vec![0x00]; // No locals.
+    for _ in 0..units {
+        body.extend_from_slice(BODY_UNIT);
+    }
+    body.extend([0x20, 0x00, 0x0b]); // local.get 0; end
+    section(&mut bytes, 10, |out| {
+        leb128(out, u64::from(shape.functions));
+        for _ in 0..shape.functions {
+            leb128(out, body.len() as u64);
+            out.extend_from_slice(&body);
+        }
+    });
+
+    if shape.data_segments > 0 {
+        section(&mut bytes, 11, |out| {
+            leb128(out, u64::from(shape.data_segments));
+            for i in 0..shape.data_segments {
+                // (data (i32.const offset) ...)
+                let offset = u64::from(i) * u64::from(shape.data_segment_size);
+                out.extend([0x00, 0x41]);
+                sleb128(out, i64::try_from(offset).unwrap_or(i64::MAX));
+                out.push(0x0b);
+                leb128(out, u64::from(shape.data_segment_size));
+                out.extend((0..shape.data_segment_size).map(|j| j as u8));
+            }
+        });
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    #[test]
+    fn generated_modules_are_valid() {
+        for (name, shape) in ModuleShape::ALL {
+            let bytes = generate(&shape);
+            let module = Module::decode_bytes(&bytes, &mut NoCustomSectionVisitor {}, Global)
+                .unwrap_or_else(|err| panic!("{name}: {err:?}"));
+            assert_eq!(module.funcsec.len(), shape.functions as usize, "{name}");
+            assert_eq!(module.datasec.len(), shape.data_segments as usize, "{name}");
+            module
+                .validate()
+                .unwrap_or_else(|err| panic!("{name}: {err:?}"));
+        }
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Comparison of modules for semantic equality.
+
+use core::slice;
+
+use crate::types::{
+    BlockType, ConstExpr, DataMode, ElementInit, ElementMode, Expression, FuncIdx,
+    ImportDescriptor, Instruction, Local, Name, Operands, TypeIdx, TypeSection, ValType,
+};
+use crate::{Allocator, Module};
+
+impl<A: Allocator> Module<A> {
+    /// Whether the module is semantically equal to another: whether they
+    /// define the same entities, with the same types, initializers, and code,
+    /// however they were encoded.
+    ///
+    /// The comparison disregards:
+    /// * encoding choices, like LEB128 widths, whether expressions are packed,
+    ///   and whether element segments list function indices or the
+    ///   equivalent `ref.func` expressions;
+    /// * custom sections (which are not part of a decoded module), and the
+    ///   interleaving of imports of different kinds;
+    /// * the type section itself, with type indices (including those of block
+    ///   types) compared by the function types they refer to, so that modules
+    ///   differing only in the deduplication of types are equal;
+    /// * the data count section, which is implied by the data section.
+    ///
+    /// Instructions of in-flight proposals compare their type indices as is.
+    /// A module with a section skipped on decoding (see
+    /// [`DecodeOptions::skip_code`](crate::decode::DecodeOptions::skip_code))
+    /// is equal to no module, not even itself, as the section's contents
+    /// cannot be compared.
+    pub fn semantically_eq<B: Allocator>(&self, other: &Module<B>) -> bool {
+        let cmp = Comparison { a: self, b: other };
+        !has_skipped_section(self)
+            && !has_skipped_section(other)
+            && self.version as u32 == other.version as u32
+            && all_eq(&self.importsec, &other.importsec, |a, b| {
+                names_eq(&a.module, &b.module)
+                    && names_eq(&a.field, &b.field)
+                    && match (a.descriptor, b.descriptor) {
+                        (ImportDescriptor::Function(a), ImportDescriptor::Function(b)) => {
+                            cmp.types_eq(a, b)
+                        }
+                        (ImportDescriptor::Table(a), ImportDescriptor::Table(b)) => a == b,
+                        (ImportDescriptor::Memory(a), ImportDescriptor::Memory(b)) => a == b,
+                        (ImportDescriptor::Global(a), ImportDescriptor::Global(b)) => a == b,
+                        _ => false,
+                    }
+            })
+            && all_eq(&self.funcsec, &other.funcsec, |a, b| cmp.types_eq(*a, *b))
+            && self.tablesec[..] == other.tablesec[..]
+            && self.memsec[..] == other.memsec[..]
+            && all_eq(&self.globalsec, &other.globalsec, |a, b| {
+                a.ty == b.ty && cmp.exprs_eq(&a.init, &b.init)
+            })
+            && all_eq(&self.exportsec, &other.exportsec, |a, b| {
+                names_eq(&a.field, &b.field) && a.descriptor == b.descriptor
+            })
+            && self.startsec.map(|start| *start) == other.startsec.map(|start| *start)
+            && all_eq(&self.elemsec, &other.elemsec, |a, b| {
+                a.ty == b.ty
+                    && match (&a.mode, &b.mode) {
+                        (ElementMode::Passive, ElementMode::Passive)
+                        | (ElementMode::Declarative, ElementMode::Declarative) => true,
+                        (ElementMode::Active(a), ElementMode::Active(b)) => {
+                            a.table == b.table && cmp.exprs_eq(&a.offset, &b.offset)
+                        }
+                        _ => false,
+                    }
+                    && cmp.elem_inits_eq(&a.init, &b.init)
+            })
+            && all_eq(&self.codesec, &other.codesec, |a, b| {
+                all_eq(&a.locals, &b.locals, locals_eq) && cmp.exprs_eq(&a.code, &b.code)
+            })
+            && all_eq(&self.datasec, &other.datasec, |a, b| {
+                a.init[..] == b.init[..]
+                    && match (&a.mode, &b.mode) {
+                        (DataMode::Passive(), DataMode::Passive()) => true,
+                        (DataMode::Active(a), DataMode::Active(b)) => {
+                            a.memory == b.memory && cmp.exprs_eq(&a.offset, &b.offset)
+                        }
+                        _ => false,
+                    }
+            })
+    }
+}
+
+fn has_skipped_section<A: Allocator>(module: &Module<A>) -> bool {
+    module.skipped_codesec.is_some() || module.skipped_datasec.is_some()
+}
+
+struct Comparison<'a, A: Allocator, B: Allocator> {
+    a: &'a Module<A>,
+    b: &'a Module<B>,
+}
+
+impl<A: Allocator, B: Allocator> Comparison<'_, A, B> {
+    fn types_eq(&self, a: TypeIdx, b: TypeIdx) -> bool {
+        self.block_types_eq(BlockType::TypeIndex(a), BlockType::TypeIndex(b))
+    }
+
+    fn block_types_eq(&self, a: BlockType, b: BlockType) -> bool {
+        match (
+            signature(&self.a.typesec, &a),
+            signature(&self.b.typesec, &b),
+        ) {
+            (Some(a), Some(b)) => a == b,
+            // Out-of-range indices, in unvalidated modules.
+            (None, None) => a == b,
+            _ => false,
+        }
+    }
+
+    fn exprs_eq(&self, a: &Expression<A>, b: &Expression<B>) -> bool {
+        let mut a = a.instructions();
+        let mut b = b.instructions();
+        loop {
+            match (a.next(), b.next()) {
+                (Some(a), Some(b)) if self.instrs_eq(&a, &b) => {}
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    // Instructions are compared without their offsets, which depend on the
+    // encoding.
+    fn instrs_eq(&self, a: &Instruction<'_>, b: &Instruction<'_>) -> bool {
+        if a.op != b.op {
+            return false;
+        }
+        match (a.operands, b.operands) {
+            (Operands::None, Operands::None) => true,
+            (Operands::BlockType(a), Operands::BlockType(b)) => self.block_types_eq(a, b),
+            (
+                Operands::BrTable {
+                    labels: a_labels,
+                    default: a_default,
+                },
+                Operands::BrTable {
+                    labels: b_labels,
+                    default: b_default,
+                },
+            ) => a_default == b_default && a_labels.iter().eq(b_labels.iter()),
+            (Operands::CallIndirect(a), Operands::CallIndirect(b)) => {
+                a.table == b.table && self.types_eq(a.ty, b.ty)
+            }
+            // Floats are compared bitwise, so that NaNs compare by payload.
+            (Operands::F32(a), Operands::F32(b)) => a.to_bits() == b.to_bits(),
+            (Operands::F64(a), Operands::F64(b)) => a.to_bits() == b.to_bits(),
+            (Operands::I32(a), Operands::I32(b)) => a == b,
+            (Operands::I64(a), Operands::I64(b)) => a == b,
+            (Operands::Idx(a), Operands::Idx(b)) => a == b,
+            (Operands::MemArg(a), Operands::MemArg(b)) => a == b,
+            (Operands::RefType(a), Operands::RefType(b)) => a == b,
+            (Operands::SelectT(a), Operands::SelectT(b)) => a.iter().eq(b.iter()),
+            (Operands::TableCopy(a), Operands::TableCopy(b)) => a == b,
+            (Operands::TableInit(a), Operands::TableInit(b)) => a == b,
+            #[cfg(feature = "experimental")]
+            (Operands::ContBind(a), Operands::ContBind(b)) => a == b,
+            #[cfg(feature = "experimental")]
+            (
+                Operands::Resume {
+                    ty: a_ty,
+                    handlers: a_handlers,
+                },
+                Operands::Resume {
+                    ty: b_ty,
+                    handlers: b_handlers,
+                },
+            ) => a_ty == b_ty && a_handlers.iter().eq(b_handlers.iter()),
+            #[cfg(feature = "experimental")]
+            (
+                Operands::ResumeThrow {
+                    ty: a_ty,
+                    tag: a_tag,
+                    handlers: a_handlers,
+                },
+                Operands::ResumeThrow {
+                    ty: b_ty,
+                    tag: b_tag,
+                    handlers: b_handlers,
+                },
+            ) => a_ty == b_ty && a_tag == b_tag && a_handlers.iter().eq(b_handlers.iter()),
+            #[cfg(feature = "experimental")]
+            (Operands::Switch(a), Operands::Switch(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    // Function indices are equivalent to the corresponding `ref.func`
+    // expressions.
+    fn elem_inits_eq(&self, a: &ElementInit<A>, b: &ElementInit<B>) -> bool {
+        match (a, b) {
+            (ElementInit::FunctionIndices(a), ElementInit::FunctionIndices(b)) => a[..] == b[..],
+            (ElementInit::Expressions(a), ElementInit::Expressions(b)) => {
+                all_eq(a, b, |a, b| self.exprs_eq(a, b))
+            }
+            (ElementInit::FunctionIndices(funcs), ElementInit::Expressions(exprs)) => {
+                all_eq(funcs, exprs, |funcidx, expr| is_ref_func(expr, *funcidx))
+            }
+            (ElementInit::Expressions(exprs), ElementInit::FunctionIndices(funcs)) => {
+                all_eq(exprs, funcs, |expr, funcidx| is_ref_func(expr, *funcidx))
+            }
+        }
+    }
+}
+
+// The parameter and result types of a block type, if in range.
+fn signature<'a, A: Allocator>(
+    typesec: &'a TypeSection<A>,
+    blocktype: &'a BlockType,
+) -> Option<(&'a [ValType], &'a [ValType])> {
+    match blocktype {
+        BlockType::Empty => Some((&[], &[])),
+        BlockType::Result(ty) => Some((&[], slice::from_ref(ty))),
+        BlockType::TypeIndex(idx) => typesec
+            .get(**idx as usize)
+            .map(|ty| (&ty.parameters[..], &ty.results[..])),
+    }
+}
+
+fn is_ref_func<A: Allocator>(expr: &Expression<A>, funcidx: FuncIdx) -> bool {
+    expr.as_const() == Some(ConstExpr::RefFunc(funcidx))
+}
+
+fn names_eq<A: Allocator, B: Allocator>(a: &Name<A>, b: &Name<B>) -> bool {
+    a[..] == b[..] && a.raw_bytes() == b.raw_bytes()
+}
+
+fn locals_eq(a: &Local, b: &Local) -> bool {
+    match (a, b) {
+        (Local::I32(a), Local::I32(b)) => a == b,
+        (Local::I64(a), Local::I64(b)) => a == b,
+        (Local::F32(a), Local::F32(b)) => a.to_bits() == b.to_bits(),
+        (Local::F64(a), Local::F64(b)) => a.to_bits() == b.to_bits(),
+        (Local::Vec(a), Local::Vec(b)) => a == b,
+        (Local::FuncRef(a), Local::FuncRef(b)) | (Local::ExternRef(a), Local::ExternRef(b)) => {
+            a == b
+        }
+        _ => false,
+    }
+}
+
+// Whether two slices are of equal length and pairwise equal per `eq`.
+fn all_eq<T, U>(a: &[T], b: &[U], eq: impl Fn(&T, &U) -> bool) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b))
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::{DecodeOptions, NoCustomSectionVisitor};
+
+    fn decode(bytes: &[u8], packed_expressions: bool) -> Module<Global> {
+        let options = DecodeOptions {
+            packed_expressions,
+            ..DecodeOptions::default()
+        };
+        Module::decode_bytes_with_options(bytes, &mut NoCustomSectionVisitor {}, Global, &options)
+            .unwrap()
+    }
+
+    #[test]
+    fn semantic_equality() {
+        let deduplicated = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x09, 0x01, 0x07, 0x00, // codesec
+            0x02, 0x7f, // block (result i32)
+            0x41, 0x01, // i32.const 1
+            0x0b, 0x0b, // end end
+        ];
+        let duplicated = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x09, 0x02, // typesec
+            0x60, 0x00, 0x01, 0x7f, // (type (func (result i32)))
+            0x60, 0x00, 0x01, 0x7f, // (type (func (result i32)))
+            0x03, 0x02, 0x01, 0x01, // funcsec
+            0x00, 0x03, 0x01, b'a', 0xff, // customsec
+            0x0a, 0x0a, 0x01, 0x08, 0x00, // codesec
+            0x02, 0x01, // block (type 1)
+            0x41, 0x81, 0x00, // i32.const 1 (padded)
+            0x0b, 0x0b, // end end
+        ];
+
+        let module = decode(&deduplicated, false);
+        assert!(module.semantically_eq(&module));
+        assert!(module.semantically_eq(&decode(&deduplicated, true)));
+        assert!(module.semantically_eq(&decode(&duplicated, false)));
+        assert!(decode(&duplicated, true).semantically_eq(&module));
+
+        let mut different = deduplicated;
+        different[27] = 0x02; // i32.const 2
+        assert!(!module.semantically_eq(&decode(&different, false)));
+    }
+}
@@ -10,7 +10,9 @@
 //! nightly Rust (using `core`) and stable Rust (using `allocator-api2`, the
 //! conventional polyfill).
 
-// The `nightly` cfg value is auto-detected and set in the crate's build script.
+// The `nightly` cfg value is set by the crate's build script when the `nightly`
+// feature is enabled on a nightly toolchain; the stable backend is otherwise
+// used regardless of toolchain.
 
 pub mod alloc {
     #[cfg(nightly)]
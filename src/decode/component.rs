@@ -0,0 +1,252 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Decoding of the envelope of component binaries, as far as the core modules
+//! they embed.
+//!
+//! See <https://github.com/WebAssembly/component-model/blob/main/design/mvp/Binary.md>.
+
+use crate::Allocator;
+use crate::core_compat::vec::Vec;
+use crate::storage::{self, MemoryEof, Stream};
+
+use super::{ContextId, ContextStack, Decoder, Error, ErrorWithContext, Magic};
+
+// The layer of component binaries, per the upper half of the version field
+// (which is zero for core modules).
+pub(super) const COMPONENT_LAYER: u16 = 1;
+
+// The IDs of the component sections of interest, and the largest ID.
+const CORE_MODULE_SECTION: u8 = 1;
+const COMPONENT_SECTION: u8 = 4;
+const MAX_SECTION_ID: u8 = 12;
+
+/// The header of a component binary.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ComponentHeader {
+    /// The version of the component binary format, which is subject to
+    /// change until the component model is standardized.
+    pub version: u16,
+}
+
+/// A core module embedded within a component binary.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CoreModule {
+    /// The byte offset of the module within the outermost component.
+    pub offset: usize,
+    /// The length of the module in bytes.
+    pub len: u32,
+    /// The nesting depth of the component that embeds the module, with 0
+    /// indicating the outermost component.
+    pub depth: u32,
+}
+
+impl CoreModule {
+    /// Returns the bytes of the module within those of the outermost
+    /// component, or `None` if out of bounds, for decoding with
+    /// [`Module::decode_bytes`](crate::Module::decode_bytes) and the like.
+    pub fn bytes<'a>(&self, component: &'a [u8]) -> Option<&'a [u8]> {
+        component.get(self.offset..self.offset.checked_add(self.len as usize)?)
+    }
+}
+
+/// A component binary, decoded only as far as its header and the locations
+/// of the core modules it embeds.
+pub struct Component<A: Allocator> {
+    /// The header of the outermost component.
+    pub header: ComponentHeader,
+    /// The core modules embedded by the component, including those of nested
+    /// components, in binary order.
+    pub core_modules: Vec<CoreModule, A>,
+}
+
+/// Decodes the envelope of a component binary from streaming storage,
+/// enumerating the core modules it embeds.
+///
+/// Only section headers and the headers of nested components are decoded.
+/// Decoding a component as a module instead fails with
+/// [`Error::UnexpectedComponent`].
+pub fn decode_component<Storage: Stream, A: Allocator>(
+    storage: Storage,
+    alloc: A,
+) -> Result<Component<A>, ErrorWithContext<Storage::Error, A>> {
+    let mut context = ContextStack::default();
+    let mut decoder = Decoder::new(storage);
+    let result = read_component(&mut decoder, &mut context, alloc);
+    result.map_err(|error| ErrorWithContext { error, context })
+}
+
+/// Decodes the envelope of a component binary directly from memory.
+pub fn decode_component_bytes<Bytes: AsRef<[u8]>, A: Allocator>(
+    bytes: Bytes,
+    alloc: A,
+) -> Result<Component<A>, ErrorWithContext<MemoryEof, A>> {
+    decode_component(storage::Buffer::new(bytes), alloc)
+}
+
+// Walks the sections of a component and of the components nested within it,
+// which are tracked by their ends rather than by recursion.
+fn read_component<Storage: Stream, A: Allocator>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    alloc: A,
+) -> Result<Component<A>, Error<Storage::Error>> {
+    let header = read_component_header(decoder, context)?;
+    let mut core_modules = Vec::new_in(alloc.clone());
+    // The (start, end) offsets of the payloads of the nested components
+    // being walked.
+    let mut nested: Vec<(usize, usize), A> = Vec::new_in(alloc);
+    loop {
+        let offset = decoder.offset();
+        if let Some(&(start, end)) = nested.last()
+            && offset >= end
+        {
+            if offset > end {
+                return Err(Error::InvalidComponentLength {
+                    expected: u32::try_from(end - start).unwrap_or(u32::MAX),
+                    actual: u32::try_from(offset - start).unwrap_or(u32::MAX),
+                });
+            }
+            nested.pop();
+            continue;
+        }
+
+        // As with modules, the end of the outermost component is an EOF at a
+        // section boundary.
+        let outermost = nested.is_empty();
+        let id = decoder.with_context(context, ContextId::SectionId, |decoder, _| {
+            let id = match decoder.read_byte_raw() {
+                Err(Error::Storage(ref err)) if outermost && Storage::is_eof(err) => {
+                    return Ok(None);
+                }
+                id => id?,
+            };
+            if id > MAX_SECTION_ID {
+                return Err(Error::InvalidToken(id));
+            }
+            Ok(Some(id))
+        })?;
+        let Some(id) = id else {
+            break;
+        };
+        let len: u32 = decoder.read_bounded(context)?;
+        let start = decoder.offset();
+
+        match id {
+            CORE_MODULE_SECTION => {
+                core_modules.try_reserve(1)?;
+                core_modules.push(CoreModule {
+                    offset: start,
+                    len,
+                    depth: nested.len() as u32,
+                });
+                decoder.skip_bytes(context, len as usize)?;
+            }
+            COMPONENT_SECTION => {
+                read_component_header(decoder, context)?;
+                nested.try_reserve(1)?;
+                nested.push((start, start.saturating_add(len as usize)));
+            }
+            _ => decoder.skip_bytes(context, len as usize)?,
+        }
+    }
+    Ok(Component {
+        header,
+        core_modules,
+    })
+}
+
+// Reads the magic value and version that begin a component.
+fn read_component_header<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+) -> Result<ComponentHeader, Error<Storage::Error>> {
+    decoder.read_bounded::<Magic, _>(context)?;
+    decoder.with_context(context, ContextId::Version, |decoder, _| {
+        let mut buf = [0u8; 4];
+        decoder.read_exact_raw(&mut buf)?;
+        let value = u32::from_le_bytes(buf);
+        let (version, layer) = (value as u16, (value >> 16) as u16);
+        if layer != COMPONENT_LAYER {
+            return Err(Error::UnknownVersion(value));
+        }
+        Ok(ComponentHeader { version })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::scan::scan_bytes;
+
+    const COMPONENT: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x0d, 0x00, 0x01, 0x00, // version 0xd, layer 1
+        0x01, 0x08, // core module
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // (module)
+        0x04, 0x16, // component
+        0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00, // header
+        0x01, 0x08, // core module
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // (module)
+        0x00, 0x02, 0x01, b'x', // custom section
+        0x07, 0x01, 0x00, // type section
+    ];
+
+    #[test]
+    fn core_modules() {
+        let component = decode_component_bytes(COMPONENT, Global).unwrap();
+        assert_eq!(component.header, ComponentHeader { version: 0x0d });
+        assert_eq!(
+            &component.core_modules[..],
+            [
+                CoreModule {
+                    offset: 10,
+                    len: 8,
+                    depth: 0,
+                },
+                CoreModule {
+                    offset: 30,
+                    len: 8,
+                    depth: 1,
+                },
+            ]
+        );
+        for module in &component.core_modules {
+            let bytes = module.bytes(COMPONENT).unwrap();
+            scan_bytes(bytes, Global).unwrap();
+        }
+
+        let error = scan_bytes(COMPONENT, Global).err().unwrap();
+        assert_eq!(error.error, Error::UnexpectedComponent(0x0d));
+
+        let error = decode_component_bytes(&COMPONENT[10..18], Global)
+            .err()
+            .unwrap();
+        assert_eq!(error.error, Error::UnknownVersion(1));
+    }
+
+    #[test]
+    fn malformed_nesting() {
+        // The nested component is declared one byte short of its sections.
+        let mut bytes = COMPONENT.to_vec();
+        bytes[19] = 0x15;
+        let error = decode_component_bytes(&bytes, Global).err().unwrap();
+        assert_eq!(
+            error.error,
+            Error::InvalidComponentLength {
+                expected: 0x15,
+                actual: 0x16,
+            }
+        );
+
+        // Nested components do not end at EOF.
+        let error = decode_component_bytes(&COMPONENT[..38], Global)
+            .err()
+            .unwrap();
+        assert_eq!(error.error, Error::Storage(MemoryEof {}));
+    }
+}
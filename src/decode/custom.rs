@@ -0,0 +1,362 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Visitors for the standard tool-conventions custom sections.
+//!
+//! See <https://github.com/WebAssembly/tool-conventions>.
+
+use num_enum::TryFromPrimitive;
+
+use crate::Allocator;
+use crate::core_compat::vec::Vec;
+use crate::storage::{Buffer, MemoryEof, Stream};
+use crate::types::{CustomSection, Name, SectionId};
+
+use super::{
+    BoundedDecodable, ContextId, ContextStack, Contextual, CustomSectionPosition,
+    CustomSectionVisitor, Decodable, Decoder, Error, ErrorWithContext,
+};
+
+/// The result of parsing a custom section. Errors are reported with offsets
+/// relative to the start of the section's payload (i.e., following its name).
+pub type CustomSectionResult<T, A> = Result<Vec<T, A>, ErrorWithContext<MemoryEof, A>>;
+
+/// A (name, version) pair within a field of the `producers` section.
+pub struct Producer<A: Allocator> {
+    /// The name of the producer (e.g., "rustc" or "clang").
+    pub name: Name<A>,
+    /// The version of the producer, which may be empty.
+    pub version: Name<A>,
+}
+
+/// A field of the `producers` section (e.g., "language", "processed-by", or
+/// "sdk").
+pub struct ProducersField<A: Allocator> {
+    /// The name of the field.
+    pub name: Name<A>,
+    /// The producers listed under the field, in the order given.
+    pub values: Vec<Producer<A>, A>,
+}
+
+/// The prefix of an entry in the `target_features` section.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum FeaturePrefix {
+    /// The feature is used by the module.
+    Used = b'+',
+    /// The feature must not be used by the module or anything linked with it.
+    Disallowed = b'-',
+    /// The feature is required by the module or anything linked with it.
+    Required = b'=',
+}
+
+/// An entry of the `target_features` section.
+pub struct TargetFeature<A: Allocator> {
+    /// How the feature relates to the module.
+    pub prefix: FeaturePrefix,
+    /// The name of the feature (e.g., "bulk-memory" or "simd128").
+    pub name: Name<A>,
+}
+
+/// A visitor that parses the `producers` custom section.
+pub struct ProducersVisitor<A: Allocator> {
+    alloc: A,
+    fields: Option<CustomSectionResult<ProducersField<A>, A>>,
+}
+
+impl<A: Allocator> ProducersVisitor<A> {
+    /// The name of the custom section parsed.
+    pub const SECTION_NAME: &str = "producers";
+
+    /// Creates a visitor, allocating the parsed fields with the given
+    /// allocator.
+    pub fn new(alloc: A) -> Self {
+        Self {
+            alloc,
+            fields: None,
+        }
+    }
+
+    /// Returns the parsed fields, or `None` if the section was not present.
+    pub fn finish(self) -> Option<CustomSectionResult<ProducersField<A>, A>> {
+        self.fields
+    }
+}
+
+impl<A: Allocator> CustomSectionVisitor<A> for ProducersVisitor<A> {
+    fn should_visit(&self, name: &str) -> bool {
+        name == Self::SECTION_NAME
+    }
+
+    fn visit(&mut self, custom: CustomSection<A>, _: CustomSectionPosition) {
+        self.fields = Some(parse(&custom, &self.alloc));
+    }
+}
+
+/// A visitor that parses the `target_features` custom section.
+pub struct TargetFeaturesVisitor<A: Allocator> {
+    alloc: A,
+    features: Option<CustomSectionResult<TargetFeature<A>, A>>,
+}
+
+impl<A: Allocator> TargetFeaturesVisitor<A> {
+    /// The name of the custom section parsed.
+    pub const SECTION_NAME: &str = "target_features";
+
+    /// Creates a visitor, allocating the parsed features with the given
+    /// allocator.
+    pub fn new(alloc: A) -> Self {
+        Self {
+            alloc,
+            features: None,
+        }
+    }
+
+    /// Returns the parsed features, or `None` if the section was not present.
+    pub fn finish(self) -> Option<CustomSectionResult<TargetFeature<A>, A>> {
+        self.features
+    }
+}
+
+impl<A: Allocator> CustomSectionVisitor<A> for TargetFeaturesVisitor<A> {
+    fn should_visit(&self, name: &str) -> bool {
+        name == Self::SECTION_NAME
+    }
+
+    fn visit(&mut self, custom: CustomSection<A>, _: CustomSectionPosition) {
+        self.features = Some(parse(&custom, &self.alloc));
+    }
+}
+
+// Dispatches each custom section to the first of the two visitors that wants
+// it, allowing visitors to be combined.
+impl<A, V, W> CustomSectionVisitor<A> for (V, W)
+where
+    A: Allocator,
+    V: CustomSectionVisitor<A>,
+    W: CustomSectionVisitor<A>,
+{
+    fn should_visit(&self, name: &str) -> bool {
+        self.0.should_visit(name) || self.1.should_visit(name)
+    }
+
+    fn visit(&mut self, custom: CustomSection<A>, position: CustomSectionPosition) {
+        if self.0.should_visit(&custom.name) {
+            self.0.visit(custom, position);
+        } else {
+            self.1.visit(custom, position);
+        }
+    }
+
+    fn should_stream(&self, name: &str) -> bool {
+        if self.0.should_visit(name) {
+            self.0.should_stream(name)
+        } else {
+            self.1.should_stream(name)
+        }
+    }
+
+    fn visit_chunk(
+        &mut self,
+        name: &str,
+        position: CustomSectionPosition,
+        chunk: &[u8],
+        remaining: usize,
+    ) {
+        if self.0.should_visit(name) {
+            self.0.visit_chunk(name, position, chunk, remaining);
+        } else {
+            self.1.visit_chunk(name, position, chunk, remaining);
+        }
+    }
+}
+
+// Parses the payload of a custom section as a vector, which must span it
+// entirely.
+fn parse<T, A>(custom: &CustomSection<A>, alloc: &A) -> CustomSectionResult<T, A>
+where
+    T: Decodable<A> + Contextual,
+    A: Allocator,
+{
+    parse_payload(custom, |decoder, context| {
+        Decodable::decode(decoder, context, alloc)
+    })
+}
+
+// Parses the payload of a custom section with a given function, which must
+// consume it entirely.
+pub(super) fn parse_payload<T, A, F>(
+    custom: &CustomSection<A>,
+    f: F,
+) -> Result<T, ErrorWithContext<MemoryEof, A>>
+where
+    A: Allocator,
+    F: FnOnce(&mut Decoder<Buffer<&[u8]>>, &mut ContextStack<A>) -> Result<T, Error<MemoryEof>>,
+{
+    let mut context = ContextStack::default();
+    let mut decoder = Decoder::new(Buffer::new(&custom.bytes[..]));
+    let result = decoder.with_context(&mut context, ContextId::CustomSec, |decoder, context| {
+        let parsed = f(decoder, context)?;
+        let actual = decoder.offset();
+        if actual != custom.bytes.len() {
+            return Err(Error::InvalidSectionLength {
+                id: SectionId::Custom,
+                expected: u32::try_from(custom.bytes.len()).unwrap_or(u32::MAX),
+                actual: u32::try_from(actual).unwrap_or(u32::MAX),
+            });
+        }
+        Ok(parsed)
+    });
+    result.map_err(|error| ErrorWithContext { error, context })
+}
+
+impl<A: Allocator> Contextual for Producer<A> {
+    const ID: ContextId = ContextId::Producer;
+}
+
+impl<A: Allocator> Decodable<A> for Producer<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            name: decoder.read(context, alloc)?,
+            version: decoder.read(context, alloc)?,
+        })
+    }
+}
+
+impl<A: Allocator> Contextual for ProducersField<A> {
+    const ID: ContextId = ContextId::ProducersField;
+}
+
+impl<A: Allocator> Decodable<A> for ProducersField<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            name: decoder.read(context, alloc)?,
+            values: Decodable::decode(decoder, context, alloc)?,
+        })
+    }
+}
+
+impl Contextual for FeaturePrefix {
+    const ID: ContextId = ContextId::FeaturePrefix;
+}
+
+impl BoundedDecodable for FeaturePrefix {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        _: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let byte = decoder.read_byte_raw()?;
+        Self::try_from(byte).map_err(|_| Error::InvalidToken(byte))
+    }
+}
+
+impl<A: Allocator> Contextual for TargetFeature<A> {
+    const ID: ContextId = ContextId::TargetFeature;
+}
+
+impl<A: Allocator> Decodable<A> for TargetFeature<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            prefix: decoder.read_bounded(context)?,
+            name: decoder.read(context, alloc)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::scan::visit_custom_section_bytes;
+
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x00, 0x1f, // customsec
+        0x09, b'p', b'r', b'o', b'd', b'u', b'c', b'e', b'r', b's', // name
+        0x01, // 1 field
+        0x08, b'l', b'a', b'n', b'g', b'u', b'a', b'g', b'e', // field name
+        0x01, // 1 value
+        0x04, b'R', b'u', b's', b't', // name
+        0x04, b'1', b'.', b'9', b'0', // version
+        0x00, 0x1c, // customsec
+        0x0f, b't', b'a', b'r', b'g', b'e', b't', b'_', b'f', b'e', b'a', b't', b'u', b'r', b'e',
+        b's', // name
+        0x02, // 2 features
+        b'+', 0x04, b's', b'i', b'm', b'd', // +simd
+        b'-', 0x03, b'g', b'c', b'x', // -gcx
+    ];
+
+    #[test]
+    fn producers_and_target_features() {
+        let mut visitors = (
+            ProducersVisitor::new(Global),
+            TargetFeaturesVisitor::new(Global),
+        );
+        visit_custom_section_bytes(MODULE, &mut visitors, Global).unwrap();
+
+        let producers = visitors.0.finish().unwrap().unwrap();
+        assert_eq!(producers.len(), 1);
+        assert_eq!(&**producers[0].name, "language");
+        assert_eq!(producers[0].values.len(), 1);
+        assert_eq!(&**producers[0].values[0].name, "Rust");
+        assert_eq!(&**producers[0].values[0].version, "1.90");
+
+        let features = visitors.1.finish().unwrap().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].prefix, FeaturePrefix::Used);
+        assert_eq!(&**features[0].name, "simd");
+        assert_eq!(features[1].prefix, FeaturePrefix::Disallowed);
+        assert_eq!(&**features[1].name, "gcx");
+    }
+
+    #[test]
+    fn malformed_target_features() {
+        let mut visitor = TargetFeaturesVisitor::new(Global);
+
+        let mut bad_prefix = MODULE[..8].to_vec();
+        bad_prefix.extend_from_slice(&[
+            0x00, 0x13, // customsec
+            0x0f, b't', b'a', b'r', b'g', b'e', b't', b'_', b'f', b'e', b'a', b't', b'u', b'r',
+            b'e', b's', // name
+            0x01, b'!', 0x00, // !""
+        ]);
+        visit_custom_section_bytes(&bad_prefix, &mut visitor, Global).unwrap();
+        let error = visitor.finish().unwrap().err().unwrap();
+        assert_eq!(error.error, Error::InvalidToken(b'!'));
+
+        let mut visitor = TargetFeaturesVisitor::new(Global);
+        let mut trailing = MODULE[..8].to_vec();
+        trailing.extend_from_slice(&[
+            0x00, 0x13, // customsec
+            0x0f, b't', b'a', b'r', b'g', b'e', b't', b'_', b'f', b'e', b'a', b't', b'u', b'r',
+            b'e', b's', // name
+            0x00, 0xaa, 0xbb, // no features, then trailing bytes
+        ]);
+        visit_custom_section_bytes(&trailing, &mut visitor, Global).unwrap();
+        let error = visitor.finish().unwrap().err().unwrap();
+        assert_eq!(
+            error.error,
+            Error::InvalidSectionLength {
+                id: SectionId::Custom,
+                expected: 3,
+                actual: 1,
+            }
+        );
+    }
+}
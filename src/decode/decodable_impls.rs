@@ -14,11 +14,15 @@ use crate::Allocator;
 use crate::core_compat::boxed::Box;
 use crate::core_compat::vec::Vec;
 use crate::storage::Stream;
+#[cfg(feature = "decode")]
+use crate::trace::trace_span;
 use crate::types::*;
 
+#[cfg(feature = "decode")]
+use super::transcode_expression;
 use super::{
-    BoundedDecodable, ContextId, ContextStack, Contextual, Decodable, Decoder, Error, Magic,
-    transcode_expression,
+    BoundedDecodable, COMPONENT_LAYER, ContextId, ContextStack, Contextual, Decodable, Decoder,
+    Error, Magic,
 };
 
 /// Maximum number of local variables per function. It serves to give a
@@ -53,9 +57,9 @@ macro_rules! impl_contextual {
 macro_rules! impl_parsable_for_u8_enum {
     ($type:ty) => {
         impl BoundedDecodable for $type {
-            fn decode<Storage: Stream>(
+            fn decode<A: Allocator, Storage: Stream>(
                 decoder: &mut Decoder<Storage>,
-                _: &mut ContextStack,
+                _: &mut ContextStack<A>,
             ) -> Result<Self, Error<Storage::Error>> {
                 let byte = decoder.read_byte_raw()?;
                 Self::try_from(byte).map_err(|_| Error::InvalidToken(byte))
@@ -67,9 +71,9 @@ macro_rules! impl_parsable_for_u8_enum {
 macro_rules! impl_parsable_for_leb128_u32_enum {
     ($type:ty, $make_err:path) => {
         impl BoundedDecodable for $type {
-            fn decode<Storage: Stream>(
+            fn decode<A: Allocator, Storage: Stream>(
                 decoder: &mut Decoder<Storage>,
-                _: &mut ContextStack,
+                _: &mut ContextStack<A>,
             ) -> Result<Self, Error<Storage::Error>> {
                 let val: u32 = decoder.read_leb128_raw()?;
                 Self::try_from(val).map_err(|_| $make_err(val))
@@ -81,9 +85,9 @@ macro_rules! impl_parsable_for_leb128_u32_enum {
 macro_rules! impl_parsable_for_le_u32_enum {
     ($type:ty, $make_err:path) => {
         impl BoundedDecodable for $type {
-            fn decode<Storage: Stream>(
+            fn decode<A: Allocator, Storage: Stream>(
                 decoder: &mut Decoder<Storage>,
-                _: &mut ContextStack,
+                _: &mut ContextStack<A>,
             ) -> Result<Self, Error<Storage::Error>> {
                 let mut buf = [0u8; 4];
                 decoder.read_exact_raw(&mut buf)?;
@@ -99,7 +103,7 @@ macro_rules! impl_parsable_for_newtype {
         impl<A: Allocator> Decodable<A> for $type<A> {
             fn decode<Storage: Stream>(
                 decoder: &mut Decoder<Storage>,
-                context: &mut ContextStack,
+                context: &mut ContextStack<A>,
                 alloc: &A,
             ) -> Result<Self, Error<Storage::Error>> {
                 Ok(Self::new(<Self as ops::Deref>::Target::decode(
@@ -110,9 +114,9 @@ macro_rules! impl_parsable_for_newtype {
     };
     ($type:ident) => {
         impl BoundedDecodable for $type {
-            fn decode<Storage: Stream>(
+            fn decode<A: Allocator, Storage: Stream>(
                 decoder: &mut Decoder<Storage>,
-                context: &mut ContextStack,
+                context: &mut ContextStack<A>,
             ) -> Result<Self, Error<Storage::Error>> {
                 Ok(Self::new(
                     <<Self as ops::Deref>::Target as BoundedDecodable>::decode(decoder, context)?,
@@ -122,6 +126,16 @@ macro_rules! impl_parsable_for_newtype {
     };
 }
 
+// Component binaries share the magic of modules, but with a nonzero layer in
+// the upper half of the version field.
+fn unknown_version<StorageError>(version: u32) -> Error<StorageError> {
+    if (version >> 16) as u16 == COMPONENT_LAYER {
+        Error::UnexpectedComponent(version as u16)
+    } else {
+        Error::UnknownVersion(version)
+    }
+}
+
 impl<T, A> Decodable<A> for Vec<T, A>
 where
     T: Decodable<A> + Contextual,
@@ -129,24 +143,41 @@ where
 {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
-        let mut len: u32 = decoder.read_bounded(context)?;
-        let mut vec = Vec::new_in(alloc.clone());
-        vec.try_reserve_exact(len as usize)?;
-        while len > 0 {
-            vec.push(decoder.read(context, alloc)?);
-            len -= 1;
-        }
-        Ok(vec)
+        decode_vec(decoder, context, alloc, |_| Ok(()))
+    }
+}
+
+// Decodes a vector, first checking its length with a given callback.
+fn decode_vec<T, A, Storage, F>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    alloc: &A,
+    check_len: F,
+) -> Result<Vec<T, A>, Error<Storage::Error>>
+where
+    T: Decodable<A> + Contextual,
+    A: Allocator,
+    Storage: Stream,
+    F: FnOnce(u32) -> Result<(), Error<Storage::Error>>,
+{
+    let len: u32 = decoder.read_bounded(context)?;
+    check_len(len)?;
+    let mut vec = Vec::new_in(alloc.clone());
+    vec.try_reserve_exact(len as usize)?;
+    for i in 0..len {
+        vec.push(decoder.read_nth(context, alloc, i)?);
     }
+    Ok(vec)
 }
 
 impl_contextual!(i32, ContextId::I32);
 impl_contextual!(i64, ContextId::I64);
 impl_contextual!(f32, ContextId::F32);
 impl_contextual!(f64, ContextId::F64);
+impl_contextual!(AtomicOpcode, ContextId::AtomicOpcode);
 impl_contextual!(BulkOpcode, ContextId::BulkOpcode);
 impl_contextual!(BrTableOperands<A: Allocator>, ContextId::BrTableOperands);
 impl_contextual!(CallIndirectOperands, ContextId::U32);
@@ -155,11 +186,13 @@ impl_contextual!(CustomSection<A: Allocator>, ContextId::CustomSec);
 impl_contextual!(DataIdx, ContextId::DataIdx);
 impl_contextual!(DataSection<A: Allocator>, ContextId::DataSec);
 impl_contextual!(DataSegment<A: Allocator>, ContextId::Data);
+#[cfg(feature = "decode")]
 impl_contextual!(DataSegmentToken, ContextId::DataToken);
 impl_contextual!(ElemIdx, ContextId::ElemIdx);
 impl_contextual!(ElementKind, ContextId::ElemKind);
 impl_contextual!(ElementSection<A: Allocator>, ContextId::ElemSec);
 impl_contextual!(ElementSegment<A: Allocator>, ContextId::Elem);
+#[cfg(feature = "decode")]
 impl_contextual!(ElementSegmentToken, ContextId::ElemToken);
 impl_contextual!(Export<A: Allocator>, ContextId::Export);
 impl_contextual!(ExportDescriptor, ContextId::ExportDesc);
@@ -205,6 +238,7 @@ impl_contextual!(TableType, ContextId::TableType);
 impl_contextual!(TypeIdx, ContextId::TypeIdx);
 impl_contextual!(TypeSection<A: Allocator>, ContextId::TypeSec);
 impl_contextual!(u32, ContextId::U32);
+impl_contextual!(u64, ContextId::U64);
 impl_contextual!(u8, ContextId::Byte);
 impl_contextual!(ValType, ContextId::ValType);
 impl_contextual!(Vec<u8, A>, ContextId::VecByte);
@@ -216,23 +250,47 @@ impl_contextual!(Vec<LabelIdx, A>, ContextId::VecLabelIdx);
 impl_contextual!(Vec<ValType, A>, ContextId::VecValType);
 impl_contextual!(Version, ContextId::Version);
 
+#[cfg(feature = "experimental")]
+impl_contextual!(ContBindOperands, ContextId::U32);
+#[cfg(feature = "experimental")]
+impl_contextual!(SwitchOperands, ContextId::U32);
+#[cfg(feature = "experimental")]
+impl_contextual!(TagIdx, ContextId::TagIdx);
+
 impl_parsable_for_u8_enum!(ElementKind);
 impl_parsable_for_u8_enum!(ExportDescriptorToken);
 impl_parsable_for_u8_enum!(FunctionTypeToken);
 impl_parsable_for_u8_enum!(GlobalTypeMutability);
 impl_parsable_for_u8_enum!(ImportDescriptorToken);
 impl_parsable_for_u8_enum!(LimitsToken);
-impl_parsable_for_u8_enum!(Opcode);
 impl_parsable_for_u8_enum!(RefType);
 impl_parsable_for_u8_enum!(SectionId);
 impl_parsable_for_u8_enum!(ValType);
 
+impl_parsable_for_leb128_u32_enum!(AtomicOpcode, Error::InvalidAtomicOpcode);
 impl_parsable_for_leb128_u32_enum!(BulkOpcode, Error::InvalidBulkOpcode);
+#[cfg(feature = "decode")]
 impl_parsable_for_leb128_u32_enum!(DataSegmentToken, Error::InvalidDataToken);
+#[cfg(feature = "decode")]
 impl_parsable_for_leb128_u32_enum!(ElementSegmentToken, Error::InvalidElementToken);
 
 impl_parsable_for_le_u32_enum!(Magic, Error::InvalidMagic);
-impl_parsable_for_le_u32_enum!(Version, Error::UnknownVersion);
+impl_parsable_for_le_u32_enum!(Version, unknown_version);
+
+impl BoundedDecodable for Opcode {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        _: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let byte = decoder.read_byte_raw()?;
+        match Self::try_from(byte) {
+            // The wafer-types opcodes of in-flight proposals may be enabled by
+            // another dependent, but are only decoded if enabled here.
+            Ok(op) if cfg!(feature = "experimental") || !op.is_experimental() => Ok(op),
+            _ => Err(Error::InvalidToken(byte)),
+        }
+    }
+}
 
 impl_parsable_for_newtype!(DataIdx);
 impl_parsable_for_newtype!(ElemIdx);
@@ -245,58 +303,71 @@ impl_parsable_for_newtype!(MemType);
 impl_parsable_for_newtype!(StartSection);
 impl_parsable_for_newtype!(TableIdx);
 impl_parsable_for_newtype!(TypeIdx);
+#[cfg(feature = "experimental")]
+impl_parsable_for_newtype!(TagIdx);
+#[cfg(feature = "decode")]
 impl_parsable_for_newtype!(CodeSection<A>);
+#[cfg(feature = "decode")]
 impl_parsable_for_newtype!(DataSection<A>);
+#[cfg(feature = "decode")]
 impl_parsable_for_newtype!(ElementSection<A>);
-impl_parsable_for_newtype!(ExportSection<A>);
 impl_parsable_for_newtype!(FunctionSection<A>);
+#[cfg(feature = "decode")]
 impl_parsable_for_newtype!(GlobalSection<A>);
-impl_parsable_for_newtype!(ImportSection<A>);
 impl_parsable_for_newtype!(MemorySection<A>);
 impl_parsable_for_newtype!(ResultType<A>);
 impl_parsable_for_newtype!(TableSection<A>);
 impl_parsable_for_newtype!(TypeSection<A>);
 
 impl BoundedDecodable for u8 {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        _: &mut ContextStack,
+        _: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         decoder.read_byte_raw()
     }
 }
 
 impl BoundedDecodable for u32 {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        _: &mut ContextStack,
+        _: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        decoder.read_leb128_raw()
+    }
+}
+
+impl BoundedDecodable for u64 {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        _: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         decoder.read_leb128_raw()
     }
 }
 
 impl BoundedDecodable for i32 {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        _: &mut ContextStack,
+        _: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         decoder.read_leb128_raw()
     }
 }
 
 impl BoundedDecodable for i64 {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        _: &mut ContextStack,
+        _: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         decoder.read_leb128_raw()
     }
 }
 
 impl BoundedDecodable for f32 {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        _: &mut ContextStack,
+        _: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         let mut buf = [0u8; 4];
         decoder.read_exact_raw(&mut buf)?;
@@ -305,9 +376,9 @@ impl BoundedDecodable for f32 {
 }
 
 impl BoundedDecodable for f64 {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        _: &mut ContextStack,
+        _: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         let mut buf = [0u8; 8];
         decoder.read_exact_raw(&mut buf)?;
@@ -316,9 +387,9 @@ impl BoundedDecodable for f64 {
 }
 
 impl BoundedDecodable for CallIndirectOperands {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
             table: decoder.read_bounded(context)?,
@@ -328,9 +399,9 @@ impl BoundedDecodable for CallIndirectOperands {
 }
 
 impl BoundedDecodable for MemArg {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
             align: decoder.read_bounded(context)?,
@@ -340,9 +411,9 @@ impl BoundedDecodable for MemArg {
 }
 
 impl BoundedDecodable for TableCopyOperands {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
             src: decoder.read_bounded(context)?,
@@ -352,9 +423,9 @@ impl BoundedDecodable for TableCopyOperands {
 }
 
 impl BoundedDecodable for TableInitOperands {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
             table: decoder.read_bounded(context)?,
@@ -363,10 +434,36 @@ impl BoundedDecodable for TableInitOperands {
     }
 }
 
+#[cfg(feature = "experimental")]
+impl BoundedDecodable for ContBindOperands {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            src: decoder.read_bounded(context)?,
+            dst: decoder.read_bounded(context)?,
+        })
+    }
+}
+
+#[cfg(feature = "experimental")]
+impl BoundedDecodable for SwitchOperands {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            ty: decoder.read_bounded(context)?,
+            tag: decoder.read_bounded(context)?,
+        })
+    }
+}
+
 impl<A: Allocator> Decodable<A> for BrTableOperands<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
@@ -379,7 +476,7 @@ impl<A: Allocator> Decodable<A> for BrTableOperands<A> {
 impl<A: Allocator> Decodable<A> for SelectTOperands<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
@@ -389,20 +486,24 @@ impl<A: Allocator> Decodable<A> for SelectTOperands<A> {
 }
 
 impl BoundedDecodable for BlockType {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        _: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
-        let value: i32 = decoder.read_bounded(context)?;
-        match value {
+        // Block types are encoded as an s33: either a negative single-byte
+        // encoding of a value type (or of the empty type, 0x40), or a
+        // non-negative type index.
+        match decoder.read_s33_raw()? {
             n if n < 0 => {
                 // For single-byte values encoded as signed LEB128:
                 // - Bytes 0x40-0x7F (64-127) become negative when interpreted as signed
                 // - Conversion: signed_byte = original_byte - 128 (for bytes >= 64)
                 // - Reverse: original_byte = signed_byte + 128
-                let byte = u8::try_from(n + 128)
-                    .expect("Signed LEB128 block type value must be convertible to single byte");
-
+                // Values below -64 only come from multi-byte encodings.
+                if n < -0x40 {
+                    return Err(Error::InvalidBlockType(n));
+                }
+                let byte = (n.cast_unsigned() & 0x7f) as u8;
                 if byte == 0x40 {
                     Ok(BlockType::Empty)
                 } else {
@@ -412,20 +513,61 @@ impl BoundedDecodable for BlockType {
                     }
                 }
             }
-            n => Ok(BlockType::TypeIndex(TypeIdx::new(
-                u32::try_from(n).unwrap(),
-            ))),
+            // A non-negative s33 fits in a u32.
+            n => Ok(BlockType::TypeIndex(TypeIdx::new(n.cast_unsigned() as u32))),
         }
     }
 }
 
+impl<A: Allocator> Decodable<A> for ImportSection<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let max = decoder.options.max_imports;
+        let imports = decode_vec(decoder, context, alloc, |len| {
+            if len as usize > max {
+                return Err(Error::TooManyImports(len));
+            }
+            Ok(())
+        })?;
+        Ok(Self::new(imports))
+    }
+}
+
+impl<A: Allocator> Decodable<A> for ExportSection<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let max = decoder.options.max_exports;
+        let exports = decode_vec(decoder, context, alloc, |len| {
+            if len as usize > max {
+                return Err(Error::TooManyExports(len));
+            }
+            Ok(())
+        })?;
+        if decoder.options.reject_duplicate_exports
+            && let Some(idx) = find_duplicate_export(&exports, alloc.clone())?
+        {
+            return Err(Error::DuplicateExportName(idx as u32));
+        }
+        Ok(Self::new(exports))
+    }
+}
+
 impl<A: Allocator> Decodable<A> for Name<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         let len: u32 = decoder.read_bounded(context)?;
+        if len as usize > decoder.options.max_name_len {
+            return Err(Error::NameTooLong(len));
+        }
         let mut bytes = Vec::new_in(alloc.clone());
         bytes.try_reserve_exact(len as usize)?;
         // Safety: With the previous call, there is sufficient capacity and any
@@ -434,16 +576,53 @@ impl<A: Allocator> Decodable<A> for Name<A> {
         unsafe { bytes.set_len(len as usize) };
         decoder.read_exact(context, &mut bytes)?;
 
-        str::from_utf8(&bytes).map_err(|_| Error::InvalidUtf8)?;
-        let bytes_ptr = Box::into_raw(bytes.into_boxed_slice());
+        if str::from_utf8(&bytes).is_ok() {
+            // Safety: We have just validated that the byte sequence is valid
+            // UTF-8.
+            return Ok(Self::new(unsafe { into_boxed_str(bytes, alloc) }));
+        }
+        if !decoder.options.lossy_names {
+            return Err(Error::InvalidUtf8);
+        }
 
-        // Safety: The ABIs of [u8] and str are identical, and we have already
-        // validated that the byte sequence is valid UTF-8.
-        let str = unsafe { Box::from_raw_in(bytes_ptr as *mut str, alloc.clone()) };
-        Ok(Self::new(str))
+        let replacement = char::REPLACEMENT_CHARACTER.len_utf8();
+        let lossy_len = bytes
+            .utf8_chunks()
+            .map(|chunk| {
+                let invalid = if chunk.invalid().is_empty() {
+                    0
+                } else {
+                    replacement
+                };
+                chunk.valid().len() + invalid
+            })
+            .sum();
+        let mut lossy = Vec::new_in(alloc.clone());
+        lossy.try_reserve_exact(lossy_len)?;
+        for chunk in bytes.utf8_chunks() {
+            lossy.extend_from_slice(chunk.valid().as_bytes());
+            if !chunk.invalid().is_empty() {
+                let mut buf = [0; 4];
+                let encoded = char::REPLACEMENT_CHARACTER.encode_utf8(&mut buf);
+                lossy.extend_from_slice(encoded.as_bytes());
+            }
+        }
+        // Safety: The byte sequence was built from valid UTF-8 chunks and
+        // encoded replacement characters.
+        let name = unsafe { into_boxed_str(lossy, alloc) };
+        Ok(Self::new_lossy(name, bytes.into_boxed_slice()))
     }
 }
 
+// Safety: The byte sequence must be valid UTF-8.
+pub(crate) unsafe fn into_boxed_str<A: Allocator>(bytes: Vec<u8, A>, alloc: &A) -> Box<str, A> {
+    let bytes_ptr = Box::into_raw(bytes.into_boxed_slice());
+
+    // Safety: The ABIs of [u8] and str are identical, and the caller has
+    // guaranteed that the byte sequence is valid UTF-8.
+    unsafe { Box::from_raw_in(bytes_ptr as *mut str, alloc.clone()) }
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, TryFromPrimitive)]
 enum FunctionTypeToken {
@@ -453,10 +632,10 @@ enum FunctionTypeToken {
 impl<A: Allocator> Decodable<A> for FunctionType<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
-        decoder.read_bounded::<FunctionTypeToken>(context)?;
+        decoder.read_bounded::<FunctionTypeToken, _>(context)?;
         Ok(Self {
             parameters: decoder.read(context, alloc)?,
             results: decoder.read(context, alloc)?,
@@ -467,41 +646,69 @@ impl<A: Allocator> Decodable<A> for FunctionType<A> {
 #[derive(Copy, Clone, TryFromPrimitive)]
 #[repr(u8)]
 enum LimitsToken {
-    WithoutMax = 0x00,
-    WithMax = 0x01,
+    Min = 0x00,
+    MinMax = 0x01,
+    SharedMin = 0x02,
+    SharedMinMax = 0x03,
+}
+
+impl LimitsToken {
+    fn is_shared(self) -> bool {
+        matches!(self, LimitsToken::SharedMin | LimitsToken::SharedMinMax)
+    }
+}
+
+// Decodes the limits following a given token.
+fn decode_limits<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    token: LimitsToken,
+) -> Result<Limits, Error<Storage::Error>> {
+    let min: u32 = decoder.read_bounded(context)?;
+    let max = match token {
+        LimitsToken::Min | LimitsToken::SharedMin => None,
+        LimitsToken::MinMax | LimitsToken::SharedMinMax => Some(decoder.read_bounded(context)?),
+    };
+    Ok(Limits {
+        min,
+        max,
+        shared: token.is_shared(),
+    })
 }
 
 impl BoundedDecodable for Limits {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
-        let token: LimitsToken = decoder.read_bounded(context)?;
-        let min: u32 = decoder.read_bounded(context)?;
-        let max = match token {
-            LimitsToken::WithoutMax => None,
-            LimitsToken::WithMax => Some(decoder.read_bounded(context)?),
-        };
-        Ok(Self { min, max })
+        let token = decoder.read_bounded(context)?;
+        decode_limits(decoder, context, token)
     }
 }
 
 impl BoundedDecodable for TableType {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
+        let reftype = decoder.read_bounded(context)?;
+        // Only memories may be shared: the shared flag is malformed in table
+        // limits, rather than merely invalid.
+        let token: LimitsToken = decoder.read_bounded(context)?;
+        if token.is_shared() {
+            return Err(Error::InvalidToken(token as u8));
+        }
         Ok(Self {
-            reftype: decoder.read_bounded(context)?,
-            limits: decoder.read_bounded(context)?,
+            reftype,
+            limits: decode_limits(decoder, context, token)?,
         })
     }
 }
 
 impl BoundedDecodable for GlobalType {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
             value: decoder.read_bounded(context)?,
@@ -510,10 +717,11 @@ impl BoundedDecodable for GlobalType {
     }
 }
 
+#[cfg(feature = "decode")]
 impl<A: Allocator> Decodable<A> for Expression<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         transcode_expression(decoder, context, alloc)
@@ -529,9 +737,9 @@ enum ImportDescriptorToken {
     Global = 0x3,
 }
 impl BoundedDecodable for ImportDescriptor {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         type Token = ImportDescriptorToken;
 
@@ -547,7 +755,7 @@ impl BoundedDecodable for ImportDescriptor {
 impl<A: Allocator> Decodable<A> for Import<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
@@ -558,10 +766,11 @@ impl<A: Allocator> Decodable<A> for Import<A> {
     }
 }
 
+#[cfg(feature = "decode")]
 impl<A: Allocator> Decodable<A> for Global<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
@@ -580,9 +789,9 @@ enum ExportDescriptorToken {
     Global = 0x3,
 }
 impl BoundedDecodable for ExportDescriptor {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>> {
         type Token = ExportDescriptorToken;
 
@@ -598,7 +807,7 @@ impl BoundedDecodable for ExportDescriptor {
 impl<A: Allocator> Decodable<A> for Export<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         Ok(Self {
@@ -608,10 +817,11 @@ impl<A: Allocator> Decodable<A> for Export<A> {
     }
 }
 
+#[cfg(feature = "decode")]
 impl<A: Allocator> Decodable<A> for ElementSegment<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         let token: ElementSegmentToken = decoder.read_bounded(context)?;
@@ -706,6 +916,7 @@ impl<A: Allocator> Decodable<A> for ElementSegment<A> {
     }
 }
 
+#[cfg(feature = "decode")]
 #[derive(Copy, Clone, TryFromPrimitive)]
 #[repr(u32)]
 enum ElementSegmentToken {
@@ -736,15 +947,15 @@ impl From<ElementKind> for RefType {
 impl<A: Allocator> Decodable<A> for Locals<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         let num_groups: u32 = decoder.read_bounded(context)?;
         let mut locals = Vec::new_in(alloc.clone());
         for _ in 0..num_groups {
             let count: u32 = decoder.read_bounded(context)?;
-            let local = Local::from(decoder.read_bounded::<ValType>(context)?);
-            let subtotal = locals.len() + (count as usize);
+            let local = Local::from(decoder.read_bounded::<ValType, _>(context)?);
+            let subtotal = locals.len().saturating_add(count as usize);
             if subtotal > MAX_LOCALS_PER_FUNCTION {
                 return Err(Error::TooManyLocals(subtotal));
             }
@@ -762,20 +973,29 @@ impl From<ValType> for Local {
             ValType::I64 => Local::I64(0),
             ValType::F32 => Local::F32(0.0),
             ValType::F64 => Local::F64(0.0),
+            ValType::Vec => Local::Vec(0),
             ValType::FuncRef => Local::FuncRef(0),
-            ValType::Vec | ValType::ExternRef => todo!(),
+            ValType::ExternRef => Local::ExternRef(0),
         }
     }
 }
 
+#[cfg(feature = "decode")]
 impl<A: Allocator> Decodable<A> for Function<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
-        let expected_size = decoder.read_bounded::<u32>(context)? as usize;
+        decoder.check_cancelled()?;
+        let expected_size = decoder.read_bounded::<u32, _>(context)? as usize;
         let offset_start = decoder.offset();
+        trace_span!(
+            TRACE,
+            "function",
+            offset = offset_start,
+            size = expected_size
+        );
         let locals = decoder.read(context, alloc)?;
         let code = decoder.read(context, alloc)?;
         let actual_size = decoder.offset() - offset_start;
@@ -789,6 +1009,7 @@ impl<A: Allocator> Decodable<A> for Function<A> {
     }
 }
 
+#[cfg(feature = "decode")]
 #[derive(Copy, Clone, TryFromPrimitive)]
 #[repr(u32)]
 enum DataSegmentToken {
@@ -797,38 +1018,32 @@ enum DataSegmentToken {
     ActiveWithMemIdx = 2,
 }
 
+#[cfg(feature = "decode")]
 impl<A: Allocator> Decodable<A> for DataSegment<A> {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>> {
-        let token: DataSegmentToken = decoder.read_bounded(context)?;
-        match token {
-            DataSegmentToken::ActiveNoMemIdx => {
-                let offset: Expression<A> = decoder.read(context, alloc)?;
-                let init: Vec<u8, A> = decoder.read(context, alloc)?;
-                Ok(Self {
-                    init,
-                    mode: DataMode::Active(DataModeActive {
-                        memory: MemIdx::new(0),
-                        offset,
-                    }),
-                })
-            }
-            DataSegmentToken::Passive => Ok(Self {
-                init: decoder.read(context, alloc)?,
-                mode: DataMode::Passive(),
-            }),
-            DataSegmentToken::ActiveWithMemIdx => {
-                let memory = decoder.read_bounded(context)?;
-                let offset: Expression<A> = decoder.read(context, alloc)?;
-                let init: Vec<u8, A> = decoder.read(context, alloc)?;
-                Ok(Self {
-                    init,
-                    mode: DataMode::Active(DataModeActive { memory, offset }),
-                })
-            }
-        }
+        let mode = decode_data_mode(decoder, context, alloc)?;
+        let init = decoder.read(context, alloc)?;
+        Ok(Self { init, mode })
     }
 }
+
+// Decodes the mode of a data segment, which precedes its contents.
+#[cfg(feature = "decode")]
+pub(super) fn decode_data_mode<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    alloc: &A,
+) -> Result<DataMode<A>, Error<Storage::Error>> {
+    let token: DataSegmentToken = decoder.read_bounded(context)?;
+    let memory = match token {
+        DataSegmentToken::ActiveNoMemIdx => MemIdx::new(0),
+        DataSegmentToken::Passive => return Ok(DataMode::Passive()),
+        DataSegmentToken::ActiveWithMemIdx => decoder.read_bounded(context)?,
+    };
+    let offset = decoder.read(context, alloc)?;
+    Ok(DataMode::Active(DataModeActive { memory, offset }))
+}
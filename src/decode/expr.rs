@@ -18,10 +18,16 @@ use crate::core_compat::vec::Vec;
 use crate::decode::BoundedDecodable;
 use crate::storage::Stream;
 use crate::types::{
-    BlockType, BrTableOperands, BulkOpcode, CallIndirectOperands, Expression, LabelIdx, MemArg,
-    Opcode, RefType, SelectTOperands, TableCopyOperands, TableInitOperands, ValType,
+    AtomicOpcode, BlockType, BrTableOperands, BulkOpcode, CallIndirectOperands, Expression,
+    Instruction, LabelIdx, MemArg, Opcode, OperandLayout, OperandType, Operands, Operator, RefType,
+    SelectTOperands, TableCopyOperands, TableInitOperands, ValType,
 };
 
+#[cfg(feature = "experimental")]
+use crate::types::{ContBindOperands, Handler, Handlers, SwitchOperands, TagIdx};
+
+#[cfg(feature = "experimental")]
+use super::ContextId;
 use super::{ContextStack, Contextual, Decodable, Decoder, Error};
 
 // The maximum natural alignment of any of the structures we use to represent
@@ -36,14 +42,15 @@ struct AlignedAllocator<A: Allocator>(A);
 // Safety: Soundness is deferred to the wrapped allocator.
 unsafe impl<A: Allocator> core_compat::alloc::Allocator for AlignedAllocator<A> {
     fn allocate(&self, layout: Layout) -> Result<ptr::NonNull<[u8]>, AllocError> {
-        let layout = layout.align_to(MAX_NATURAL_ALIGNMENT).unwrap();
-        self.0.allocate(layout)
+        self.0.allocate(aligned(layout)?)
     }
 
     unsafe fn deallocate(&self, ptr: ptr::NonNull<u8>, layout: Layout) {
-        let layout = layout.align_to(MAX_NATURAL_ALIGNMENT).unwrap();
-        // Safety: Soundness is deferred to the wrapped allocator.
-        unsafe { self.0.deallocate(ptr, layout) }
+        // The layout was necessarily successfully aligned on allocation.
+        if let Ok(layout) = aligned(layout) {
+            // Safety: Soundness is deferred to the wrapped allocator.
+            unsafe { self.0.deallocate(ptr, layout) }
+        }
     }
 
     unsafe fn grow(
@@ -52,10 +59,8 @@ unsafe impl<A: Allocator> core_compat::alloc::Allocator for AlignedAllocator<A>
         old_layout: Layout,
         new_layout: Layout,
     ) -> Result<ptr::NonNull<[u8]>, AllocError> {
-        let old_layout = old_layout.align_to(MAX_NATURAL_ALIGNMENT).unwrap();
-        let new_layout = new_layout.align_to(MAX_NATURAL_ALIGNMENT).unwrap();
         // Safety: Soundness is deferred to the wrapped allocator.
-        unsafe { self.0.grow(ptr, old_layout, new_layout) }
+        unsafe { self.0.grow(ptr, aligned(old_layout)?, aligned(new_layout)?) }
     }
 
     unsafe fn shrink(
@@ -64,21 +69,28 @@ unsafe impl<A: Allocator> core_compat::alloc::Allocator for AlignedAllocator<A>
         old_layout: Layout,
         new_layout: Layout,
     ) -> Result<ptr::NonNull<[u8]>, AllocError> {
-        let old_layout = old_layout.align_to(MAX_NATURAL_ALIGNMENT).unwrap();
-        let new_layout = new_layout.align_to(MAX_NATURAL_ALIGNMENT).unwrap();
         // Safety: Soundness is deferred to the wrapped allocator.
-        unsafe { self.0.shrink(ptr, old_layout, new_layout) }
+        unsafe {
+            self.0
+                .shrink(ptr, aligned(old_layout)?, aligned(new_layout)?)
+        }
     }
 }
 
+fn aligned(layout: Layout) -> Result<Layout, AllocError> {
+    layout
+        .align_to(MAX_NATURAL_ALIGNMENT)
+        .map_err(|_| AllocError)
+}
+
 // A type that may appear within a decoded Expression, re-encoded by
 // 'transcoding' directly from the decoder to the builder.
 trait Transcodable<A: Allocator>: Decodable<A> + Contextual {
     fn write_to(self, builder: &mut ExpressionBuilder<A>) -> Result<(), TryReserveError>;
 
-    fn transcode<Storage: Stream>(
+    fn transcode<C: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<C>,
         builder: &mut ExpressionBuilder<A>,
     ) -> Result<(), Error<Storage::Error>>;
 }
@@ -89,32 +101,36 @@ where
     A: Allocator,
 {
     fn write_to(self, builder: &mut ExpressionBuilder<A>) -> Result<(), TryReserveError> {
-        let data = &mut builder.data;
-
+        let align = if builder.packed {
+            1
+        } else {
+            align_of::<Self>()
+        };
         // The alignment of `data`'s allocation ensures that the byte at
-        // `aligned_pos` has T's natural alignment.
-        let pos = data.len();
-        let aligned_pos = pos.next_multiple_of(align_of::<Self>());
+        // `aligned_pos` has T's natural alignment, unless packed.
+        let pos = builder.data.len();
+        let aligned_pos = pos.next_multiple_of(align);
         let padding = aligned_pos - pos;
-        data.try_reserve_exact(padding + size_of::<Self>())?;
+        builder.reserve(padding + size_of::<Self>())?;
+        let data = &mut builder.data;
         if padding > 0 {
             data.resize(pos + padding, 0);
         }
 
-        // Safety: Per the above, the address being written to has T's natural
-        // alignment, and the call to try_reserve_exact() ensures that the
-        // capacity is `aligned_pos + size_of::<Self>()`.
+        // Safety: The call to reserve() ensures that the capacity is at least
+        // `aligned_pos + size_of::<Self>()`, and the write does not assume
+        // alignment.
         unsafe {
             let write_ptr = data.as_mut_ptr().add(aligned_pos);
-            write_ptr.cast::<Self>().write(self);
+            write_ptr.cast::<Self>().write_unaligned(self);
             data.set_len(aligned_pos + size_of::<Self>());
         }
         Ok(())
     }
 
-    fn transcode<Storage: Stream>(
+    fn transcode<C: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<C>,
         builder: &mut ExpressionBuilder<A>,
     ) -> Result<(), Error<Storage::Error>> {
         let value: Self = decoder.read_bounded(context)?;
@@ -136,9 +152,9 @@ where
         Ok(())
     }
 
-    fn transcode<Storage: Stream>(
+    fn transcode<C: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<C>,
         builder: &mut ExpressionBuilder<A>,
     ) -> Result<(), Error<Storage::Error>> {
         let len: u32 = decoder.read_bounded(context)?;
@@ -157,9 +173,9 @@ impl<A: Allocator> Transcodable<A> for BrTableOperands<A> {
         builder.write(self.default)
     }
 
-    fn transcode<Storage: Stream>(
+    fn transcode<C: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<C>,
         builder: &mut ExpressionBuilder<A>,
     ) -> Result<(), Error<Storage::Error>> {
         Vec::<LabelIdx, A>::transcode(decoder, context, builder)?;
@@ -174,164 +190,313 @@ impl<A: Allocator> Transcodable<A> for SelectTOperands<A> {
         builder.write(self.types)
     }
 
-    fn transcode<Storage: Stream>(
+    fn transcode<C: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<C>,
         builder: &mut ExpressionBuilder<A>,
     ) -> Result<(), Error<Storage::Error>> {
         Vec::<ValType, A>::transcode(decoder, context, builder)
     }
 }
 
-// A simple builder for creating
+// The re-encoding of a `resume` or `resume_throw` handler, as read back by
+// `Handlers`.
+#[cfg(feature = "experimental")]
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct EncodedHandler {
+    kind: u32,
+    tag: TagIdx,
+    label: LabelIdx,
+}
+
+#[cfg(feature = "experimental")]
+impl Contextual for EncodedHandler {
+    const ID: ContextId = ContextId::Handler;
+}
+
+#[cfg(feature = "experimental")]
+impl<A: Allocator> Contextual for Vec<EncodedHandler, A> {
+    const ID: ContextId = ContextId::VecHandler;
+}
+
+#[cfg(feature = "experimental")]
+impl BoundedDecodable for EncodedHandler {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let kind = decoder.read_byte_raw()?;
+        if kind > 0x01 {
+            return Err(Error::InvalidToken(kind));
+        }
+        let tag = decoder.read_bounded(context)?;
+        let label = if kind == 0x00 {
+            decoder.read_bounded(context)?
+        } else {
+            LabelIdx::new(0)
+        };
+        Ok(Self {
+            kind: u32::from(kind),
+            tag,
+            label,
+        })
+    }
+}
+
+// A simple builder for creating expressions, either by transcoding them from
+// the decoder or by re-encoding instructions read back from another (see
+// `transform`).
 #[derive(Debug)]
-struct ExpressionBuilder<A: Allocator> {
+pub(crate) struct ExpressionBuilder<A: Allocator> {
     data: Vec<u8, AlignedAllocator<A>>,
+    // Whether operands are written unaligned, without padding.
+    packed: bool,
+    // Whether the builder is scratch space, reused across expressions: its
+    // data then grows amortized, and is copied out into an allocation of the
+    // exact size on finishing each expression (see `copy_out()`). Otherwise,
+    // the data grows exactly, as it becomes the expression's own.
+    scratch: bool,
 }
 
 impl<A: Allocator> ExpressionBuilder<A> {
-    fn new(alloc: A) -> Self {
+    pub(crate) fn new(alloc: A, packed: bool) -> Self {
         let aligned_alloc = AlignedAllocator(alloc);
         Self {
             data: Vec::new_in(aligned_alloc),
+            packed,
+            scratch: false,
+        }
+    }
+
+    pub(crate) fn new_scratch(alloc: A) -> Self {
+        Self {
+            scratch: true,
+            ..Self::new(alloc, false)
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        if self.scratch {
+            self.data.try_reserve(additional)
+        } else {
+            self.data.try_reserve_exact(additional)
         }
     }
 
-    fn finalize(self) -> Expression<A> {
+    // Copies the expression built so far out of scratch space into one of its
+    // own, clearing the builder for reuse.
+    fn copy_out<B: Allocator>(&mut self, alloc: B) -> Result<Expression<B>, TryReserveError> {
+        debug_assert!(self.scratch);
+        let mut builder = ExpressionBuilder::new(alloc, self.packed);
+        builder.data.try_reserve_exact(self.data.len())?;
+        builder.data.extend_from_slice(&self.data);
+        self.data.clear();
+        Ok(builder.finalize())
+    }
+
+    pub(crate) fn finalize(self) -> Expression<A> {
+        debug_assert!(!self.scratch);
         let (ptr, len, _, alloc) = self.data.into_raw_parts_with_alloc();
         let ptr: *mut [u8] = ptr::slice_from_raw_parts_mut(ptr, len);
         // Safety: The allocation is truly being managed by the wrapped
         // allocator A.
-        unsafe { Expression::new(Box::from_raw_in(ptr, alloc.0)) }
+        let bytes = unsafe { Box::from_raw_in(ptr, alloc.0) };
+        if self.packed {
+            Expression::new_packed(bytes)
+        } else {
+            Expression::new(bytes)
+        }
     }
 
     fn write<T: Transcodable<A>>(&mut self, value: T) -> Result<(), TryReserveError> {
         value.write_to(self)
     }
+
+    // Re-encodes an instruction read back from an expression, which is the
+    // inverse of `Instructions::read_instruction()`.
+    pub(crate) fn write_instruction(
+        &mut self,
+        instr: &Instruction<'_>,
+    ) -> Result<(), TryReserveError> {
+        match instr.op {
+            Operator::Basic(op) => self.write(op)?,
+            Operator::Bulk(op) => {
+                self.write(Opcode::BulkPrefix)?;
+                self.write(op)?;
+            }
+            Operator::Atomic(op) => {
+                self.write(Opcode::AtomicPrefix)?;
+                self.write(op)?;
+            }
+        }
+        match instr.operands {
+            Operands::None => Ok(()),
+            Operands::BlockType(block_type) => self.write(block_type),
+            Operands::BrTable { labels, default } => {
+                self.write(labels.len() as u32)?;
+                for label in labels.iter() {
+                    self.write(label)?;
+                }
+                self.write(default)
+            }
+            Operands::CallIndirect(operands) => self.write(operands),
+            Operands::F32(value) => self.write(value),
+            Operands::F64(value) => self.write(value),
+            Operands::I32(value) => self.write(value),
+            Operands::I64(value) => self.write(value),
+            Operands::Idx(idx) => self.write(idx),
+            Operands::MemArg(memarg) => self.write(memarg),
+            Operands::RefType(ty) => self.write(ty),
+            Operands::SelectT(types) => {
+                self.write(types.len() as u32)?;
+                for ty in types.iter() {
+                    self.write(ty)?;
+                }
+                Ok(())
+            }
+            Operands::TableCopy(operands) => self.write(operands),
+            Operands::TableInit(operands) => self.write(operands),
+            #[cfg(feature = "experimental")]
+            Operands::ContBind(operands) => self.write(operands),
+            #[cfg(feature = "experimental")]
+            Operands::Resume { ty, handlers } => {
+                self.write(ty)?;
+                self.write_handlers(handlers)
+            }
+            #[cfg(feature = "experimental")]
+            Operands::ResumeThrow { ty, tag, handlers } => {
+                self.write(SwitchOperands { ty, tag })?;
+                self.write_handlers(handlers)
+            }
+            #[cfg(feature = "experimental")]
+            Operands::Switch(operands) => self.write(operands),
+        }
+    }
+
+    #[cfg(feature = "experimental")]
+    fn write_handlers(&mut self, handlers: Handlers<'_>) -> Result<(), TryReserveError> {
+        self.write(handlers.len() as u32)?;
+        for handler in handlers.iter() {
+            let (kind, tag, label) = match handler {
+                Handler::OnLabel { tag, label } => (0, tag, label),
+                Handler::OnSwitch(tag) => (1, tag, LabelIdx::new(0)),
+            };
+            self.write(EncodedHandler { kind, tag, label })?;
+        }
+        Ok(())
+    }
 }
 
 pub(super) fn transcode_expression<A: Allocator, Storage: Stream>(
     decoder: &mut Decoder<Storage>,
-    context: &mut ContextStack,
+    context: &mut ContextStack<A>,
     alloc: &A,
 ) -> Result<Expression<A>, Error<Storage::Error>> {
-    let mut builder = ExpressionBuilder::new(alloc.clone());
-    macro_rules! transcode {
-        ($operand_type:ty) => {
-            <$operand_type>::transcode(decoder, context, &mut builder)
-        };
+    let packed = decoder.options.packed_expressions;
+    if let Some(mut scratch) = decoder.expr_scratch.take() {
+        scratch.packed = packed;
+        scratch.data.clear();
+        let expr = transcode_into(decoder, context, &mut scratch)
+            .and_then(|()| Ok(scratch.copy_out(alloc.clone())?));
+        decoder.expr_scratch = Some(scratch);
+        return expr;
     }
+    let mut builder = ExpressionBuilder::new(alloc.clone(), packed);
+    transcode_into(decoder, context, &mut builder)?;
+    Ok(builder.finalize())
+}
+
+fn transcode_into<C: Allocator, A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<C>,
+    builder: &mut ExpressionBuilder<A>,
+) -> Result<(), Error<Storage::Error>> {
     let mut depth = 0u32;
     loop {
         let op: Opcode = decoder.read_bounded(context)?;
         builder.write(op)?;
+        transcode_operands(decoder, context, builder, op.operand_type())?;
 
         match op {
-            Opcode::Block | Opcode::If | Opcode::Loop => {
-                transcode!(BlockType)?;
-                depth += 1;
-            }
-            Opcode::Br
-            | Opcode::BrIf
-            | Opcode::Call
-            | Opcode::GlobalGet
-            | Opcode::GlobalSet
-            | Opcode::LocalGet
-            | Opcode::LocalSet
-            | Opcode::LocalTee
-            | Opcode::RefFunc
-            | Opcode::TableGet
-            | Opcode::TableSet => transcode!(u32)?,
-            Opcode::BrTable => transcode!(BrTableOperands::<A>)?,
-            Opcode::BulkPrefix => transcode_bulk_op(decoder, context, &mut builder)?,
-            Opcode::CallIndirect => transcode!(CallIndirectOperands)?,
-            Opcode::End => {
-                if depth == 0 {
-                    break;
-                }
-                depth -= 1;
-            }
-            Opcode::F32Const => transcode!(f32)?,
-            Opcode::F32Load
-            | Opcode::F32Store
-            | Opcode::F64Load
-            | Opcode::F64Store
-            | Opcode::I32Load
-            | Opcode::I32Load8S
-            | Opcode::I32Load8U
-            | Opcode::I32Load16S
-            | Opcode::I32Load16U
-            | Opcode::I32Store
-            | Opcode::I32Store8
-            | Opcode::I32Store16
-            | Opcode::I64Load
-            | Opcode::I64Load8S
-            | Opcode::I64Load8U
-            | Opcode::I64Load16S
-            | Opcode::I64Load16U
-            | Opcode::I64Load32S
-            | Opcode::I64Load32U
-            | Opcode::I64Store
-            | Opcode::I64Store8
-            | Opcode::I64Store16
-            | Opcode::I64Store32 => transcode!(MemArg)?,
-            Opcode::F64Const => transcode!(f64)?,
-            Opcode::I32Const => transcode!(i32)?,
-            Opcode::I64Const => transcode!(i64)?,
-            Opcode::MemoryGrow | Opcode::MemorySize => {
-                decoder.read_zero_byte(context)?;
-            }
-            Opcode::RefNull => transcode!(RefType)?,
-            Opcode::SelectT => transcode!(SelectTOperands::<A>)?,
-            Opcode::VectorPrefix => transcode_vector_op(decoder, context, &mut builder)?,
-            _ => {} // No operands
+            Opcode::Block | Opcode::If | Opcode::Loop => depth += 1,
+            Opcode::End if depth == 0 => break,
+            Opcode::End => depth -= 1,
+            _ => {}
         }
     }
-
-    Ok(builder.finalize())
+    Ok(())
 }
 
-fn transcode_bulk_op<A: Allocator, Storage: Stream>(
+fn transcode_operands<C: Allocator, A: Allocator, Storage: Stream>(
     decoder: &mut Decoder<Storage>,
-    context: &mut ContextStack,
+    context: &mut ContextStack<C>,
     builder: &mut ExpressionBuilder<A>,
+    operand_type: OperandType,
 ) -> Result<(), Error<Storage::Error>> {
-    let bulk_op: BulkOpcode = decoder.read_bounded(context)?;
-    builder.write(bulk_op)?;
-
     macro_rules! transcode {
         ($operand_type:ty) => {
             <$operand_type>::transcode(decoder, context, builder)
         };
     }
-    match bulk_op {
-        BulkOpcode::DataDrop
-        | BulkOpcode::ElemDrop
-        | BulkOpcode::TableFill
-        | BulkOpcode::TableGrow
-        | BulkOpcode::TableSize => transcode!(u32)?,
-        BulkOpcode::MemoryCopy => {
-            decoder.read_zero_byte(context)?;
-            decoder.read_zero_byte(context)?;
+    match operand_type {
+        OperandType::None => {}
+        OperandType::AtomicPrefix => {
+            let atomic_op: AtomicOpcode = decoder.read_bounded(context)?;
+            builder.write(atomic_op)?;
+            transcode_operands(decoder, context, builder, atomic_op.operand_type())?;
         }
-        BulkOpcode::MemoryFill => {
-            decoder.read_zero_byte(context)?;
+        OperandType::BlockType => transcode!(BlockType)?,
+        OperandType::BrTable => transcode!(BrTableOperands::<A>)?,
+        OperandType::BulkPrefix => {
+            let bulk_op: BulkOpcode = decoder.read_bounded(context)?;
+            builder.write(bulk_op)?;
+            transcode_operands(decoder, context, builder, bulk_op.operand_type())?;
         }
-        BulkOpcode::MemoryInit => {
+        OperandType::CallIndirect => transcode!(CallIndirectOperands)?,
+        OperandType::F32 => transcode!(f32)?,
+        OperandType::F64 => transcode!(f64)?,
+        OperandType::I32 => transcode!(i32)?,
+        OperandType::I64 => transcode!(i64)?,
+        OperandType::Idx => transcode!(u32)?,
+        OperandType::MemArg => transcode!(MemArg)?,
+        OperandType::MemoryInit => {
             transcode!(u32)?;
             decoder.read_zero_byte(context)?;
         }
-        BulkOpcode::TableCopy => transcode!(TableCopyOperands)?,
-        BulkOpcode::TableInit => transcode!(TableInitOperands)?,
-        _ => {} // No operands
+        OperandType::RefType => transcode!(RefType)?,
+        OperandType::SelectT => transcode!(SelectTOperands::<A>)?,
+        OperandType::TableCopy => transcode!(TableCopyOperands)?,
+        OperandType::TableInit => transcode!(TableInitOperands)?,
+        OperandType::VectorPrefix => transcode_vector_op(decoder, context, builder)?,
+        #[cfg(feature = "experimental")]
+        OperandType::ContBind => transcode!(ContBindOperands)?,
+        #[cfg(feature = "experimental")]
+        OperandType::Resume => {
+            transcode!(u32)?;
+            transcode!(Vec::<EncodedHandler, A>)?;
+        }
+        #[cfg(feature = "experimental")]
+        OperandType::ResumeThrow => {
+            transcode!(SwitchOperands)?;
+            transcode!(Vec::<EncodedHandler, A>)?;
+        }
+        #[cfg(feature = "experimental")]
+        OperandType::Switch => transcode!(SwitchOperands)?,
+        OperandType::ZeroBytes(count) => {
+            for _ in 0..count {
+                decoder.read_zero_byte(context)?;
+            }
+        }
     }
     Ok(())
 }
 
-fn transcode_vector_op<A: Allocator, Storage: Stream>(
-    _decoder: &mut Decoder<Storage>,
-    _context: &mut ContextStack,
+fn transcode_vector_op<C: Allocator, A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<C>,
     _builder: &mut ExpressionBuilder<A>,
 ) -> Result<(), Error<Storage::Error>> {
-    todo!("vector instructions");
+    let op: u32 = decoder.read_bounded(context)?;
+    Err(Error::UnsupportedVectorOpcode(op))
 }
@@ -29,6 +29,11 @@ impl Leb128 for i32 {
     const IS_SIGNED: bool = true;
 }
 
+impl Leb128 for u64 {
+    const MAX_BITS: u32 = 64;
+    const IS_SIGNED: bool = false;
+}
+
 impl Leb128 for i64 {
     const MAX_BITS: u32 = 64;
     const IS_SIGNED: bool = true;
@@ -43,7 +48,28 @@ pub(super) trait Error {
 //
 // Implements LEB128 decoding per WASM specification. Validates encoding
 // constraints including maximum length and proper unused bit handling.
-pub(super) fn read<T, F, E>(mut read_byte: F) -> Result<T, E>
+pub(super) fn read<T, F, E>(read_byte: F) -> Result<T, E>
+where
+    T: Leb128,
+    F: FnMut() -> Result<u8, E>,
+    E: Error,
+{
+    read_bits(T::MAX_BITS, read_byte)
+}
+
+// Read a signed 33-bit LEB128-encoded value (an s33, as used to encode block
+// types), sign-extended to an i64.
+pub(super) fn read_s33<F, E>(read_byte: F) -> Result<i64, E>
+where
+    F: FnMut() -> Result<u8, E>,
+    E: Error,
+{
+    read_bits::<i64, _, _>(33, read_byte)
+}
+
+// Read a LEB128-encoded value of a given bit width, no greater than that of
+// the type it is read into.
+fn read_bits<T, F, E>(max_bits: u32, mut read_byte: F) -> Result<T, E>
 where
     T: Leb128,
     F: FnMut() -> Result<u8, E>,
@@ -53,12 +79,14 @@ where
     const LAST_CHUNK_MASK: u8 = 0x80;
     const SIGN_EXTEND_MASK: u8 = 0x40;
 
+    debug_assert!(max_bits <= T::MAX_BITS);
+
     let mut result = T::from(0);
     let mut shift = 0;
     let mut byte;
 
     loop {
-        if shift >= T::MAX_BITS {
+        if shift >= max_bits {
             return Err(E::invalid_leb128());
         }
 
@@ -66,8 +94,8 @@ where
         let content = byte & CONTENT_MASK;
 
         // Validate that the final byte doesn't overflow the remaining bits.
-        if T::MAX_BITS - shift < 7 {
-            let remaining_bits = T::MAX_BITS - shift;
+        if max_bits - shift < 7 {
+            let remaining_bits = max_bits - shift;
             let valid = if T::IS_SIGNED {
                 // For signed types, the unused bits must be consistent with the
                 // sign bit.
@@ -134,10 +162,18 @@ mod tests {
         read::<i32, _, _>(byte_reader(bytes))
     }
 
+    fn read_u64(bytes: &[u8]) -> Result<u64, TestError> {
+        read::<u64, _, _>(byte_reader(bytes))
+    }
+
     fn read_i64(bytes: &[u8]) -> Result<i64, TestError> {
         read::<i64, _, _>(byte_reader(bytes))
     }
 
+    fn read_s33(bytes: &[u8]) -> Result<i64, TestError> {
+        super::read_s33(byte_reader(bytes))
+    }
+
     #[test]
     fn test_u32_basic_values() {
         // Single byte values.
@@ -330,6 +366,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_u64_basic_values() {
+        // Single byte values.
+        assert_eq!(read_u64(&[0x00]), Ok(0));
+        assert_eq!(read_u64(&[0x7f]), Ok(127));
+
+        // Multi-byte values.
+        assert_eq!(read_u64(&[0x80, 0x01]), Ok(0x80));
+        assert_eq!(read_u64(&[0xff, 0xff, 0xff, 0xff, 0x1f]), Ok(0x1_ffff_ffff));
+        assert_eq!(
+            read_u64(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]),
+            Ok(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_u64_non_minimal_valid() {
+        // Non-minimal but valid encodings (padded with leading zeros).
+        assert_eq!(read_u64(&[0x80, 0x00]), Ok(0x0));
+        assert_eq!(
+            read_u64(&[0x82, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00]),
+            Ok(0x2)
+        );
+    }
+
+    #[test]
+    fn test_u64_integer_representation_too_long() {
+        // More than 10 bytes for u64.
+        assert_eq!(
+            read_u64(&[
+                0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00
+            ]),
+            Err(TestError::InvalidLeb128)
+        );
+    }
+
+    #[test]
+    fn test_u64_integer_too_large() {
+        // Unused bits set.
+        assert_eq!(
+            read_u64(&[0x82, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7e]),
+            Err(TestError::InvalidLeb128)
+        );
+
+        // Single unused bit set.
+        assert_eq!(
+            read_u64(&[0x82, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02]),
+            Err(TestError::InvalidLeb128)
+        );
+    }
+
+    #[test]
+    fn test_s33_values() {
+        // Single byte values, as used for value types and the empty block
+        // type.
+        assert_eq!(read_s33(&[0x00]), Ok(0));
+        assert_eq!(read_s33(&[0x40]), Ok(-0x40));
+        assert_eq!(read_s33(&[0x7f]), Ok(-1));
+
+        // Type indices beyond the range of an i32.
+        assert_eq!(
+            read_s33(&[0xff, 0xff, 0xff, 0xff, 0x0f]),
+            Ok(i64::from(u32::MAX))
+        );
+
+        // Extreme values.
+        assert_eq!(
+            read_s33(&[0x80, 0x80, 0x80, 0x80, 0x70]),
+            Ok(-0x1_0000_0000)
+        );
+    }
+
+    #[test]
+    fn test_s33_invalid() {
+        // More than 5 bytes.
+        assert_eq!(
+            read_s33(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]),
+            Err(TestError::InvalidLeb128)
+        );
+
+        // Unused bits not properly set for 0.
+        assert_eq!(
+            read_s33(&[0x80, 0x80, 0x80, 0x80, 0x20]),
+            Err(TestError::InvalidLeb128)
+        );
+
+        // Unused bits not properly set for -1.
+        assert_eq!(
+            read_s33(&[0xff, 0xff, 0xff, 0xff, 0x5f]),
+            Err(TestError::InvalidLeb128)
+        );
+    }
+
     #[test]
     fn test_incomplete_encoding() {
         // Incomplete encodings (missing final byte)
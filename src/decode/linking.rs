@@ -0,0 +1,682 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! A visitor for the custom sections of relocatable modules (i.e., object
+//! files), as emitted by LLVM and consumed by static linkers like wasm-ld.
+//!
+//! See <https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md>.
+
+use num_enum::TryFromPrimitive;
+
+use crate::Allocator;
+use crate::core_compat::vec::Vec;
+use crate::storage::{MemoryEof, Stream};
+use crate::types::{CustomSection, Name, SectionId};
+
+use super::custom::parse_payload;
+use super::{
+    BoundedDecodable, ContextId, ContextStack, Contextual, CustomSectionPosition,
+    CustomSectionVisitor, Decodable, Decoder, Error, ErrorWithContext,
+};
+
+// The only version of the linking metadata that is understood.
+const LINKING_VERSION: u32 = 2;
+
+// The IDs of the understood subsections of the `linking` section.
+const WASM_SEGMENT_INFO: u8 = 5;
+const WASM_INIT_FUNCS: u8 = 6;
+const WASM_SYMBOL_TABLE: u8 = 8;
+
+/// The flags of a symbol, as a bitset of the associated constants.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SymbolFlags(
+    /// The raw bits of the flags.
+    pub u32,
+);
+
+impl SymbolFlags {
+    /// The symbol may be overridden by a strong definition of the same name.
+    pub const BINDING_WEAK: Self = Self(0x1);
+    /// The symbol is local to its object file.
+    pub const BINDING_LOCAL: Self = Self(0x2);
+    /// The symbol is not to be exported from a linked shared library.
+    pub const VISIBILITY_HIDDEN: Self = Self(0x4);
+    /// The symbol is not defined by the object file.
+    pub const UNDEFINED: Self = Self(0x10);
+    /// The symbol is to be exported from the linked module.
+    pub const EXPORTED: Self = Self(0x20);
+    /// The symbol has a name of its own, even though undefined.
+    pub const EXPLICIT_NAME: Self = Self(0x40);
+    /// The symbol is not to be stripped by the linker.
+    pub const NO_STRIP: Self = Self(0x80);
+    /// The symbol is thread-local.
+    pub const TLS: Self = Self(0x100);
+    /// The symbol's address is absolute, rather than relative to a segment.
+    pub const ABSOLUTE: Self = Self(0x200);
+
+    /// Whether all of the given flags are set.
+    pub fn contains(self, flags: Self) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+}
+
+/// The location of a defined data symbol.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DataSymbol {
+    /// The index of the data segment containing the symbol.
+    pub segment: u32,
+    /// The offset of the symbol within the segment.
+    pub offset: u64,
+    /// The size of the symbol in bytes.
+    pub size: u64,
+}
+
+/// What a symbol refers to, by kind.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SymbolTarget {
+    /// A function, by index in the function index space.
+    Function(u32),
+    /// Data, by location, which is None if the symbol is undefined.
+    Data(Option<DataSymbol>),
+    /// A global, by index in the global index space.
+    Global(u32),
+    /// A section, by index among the sections of the module.
+    Section(u32),
+    /// A tag (of the exception-handling proposal), by index in the tag index
+    /// space.
+    Tag(u32),
+    /// A table, by index in the table index space.
+    Table(u32),
+}
+
+/// An entry of the symbol table.
+pub struct SymbolInfo<A: Allocator> {
+    /// The flags of the symbol.
+    pub flags: SymbolFlags,
+    /// What the symbol refers to.
+    pub target: SymbolTarget,
+    /// The name of the symbol, which is None for section symbols (named by
+    /// their sections) and for undefined symbols without
+    /// [`SymbolFlags::EXPLICIT_NAME`] (named by their imports).
+    pub name: Option<Name<A>>,
+}
+
+/// Linking information about a data segment.
+pub struct SegmentInfo<A: Allocator> {
+    /// The name of the segment (e.g., ".rodata.str").
+    pub name: Name<A>,
+    /// The alignment of the segment, as a power of two.
+    pub alignment: u32,
+    /// The segment's flags (e.g., 0x1 for null-terminated strings, or 0x2
+    /// for thread-local data).
+    pub flags: u32,
+}
+
+/// A function to be called on initialization of the linked module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InitFunc {
+    /// The priority of the call, with lower priorities called first.
+    pub priority: u32,
+    /// The index of the function's symbol.
+    pub symbol: u32,
+}
+
+/// The kind of a relocation, which determines the encoding of the value
+/// at its offset and the index space of the index it refers to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum RelocationKind {
+    /// A function index, as a 5-byte LEB128 (e.g., of a `call`).
+    FunctionIndexLeb = 0,
+    /// The table index of a function, as a 5-byte signed LEB128 (e.g., of
+    /// an `i32.const` taking a function's address).
+    TableIndexSleb = 1,
+    /// The table index of a function, as a 4-byte integer (e.g., in data).
+    TableIndexI32 = 2,
+    /// A memory address, as a 5-byte LEB128 (e.g., of a load's offset).
+    MemoryAddrLeb = 3,
+    /// A memory address, as a 5-byte signed LEB128 (e.g., of an
+    /// `i32.const`).
+    MemoryAddrSleb = 4,
+    /// A memory address, as a 4-byte integer (e.g., in data).
+    MemoryAddrI32 = 5,
+    /// A type index, as a 5-byte LEB128 (e.g., of a `call_indirect`).
+    TypeIndexLeb = 6,
+    /// A global index, as a 5-byte LEB128 (e.g., of a `global.get`).
+    GlobalIndexLeb = 7,
+    /// An offset into the code section, as a 4-byte integer (e.g., in debug
+    /// info).
+    FunctionOffsetI32 = 8,
+    /// An offset into a section, as a 4-byte integer (e.g., in debug info).
+    SectionOffsetI32 = 9,
+    /// A tag index, as a 5-byte LEB128 (e.g., of a `throw`).
+    TagIndexLeb = 10,
+    /// A memory address relative to `__memory_base`, as a 5-byte signed
+    /// LEB128.
+    MemoryAddrRelSleb = 11,
+    /// The table index of a function relative to `__table_base`, as a 5-byte
+    /// signed LEB128.
+    TableIndexRelSleb = 12,
+    /// A global index, as a 4-byte integer.
+    GlobalIndexI32 = 13,
+    /// The 64-bit counterpart of [`Self::MemoryAddrLeb`], as a 10-byte
+    /// LEB128.
+    MemoryAddrLeb64 = 14,
+    /// The 64-bit counterpart of [`Self::MemoryAddrSleb`], as a 10-byte
+    /// signed LEB128.
+    MemoryAddrSleb64 = 15,
+    /// The 64-bit counterpart of [`Self::MemoryAddrI32`], as an 8-byte
+    /// integer.
+    MemoryAddrI64 = 16,
+    /// The 64-bit counterpart of [`Self::MemoryAddrRelSleb`], as a 10-byte
+    /// signed LEB128.
+    MemoryAddrRelSleb64 = 17,
+    /// The 64-bit counterpart of [`Self::TableIndexSleb`], as a 10-byte
+    /// signed LEB128.
+    TableIndexSleb64 = 18,
+    /// The 64-bit counterpart of [`Self::TableIndexI32`], as an 8-byte
+    /// integer.
+    TableIndexI64 = 19,
+    /// A table index (rather than an index into a table), as a 5-byte
+    /// LEB128.
+    TableNumberLeb = 20,
+    /// A memory address relative to `__tls_base`, as a 5-byte signed LEB128.
+    MemoryAddrTlsSleb = 21,
+    /// The 64-bit counterpart of [`Self::FunctionOffsetI32`], as an 8-byte
+    /// integer.
+    FunctionOffsetI64 = 22,
+    /// A memory address relative to the address of the relocated value, as a
+    /// 4-byte integer.
+    MemoryAddrLocrelI32 = 23,
+    /// The 64-bit counterpart of [`Self::TableIndexRelSleb`], as a 10-byte
+    /// signed LEB128.
+    TableIndexRelSleb64 = 24,
+    /// The 64-bit counterpart of [`Self::MemoryAddrTlsSleb`], as a 10-byte
+    /// signed LEB128.
+    MemoryAddrTlsSleb64 = 25,
+    /// A function index, as a 4-byte integer (e.g., in debug info).
+    FunctionIndexI32 = 26,
+}
+
+impl RelocationKind {
+    /// Whether relocations of this kind have an addend.
+    pub fn has_addend(self) -> bool {
+        matches!(
+            self,
+            Self::MemoryAddrLeb
+                | Self::MemoryAddrSleb
+                | Self::MemoryAddrI32
+                | Self::FunctionOffsetI32
+                | Self::SectionOffsetI32
+                | Self::MemoryAddrRelSleb
+                | Self::MemoryAddrLeb64
+                | Self::MemoryAddrSleb64
+                | Self::MemoryAddrI64
+                | Self::MemoryAddrRelSleb64
+                | Self::MemoryAddrTlsSleb
+                | Self::FunctionOffsetI64
+                | Self::MemoryAddrLocrelI32
+                | Self::MemoryAddrTlsSleb64
+        )
+    }
+
+    // Whether the addend of a relocation of this kind is encoded as 64-bit.
+    fn has_wide_addend(self) -> bool {
+        matches!(
+            self,
+            Self::MemoryAddrLeb64
+                | Self::MemoryAddrSleb64
+                | Self::MemoryAddrI64
+                | Self::MemoryAddrRelSleb64
+                | Self::FunctionOffsetI64
+                | Self::MemoryAddrTlsSleb64
+        )
+    }
+}
+
+/// An entry of a relocation section.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Relocation {
+    /// The kind of the relocation.
+    pub kind: RelocationKind,
+    /// The offset of the value to relocate within the target section's
+    /// contents.
+    pub offset: u32,
+    /// The index referred to, which is that of a symbol or (for
+    /// [`RelocationKind::TypeIndexLeb`]) of a type.
+    pub index: u32,
+    /// The addend to apply, which is zero for kinds without one.
+    pub addend: i64,
+}
+
+/// A `reloc.*` section, giving the relocations of another section.
+pub struct RelocationSection<A: Allocator> {
+    /// The name of the section (e.g., "reloc.CODE").
+    pub name: Name<A>,
+    /// The index of the section to relocate among the sections of the module.
+    pub section: u32,
+    /// The relocations, in the order given.
+    pub entries: Vec<Relocation, A>,
+}
+
+/// The metadata of a relocatable module, from its `linking` and `reloc.*`
+/// sections.
+///
+/// Only the symbol table, segment info, and init funcs subsections of the
+/// `linking` section are parsed; others (e.g., COMDAT info) are skipped.
+pub struct ObjectMetadata<A: Allocator> {
+    /// The version of the linking metadata, which is always 2.
+    pub version: u32,
+    /// The symbol table, indexed by symbol index.
+    pub symbols: Vec<SymbolInfo<A>, A>,
+    /// The linking information of the data segments, indexed as in the data
+    /// section.
+    pub segments: Vec<SegmentInfo<A>, A>,
+    /// The functions to call on initialization, in the order given.
+    pub init_funcs: Vec<InitFunc, A>,
+    /// The relocation sections, in module order.
+    pub relocations: Vec<RelocationSection<A>, A>,
+}
+
+/// A visitor that parses the `linking` and `reloc.*` custom sections of a
+/// relocatable module.
+pub struct ObjectMetadataVisitor<A: Allocator> {
+    alloc: A,
+    metadata: Option<ObjectMetadata<A>>,
+    relocations: Vec<RelocationSection<A>, A>,
+    error: Option<ErrorWithContext<MemoryEof, A>>,
+}
+
+impl<A: Allocator> ObjectMetadataVisitor<A> {
+    /// The name of the custom section holding the linking metadata.
+    pub const LINKING_SECTION_NAME: &str = "linking";
+    /// The prefix of the names of the custom sections holding relocations.
+    pub const RELOC_SECTION_PREFIX: &str = "reloc.";
+
+    /// Creates a visitor, allocating the parsed metadata with the given
+    /// allocator.
+    pub fn new(alloc: A) -> Self {
+        Self {
+            relocations: Vec::new_in(alloc.clone()),
+            alloc,
+            metadata: None,
+            error: None,
+        }
+    }
+
+    /// Returns the parsed metadata, or `None` if there was no `linking`
+    /// section (i.e., the module is not relocatable). The first error among
+    /// the sections is reported, with offsets relative to the start of that
+    /// section's payload.
+    pub fn finish(self) -> Option<Result<ObjectMetadata<A>, ErrorWithContext<MemoryEof, A>>> {
+        if let Some(error) = self.error {
+            return Some(Err(error));
+        }
+        let mut metadata = self.metadata?;
+        metadata.relocations = self.relocations;
+        Some(Ok(metadata))
+    }
+
+    fn parse(&mut self, custom: CustomSection<A>) -> Result<(), ErrorWithContext<MemoryEof, A>> {
+        let alloc = &self.alloc;
+        if &**custom.name == Self::LINKING_SECTION_NAME {
+            let len = custom.bytes.len();
+            self.metadata = Some(parse_payload(&custom, |decoder, context| {
+                decode_linking(decoder, context, alloc, len)
+            })?);
+            return Ok(());
+        }
+
+        let (section, entries) = parse_payload(&custom, |decoder, context| {
+            let section: u32 = decoder.read_bounded(context)?;
+            let entries: Vec<Relocation, A> = Decodable::decode(decoder, context, alloc)?;
+            Ok((section, entries))
+        })?;
+        if self.relocations.try_reserve(1).is_err() {
+            return Err(ErrorWithContext {
+                error: Error::AllocError,
+                context: ContextStack::default(),
+            });
+        }
+        self.relocations.push(RelocationSection {
+            name: custom.name,
+            section,
+            entries,
+        });
+        Ok(())
+    }
+}
+
+impl<A: Allocator> CustomSectionVisitor<A> for ObjectMetadataVisitor<A> {
+    fn should_visit(&self, name: &str) -> bool {
+        name == Self::LINKING_SECTION_NAME || name.starts_with(Self::RELOC_SECTION_PREFIX)
+    }
+
+    fn visit(&mut self, custom: CustomSection<A>, _: CustomSectionPosition) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(error) = self.parse(custom) {
+            self.error = Some(error);
+        }
+    }
+}
+
+// Decodes the `linking` section of a given length, leaving relocations to be
+// filled in from the `reloc.*` sections.
+fn decode_linking<Storage: Stream, A: Allocator>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    alloc: &A,
+    len: usize,
+) -> Result<ObjectMetadata<A>, Error<Storage::Error>> {
+    let version: u32 = decoder.read_bounded(context)?;
+    if version != LINKING_VERSION {
+        return Err(Error::UnknownVersion(version));
+    }
+    let mut metadata = ObjectMetadata {
+        version,
+        symbols: Vec::new_in(alloc.clone()),
+        segments: Vec::new_in(alloc.clone()),
+        init_funcs: Vec::new_in(alloc.clone()),
+        relocations: Vec::new_in(alloc.clone()),
+    };
+
+    let mut ordinal = 0;
+    while decoder.offset() < len {
+        let id = ContextId::LinkingSubsection;
+        decoder.with_context_nth(context, id, Some(ordinal), |decoder, context| {
+            let id = decoder.read_byte_raw()?;
+            let size: u32 = decoder.read_bounded(context)?;
+            let start = decoder.offset();
+            match id {
+                WASM_SEGMENT_INFO => {
+                    metadata.segments = Decodable::decode(decoder, context, alloc)?;
+                }
+                WASM_INIT_FUNCS => {
+                    metadata.init_funcs = Decodable::decode(decoder, context, alloc)?;
+                }
+                WASM_SYMBOL_TABLE => {
+                    metadata.symbols = Decodable::decode(decoder, context, alloc)?;
+                }
+                _ => decoder.skip_bytes(context, size as usize)?,
+            }
+            let actual = decoder.offset() - start;
+            if actual != size as usize {
+                return Err(Error::InvalidSectionLength {
+                    id: SectionId::Custom,
+                    expected: size,
+                    actual: u32::try_from(actual).unwrap_or(u32::MAX),
+                });
+            }
+            Ok(())
+        })?;
+        ordinal += 1;
+    }
+    Ok(metadata)
+}
+
+// The kind of a symbol, as encoded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
+#[repr(u8)]
+enum SymbolKind {
+    Function = 0,
+    Data = 1,
+    Global = 2,
+    Section = 3,
+    Tag = 4,
+    Table = 5,
+}
+
+impl Contextual for SymbolKind {
+    const ID: ContextId = ContextId::SymbolKind;
+}
+
+impl BoundedDecodable for SymbolKind {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        _: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let byte = decoder.read_byte_raw()?;
+        Self::try_from(byte).map_err(|_| Error::InvalidToken(byte))
+    }
+}
+
+impl<A: Allocator> Contextual for SymbolInfo<A> {
+    const ID: ContextId = ContextId::SymbolInfo;
+}
+
+impl<A: Allocator> Decodable<A> for SymbolInfo<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let kind: SymbolKind = decoder.read_bounded(context)?;
+        let flags = SymbolFlags(decoder.read_bounded(context)?);
+        let defined = !flags.contains(SymbolFlags::UNDEFINED);
+        let target: fn(u32) -> SymbolTarget = match kind {
+            SymbolKind::Function => SymbolTarget::Function,
+            SymbolKind::Global => SymbolTarget::Global,
+            SymbolKind::Tag => SymbolTarget::Tag,
+            SymbolKind::Table => SymbolTarget::Table,
+            SymbolKind::Data => {
+                let name = decoder.read(context, alloc)?;
+                let data = if defined {
+                    Some(DataSymbol {
+                        segment: decoder.read_bounded(context)?,
+                        offset: decoder.read_bounded(context)?,
+                        size: decoder.read_bounded(context)?,
+                    })
+                } else {
+                    None
+                };
+                return Ok(Self {
+                    flags,
+                    target: SymbolTarget::Data(data),
+                    name: Some(name),
+                });
+            }
+            SymbolKind::Section => {
+                return Ok(Self {
+                    flags,
+                    target: SymbolTarget::Section(decoder.read_bounded(context)?),
+                    name: None,
+                });
+            }
+        };
+
+        let index = decoder.read_bounded(context)?;
+        let name = if defined || flags.contains(SymbolFlags::EXPLICIT_NAME) {
+            Some(decoder.read(context, alloc)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            flags,
+            target: target(index),
+            name,
+        })
+    }
+}
+
+impl<A: Allocator> Contextual for SegmentInfo<A> {
+    const ID: ContextId = ContextId::SegmentInfo;
+}
+
+impl<A: Allocator> Decodable<A> for SegmentInfo<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            name: decoder.read(context, alloc)?,
+            alignment: decoder.read_bounded(context)?,
+            flags: decoder.read_bounded(context)?,
+        })
+    }
+}
+
+impl Contextual for InitFunc {
+    const ID: ContextId = ContextId::InitFunc;
+}
+
+impl BoundedDecodable for InitFunc {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            priority: decoder.read_bounded(context)?,
+            symbol: decoder.read_bounded(context)?,
+        })
+    }
+}
+
+impl Contextual for RelocationKind {
+    const ID: ContextId = ContextId::RelocationKind;
+}
+
+impl BoundedDecodable for RelocationKind {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        _: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let byte = decoder.read_byte_raw()?;
+        Self::try_from(byte).map_err(|_| Error::InvalidToken(byte))
+    }
+}
+
+impl Contextual for Relocation {
+    const ID: ContextId = ContextId::Relocation;
+}
+
+impl BoundedDecodable for Relocation {
+    fn decode<A: Allocator, Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+    ) -> Result<Self, Error<Storage::Error>> {
+        let kind: RelocationKind = decoder.read_bounded(context)?;
+        let offset = decoder.read_bounded(context)?;
+        let index = decoder.read_bounded(context)?;
+        let addend = if kind.has_wide_addend() {
+            decoder.read_bounded(context)?
+        } else if kind.has_addend() {
+            i64::from(decoder.read_bounded::<i32, _>(context)?)
+        } else {
+            0
+        };
+        Ok(Self {
+            kind,
+            offset,
+            index,
+            addend,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::scan::visit_custom_section_bytes;
+
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x00, 0x29, // customsec
+        0x07, b'l', b'i', b'n', b'k', b'i', b'n', b'g', // name
+        0x02, // version
+        0x08, 0x10, 0x03, // WASM_SYMBOL_TABLE: 3 symbols
+        0x00, 0x00, 0x00, 0x01, b'f', // function 0 "f"
+        0x01, 0x00, 0x01, b'd', 0x00, 0x04, 0x08, // data "d" in segment 0
+        0x00, 0x10, 0x01, // undefined function 1
+        0x05, 0x09, 0x01, // WASM_SEGMENT_INFO: 1 segment
+        0x05, b'.', b'd', b'a', b't', b'a', 0x02, 0x00, // ".data"
+        0x07, 0x01, 0x00, // WASM_COMDAT_INFO (skipped)
+        0x00, 0x14, // customsec
+        0x0a, b'r', b'e', b'l', b'o', b'c', b'.', b'C', b'O', b'D', b'E', // name
+        0x03, 0x02, // section 3: 2 relocations
+        0x00, 0x05, 0x00, // R_WASM_FUNCTION_INDEX_LEB
+        0x04, 0x0a, 0x01, 0x7c, // R_WASM_MEMORY_ADDR_SLEB, with addend -4
+    ];
+
+    #[test]
+    fn object_metadata() {
+        let mut visitor = ObjectMetadataVisitor::new(Global);
+        visit_custom_section_bytes(MODULE, &mut visitor, Global).unwrap();
+        let metadata = visitor.finish().unwrap().unwrap();
+
+        assert_eq!(metadata.version, 2);
+        let symbols: std::vec::Vec<_> = metadata
+            .symbols
+            .iter()
+            .map(|symbol| {
+                (
+                    symbol.flags,
+                    symbol.target,
+                    symbol.name.as_ref().map(|name| &***name),
+                )
+            })
+            .collect();
+        assert_eq!(
+            symbols,
+            [
+                (SymbolFlags(0), SymbolTarget::Function(0), Some("f")),
+                (
+                    SymbolFlags(0),
+                    SymbolTarget::Data(Some(DataSymbol {
+                        segment: 0,
+                        offset: 4,
+                        size: 8,
+                    })),
+                    Some("d"),
+                ),
+                (SymbolFlags::UNDEFINED, SymbolTarget::Function(1), None),
+            ]
+        );
+
+        assert_eq!(metadata.segments.len(), 1);
+        assert_eq!(&**metadata.segments[0].name, ".data");
+        assert_eq!(metadata.segments[0].alignment, 2);
+        assert!(metadata.init_funcs.is_empty());
+
+        assert_eq!(metadata.relocations.len(), 1);
+        let relocations = &metadata.relocations[0];
+        assert_eq!(&**relocations.name, "reloc.CODE");
+        assert_eq!(relocations.section, 3);
+        assert_eq!(
+            &relocations.entries[..],
+            [
+                Relocation {
+                    kind: RelocationKind::FunctionIndexLeb,
+                    offset: 5,
+                    index: 0,
+                    addend: 0,
+                },
+                Relocation {
+                    kind: RelocationKind::MemoryAddrSleb,
+                    offset: 10,
+                    index: 1,
+                    addend: -4,
+                },
+            ]
+        );
+
+        // Modules without a linking section are not relocatable.
+        let mut visitor = ObjectMetadataVisitor::new(Global);
+        visit_custom_section_bytes(&MODULE[..8], &mut visitor, Global).unwrap();
+        assert!(visitor.finish().is_none());
+
+        let mut unknown_version = MODULE.to_vec();
+        unknown_version[18] = 0x01;
+        let mut visitor = ObjectMetadataVisitor::new(Global);
+        visit_custom_section_bytes(&unknown_version, &mut visitor, Global).unwrap();
+        let error = visitor.finish().unwrap().err().unwrap();
+        assert_eq!(error.error, Error::UnknownVersion(1));
+    }
+}
@@ -5,33 +5,92 @@
 // https://opensource.org/licenses/MIT
 
 //! WebAssembly binary format parsing.
+//!
+//! # Stack usage
+//!
+//! Decoding does not recurse on its input: calls nest only as deeply as the
+//! fixed structure of the binary format (module, section, vector, item, and
+//! the expressions within items), and expressions are transcoded in a flat
+//! loop, however deeply their blocks nest. Native stack usage is thus bounded
+//! independently of the module decoded, which suits environments with small
+//! stacks: decoding and validating deeply nested code is tested on a thread
+//! given an 8 KiB stack in optimized builds (unoptimized builds use several
+//! times more). Platforms may round thread stacks up to a minimum size (16 KiB
+//! with glibc), in which case the test bounds usage by that instead.
 
+mod component;
+mod custom;
 mod decodable_impls;
+#[cfg(feature = "decode")]
 mod expr;
 mod leb128;
+mod linking;
+mod names;
+#[cfg(feature = "decode")]
+mod push;
+#[cfg(all(any(test, feature = "test-util"), feature = "decode"))]
+pub mod test;
 
+use component::COMPONENT_LAYER;
+#[cfg(feature = "decode")]
+use decodable_impls::decode_data_mode;
+#[cfg(feature = "decode")]
+pub(crate) use decodable_impls::into_boxed_str;
+#[cfg(feature = "decode")]
+pub(crate) use expr::ExpressionBuilder;
+#[cfg(feature = "decode")]
 use expr::transcode_expression;
 
+pub use component::{
+    Component, ComponentHeader, CoreModule, decode_component, decode_component_bytes,
+};
+pub use custom::{
+    CustomSectionResult, FeaturePrefix, Producer, ProducersField, ProducersVisitor, TargetFeature,
+    TargetFeaturesVisitor,
+};
+pub use linking::{
+    DataSymbol, InitFunc, ObjectMetadata, ObjectMetadataVisitor, Relocation, RelocationKind,
+    RelocationSection, SegmentInfo, SymbolFlags, SymbolInfo, SymbolTarget,
+};
+pub use names::NameSectionVisitor;
+#[cfg(feature = "decode")]
+pub use push::{PushDecoder, Status};
+
 use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use num_enum::TryFromPrimitive;
 
 use leb128::Leb128;
 
+use crate::Allocator;
+#[cfg(feature = "decode")]
+use crate::Module;
+use crate::core_compat::alloc::Global;
 use crate::core_compat::alloc::collections::TryReserveError;
 use crate::core_compat::boxed::Box;
 use crate::core_compat::vec::Vec;
+use crate::scan::SectionInfo;
 use crate::storage::Stream;
+#[cfg(feature = "decode")]
+use crate::trace::{trace_error, trace_event, trace_span};
+#[cfg(feature = "decode")]
 use crate::types::{
-    CodeSection, CustomSection, DataSection, ElementSection, ExportSection, FunctionSection,
-    GlobalSection, ImportSection, MemorySection, Name, SectionId, TableSection, TypeSection,
-    Version,
+    CodeSection, DataSection, DataSegment, ElementSection, ExportSection, ExternKind,
+    FunctionSection, GlobalSection, ImportSection, MemorySection, SkippedSection, StartSection,
+    TableSection, TypeSection,
 };
-use crate::{Allocator, Module};
+use crate::types::{CustomSection, DataMode, Name, SectionId, Version};
+
+// The default maximum parsing depth of this implementation (which is also
+// pretty much the lower bound implicitly suggested by the spec), as well as the
+// number of context frames that can be recorded without allocation.
+const DEFAULT_MAX_DEPTH: usize = 6;
 
-// The maximum parsing depth of this implementation (which is also pretty much
-// the lower bound implicitly suggested by the spec).
-const MAX_DEPTH: usize = 6;
+// The default maximum numbers of imports and exports, per the implementation
+// limits of the JS API.
+const DEFAULT_MAX_IMPORTS: usize = 100_000;
+const DEFAULT_MAX_EXPORTS: usize = 100_000;
 
 // We represent this as an enum with one value to leverage existing "decode this
 // u32 enum" machinery to check for a valid magic value.
@@ -47,6 +106,7 @@ enum Magic {
 enum ContextId {
     #[default]
     Invalid,
+    AtomicOpcode,
     BlockType,
     BrTableOperands,
     BulkOpcode,
@@ -56,11 +116,13 @@ enum ContextId {
     Data,
     DataIdx,
     DataSec,
+    #[cfg(feature = "decode")]
     DataToken,
     Elem,
     ElemIdx,
     ElemKind,
     ElemSec,
+    #[cfg(feature = "decode")]
     ElemToken,
     Export,
     ExportDesc,
@@ -69,6 +131,7 @@ enum ContextId {
     Expr,
     F32,
     F64,
+    FeaturePrefix,
     Func,
     FuncIdx,
     FuncType,
@@ -78,15 +141,19 @@ enum ContextId {
     GlobalIdx,
     GlobalSec,
     GlobalType,
+    #[cfg(feature = "experimental")]
+    Handler,
     I32,
     I64,
     Import,
     ImportDesc,
     ImportDescToken,
     ImportSec,
+    InitFunc,
     LabelIdx,
     Limits,
     LimitsMaxToken,
+    LinkingSubsection,
     LocalIdx,
     Locals,
     Magic,
@@ -96,25 +163,40 @@ enum ContextId {
     MemorySec,
     Mut,
     Name,
+    NameAssoc,
+    NameSubsection,
     Opcode,
+    Producer,
+    ProducersField,
     ReadingBytes,
     RefType,
+    Relocation,
+    RelocationKind,
     ResultType,
     SectionId,
+    SegmentInfo,
     SelectTOperands,
     SkippingBytes,
     StartSec,
+    SymbolInfo,
+    SymbolKind,
     TableIdx,
     TableSec,
     TableType,
+    #[cfg(feature = "experimental")]
+    TagIdx,
+    TargetFeature,
     TypeIdx,
     TypeSec,
     U32,
+    U64,
     ValType,
     VecByte,
     VecCode,
     VecExpr,
     VecFuncIdx,
+    #[cfg(feature = "experimental")]
+    VecHandler,
     VecLabelIdx,
     VecValType,
     Version,
@@ -124,6 +206,7 @@ impl From<ContextId> for &'static str {
     fn from(id: ContextId) -> Self {
         match id {
             ContextId::Invalid => unreachable!("invalid context somehow reached!?"),
+            ContextId::AtomicOpcode => "atomic opcode",
             ContextId::BrTableOperands => "br_table operands",
             ContextId::BulkOpcode => "bulk opcode",
             ContextId::Byte => "byte",
@@ -132,11 +215,13 @@ impl From<ContextId> for &'static str {
             ContextId::Data => "data",
             ContextId::DataIdx => "dataidx",
             ContextId::DataSec => "datasec",
+            #[cfg(feature = "decode")]
             ContextId::DataToken => "data token",
             ContextId::Elem => "elem",
             ContextId::ElemIdx => "elemidx",
             ContextId::ElemKind => "elemkind",
             ContextId::ElemSec => "elemsec",
+            #[cfg(feature = "decode")]
             ContextId::ElemToken => "elem token",
             ContextId::Func => "func",
             ContextId::Export => "export",
@@ -146,6 +231,7 @@ impl From<ContextId> for &'static str {
             ContextId::Expr => "expr",
             ContextId::F32 => "f32",
             ContextId::F64 => "f64",
+            ContextId::FeaturePrefix => "feature prefix",
             ContextId::FuncIdx => "funcidx",
             ContextId::FuncType => "functype",
             ContextId::FuncTypeToken => "functype token",
@@ -154,15 +240,19 @@ impl From<ContextId> for &'static str {
             ContextId::GlobalIdx => "globalidx",
             ContextId::GlobalSec => "globalsec",
             ContextId::GlobalType => "globaltype",
+            #[cfg(feature = "experimental")]
+            ContextId::Handler => "handler",
             ContextId::I32 => "i32",
             ContextId::I64 => "i64",
             ContextId::Import => "import",
             ContextId::ImportDesc => "importdesc",
             ContextId::ImportDescToken => "importdesc token",
             ContextId::ImportSec => "importsec",
+            ContextId::InitFunc => "init func",
             ContextId::LabelIdx => "labelidx",
             ContextId::Limits => "limits",
             ContextId::LimitsMaxToken => "limits max token",
+            ContextId::LinkingSubsection => "linking subsection",
             ContextId::LocalIdx => "localidx",
             ContextId::Locals => "locals",
             ContextId::Magic => "magic",
@@ -172,26 +262,41 @@ impl From<ContextId> for &'static str {
             ContextId::MemorySec => "memsec",
             ContextId::Mut => "mut",
             ContextId::Name => "name",
+            ContextId::NameAssoc => "nameassoc",
+            ContextId::NameSubsection => "name subsection",
             ContextId::Opcode => "opcode",
+            ContextId::Producer => "producer",
+            ContextId::ProducersField => "producers field",
             ContextId::ReadingBytes => "reading bytes",
             ContextId::RefType => "reftype",
+            ContextId::Relocation => "relocation",
+            ContextId::RelocationKind => "relocation kind",
             ContextId::ResultType => "resulttype",
             ContextId::SectionId => "section ID",
+            ContextId::SegmentInfo => "segment info",
             ContextId::SelectTOperands => "select_t operands",
             ContextId::SkippingBytes => "skipping bytes",
             ContextId::StartSec => "startsec",
+            ContextId::SymbolInfo => "syminfo",
+            ContextId::SymbolKind => "symbol kind",
             ContextId::TableIdx => "tableidx",
             ContextId::TableSec => "tablesec",
             ContextId::TableType => "tabletype",
+            #[cfg(feature = "experimental")]
+            ContextId::TagIdx => "tagidx",
+            ContextId::TargetFeature => "target feature",
             ContextId::TypeIdx => "typeidx",
             ContextId::TypeSec => "typesec",
             ContextId::U32 => "u32",
+            ContextId::U64 => "u64",
             ContextId::ValType => "valtype",
             ContextId::VecByte => "vec(byte)",
             ContextId::BlockType => "blocktype",
             ContextId::VecCode => "vec(code)",
             ContextId::VecExpr => "vec(expr)",
             ContextId::VecFuncIdx => "vec(funcidx)",
+            #[cfg(feature = "experimental")]
+            ContextId::VecHandler => "vec(handler)",
             ContextId::VecLabelIdx => "vec(labelidx)",
             ContextId::VecValType => "vec(valtype)",
             ContextId::Version => "version",
@@ -211,25 +316,253 @@ struct ContextFrame {
 
     // Byte offset in the stream where this context was entered.
     offset: usize,
+
+    // The index of the item within its enclosing vector, if any.
+    ordinal: Option<u32>,
+}
+
+/// A flag by which decoding can be cancelled from elsewhere, as on a timeout
+/// (see [`DecodeOptions::cancel`]).
+#[derive(Debug, Default)]
+pub struct CancelToken(AtomicBool);
+
+impl CancelToken {
+    /// Creates a token that is not cancelled.
+    pub const fn new() -> Self {
+        Self(AtomicBool::new(false))
+    }
+
+    /// Requests that decoding observing the token be cancelled.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Withdraws any request for cancellation, so that the token can be
+    /// reused.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Options controlling module decoding.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug)]
+pub struct DecodeOptions<'a> {
+    /// The maximum nesting depth of parsing, beyond which decoding fails with
+    /// [`Error::ExcessiveParsingDepth`]. Defaults to 6.
+    ///
+    /// This bounds the parsing context, which follows the fixed structure of
+    /// the binary format; it is not needed to bound stack usage, as decoding
+    /// does not recurse (see the [module documentation](crate::decode)).
+    pub max_depth: usize,
+    /// The maximum number of frames of parsing context recorded for error
+    /// reporting, beyond which the innermost frames are only counted.
+    /// Defaults to 6, which requires no allocation; frames beyond the sixth
+    /// are recorded on the heap (with the global allocator).
+    pub max_context_frames: usize,
+    /// Whether names that are not valid UTF-8 are decoded lossily, rather than
+    /// failing with [`Error::InvalidUtf8`]. Invalid sequences are replaced by
+    /// U+FFFD, with the original bytes preserved (see [`Name::raw_bytes`]).
+    /// Defaults to false.
+    pub lossy_names: bool,
+    /// The maximum byte length of a name, beyond which decoding fails with
+    /// [`Error::NameTooLong`]. Defaults to no limit, as neither the core
+    /// specification nor the implementation limits of the JS API bound the
+    /// length of names beyond the size of the module.
+    pub max_name_len: usize,
+    /// The maximum number of imports, beyond which decoding fails with
+    /// [`Error::TooManyImports`]. Defaults to 100000.
+    pub max_imports: usize,
+    /// The maximum number of exports, beyond which decoding fails with
+    /// [`Error::TooManyExports`]. Defaults to 100000.
+    pub max_exports: usize,
+    /// Whether expressions are packed, with operands stored unaligned rather
+    /// than padded out to their natural alignments. This reduces memory use
+    /// (by up to half, for constant-heavy code) at some cost to the speed of
+    /// reading them back. Defaults to false.
+    pub packed_expressions: bool,
+    /// Whether duplicate export names are rejected on decoding, failing with
+    /// [`Error::DuplicateExportName`], rather than only on validation. This
+    /// lets modules that are bound to fail validation be rejected before the
+    /// rest of them is decoded, at the cost of an allocation proportional to
+    /// the number of exports. Defaults to false.
+    pub reject_duplicate_exports: bool,
+    /// A token checked before each section and each function body, with
+    /// decoding failing with [`Error::Cancelled`] once it is cancelled.
+    /// Defaults to none.
+    ///
+    /// The token is borrowed for the lifetime of the options, so it may live
+    /// on the stack of the thread that cancels decoding.
+    pub cancel: Option<&'a CancelToken>,
+    /// Whether the payload of the code section is skipped over rather than
+    /// decoded, leaving [`Module::codesec`] empty and recording the section's
+    /// extent in [`Module::skipped_codesec`]. This spares the cost of
+    /// transcoding function bodies when only a module's interface is of
+    /// interest. Such modules fail validation (with
+    /// `validate::Error::SkippedSection`) and cannot be merged. Defaults to
+    /// false.
+    pub skip_code: bool,
+    /// Whether the payload of the data section is skipped over rather than
+    /// decoded, leaving [`Module::datasec`] empty and recording the section's
+    /// extent in [`Module::skipped_datasec`]. As with `skip_code`, such modules
+    /// fail validation. Defaults to false.
+    pub skip_data: bool,
+}
+
+impl Default for DecodeOptions<'_> {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_context_frames: DEFAULT_MAX_DEPTH,
+            lossy_names: false,
+            max_name_len: usize::MAX,
+            max_imports: DEFAULT_MAX_IMPORTS,
+            max_exports: DEFAULT_MAX_EXPORTS,
+            packed_expressions: false,
+            reject_duplicate_exports: false,
+            cancel: None,
+            skip_code: false,
+            skip_data: false,
+        }
+    }
+}
+
+// The recorded frames of a ContextStack, as (offset, ID, ordinal) triples.
+// These are stored inline until there are more than fit, at which point they
+// are all moved to the heap. (The inline arrays are kept separate, rather than
+// as an array of triples, to avoid padding; decoding results stay smaller this
+// way.) Frames can only be moved to the heap given an allocator, without which
+// any beyond those that fit are dropped.
+#[derive(Clone, Debug)]
+enum Frames<A: Allocator> {
+    Inline {
+        offsets: [usize; DEFAULT_MAX_DEPTH],
+        ordinals: [u32; DEFAULT_MAX_DEPTH],
+        ids: [ContextId; DEFAULT_MAX_DEPTH],
+        alloc: Option<A>,
+    },
+    Spilled(Vec<(usize, ContextId, u32), A>),
+}
+
+impl<A: Allocator> Frames<A> {
+    // Records the frame of a given index, discarding any beyond it, and
+    // returning false on allocation failure.
+    fn record(&mut self, index: usize, frame: (usize, ContextId, u32)) -> bool {
+        match self {
+            Frames::Inline {
+                offsets,
+                ordinals,
+                ids,
+                alloc,
+            } => {
+                if index < DEFAULT_MAX_DEPTH {
+                    (offsets[index], ids[index], ordinals[index]) = frame;
+                    return true;
+                }
+                let Some(alloc) = alloc else {
+                    return false;
+                };
+                let mut spilled = Vec::new_in(alloc.clone());
+                if spilled.try_reserve(index + 1).is_err() {
+                    return false;
+                }
+                spilled.extend((0..index).map(|i| (offsets[i], ids[i], ordinals[i])));
+                spilled.push(frame);
+                *self = Frames::Spilled(spilled);
+                true
+            }
+            Frames::Spilled(spilled) => {
+                spilled.truncate(index);
+                if spilled.try_reserve(1).is_err() {
+                    return false;
+                }
+                spilled.push(frame);
+                true
+            }
+        }
+    }
+
+    fn get(&self, index: usize) -> (usize, ContextId, u32) {
+        match self {
+            Frames::Inline {
+                offsets,
+                ordinals,
+                ids,
+                ..
+            } => (offsets[index], ids[index], ordinals[index]),
+            Frames::Spilled(spilled) => spilled[index],
+        }
+    }
 }
 
 /// Stack for tracking parsing context during error reporting.
-#[derive(Clone, Debug, Default)]
-pub(crate) struct ContextStack {
-    offsets: [usize; MAX_DEPTH],
-    ids: [ContextId; MAX_DEPTH],
-    depth: u8,
+#[derive(Clone, Debug)]
+pub(crate) struct ContextStack<A: Allocator> {
+    frames: Frames<A>,
+
+    // The current parsing depth, of which only the outermost `recorded` frames
+    // are actually recorded. These are kept small to keep decoding results
+    // small.
+    depth: u16,
+    recorded: u16,
+
+    max_depth: u16,
+    max_frames: u16,
+}
+
+// A stack per the default options, which records no more frames than are
+// stored inline and so needs no allocator.
+impl<A: Allocator> Default for ContextStack<A> {
+    fn default() -> Self {
+        Self::with_alloc(&DecodeOptions::default(), None)
+    }
 }
 
-impl ContextStack {
+impl<A: Allocator> ContextStack<A> {
+    // Vector lengths are u32s, so this cannot be the index of an element.
+    const NO_ORDINAL: u32 = u32::MAX;
+
+    #[cfg(feature = "decode")]
+    pub(crate) fn new(options: &DecodeOptions, alloc: A) -> Self {
+        Self::with_alloc(options, Some(alloc))
+    }
+
+    fn with_alloc(options: &DecodeOptions, alloc: Option<A>) -> Self {
+        Self {
+            frames: Frames::Inline {
+                offsets: [0; DEFAULT_MAX_DEPTH],
+                ordinals: [Self::NO_ORDINAL; DEFAULT_MAX_DEPTH],
+                ids: [ContextId::Invalid; DEFAULT_MAX_DEPTH],
+                alloc,
+            },
+            depth: 0,
+            recorded: 0,
+            max_depth: u16::try_from(options.max_depth).unwrap_or(u16::MAX),
+            max_frames: u16::try_from(options.max_context_frames).unwrap_or(u16::MAX),
+        }
+    }
+
     // Pushes a new context frame, returning true if successful.
-    fn push(&mut self, id: ContextId, offset: usize) -> bool {
-        let depth = self.depth as usize;
-        if depth >= MAX_DEPTH {
+    fn push(&mut self, id: ContextId, offset: usize, ordinal: Option<u32>) -> bool {
+        if self.depth >= self.max_depth {
             return false;
         }
-        self.offsets[depth] = offset;
-        self.ids[depth] = id;
+        // A frame is only recorded if all of its parents were; it is fine to
+        // drop frames on allocation failure, as these are only diagnostics.
+        if self.recorded == self.depth
+            && self.depth < self.max_frames
+            && self.frames.record(
+                self.depth as usize,
+                (offset, id, ordinal.unwrap_or(Self::NO_ORDINAL)),
+            )
+        {
+            self.recorded += 1;
+        }
         self.depth += 1;
         true
     }
@@ -237,32 +570,70 @@ impl ContextStack {
     // Pop the top context frame.
     fn pop(&mut self) {
         debug_assert!(self.depth > 0, "{self:#?}");
+        if self.recorded == self.depth {
+            self.recorded -= 1;
+        }
         self.depth -= 1;
     }
 
-    // Returns an iterator over frames in "pushed" order (outermost to
+    // The number of frames that are unrecorded.
+    fn truncated(&self) -> usize {
+        (self.depth - self.recorded) as usize
+    }
+
+    // Returns an iterator over recorded frames in "pushed" order (outermost to
     // innermost).
     fn iter(&self) -> impl Iterator<Item = ContextFrame> + '_ {
-        self.offsets
-            .iter()
-            .zip(&self.ids)
-            .take(self.depth as usize)
-            .map(|(&offset, &id)| ContextFrame {
+        (0..self.recorded as usize).map(|i| {
+            let (offset, id, ordinal) = self.frames.get(i);
+            ContextFrame {
                 context: id.into(),
                 offset,
-            })
+                ordinal: (ordinal != Self::NO_ORDINAL).then_some(ordinal),
+            }
+        })
     }
 }
 
 /// A parsing error with additional context around what hierarchy of things were
-/// being decoded at the time.
-pub struct ErrorWithContext<StorageError> {
+/// being decoded at the time. Any context beyond what is stored inline is
+/// allocated with `A`, the allocator of the decoded module's metadata.
+pub struct ErrorWithContext<StorageError, A: Allocator = Global> {
     /// The underlying parsing error.
     pub error: Error<StorageError>,
-    pub(crate) context: ContextStack,
+    pub(crate) context: ContextStack<A>,
+}
+
+impl<StorageError, A: Allocator> ErrorWithContext<StorageError, A> {
+    /// The underlying parsing error.
+    pub fn error(&self) -> &Error<StorageError> {
+        &self.error
+    }
+
+    /// Returns the underlying parsing error.
+    pub fn into_error(self) -> Error<StorageError> {
+        self.error
+    }
+
+    /// Iterates over the recorded frames of context, from outermost to
+    /// innermost, as triples of the byte offset at which each was entered, a
+    /// description of what was being parsed (e.g., "codesec" or "expr"), and
+    /// the index of the item within its enclosing vector, if any (e.g., that
+    /// of a function body within the code section).
+    pub fn frames(&self) -> impl Iterator<Item = (usize, &'static str, Option<u32>)> + '_ {
+        self.context
+            .iter()
+            .map(|frame| (frame.offset, frame.context, frame.ordinal))
+    }
+
+    /// The number of innermost frames of context that were not recorded, per
+    /// [`DecodeOptions::max_context_frames`].
+    pub fn truncated_frames(&self) -> usize {
+        self.context.truncated()
+    }
 }
 
-impl<StorageError: fmt::Debug> fmt::Debug for ErrorWithContext<StorageError> {
+impl<StorageError: fmt::Debug, A: Allocator> fmt::Debug for ErrorWithContext<StorageError, A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self.error)?;
         for (i, frame) in self.context.iter().enumerate() {
@@ -271,16 +642,40 @@ impl<StorageError: fmt::Debug> fmt::Debug for ErrorWithContext<StorageError> {
                 write!(f, "  ")?;
             }
             write!(f, "{}", frame.context)?;
+            if let Some(ordinal) = frame.ordinal {
+                write!(f, " #{ordinal}")?;
+            }
+        }
+        let truncated = self.context.truncated();
+        if truncated > 0 {
+            writeln!(f)?;
+            for _ in 0..self.context.recorded {
+                write!(f, "  ")?;
+            }
+            write!(f, "({truncated} more frames truncated)")?;
         }
         Ok(())
     }
 }
 
 /// Represents errors that can arise during module parsing.
+///
+/// Variants may be added in any release; for matching that is stable across
+/// releases, see [`Error::kind`].
 #[derive(Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Error<StorageError> {
     /// Failed memory allocation.
     AllocError,
+    /// Decoding was cancelled (see [`DecodeOptions::cancel`]).
+    Cancelled,
+    /// A [`DataSegmentSink`] aborted decoding on receiving the contents of the
+    /// data segment of the given index.
+    DataSinkAborted(u32),
+    /// An export has the name of an earlier export, given by the index of the
+    /// former within the export section (see
+    /// [`DecodeOptions::reject_duplicate_exports`]).
+    DuplicateExportName(u32),
     /// A given section appears more than once in the module.
     DuplicateSection(SectionId),
     /// Decoder context stack exceeded maximum depth to prevent stack overflow.
@@ -288,8 +683,15 @@ pub enum Error<StorageError> {
         context: &'static str,
         offset: usize,
     },
+    /// Invalid atomic memory operation opcode encountered.
+    InvalidAtomicOpcode(u32),
+    /// A block type encoded as a negative s33 other than that of a value type
+    /// or of the empty type.
+    InvalidBlockType(i64),
     /// Invalid bulk memory/table operation opcode encountered.
     InvalidBulkOpcode(u32),
+    /// The sections of a nested component don't span its declared length.
+    InvalidComponentLength { expected: u32, actual: u32 },
     /// Invalid data segment token encountered.
     InvalidDataToken(u32),
     /// Invalid element segment token encountered.
@@ -312,26 +714,49 @@ pub enum Error<StorageError> {
     InvalidUtf8,
     /// Invalid value type encoding encountered.
     InvalidValType(u8),
+    /// A declared length, given in bytes, exceeds what remains of the stream
+    /// (see [`Stream::len_hint`]).
+    LengthOutOfBounds(usize),
+    /// A name exceeds the configured maximum length, given in bytes.
+    NameTooLong(u32),
     /// (Non-custom) sections appear in the wrong order.
     OutOfOrderSection { before: SectionId, after: SectionId },
     /// Error from the underlying storage.
     Storage(StorageError),
+    /// The export section declares more exports than the configured maximum.
+    TooManyExports(u32),
+    /// The import section declares more imports than the configured maximum.
+    TooManyImports(u32),
     /// Function declares too many local variables (exceeding an
     /// implementation-defined limit).
     TooManyLocals(usize),
+    /// The binary is a component (of the given version of the component
+    /// binary format) rather than a module (see [`decode_component`]).
+    UnexpectedComponent(u16),
     /// Unsupported WebAssembly version number.
     UnknownVersion(u32),
+    /// Vector instructions are not yet supported.
+    UnsupportedVectorOpcode(u32),
 }
 
 impl<StorageError: fmt::Debug> fmt::Debug for Error<StorageError> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::AllocError => write!(f, "allocation failure"),
+            Error::Cancelled => write!(f, "decoding cancelled"),
+            Error::DataSinkAborted(idx) => write!(f, "data sink aborted (data segment {idx})"),
+            Error::DuplicateExportName(idx) => write!(f, "duplicate export name (export {idx})"),
             Error::DuplicateSection(id) => write!(f, "duplicate of section ({id:?})"),
             Error::ExcessiveParsingDepth { context, offset } => {
                 write!(f, "unexpected frame at {offset:#x}: {context}")
             }
+            Error::InvalidAtomicOpcode(op) => write!(f, "invalid atomic opcode ({op:#x})"),
+            Error::InvalidBlockType(value) => write!(f, "invalid block type ({value})"),
             Error::InvalidBulkOpcode(op) => write!(f, "invalid bulk opcode ({op:#x})"),
+            Error::InvalidComponentLength { expected, actual } => write!(
+                f,
+                "invalid component length: expected {expected:#x}; got {actual:#x}"
+            ),
             Error::InvalidDataToken(token) => write!(f, "invalid data token ({token:#x})"),
             Error::InvalidElementToken(token) => write!(f, "invalid element token ({token:#x})"),
             Error::InvalidFunctionLength { expected, actual } => write!(
@@ -351,14 +776,137 @@ impl<StorageError: fmt::Debug> fmt::Debug for Error<StorageError> {
             Error::InvalidToken(token) => write!(f, "invalid byte token ({token:#x})"),
             Error::InvalidUtf8 => write!(f, "invalid UTF-8"),
             Error::InvalidValType(valtype) => write!(f, "invalid valtype ({valtype:#x})"),
+            Error::LengthOutOfBounds(len) => write!(f, "length out of bounds: {len} bytes"),
+            Error::NameTooLong(len) => write!(f, "name too long: {len} bytes"),
             Error::OutOfOrderSection { before, after } => {
                 write!(f, "out-of-order sections: {before:?} before {after:?}")
             }
             Error::Storage(err) => write!(f, "{err:?}"),
+            Error::TooManyExports(count) => write!(f, "too many exports: {count}"),
+            Error::TooManyImports(count) => write!(f, "too many imports: {count}"),
             Error::TooManyLocals(count) => {
                 write!(f, "too many locals: at least {count} were specified")
             }
+            Error::UnexpectedComponent(version) => {
+                write!(f, "unexpected component (version {version:#x})")
+            }
             Error::UnknownVersion(version) => write!(f, "unknown version ({version:#x})"),
+            Error::UnsupportedVectorOpcode(op) => {
+                write!(f, "unsupported vector opcode ({op:#x})")
+            }
+        }
+    }
+}
+
+/// The kind of an [`Error`], with a variant for each of its own, as for
+/// matching on errors in a way that is stable across releases.
+///
+/// Each kind has a stable code, its discriminant, which is never reassigned
+/// (see [`code`](Self::code)).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+#[repr(u16)]
+pub enum ErrorKind {
+    /// See [`Error::AllocError`].
+    AllocError = 1,
+    /// See [`Error::Cancelled`].
+    Cancelled = 2,
+    /// See [`Error::DataSinkAborted`].
+    DataSinkAborted = 29,
+    /// See [`Error::DuplicateExportName`].
+    DuplicateExportName = 3,
+    /// See [`Error::DuplicateSection`].
+    DuplicateSection = 4,
+    /// See [`Error::ExcessiveParsingDepth`].
+    ExcessiveParsingDepth = 5,
+    /// See [`Error::InvalidAtomicOpcode`].
+    InvalidAtomicOpcode = 6,
+    /// See [`Error::InvalidBlockType`].
+    InvalidBlockType = 7,
+    /// See [`Error::InvalidBulkOpcode`].
+    InvalidBulkOpcode = 8,
+    /// See [`Error::InvalidComponentLength`].
+    InvalidComponentLength = 12,
+    /// See [`Error::InvalidDataToken`].
+    InvalidDataToken = 9,
+    /// See [`Error::InvalidElementToken`].
+    InvalidElementToken = 10,
+    /// See [`Error::InvalidFunctionLength`].
+    InvalidFunctionLength = 11,
+    /// See [`Error::InvalidLeb128`].
+    InvalidLeb128 = 13,
+    /// See [`Error::InvalidMagic`].
+    InvalidMagic = 14,
+    /// See [`Error::InvalidSectionLength`].
+    InvalidSectionLength = 15,
+    /// See [`Error::InvalidToken`].
+    InvalidToken = 16,
+    /// See [`Error::InvalidUtf8`].
+    InvalidUtf8 = 17,
+    /// See [`Error::InvalidValType`].
+    InvalidValType = 18,
+    /// See [`Error::LengthOutOfBounds`].
+    LengthOutOfBounds = 19,
+    /// See [`Error::NameTooLong`].
+    NameTooLong = 20,
+    /// See [`Error::OutOfOrderSection`].
+    OutOfOrderSection = 21,
+    /// See [`Error::Storage`].
+    Storage = 22,
+    /// See [`Error::TooManyExports`].
+    TooManyExports = 23,
+    /// See [`Error::TooManyImports`].
+    TooManyImports = 24,
+    /// See [`Error::TooManyLocals`].
+    TooManyLocals = 25,
+    /// See [`Error::UnexpectedComponent`].
+    UnexpectedComponent = 26,
+    /// See [`Error::UnknownVersion`].
+    UnknownVersion = 27,
+    /// See [`Error::UnsupportedVectorOpcode`].
+    UnsupportedVectorOpcode = 28,
+}
+
+impl ErrorKind {
+    /// The stable code of the kind.
+    pub const fn code(self) -> u16 {
+        self as u16
+    }
+}
+
+impl<StorageError> Error<StorageError> {
+    /// Returns the kind of the error.
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Error::AllocError => ErrorKind::AllocError,
+            Error::Cancelled => ErrorKind::Cancelled,
+            Error::DataSinkAborted(_) => ErrorKind::DataSinkAborted,
+            Error::DuplicateExportName(_) => ErrorKind::DuplicateExportName,
+            Error::DuplicateSection(_) => ErrorKind::DuplicateSection,
+            Error::ExcessiveParsingDepth { .. } => ErrorKind::ExcessiveParsingDepth,
+            Error::InvalidAtomicOpcode(_) => ErrorKind::InvalidAtomicOpcode,
+            Error::InvalidBlockType(_) => ErrorKind::InvalidBlockType,
+            Error::InvalidBulkOpcode(_) => ErrorKind::InvalidBulkOpcode,
+            Error::InvalidComponentLength { .. } => ErrorKind::InvalidComponentLength,
+            Error::InvalidDataToken(_) => ErrorKind::InvalidDataToken,
+            Error::InvalidElementToken(_) => ErrorKind::InvalidElementToken,
+            Error::InvalidFunctionLength { .. } => ErrorKind::InvalidFunctionLength,
+            Error::InvalidLeb128 => ErrorKind::InvalidLeb128,
+            Error::InvalidMagic(_) => ErrorKind::InvalidMagic,
+            Error::InvalidSectionLength { .. } => ErrorKind::InvalidSectionLength,
+            Error::InvalidToken(_) => ErrorKind::InvalidToken,
+            Error::InvalidUtf8 => ErrorKind::InvalidUtf8,
+            Error::InvalidValType(_) => ErrorKind::InvalidValType,
+            Error::LengthOutOfBounds(_) => ErrorKind::LengthOutOfBounds,
+            Error::NameTooLong(_) => ErrorKind::NameTooLong,
+            Error::OutOfOrderSection { .. } => ErrorKind::OutOfOrderSection,
+            Error::Storage(_) => ErrorKind::Storage,
+            Error::TooManyExports(_) => ErrorKind::TooManyExports,
+            Error::TooManyImports(_) => ErrorKind::TooManyImports,
+            Error::TooManyLocals(_) => ErrorKind::TooManyLocals,
+            Error::UnexpectedComponent(_) => ErrorKind::UnexpectedComponent,
+            Error::UnknownVersion(_) => ErrorKind::UnknownVersion,
+            Error::UnsupportedVectorOpcode(_) => ErrorKind::UnsupportedVectorOpcode,
         }
     }
 }
@@ -375,30 +923,75 @@ impl<StorageError> From<TryReserveError> for Error<StorageError> {
     }
 }
 
-pub(crate) struct Decoder<Storage: Stream> {
+pub(crate) struct Decoder<'a, Storage: Stream> {
     stream: Storage,
+    options: DecodeOptions<'a>,
+    // Scratch space into which expressions are transcoded, if reused across
+    // modules (see `batch`).
+    #[cfg(feature = "decode")]
+    expr_scratch: Option<ExpressionBuilder<Global>>,
+}
+
+#[cfg(feature = "decode")]
+impl<'a, Storage: Stream> Decoder<'a, Storage> {
+    fn with_options(stream: Storage, options: &DecodeOptions<'a>) -> Self {
+        Self {
+            stream,
+            options: *options,
+            expr_scratch: None,
+        }
+    }
 }
 
-impl<Storage: Stream> Decoder<Storage> {
+impl<Storage: Stream> Decoder<'_, Storage> {
     // TODO(https://github.com/rust-lang/rust/issues/8995):
     // type Error = Error<Storage::Error>;
 
     fn new(stream: Storage) -> Self {
-        Self { stream }
+        Self {
+            stream,
+            options: DecodeOptions::default(),
+            #[cfg(feature = "decode")]
+            expr_scratch: None,
+        }
+    }
+
+    // Fails if decoding has been cancelled.
+    #[cfg(feature = "decode")]
+    fn check_cancelled(&self) -> Result<(), Error<Storage::Error>> {
+        match self.options.cancel {
+            Some(token) if token.is_cancelled() => Err(Error::Cancelled),
+            _ => Ok(()),
+        }
     }
 
     // Pushes a context frame before a call, popping it if successful.
-    fn with_context<F, R>(
+    fn with_context<A: Allocator, F, R>(
+        &mut self,
+        context: &mut ContextStack<A>,
+        id: ContextId,
+        f: F,
+    ) -> Result<R, Error<Storage::Error>>
+    where
+        F: FnOnce(&mut Self, &mut ContextStack<A>) -> Result<R, Error<Storage::Error>>,
+    {
+        self.with_context_nth(context, id, None, f)
+    }
+
+    // As with with_context(), but for the item of a given index within a
+    // vector.
+    fn with_context_nth<A: Allocator, F, R>(
         &mut self,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         id: ContextId,
+        ordinal: Option<u32>,
         f: F,
     ) -> Result<R, Error<Storage::Error>>
     where
-        F: FnOnce(&mut Self, &mut ContextStack) -> Result<R, Error<Storage::Error>>,
+        F: FnOnce(&mut Self, &mut ContextStack<A>) -> Result<R, Error<Storage::Error>>,
     {
         let offset = self.stream.offset();
-        if !context.push(id, offset) {
+        if !context.push(id, offset, ordinal) {
             return Err(Error::ExcessiveParsingDepth {
                 context: id.into(),
                 offset,
@@ -421,7 +1014,15 @@ impl<Storage: Stream> Decoder<Storage> {
         leb128::read(|| self.read_byte_raw())
     }
 
-    fn read_zero_byte(&mut self, context: &mut ContextStack) -> Result<(), Error<Storage::Error>> {
+    fn read_s33_raw(&mut self) -> Result<i64, Error<Storage::Error>> {
+        leb128::read_s33(|| self.read_byte_raw())
+    }
+
+    #[cfg(feature = "decode")]
+    fn read_zero_byte<A: Allocator>(
+        &mut self,
+        context: &mut ContextStack<A>,
+    ) -> Result<(), Error<Storage::Error>> {
         self.with_context(context, ContextId::Byte, |decoder, _| {
             let byte = decoder.read_byte_raw()?;
             if byte == 0 {
@@ -436,9 +1037,9 @@ impl<Storage: Stream> Decoder<Storage> {
         self.stream.read_exact(buf).map_err(Error::Storage)
     }
 
-    fn read_exact(
+    fn read_exact<A: Allocator>(
         &mut self,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         buf: &mut [u8],
     ) -> Result<(), Error<Storage::Error>> {
         self.with_context(context, ContextId::ReadingBytes, |decoder, _| {
@@ -446,9 +1047,9 @@ impl<Storage: Stream> Decoder<Storage> {
         })
     }
 
-    fn skip_bytes(
+    fn skip_bytes<A: Allocator>(
         &mut self,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         count: usize,
     ) -> Result<(), Error<Storage::Error>> {
         self.with_context(context, ContextId::SkippingBytes, |decoder, _| {
@@ -458,10 +1059,17 @@ impl<Storage: Stream> Decoder<Storage> {
 
     fn read_bytes<A: Allocator>(
         &mut self,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         count: usize,
         alloc: &A,
     ) -> Result<Box<[u8], A>, Error<Storage::Error>> {
+        // Declared lengths are otherwise only checked on reading, after the
+        // allocation.
+        if let Some(remaining) = self.stream.len_hint()
+            && count > remaining
+        {
+            return Err(Error::LengthOutOfBounds(count));
+        }
         let mut buf = Vec::new_in(alloc.clone());
         buf.try_reserve_exact(count)?;
 
@@ -475,7 +1083,7 @@ impl<Storage: Stream> Decoder<Storage> {
 
     fn read<A: Allocator, T: Decodable<A> + Contextual>(
         &mut self,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<T, Error<Storage::Error>> {
         self.with_context(context, T::ID, |decoder, context| {
@@ -483,9 +1091,21 @@ impl<Storage: Stream> Decoder<Storage> {
         })
     }
 
-    fn read_bounded<T: BoundedDecodable + Contextual>(
+    // Reads the item of a given index within a vector.
+    fn read_nth<A: Allocator, T: Decodable<A> + Contextual>(
         &mut self,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+        ordinal: u32,
+    ) -> Result<T, Error<Storage::Error>> {
+        self.with_context_nth(context, T::ID, Some(ordinal), |decoder, context| {
+            T::decode(decoder, context, alloc)
+        })
+    }
+
+    fn read_bounded<T: BoundedDecodable + Contextual, A: Allocator>(
+        &mut self,
+        context: &mut ContextStack<A>,
     ) -> Result<T, Error<Storage::Error>> {
         self.with_context(context, T::ID, |decoder, context| {
             T::decode(decoder, context)
@@ -501,35 +1121,70 @@ where
     /// Parse this type from the binary stream.
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         alloc: &A,
     ) -> Result<Self, Error<Storage::Error>>;
 }
 
 // Types that can be decoded from a storage stream without allocation.
 trait BoundedDecodable: Sized + Copy {
-    fn decode<Storage: Stream>(
+    fn decode<A: Allocator, Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
     ) -> Result<Self, Error<Storage::Error>>;
 }
 
 impl<Bounded: BoundedDecodable, A: Allocator> Decodable<A> for Bounded {
     fn decode<Storage: Stream>(
         decoder: &mut Decoder<Storage>,
-        context: &mut ContextStack,
+        context: &mut ContextStack<A>,
         _: &A,
     ) -> Result<Self, Error<Storage::Error>> {
         <Self as BoundedDecodable>::decode(decoder, context)
     }
 }
 
+// The size of the chunks in which custom sections and data segments are
+// streamed.
+const STREAM_CHUNK_SIZE: usize = 512;
+
+/// The position of a custom section within a module, which distinguishes
+/// custom sections of the same name.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CustomSectionPosition {
+    /// The index of the section among all custom sections of the module,
+    /// visited or not.
+    pub index: u32,
+    /// The offset of the section's contents (i.e., following its name) within
+    /// the module.
+    pub offset: usize,
+}
+
 /// Visitor pattern for processing custom sections during module parsing.
 pub trait CustomSectionVisitor<A: Allocator> {
     /// Returns whether this visitor wants to process the custom section with the given name.
     fn should_visit(&self, name: &str) -> bool;
-    /// Process a custom section. Only called if `should_visit` returned true.
-    fn visit(&mut self, custom: CustomSection<A>);
+    /// Process a custom section. Only called if `should_visit` returned true
+    /// and `should_stream` returned false.
+    fn visit(&mut self, custom: CustomSection<A>, position: CustomSectionPosition);
+    /// Returns whether a custom section to be visited should be streamed to
+    /// `visit_chunk` rather than buffered whole, avoiding a potentially large
+    /// allocation. Defaults to false.
+    fn should_stream(&self, _name: &str) -> bool {
+        false
+    }
+    /// Process the next chunk of the contents of a streamed custom section,
+    /// followed by `remaining` more bytes. A section is streamed in order,
+    /// with `remaining` zero only for its last chunk (the only one, and empty,
+    /// if the section has no contents).
+    fn visit_chunk(
+        &mut self,
+        _name: &str,
+        _position: CustomSectionPosition,
+        _chunk: &[u8],
+        _remaining: usize,
+    ) {
+    }
 }
 
 /// No-op implementation of `CustomSectionVisitor` that skips all custom sections.
@@ -539,64 +1194,142 @@ impl<A: Allocator> CustomSectionVisitor<A> for NoCustomSectionVisitor {
     fn should_visit(&self, _: &str) -> bool {
         false
     }
-    fn visit(&mut self, _: CustomSection<A>) {
+    fn visit(&mut self, _: CustomSection<A>, _: CustomSectionPosition) {
         unreachable!()
     }
 }
 
-// Parse a WebAssembly module from a storage stream.
-//
-// # Arguments
-// * `storage` - Data stream containing WASM binary
-// * `context` - Context stack for error reporting
-// * `customsec_visitor` - Handler for custom sections
-// * `alloc` - Allocator for decoded data
-pub(crate) fn decode_module<Storage, CustomSecVisitor, A>(
-    storage: Storage,
-    context: &mut ContextStack,
-    customsec_visitor: &mut CustomSecVisitor,
-    alloc: A,
-) -> Result<Module<A>, Error<Storage::Error>>
-where
-    Storage: Stream,
-    CustomSecVisitor: CustomSectionVisitor<A>,
-    A: Allocator,
-{
-    let mut decoder = Decoder::new(storage);
-    decoder.read_bounded::<Magic>(context)?;
-    let version: Version = decoder.read_bounded(context)?;
-
-    let mut typesec = TypeSection::new(Vec::new_in(alloc.clone()));
-    let mut importsec = ImportSection::new(Vec::new_in(alloc.clone()));
-    let mut funcsec = FunctionSection::new(Vec::new_in(alloc.clone()));
-    let mut tablesec = TableSection::new(Vec::new_in(alloc.clone()));
-    let mut memsec = MemorySection::new(Vec::new_in(alloc.clone()));
-    let mut globalsec = GlobalSection::new(Vec::new_in(alloc.clone()));
-    let mut exportsec = ExportSection::new(Vec::new_in(alloc.clone()));
-    let mut startsec = None;
-    let mut elemsec = ElementSection::new(Vec::new_in(alloc.clone()));
-    let mut datacountsec = None;
-    let mut codesec = CodeSection::new(Vec::new_in(alloc.clone()));
-    let mut datasec = DataSection::new(Vec::new_in(alloc.clone()));
-
-    // The last section ID seen.
-    let mut last_id = None;
-    loop {
-        // There is no in-band signal in the WASM format for the end of a
-        // module. The best we can generically do is expect an EOF at a section
-        // boundary.
-        let id = decoder.read_bounded(context);
-        if let Err(Error::Storage(ref err)) = id
-            && Storage::is_eof(err)
-        {
-            break;
+/// A receiver of the contents of data segments, to which they can be streamed
+/// on decoding rather than buffered in the module: for instance, to copy them
+/// directly into linear memory, avoiding a potentially large allocation.
+pub trait DataSegmentSink<A: Allocator> {
+    /// Returns whether the contents of the data segment of a given index and
+    /// mode should be streamed to `write`, in which case the segment's
+    /// [`init`](DataSegment::init) is left empty in the decoded module.
+    fn should_stream(&mut self, index: u32, mode: &DataMode<A>) -> bool;
+    /// Receives the next chunk of the contents of a streamed data segment,
+    /// followed by `remaining` more bytes. A segment is streamed in order,
+    /// with `remaining` zero only for its last chunk (the only one, and empty,
+    /// if the segment has no contents).
+    ///
+    /// Returning an error (e.g., if the contents do not fit where they are
+    /// being written) aborts decoding with [`Error::DataSinkAborted`].
+    fn write(&mut self, index: u32, chunk: &[u8], remaining: usize) -> Result<(), AbortDecoding>;
+}
+
+/// The error by which a [`DataSegmentSink`] aborts decoding.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AbortDecoding;
+
+/// No-op implementation of `DataSegmentSink` that streams no data segments,
+/// buffering them all in the module.
+pub struct NoDataSegmentSink {}
+
+impl<A: Allocator> DataSegmentSink<A> for NoDataSegmentSink {
+    fn should_stream(&mut self, _: u32, _: &DataMode<A>) -> bool {
+        false
+    }
+    fn write(&mut self, _: u32, _: &[u8], _: usize) -> Result<(), AbortDecoding> {
+        Ok(())
+    }
+}
+
+/// The allocators of the sections of a decoded module, so that sections of
+/// different lifetimes can be kept apart: for instance, function bodies can be
+/// decoded into an arena of their own, to be discarded once compiled.
+///
+/// The allocators are of a single type, and so would typically be handles to
+/// different arenas.
+#[cfg(feature = "decode")]
+#[derive(Clone, Debug)]
+pub struct SectionAllocators<A: Allocator> {
+    /// The allocator of the code section.
+    pub code: A,
+    /// The allocator of the data section.
+    pub data: A,
+    /// The allocator of all other sections, including the custom sections
+    /// passed to the visitor.
+    pub metadata: A,
+}
+
+#[cfg(feature = "decode")]
+impl<A: Allocator> SectionAllocators<A> {
+    /// Uses a single allocator for all sections.
+    pub fn uniform(alloc: A) -> Self {
+        Self {
+            code: alloc.clone(),
+            data: alloc.clone(),
+            metadata: alloc,
         }
-        let id = id?;
+    }
+
+    // Returns the allocator of a given section.
+    fn section(&self, id: SectionId) -> &A {
+        match id {
+            SectionId::Code => &self.code,
+            SectionId::Data => &self.data,
+            _ => &self.metadata,
+        }
+    }
+}
+
+// A module under construction, decoded one section at a time.
+#[cfg(feature = "decode")]
+struct ModuleBuilder<A: Allocator> {
+    version: Version,
+    typesec: TypeSection<A>,
+    importsec: ImportSection<A>,
+    funcsec: FunctionSection<A>,
+    tablesec: TableSection<A>,
+    memsec: MemorySection<A>,
+    globalsec: GlobalSection<A>,
+    exportsec: ExportSection<A>,
+    startsec: Option<StartSection>,
+    elemsec: ElementSection<A>,
+    datacountsec: Option<u32>,
+    codesec: CodeSection<A>,
+    datasec: DataSection<A>,
+    skipped_codesec: Option<SkippedSection>,
+    skipped_datasec: Option<SkippedSection>,
+
+    // The last (non-custom) section ID seen.
+    last_id: Option<SectionId>,
 
+    // The number of custom sections seen.
+    custom_count: u32,
+}
+
+#[cfg(feature = "decode")]
+impl<A: Allocator> ModuleBuilder<A> {
+    fn new(version: Version, allocators: &SectionAllocators<A>) -> Self {
+        let alloc = &allocators.metadata;
+        Self {
+            version,
+            typesec: TypeSection::new(Vec::new_in(alloc.clone())),
+            importsec: ImportSection::new(Vec::new_in(alloc.clone())),
+            funcsec: FunctionSection::new(Vec::new_in(alloc.clone())),
+            tablesec: TableSection::new(Vec::new_in(alloc.clone())),
+            memsec: MemorySection::new(Vec::new_in(alloc.clone())),
+            globalsec: GlobalSection::new(Vec::new_in(alloc.clone())),
+            exportsec: ExportSection::new(Vec::new_in(alloc.clone())),
+            startsec: None,
+            elemsec: ElementSection::new(Vec::new_in(alloc.clone())),
+            datacountsec: None,
+            codesec: CodeSection::new(Vec::new_in(allocators.code.clone())),
+            datasec: DataSection::new(Vec::new_in(allocators.data.clone())),
+            skipped_codesec: None,
+            skipped_datasec: None,
+            last_id: None,
+            custom_count: 0,
+        }
+    }
+
+    // Records the next section ID, ensuring that it may appear at this point.
+    fn begin_section<StorageError>(&mut self, id: SectionId) -> Result<(), Error<StorageError>> {
         // Apart from custom sections, which can appear anywhere in the format,
         // sections must appear at most once and in order.
         if id != SectionId::Custom {
-            if let Some(last_id) = last_id {
+            if let Some(last_id) = self.last_id {
                 if id <= last_id {
                     return Err(Error::OutOfOrderSection {
                         before: last_id,
@@ -607,70 +1340,1711 @@ where
                     return Err(Error::DuplicateSection(id));
                 }
             }
-            last_id = Some(id);
+            self.last_id = Some(id);
         }
+        Ok(())
+    }
 
-        let len: u32 = decoder.read_bounded(context)?;
+    // Decodes the payload of a section of a given ID and declared length, the
+    // header of which has already been read.
+    #[allow(clippy::too_many_arguments)]
+    fn decode_section<Storage, CustomSecVisitor, DataSink>(
+        &mut self,
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        customsec_visitor: &mut CustomSecVisitor,
+        data_sink: &mut DataSink,
+        allocators: &SectionAllocators<A>,
+        id: SectionId,
+        len: u32,
+    ) -> Result<(), Error<Storage::Error>>
+    where
+        Storage: Stream,
+        CustomSecVisitor: CustomSectionVisitor<A>,
+        DataSink: DataSegmentSink<A>,
+    {
+        trace_span!(DEBUG, "section", ?id, len, offset = decoder.offset());
+        let result = self.decode_section_payload(
+            decoder,
+            context,
+            customsec_visitor,
+            data_sink,
+            allocators,
+            id,
+            len,
+        );
+        trace_error!(result, "section decoding failed")
+    }
+
+    // Does the work of decode_section(), within its span.
+    #[allow(clippy::too_many_arguments)]
+    fn decode_section_payload<Storage, CustomSecVisitor, DataSink>(
+        &mut self,
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        customsec_visitor: &mut CustomSecVisitor,
+        data_sink: &mut DataSink,
+        allocators: &SectionAllocators<A>,
+        id: SectionId,
+        len: u32,
+    ) -> Result<(), Error<Storage::Error>>
+    where
+        Storage: Stream,
+        CustomSecVisitor: CustomSectionVisitor<A>,
+        DataSink: DataSegmentSink<A>,
+    {
+        decoder.check_cancelled()?;
         let offset_start = decoder.offset();
+        let alloc = allocators.section(id);
         match id {
             SectionId::Custom => {
-                let (name, len) = {
-                    let name_start = decoder.offset();
-                    let name: Name<A> = decoder.read(context, &alloc)?;
-                    let name_end = decoder.offset();
-
-                    // If the name already exceeds the purported section length,
-                    // we can break now and have the invalid length error
-                    // reported below.
-                    let len = len as usize;
-                    if name_end - name_start > len {
-                        break;
+                let index = self.custom_count;
+                self.custom_count = self.custom_count.saturating_add(1);
+                visit_custom_section(decoder, context, customsec_visitor, alloc, index, len)?;
+            }
+            SectionId::Type => self.typesec = decoder.read(context, alloc)?,
+            SectionId::Import => self.importsec = decoder.read(context, alloc)?,
+            SectionId::Function => self.funcsec = decoder.read(context, alloc)?,
+            SectionId::Table => self.tablesec = decoder.read(context, alloc)?,
+            SectionId::Memory => self.memsec = decoder.read(context, alloc)?,
+            SectionId::Global => self.globalsec = decoder.read(context, alloc)?,
+            SectionId::Export => self.exportsec = decoder.read(context, alloc)?,
+            SectionId::Start => self.startsec = Some(decoder.read(context, alloc)?),
+            SectionId::Element => self.elemsec = decoder.read(context, alloc)?,
+            SectionId::Code if decoder.options.skip_code => {
+                self.skipped_codesec = Some(skip_section(decoder, context, len)?);
+            }
+            SectionId::Code => self.codesec = decoder.read(context, alloc)?,
+            SectionId::Data if decoder.options.skip_data => {
+                self.skipped_datasec = Some(skip_section(decoder, context, len)?);
+            }
+            SectionId::Data => {
+                self.datasec = decode_data_segments(decoder, context, data_sink, alloc)?;
+            }
+            SectionId::DataCount => self.datacountsec = Some(decoder.read(context, alloc)?),
+        }
+        let actual = decoder.offset() - offset_start;
+        trace_event!(
+            DEBUG,
+            actual,
+            count = self.entry_count(id),
+            "section decoded"
+        );
+        check_section_length(id, len, actual)
+    }
+
+    // The number of entries of a decoded section, if it is a vector.
+    #[cfg(feature = "tracing")]
+    fn entry_count(&self, id: SectionId) -> Option<usize> {
+        let count = match id {
+            SectionId::Custom | SectionId::Start | SectionId::DataCount => return None,
+            SectionId::Type => self.typesec.len(),
+            SectionId::Import => self.importsec.len(),
+            SectionId::Function => self.funcsec.len(),
+            SectionId::Table => self.tablesec.len(),
+            SectionId::Memory => self.memsec.len(),
+            SectionId::Global => self.globalsec.len(),
+            SectionId::Export => self.exportsec.len(),
+            SectionId::Element => self.elemsec.len(),
+            SectionId::Code => self
+                .skipped_codesec
+                .map_or(self.codesec.len(), |skipped| skipped.count as usize),
+            SectionId::Data => self
+                .skipped_datasec
+                .map_or(self.datasec.len(), |skipped| skipped.count as usize),
+        };
+        Some(count)
+    }
+
+    fn finish(self) -> Module<A> {
+        Module {
+            version: self.version,
+            typesec: self.typesec,
+            import_order: Vec::new_in(self.importsec.allocator().clone()),
+            importsec: self.importsec,
+            funcsec: self.funcsec,
+            tablesec: self.tablesec,
+            memsec: self.memsec,
+            globalsec: self.globalsec,
+            exportsec: self.exportsec,
+            startsec: self.startsec,
+            elemsec: self.elemsec,
+            datacountsec: self.datacountsec,
+            codesec: self.codesec,
+            datasec: self.datasec,
+            skipped_codesec: self.skipped_codesec,
+            skipped_datasec: self.skipped_datasec,
+            names: None,
+        }
+    }
+}
+
+// Skips over the payload of a section, recording its leading entry count and
+// its length.
+#[cfg(feature = "decode")]
+fn skip_section<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    len: u32,
+) -> Result<SkippedSection, Error<Storage::Error>> {
+    let start = decoder.offset();
+    let count: u32 = decoder.read_bounded(context)?;
+    // If the count already exceeds the purported section length, stop here to
+    // have the invalid length error reported by the caller.
+    if let Some(remaining) = (len as usize).checked_sub(decoder.offset() - start) {
+        decoder.skip_bytes(context, remaining)?;
+    }
+    Ok(SkippedSection { count, size: len })
+}
+
+// Decodes the data section, streaming the contents of the segments that the
+// sink asks for.
+#[cfg(feature = "decode")]
+fn decode_data_segments<Storage, DataSink, A>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    data_sink: &mut DataSink,
+    alloc: &A,
+) -> Result<DataSection<A>, Error<Storage::Error>>
+where
+    Storage: Stream,
+    DataSink: DataSegmentSink<A>,
+    A: Allocator,
+{
+    decoder.with_context(context, ContextId::DataSec, |decoder, context| {
+        let len: u32 = decoder.read_bounded(context)?;
+        let mut segments = Vec::new_in(alloc.clone());
+        segments.try_reserve_exact(len as usize)?;
+        for index in 0..len {
+            let segment = decoder.with_context_nth(
+                context,
+                ContextId::Data,
+                Some(index),
+                |decoder, context| {
+                    let mode = decode_data_mode(decoder, context, alloc)?;
+                    if !data_sink.should_stream(index, &mode) {
+                        let init = decoder.read(context, alloc)?;
+                        return Ok(DataSegment { init, mode });
                     }
-                    (name, len - (name_end - name_start))
-                };
-                if customsec_visitor.should_visit(name.as_ref()) {
-                    let bytes = decoder.read_bytes(context, len, &alloc)?;
-                    customsec_visitor.visit(CustomSection { name, bytes });
-                } else {
-                    decoder.skip_bytes(context, len)?;
-                }
+                    decoder.with_context(context, ContextId::VecByte, |decoder, context| {
+                        let len: u32 = decoder.read_bounded(context)?;
+                        stream_bytes(decoder, context, len as usize, |chunk, remaining| {
+                            data_sink
+                                .write(index, chunk, remaining)
+                                .map_err(|AbortDecoding| Error::DataSinkAborted(index))
+                        })
+                    })?;
+                    Ok(DataSegment {
+                        init: Vec::new_in(alloc.clone()),
+                        mode,
+                    })
+                },
+            )?;
+            segments.push(segment);
+        }
+        Ok(DataSection::new(segments))
+    })
+}
+
+// Reads the payload of a custom section of a given index and length, passing
+// the section to a visitor as it directs: buffered whole, streamed, or not at
+// all. A name running past the section length is left to the caller to report
+// as an invalid section length.
+fn visit_custom_section<Storage, CustomSecVisitor, A>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    customsec_visitor: &mut CustomSecVisitor,
+    alloc: &A,
+    index: u32,
+    len: u32,
+) -> Result<(), Error<Storage::Error>>
+where
+    Storage: Stream,
+    CustomSecVisitor: CustomSectionVisitor<A>,
+    A: Allocator,
+{
+    let name_start = decoder.offset();
+    let name: Name<A> = decoder.read(context, alloc)?;
+    let name_end = decoder.offset();
+    let position = CustomSectionPosition {
+        index,
+        offset: name_end,
+    };
+
+    let len = len as usize;
+    if name_end - name_start > len {
+        return Ok(());
+    }
+    let len = len - (name_end - name_start);
+    if !customsec_visitor.should_visit(name.as_ref()) {
+        decoder.skip_bytes(context, len)?;
+    } else if customsec_visitor.should_stream(name.as_ref()) {
+        stream_bytes(decoder, context, len, |chunk, remaining| {
+            customsec_visitor.visit_chunk(&name, position, chunk, remaining);
+            Ok(())
+        })?;
+    } else {
+        let bytes = decoder.read_bytes(context, len, alloc)?;
+        customsec_visitor.visit(CustomSection { name, bytes }, position);
+    }
+    Ok(())
+}
+
+// Reads a given number of bytes, passing them in order to a callback in
+// chunks, along with the number of bytes remaining after each. The last chunk
+// (the only one, and empty, if there are no bytes) has none remaining.
+fn stream_bytes<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    len: usize,
+    mut f: impl FnMut(&[u8], usize) -> Result<(), Error<Storage::Error>>,
+) -> Result<(), Error<Storage::Error>> {
+    let mut chunk = [0u8; STREAM_CHUNK_SIZE];
+    let mut remaining = len;
+    loop {
+        let chunk = &mut chunk[..remaining.min(STREAM_CHUNK_SIZE)];
+        decoder.read_exact(context, chunk)?;
+        remaining -= chunk.len();
+        f(chunk, remaining)?;
+        if remaining == 0 {
+            return Ok(());
+        }
+    }
+}
+
+fn check_section_length<StorageError>(
+    id: SectionId,
+    expected: u32,
+    actual: usize,
+) -> Result<(), Error<StorageError>> {
+    if actual != (expected as usize) {
+        return Err(Error::InvalidSectionLength {
+            id,
+            expected,
+            actual: actual as u32,
+        });
+    }
+    Ok(())
+}
+
+// Reads the magic value and version that begin a module.
+fn read_header<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+) -> Result<Version, Error<Storage::Error>> {
+    decoder.read_bounded::<Magic, _>(context)?;
+    decoder.read_bounded(context)
+}
+
+// Reads the ID of the next section, returning None at the end of the module.
+//
+// There is no in-band signal in the WASM format for the end of a module. The
+// best we can generically do is expect an EOF at a section boundary: that is,
+// one encountered before any byte of the next section has been consumed.
+fn read_section_id<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+) -> Result<Option<SectionId>, Error<Storage::Error>> {
+    let start = decoder.offset();
+    match decoder.read_bounded(context) {
+        Err(Error::Storage(ref err)) if Storage::is_eof(err) && decoder.offset() == start => {
+            Ok(None)
+        }
+        id => id.map(Some),
+    }
+}
+
+// Reads the ID and declared length of the next section, returning None at the
+// end of the module.
+fn read_section_header<A: Allocator, Storage: Stream>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+) -> Result<Option<(SectionId, u32)>, Error<Storage::Error>> {
+    let Some(id) = read_section_id(decoder, context)? else {
+        return Ok(None);
+    };
+    let len = decoder.read_bounded(context)?;
+    Ok(Some((id, len)))
+}
+
+// Defines a public function decoding a section on its own.
+macro_rules! section_decoder {
+    ($(#[$attr:meta])* $name:ident -> $section:ty) => {
+        $(#[$attr])*
+        #[cfg(feature = "decode")]
+        pub fn $name<Storage: Stream, A: Allocator>(
+            storage: Storage,
+            alloc: A,
+        ) -> Result<$section, ErrorWithContext<Storage::Error, A>> {
+            let mut decoder = Decoder::new(storage);
+            let mut context = ContextStack::default();
+            decoder
+                .read(&mut context, &alloc)
+                .map_err(|error| ErrorWithContext { error, context })
+        }
+    };
+}
+
+section_decoder!(
+    /// Decodes a type section from a stream positioned at its payload (e.g.,
+    /// at the [`offset`](SectionInfo::offset) of a section found by
+    /// [`scan_sections`](crate::scan::scan_sections)).
+    ///
+    /// As with the other section decoders, reading stops at the end of the
+    /// section's contents, with no check made of its declared length.
+    decode_type_section -> TypeSection<A>
+);
+
+section_decoder!(
+    /// Decodes an import section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    ///
+    /// The imports are in binary order, rather than grouped by kind as in a
+    /// decoded [`Module`].
+    decode_import_section -> ImportSection<A>
+);
+
+section_decoder!(
+    /// Decodes a function section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    decode_function_section -> FunctionSection<A>
+);
+
+section_decoder!(
+    /// Decodes a table section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    decode_table_section -> TableSection<A>
+);
+
+section_decoder!(
+    /// Decodes a memory section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    decode_memory_section -> MemorySection<A>
+);
+
+section_decoder!(
+    /// Decodes a global section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    decode_global_section -> GlobalSection<A>
+);
+
+section_decoder!(
+    /// Decodes an export section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    ///
+    /// The exports are in binary order, rather than ordered by name as in a
+    /// decoded [`Module`].
+    decode_export_section -> ExportSection<A>
+);
+
+section_decoder!(
+    /// Decodes a start section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    decode_start_section -> StartSection
+);
+
+section_decoder!(
+    /// Decodes an element section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    decode_element_section -> ElementSection<A>
+);
+
+section_decoder!(
+    /// Decodes a data count section from a stream positioned at its payload
+    /// (see [`decode_type_section`]).
+    decode_data_count_section -> u32
+);
+
+section_decoder!(
+    /// Decodes a code section from a stream positioned at its payload (see
+    /// [`decode_type_section`]).
+    decode_code_section -> CodeSection<A>
+);
+
+/// Decodes a data section from a stream positioned at its payload (see
+/// [`decode_type_section`]).
+#[cfg(feature = "decode")]
+pub fn decode_data_section<Storage: Stream, A: Allocator>(
+    storage: Storage,
+    alloc: A,
+) -> Result<DataSection<A>, ErrorWithContext<Storage::Error, A>> {
+    let mut decoder = Decoder::new(storage);
+    let mut context = ContextStack::default();
+    decode_data_segments(
+        &mut decoder,
+        &mut context,
+        &mut NoDataSegmentSink {},
+        &alloc,
+    )
+    .map_err(|error| ErrorWithContext { error, context })
+}
+
+// Parse a WebAssembly module from a storage stream.
+//
+// # Arguments
+// * `storage` - Data stream containing WASM binary
+// * `options` - Decoding options
+// * `context` - Context stack for error reporting
+// * `customsec_visitor` - Handler for custom sections
+// * `data_sink` - Receiver of streamed data segments
+// * `allocators` - Allocators for the decoded sections
+// * `expr_scratch` - Scratch space for transcoding expressions, if any, which
+//   is left in place for reuse
+#[cfg(feature = "decode")]
+pub(crate) fn decode_module<Storage, CustomSecVisitor, DataSink, A>(
+    storage: Storage,
+    options: &DecodeOptions,
+    context: &mut ContextStack<A>,
+    customsec_visitor: &mut CustomSecVisitor,
+    data_sink: &mut DataSink,
+    allocators: &SectionAllocators<A>,
+    expr_scratch: &mut Option<ExpressionBuilder<Global>>,
+) -> Result<Module<A>, Error<Storage::Error>>
+where
+    Storage: Stream,
+    CustomSecVisitor: CustomSectionVisitor<A>,
+    DataSink: DataSegmentSink<A>,
+    A: Allocator,
+{
+    trace_span!(DEBUG, "decode");
+    let mut decoder = Decoder::with_options(storage, options);
+    decoder.expr_scratch = expr_scratch.take();
+    let module = read_module(
+        &mut decoder,
+        context,
+        customsec_visitor,
+        data_sink,
+        allocators,
+    );
+    *expr_scratch = decoder.expr_scratch.take();
+    module
+}
+
+#[cfg(feature = "decode")]
+fn read_module<Storage, CustomSecVisitor, DataSink, A>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    customsec_visitor: &mut CustomSecVisitor,
+    data_sink: &mut DataSink,
+    allocators: &SectionAllocators<A>,
+) -> Result<Module<A>, Error<Storage::Error>>
+where
+    Storage: Stream,
+    CustomSecVisitor: CustomSectionVisitor<A>,
+    DataSink: DataSegmentSink<A>,
+    A: Allocator,
+{
+    let version = read_header(decoder, context)?;
+
+    let mut builder = ModuleBuilder::new(version, allocators);
+    while let Some(id) = read_section_id(decoder, context)? {
+        builder.begin_section(id)?;
+        let len: u32 = decoder.read_bounded(context)?;
+        builder.decode_section(
+            decoder,
+            context,
+            customsec_visitor,
+            data_sink,
+            allocators,
+            id,
+            len,
+        )?;
+    }
+    Ok(builder.finish())
+}
+
+/// The result of [`Module::decode_lenient`]: either the partially decoded
+/// module alongside the recoverable errors encountered, or else a fatal error.
+#[cfg(feature = "decode")]
+pub type LenientResult<A, StorageError> = Result<
+    (Module<A>, Vec<ErrorWithContext<StorageError, A>, A>),
+    ErrorWithContext<StorageError, A>,
+>;
+
+// Parse a WebAssembly module from a storage stream as with decode_module(), but
+// recording errors within a section and resuming at the next, per the declared
+// length of the erroneous one.
+//
+// Errors in the module header are fatal. Decoding ends early on errors that
+// leave the next section boundary unknown: those in reading a section header,
+// those arising from the storage itself, and overruns of a section's declared
+// length (as storage cannot be rewound). It also ends on cancellation.
+#[cfg(feature = "decode")]
+pub(crate) fn decode_module_lenient<Storage, CustomSecVisitor, A>(
+    storage: Storage,
+    options: &DecodeOptions,
+    customsec_visitor: &mut CustomSecVisitor,
+    alloc: A,
+) -> LenientResult<A, Storage::Error>
+where
+    Storage: Stream,
+    CustomSecVisitor: CustomSectionVisitor<A>,
+    A: Allocator,
+{
+    trace_span!(DEBUG, "decode", lenient = true);
+    let allocators = SectionAllocators::uniform(alloc);
+    let mut decoder = Decoder::with_options(storage, options);
+    let mut context = ContextStack::new(options, allocators.metadata.clone());
+    let version = match read_header(&mut decoder, &mut context) {
+        Ok(version) => version,
+        Err(error) => return Err(ErrorWithContext { error, context }),
+    };
+
+    let mut builder = ModuleBuilder::new(version, &allocators);
+    let mut errors = Vec::new_in(allocators.metadata.clone());
+    let mut record = |error, context| {
+        if errors.try_reserve(1).is_err() {
+            return Err(ErrorWithContext {
+                error: Error::AllocError,
+                context,
+            });
+        }
+        errors.push(ErrorWithContext { error, context });
+        Ok(())
+    };
+    loop {
+        // Each section is decoded with a fresh context, as one left behind by
+        // an error is not unwound.
+        let mut context = ContextStack::new(options, allocators.metadata.clone());
+        let (id, len) = match read_section_header(&mut decoder, &mut context) {
+            Ok(Some(header)) => header,
+            Ok(None) => break,
+            Err(error) => {
+                record(error, context)?;
+                break;
+            }
+        };
+
+        let start = decoder.offset();
+        let Err(error) = builder.begin_section(id).and_then(|()| {
+            builder.decode_section(
+                &mut decoder,
+                &mut context,
+                customsec_visitor,
+                &mut NoDataSegmentSink {},
+                &allocators,
+                id,
+                len,
+            )
+        }) else {
+            continue;
+        };
+        let fatal = matches!(error, Error::Storage(_) | Error::Cancelled);
+        record(error, context)?;
+
+        let consumed = decoder.offset() - start;
+        if fatal || consumed > len as usize {
+            break;
+        }
+        let mut context = ContextStack::new(options, allocators.metadata.clone());
+        if let Err(error) = decoder.skip_bytes(&mut context, len as usize - consumed) {
+            record(error, context)?;
+            break;
+        }
+    }
+    Ok((builder.finish(), errors))
+}
+
+// Called at the end of Module::decode() to reorder the import and export
+// sections in a way convenient for validation:
+// * imports are *stably* reordered by type, since logical grouping makes for
+//   O(1) access by funcidx/tableidx/memidx/globalidx, easier determination of
+//   the number of imports by type, and easier separaton later on. The original
+//   order, which is significant for instantiation, is recorded in
+//   Module::import_order if it differs.
+// * exports are reordered by field name, making it easier to determine whether
+//   they are all unique.
+#[cfg(feature = "decode")]
+pub(crate) fn prepare_module_for_validation<A: Allocator, StorageError>(
+    module: &mut Module<A>,
+) -> Result<(), Error<StorageError>> {
+    let imports = &mut module.importsec.0;
+    if !imports.is_sorted_by_key(|import| import.descriptor.kind()) {
+        // The position of each import once reordered is the number of imports
+        // of lesser kinds, plus the number of preceding imports of its kind.
+        let mut offsets = [0u32; ExternKind::COUNT];
+        for import in imports.iter() {
+            for offset in &mut offsets[import.descriptor.kind() as usize + 1..] {
+                *offset += 1;
+            }
+        }
+        let mut order = Vec::new_in(imports.allocator().clone());
+        order.try_reserve_exact(imports.len())?;
+        for import in imports.iter() {
+            let offset = &mut offsets[import.descriptor.kind() as usize];
+            order.push(*offset);
+            *offset += 1;
+        }
+        imports.sort_by_key(|import| import.descriptor.kind());
+        module.import_order = order;
+    }
+    module
+        .exportsec
+        .0
+        .sort_by(|a, b| a.field.as_ref().cmp(b.field.as_ref()));
+    Ok(())
+}
+
+// Walk the section headers of a WebAssembly module from a storage stream,
+// skipping over section payloads.
+pub(crate) fn scan_module<Storage, A>(
+    storage: Storage,
+    context: &mut ContextStack<A>,
+    alloc: A,
+) -> Result<Vec<SectionInfo<A>, A>, Error<Storage::Error>>
+where
+    Storage: Stream,
+    A: Allocator,
+{
+    let mut decoder = Decoder::new(storage);
+    read_header(&mut decoder, context)?;
+
+    let mut sections = Vec::new_in(alloc.clone());
+    while let Some((id, len)) = read_section_header(&mut decoder, context)? {
+        let offset = decoder.offset();
+        let name = if id == SectionId::Custom {
+            let name: Name<A> = decoder.read(context, &alloc)?;
+            let name_len = decoder.offset() - offset;
+            if name_len > len as usize {
+                return Err(Error::InvalidSectionLength {
+                    id,
+                    expected: len,
+                    actual: name_len as u32,
+                });
             }
-            SectionId::Type => typesec = decoder.read(context, &alloc)?,
-            SectionId::Import => importsec = decoder.read(context, &alloc)?,
-            SectionId::Function => funcsec = decoder.read(context, &alloc)?,
-            SectionId::Table => tablesec = decoder.read(context, &alloc)?,
-            SectionId::Memory => memsec = decoder.read(context, &alloc)?,
-            SectionId::Global => globalsec = decoder.read(context, &alloc)?,
-            SectionId::Export => exportsec = decoder.read(context, &alloc)?,
-            SectionId::Start => startsec = Some(decoder.read(context, &alloc)?),
-            SectionId::Element => elemsec = decoder.read(context, &alloc)?,
-            SectionId::Code => codesec = decoder.read(context, &alloc)?,
-            SectionId::Data => datasec = decoder.read(context, &alloc)?,
-            SectionId::DataCount => datacountsec = Some(decoder.read(context, &alloc)?),
-        }
-        let actual_section_len = decoder.offset() - offset_start;
-        if actual_section_len != (len as usize) {
+            decoder.skip_bytes(context, len as usize - name_len)?;
+            Some(name)
+        } else {
+            decoder.skip_bytes(context, len as usize)?;
+            None
+        };
+        sections.try_reserve(1)?;
+        sections.push(SectionInfo {
+            id,
+            offset,
+            len,
+            name,
+        });
+    }
+    Ok(sections)
+}
+
+// Walk the sections of a WebAssembly module from a storage stream, passing the
+// custom sections to a visitor and skipping over the payloads of the rest.
+pub(crate) fn visit_module_custom_sections<Storage, CustomSecVisitor, A>(
+    storage: Storage,
+    context: &mut ContextStack<A>,
+    customsec_visitor: &mut CustomSecVisitor,
+    alloc: &A,
+) -> Result<(), Error<Storage::Error>>
+where
+    Storage: Stream,
+    CustomSecVisitor: CustomSectionVisitor<A>,
+    A: Allocator,
+{
+    let mut decoder = Decoder::new(storage);
+    read_header(&mut decoder, context)?;
+
+    let mut index = 0u32;
+    while let Some((id, len)) = read_section_header(&mut decoder, context)? {
+        if id != SectionId::Custom {
+            decoder.skip_bytes(context, len as usize)?;
+            continue;
+        }
+        let offset = decoder.offset();
+        visit_custom_section(&mut decoder, context, customsec_visitor, alloc, index, len)?;
+        index = index.saturating_add(1);
+        check_section_length(id, len, decoder.offset() - offset)?;
+    }
+    Ok(())
+}
+
+// Walk the section headers of a WebAssembly module from a storage stream,
+// reporting the ID, payload length, and entry count of each section with a
+// vector of entries (i.e., all but custom, start, and data count sections),
+// and otherwise skipping over section payloads.
+pub(crate) fn count_section_entries<A: Allocator, Storage, F>(
+    storage: Storage,
+    context: &mut ContextStack<A>,
+    mut f: F,
+) -> Result<(), Error<Storage::Error>>
+where
+    Storage: Stream,
+    F: FnMut(SectionId, u32, u32),
+{
+    let mut decoder = Decoder::new(storage);
+    read_header(&mut decoder, context)?;
+
+    while let Some((id, len)) = read_section_header(&mut decoder, context)? {
+        let offset = decoder.offset();
+        if !matches!(
+            id,
+            SectionId::Custom | SectionId::Start | SectionId::DataCount
+        ) {
+            let count: u32 = decoder.read_bounded(context)?;
+            f(id, len, count);
+        }
+        let read = decoder.offset() - offset;
+        if read > len as usize {
             return Err(Error::InvalidSectionLength {
                 id,
                 expected: len,
-                actual: actual_section_len as u32,
+                actual: read as u32,
             });
         }
+        decoder.skip_bytes(context, len as usize - read)?;
     }
+    Ok(())
+}
 
-    Ok(Module {
-        version,
-        typesec,
-        importsec,
-        funcsec,
-        tablesec,
-        memsec,
-        globalsec,
-        exportsec,
-        startsec,
-        elemsec,
-        datacountsec,
-        codesec,
-        datasec,
-    })
+// Walk the bytes of a WebAssembly module from a storage stream, passing them
+// in order to `f` along with whether they belong to a custom section. Only the
+// header and section headers are decoded, and section lengths are passed as
+// encoded.
+pub(crate) fn walk_module_bytes<A: Allocator, Storage, F>(
+    storage: Storage,
+    context: &mut ContextStack<A>,
+    mut f: F,
+) -> Result<(), Error<Storage::Error>>
+where
+    Storage: Stream,
+    F: FnMut(bool, &[u8]),
+{
+    let mut decoder = Decoder::new(storage);
+    let version = read_header(&mut decoder, context)?;
+    f(false, &(Magic::Value as u32).to_le_bytes());
+    f(false, &(version as u32).to_le_bytes());
+
+    let mut buf = [0u8; 256];
+    while let Some(id) = read_section_id(&mut decoder, context)? {
+        let custom = id == SectionId::Custom;
+        f(custom, &[id as u8]);
+
+        let mut encoded = [0u8; 5];
+        let mut width = 0;
+        let len: u32 = decoder.with_context(context, ContextId::U32, |decoder, _| {
+            leb128::read(|| {
+                let byte = decoder.read_byte_raw()?;
+                if let Some(slot) = encoded.get_mut(width) {
+                    *slot = byte;
+                    width += 1;
+                }
+                Ok(byte)
+            })
+        })?;
+        f(custom, &encoded[..width]);
+
+        let mut remaining = len as usize;
+        while remaining > 0 {
+            let chunk = remaining.min(buf.len());
+            decoder.read_exact(context, &mut buf[..chunk])?;
+            f(custom, &buf[..chunk]);
+            remaining -= chunk;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use core::ptr::NonNull;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::core_compat::alloc::{AllocError, Layout};
+    use crate::encoding::leb128;
+    use crate::storage::{Buffer, MemoryEof};
+    use crate::types::{ConstExpr, Import, ImportDescriptor, Local};
+
+    // A module whose only function body contains an invalid opcode.
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+        0x03, 0x02, 0x01, 0x00, // funcsec
+        0x0a, 0x07, 0x01, 0x05, 0x00, // codesec
+        0x02, 0x40, 0x06, 0x0b, // (block <invalid>)
+    ];
+
+    fn decode(bytes: &[u8], options: &DecodeOptions) -> ErrorWithContext<MemoryEof> {
+        Module::decode_bytes_with_options(bytes, &mut NoCustomSectionVisitor {}, Global, options)
+            .err()
+            .unwrap()
+    }
+
+    #[test]
+    fn context() {
+        let err = decode(MODULE, &DecodeOptions::default());
+        assert_eq!(err.truncated_frames(), 0);
+        assert!(err.frames().eq([
+            (0x14, "codesec", None),
+            (0x15, "func", Some(0)),
+            (0x17, "expr", None),
+            (0x19, "opcode", None)
+        ]));
+        assert_eq!(
+            std::format!("{err:?}"),
+            "invalid byte token (0x6)\n0x14: codesec\n0x15:   func #0\n0x17:     expr\n0x19:       opcode"
+        );
+
+        let err = decode(
+            MODULE,
+            &DecodeOptions {
+                max_context_frames: 2,
+                ..DecodeOptions::default()
+            },
+        );
+        assert_eq!(err.truncated_frames(), 2);
+        assert_eq!(err.frames().count(), 2);
+        assert_eq!(
+            std::format!("{err:?}"),
+            "invalid byte token (0x6)\n0x14: codesec\n0x15:   func #0\n    (2 more frames truncated)"
+        );
+
+        let err = decode(
+            MODULE,
+            &DecodeOptions {
+                max_depth: 3,
+                ..DecodeOptions::default()
+            },
+        );
+        assert_eq!(
+            err.into_error(),
+            Error::ExcessiveParsingDepth {
+                context: "u32",
+                offset: 0xc
+            }
+        );
+    }
+
+    #[test]
+    fn error_kinds() {
+        let err = decode(MODULE, &DecodeOptions::default());
+        assert_eq!(err.error().kind(), ErrorKind::InvalidToken);
+        assert_eq!(ErrorKind::InvalidToken.code(), 16);
+
+        let err = decode(&MODULE[..2], &DecodeOptions::default());
+        assert_eq!(err.error().kind(), ErrorKind::Storage);
+        assert_eq!(
+            Error::<MemoryEof>::UnsupportedVectorOpcode(0).kind().code(),
+            28
+        );
+    }
+
+    #[test]
+    fn spilled_context() {
+        let options = DecodeOptions {
+            max_depth: 10,
+            max_context_frames: 8,
+            ..DecodeOptions::default()
+        };
+        let mut context = ContextStack::new(&options, Global);
+        for offset in 0..10 {
+            assert!(context.push(ContextId::Byte, offset, Some(offset as u32)));
+        }
+        assert!(!context.push(ContextId::Byte, 10, None));
+        assert!(context.iter().map(|frame| frame.offset).eq(0..8));
+        assert!(
+            context
+                .iter()
+                .map(|frame| frame.ordinal)
+                .eq((0..8).map(Some))
+        );
+        assert_eq!(context.truncated(), 2);
+
+        for _ in 0..3 {
+            context.pop();
+        }
+        assert!(context.iter().map(|frame| frame.offset).eq(0..7));
+        assert_eq!(context.truncated(), 0);
+
+        // Without an allocator, only the frames stored inline are recorded.
+        let mut context = ContextStack::<Global>::with_alloc(&options, None);
+        for offset in 0..10 {
+            assert!(context.push(ContextId::Byte, offset, None));
+        }
+        assert!(context.iter().map(|frame| frame.offset).eq(0..6));
+        assert_eq!(context.truncated(), 4);
+    }
+
+    #[test]
+    fn lenient() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x61, 0x00, 0x00, // typesec (invalid functype)
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x03, 0x02, 0x01, 0x00, // funcsec (out of order)
+            0x00, 0x02, 0x01, b'n', // customsec
+            0x0b, 0x04, 0x01, // truncated datasec
+        ];
+        let (module, errors) = Module::decode_lenient(
+            crate::storage::Buffer::new(bytes),
+            &mut NoCustomSectionVisitor {},
+            Global,
+        )
+        .unwrap();
+        assert_eq!(module.typesec.len(), 0);
+        assert_eq!(module.memsec.len(), 1);
+        assert_eq!(module.funcsec.len(), 0);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].error, Error::InvalidToken(0x61));
+        assert_eq!(
+            errors[1].error,
+            Error::OutOfOrderSection {
+                before: SectionId::Memory,
+                after: SectionId::Function,
+            }
+        );
+        assert_eq!(errors[2].error, Error::Storage(MemoryEof {}));
+    }
+
+    #[test]
+    fn lenient_invalid_header() {
+        let err = Module::decode_lenient(
+            crate::storage::Buffer::new([0x00, 0x61, 0x73, 0x6d, 0x02, 0x00, 0x00, 0x00]),
+            &mut NoCustomSectionVisitor {},
+            Global,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.error, Error::UnknownVersion(2));
+    }
+
+    #[test]
+    fn cancellation() {
+        // Storage that cancels decoding on reaching a given offset.
+        struct Cancelling<'a> {
+            buffer: Buffer<&'static [u8]>,
+            at: usize,
+            token: &'a CancelToken,
+        }
+
+        impl Stream for Cancelling<'_> {
+            type Error = MemoryEof;
+
+            fn is_eof(_: &MemoryEof) -> bool {
+                true
+            }
+
+            fn offset(&mut self) -> usize {
+                self.buffer.offset()
+            }
+
+            fn read_byte(&mut self) -> Result<u8, MemoryEof> {
+                if self.buffer.offset() == self.at {
+                    self.token.cancel();
+                }
+                self.buffer.read_byte()
+            }
+        }
+
+        const MODULE: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+            0x0a, 0x07, 0x02, // codesec
+            0x02, 0x00, 0x0b, // (func)
+            0x02, 0x00, 0x0b, // (func)
+        ];
+        let token = CancelToken::new();
+        let options = DecodeOptions {
+            cancel: Some(&token),
+            ..DecodeOptions::default()
+        };
+        let decode = |at| {
+            token.reset();
+            let storage = Cancelling {
+                buffer: Buffer::new(MODULE),
+                at,
+                token: &token,
+            };
+            Module::decode_with_options(storage, &mut NoCustomSectionVisitor {}, Global, &options)
+        };
+
+        assert!(decode(MODULE.len()).is_ok());
+
+        // Cancelled within the type section, and observed at the next.
+        let err = decode(0x0b).err().unwrap();
+        assert_eq!(err.error, Error::Cancelled);
+        assert_eq!(err.frames().count(), 0);
+
+        // Cancelled within the first function body, and observed at the next.
+        let err = decode(0x17).err().unwrap();
+        assert_eq!(err.error, Error::Cancelled);
+        assert!(
+            err.frames()
+                .eq([(0x15, "codesec", None), (0x19, "func", Some(1))])
+        );
+    }
+
+    #[test]
+    fn skipped_sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x05, 0x01, 0x03, 0x00, 0xff, 0x0b, // codesec (with an invalid opcode)
+            0x0b, 0x05, 0x01, 0x01, 0x02, 0xaa, 0xbb, // datasec
+        ];
+        assert_eq!(
+            decode(&bytes, &DecodeOptions::default()).error,
+            Error::InvalidToken(0xff)
+        );
+
+        let options = DecodeOptions {
+            skip_code: true,
+            skip_data: true,
+            ..DecodeOptions::default()
+        };
+        let module = Module::decode_bytes_with_options(
+            bytes,
+            &mut NoCustomSectionVisitor {},
+            Global,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(module.funcsec.len(), 1);
+        assert!(module.codesec.is_empty());
+        assert!(module.datasec.is_empty());
+        let skipped = SkippedSection { count: 1, size: 5 };
+        assert_eq!(module.skipped_codesec, Some(skipped));
+        assert_eq!(module.skipped_datasec, Some(skipped));
+
+        // A count running past the purported section length.
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x0a, 0x01, 0x80, 0x01, // codesec
+        ];
+        assert_eq!(
+            decode(&bytes, &options).error,
+            Error::InvalidSectionLength {
+                id: SectionId::Code,
+                expected: 1,
+                actual: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn individual_sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x0b, 0x02, // importsec
+            0x00, 0x00, 0x02, 0x00, 0x00, // (import "" "" (memory 0))
+            0x00, 0x01, b'f', 0x00, 0x00, // (import "" "f" (func (type 0)))
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x07, 0x09, 0x02, // exportsec
+            0x01, b'b', 0x00, 0x01, // (export "b" (func 1))
+            0x01, b'a', 0x00, 0x00, // (export "a" (func 0))
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+        ];
+        let sections = crate::scan::scan_bytes(bytes, Global).unwrap();
+        // Each stream runs from the section's payload to the end of the module.
+        let payload = |id| {
+            let section = sections.iter().find(|section| section.id == id).unwrap();
+            Buffer::new(&bytes[section.offset..])
+        };
+
+        let typesec = decode_type_section(payload(SectionId::Type), Global).unwrap();
+        assert_eq!(typesec.len(), 1);
+        let importsec = decode_import_section(payload(SectionId::Import), Global).unwrap();
+        assert!(matches!(
+            importsec[..],
+            [
+                Import {
+                    descriptor: ImportDescriptor::Memory(_),
+                    ..
+                },
+                Import {
+                    descriptor: ImportDescriptor::Function(_),
+                    ..
+                },
+            ]
+        ));
+        let exportsec = decode_export_section(payload(SectionId::Export), Global).unwrap();
+        let names: std::vec::Vec<&str> = exportsec.iter().map(|export| &**export.field).collect();
+        assert_eq!(names, ["b", "a"]);
+        let codesec = decode_code_section(payload(SectionId::Code), Global).unwrap();
+        assert_eq!(codesec.len(), 1);
+    }
+
+    #[test]
+    fn lossy_names() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x07, 0x06, 0x01, // exportsec
+            0x02, b'a', 0xff, 0x03, 0x00, // (global 0) named "a\xff"
+        ];
+        let err = decode(&bytes, &DecodeOptions::default());
+        assert_eq!(err.error, Error::InvalidUtf8);
+
+        let options = DecodeOptions {
+            lossy_names: true,
+            ..DecodeOptions::default()
+        };
+        let module = Module::decode_bytes_with_options(
+            bytes,
+            &mut NoCustomSectionVisitor {},
+            Global,
+            &options,
+        )
+        .unwrap();
+        let name = &module.exportsec[0].field;
+        assert_eq!(&***name, "a\u{fffd}");
+        assert_eq!(name.raw_bytes(), Some(&[b'a', 0xff][..]));
+    }
+
+    #[test]
+    fn malformed_instructions() {
+        let decode_body = |body: &[u8]| {
+            let mut bytes = std::vec![
+                0x00, 0x61, 0x73, 0x6d, // magic
+                0x01, 0x00, 0x00, 0x00, // version
+                0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+                0x03, 0x02, 0x01, 0x00, // funcsec
+            ];
+            let code_len = u8::try_from(body.len() + 1).unwrap();
+            bytes.extend([0x0a, code_len + 2, 0x01, code_len, 0x00]);
+            bytes.extend_from_slice(body);
+            decode(&bytes, &DecodeOptions::default()).error
+        };
+
+        // A block type of -129, as a multi-byte signed LEB128.
+        assert_eq!(
+            decode_body(&[0x02, 0xff, 0x7e, 0x0b, 0x0b]),
+            Error::InvalidBlockType(-129)
+        );
+        // The least s33.
+        assert_eq!(
+            decode_body(&[0x02, 0x80, 0x80, 0x80, 0x80, 0x70, 0x0b, 0x0b]),
+            Error::InvalidBlockType(-0x1_0000_0000)
+        );
+        // An invalid value type.
+        assert_eq!(
+            decode_body(&[0x02, 0x60, 0x0b, 0x0b]),
+            Error::InvalidValType(0x60)
+        );
+        // A type index of 2^32, beyond the range of an s33 (and a u32).
+        assert_eq!(
+            decode_body(&[0x02, 0x80, 0x80, 0x80, 0x80, 0x10, 0x0b, 0x0b]),
+            Error::InvalidLeb128
+        );
+        // i8x16.shuffle, with its lane immediates left off.
+        assert_eq!(
+            decode_body(&[0xfd, 0x0d, 0x0b]),
+            Error::UnsupportedVectorOpcode(0x0d)
+        );
+    }
+
+    #[test]
+    fn shared_table() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x04, 0x05, 0x01, 0x70, 0x03, 0x00, 0x01, // tablesec
+        ];
+        assert_eq!(
+            decode(&bytes, &DecodeOptions::default()).error,
+            Error::InvalidToken(0x03)
+        );
+    }
+
+    // Records the positions of the visited custom sections, streaming those
+    // named "s".
+    #[derive(Default)]
+    struct RecordingVisitor {
+        visits: std::vec::Vec<(std::string::String, CustomSectionPosition, usize)>,
+        streamed: std::vec::Vec<u8>,
+        chunks: usize,
+    }
+
+    impl CustomSectionVisitor<Global> for RecordingVisitor {
+        fn should_visit(&self, name: &str) -> bool {
+            name != "skip"
+        }
+
+        fn visit(&mut self, custom: CustomSection<Global>, position: CustomSectionPosition) {
+            let name = std::string::String::from(&**custom.name);
+            self.visits.push((name, position, custom.bytes.len()));
+        }
+
+        fn should_stream(&self, name: &str) -> bool {
+            name == "s"
+        }
+
+        fn visit_chunk(
+            &mut self,
+            name: &str,
+            position: CustomSectionPosition,
+            chunk: &[u8],
+            remaining: usize,
+        ) {
+            self.streamed.extend_from_slice(chunk);
+            self.chunks += 1;
+            if remaining == 0 {
+                let len = self.streamed.len();
+                self.visits.push((name.into(), position, len));
+            }
+        }
+    }
+
+    #[test]
+    fn custom_sections() {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x00, 0x03, 0x01, b'n', 0xaa, // customsec "n"
+            0x00, 0x05, 0x04, b's', b'k', b'i', b'p', // customsec "skip"
+            0x00, 0x02, 0x01, b'n', // customsec "n"
+            0x00, 0x84, 0x08, 0x01, b's', // customsec "s", with 1024 + 2 bytes
+        ];
+        bytes.extend((0..1026u32).map(|i| i as u8));
+        let mut visitor = RecordingVisitor::default();
+        Module::decode_bytes(&bytes, &mut visitor, Global).unwrap();
+
+        let position = |index, offset| CustomSectionPosition { index, offset };
+        assert_eq!(
+            visitor.visits,
+            [
+                ("n".into(), position(0, 12), 1),
+                ("n".into(), position(2, 24), 0),
+                ("s".into(), position(3, 29), 1026),
+            ]
+        );
+        assert_eq!(visitor.chunks, 3);
+        assert!(
+            visitor
+                .streamed
+                .iter()
+                .enumerate()
+                .all(|(i, b)| *b == i as u8)
+        );
+
+        // A visited section declaring far more bytes than remain.
+        let truncated = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x00, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x01, b'n', 0xaa, // customsec "n"
+        ];
+        let error = Module::decode_bytes(truncated, &mut RecordingVisitor::default(), Global)
+            .err()
+            .unwrap();
+        assert_eq!(error.error, Error::LengthOutOfBounds(0xffff_fffd));
+    }
+
+    // Streams the contents of active data segments, up to a given capacity
+    // for each.
+    struct RecordingSink {
+        streamed: std::vec::Vec<(u32, std::vec::Vec<u8>)>,
+        chunks: usize,
+        capacity: usize,
+    }
+
+    impl RecordingSink {
+        fn new(capacity: usize) -> Self {
+            Self {
+                streamed: std::vec![],
+                chunks: 0,
+                capacity,
+            }
+        }
+    }
+
+    impl DataSegmentSink<Global> for RecordingSink {
+        fn should_stream(&mut self, index: u32, mode: &DataMode<Global>) -> bool {
+            if matches!(mode, DataMode::Active(_)) {
+                self.streamed.push((index, std::vec![]));
+                return true;
+            }
+            false
+        }
+
+        fn write(
+            &mut self,
+            index: u32,
+            chunk: &[u8],
+            _remaining: usize,
+        ) -> Result<(), AbortDecoding> {
+            let (streamed_index, bytes) = self.streamed.last_mut().unwrap();
+            assert_eq!(*streamed_index, index);
+            if bytes.len() + chunk.len() > self.capacity {
+                return Err(AbortDecoding);
+            }
+            bytes.extend_from_slice(chunk);
+            self.chunks += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn data_segment_sink() {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x0b, 0xe3, 0x04, 0x02, // datasec
+            0x01, 0x02, 0xaa, 0xbb, // (data "\aa\bb")
+            0x00, 0x41, 0x08, 0x0b, // (data (i32.const 8) ...
+            0xd8, 0x04, // ... with 512 + 88 bytes)
+        ];
+        bytes.extend((0..600u32).map(|i| i as u8));
+        let mut sink = RecordingSink::new(600);
+        let module = Module::decode_with_data_sink(
+            Buffer::new(&bytes),
+            &mut NoCustomSectionVisitor {},
+            &mut sink,
+            Global,
+            &DecodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(sink.chunks, 2);
+        assert_eq!(sink.streamed.len(), 1);
+        let (index, streamed) = &sink.streamed[0];
+        assert_eq!(*index, 1);
+        assert!(streamed.iter().copied().eq((0..600u32).map(|i| i as u8)));
+
+        assert_eq!(module.datasec.len(), 2);
+        assert_eq!(module.datasec[0].init[..], [0xaa, 0xbb]);
+        assert!(module.datasec[1].init.is_empty());
+        assert!(matches!(
+            &module.datasec[1].mode,
+            DataMode::Active(active) if active.offset.as_const() == Some(ConstExpr::I32(8))
+        ));
+
+        // A sink short of room aborts decoding.
+        let err = Module::decode_with_data_sink(
+            Buffer::new(&bytes),
+            &mut NoCustomSectionVisitor {},
+            &mut RecordingSink::new(599),
+            Global,
+            &DecodeOptions::default(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.error, Error::DataSinkAborted(1));
+    }
+
+    // Delegates to the global allocator, counting the allocations made under
+    // each tag.
+    #[derive(Clone, Debug)]
+    struct Tagged(usize);
+
+    static TAGGED_ALLOCATIONS: [AtomicUsize; 3] = [const { AtomicUsize::new(0) }; 3];
+
+    // Safety: Soundness is deferred to the global allocator.
+    unsafe impl crate::core_compat::alloc::Allocator for Tagged {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            TAGGED_ALLOCATIONS[self.0].fetch_add(1, Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            // Safety: Soundness is deferred to the global allocator.
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[test]
+    fn section_allocators() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+            0x0b, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0xaa, // datasec
+        ];
+        let (code, data, metadata) = (Tagged(0), Tagged(1), Tagged(2));
+        let allocators = SectionAllocators {
+            code,
+            data,
+            metadata,
+        };
+        let module = Module::decode_with_allocators(
+            Buffer::new(&bytes),
+            &mut NoCustomSectionVisitor {},
+            &mut NoDataSegmentSink {},
+            &allocators,
+            &DecodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(module.codesec.allocator().0, 0);
+        assert_eq!(module.datasec.allocator().0, 1);
+        assert_eq!(module.typesec.allocator().0, 2);
+        assert_eq!(module.funcsec.allocator().0, 2);
+        for allocations in &TAGGED_ALLOCATIONS {
+            assert!(allocations.load(Ordering::Relaxed) > 0);
+        }
+    }
+
+    #[test]
+    fn locals() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x0a, 0x01, 0x08, // codesec
+            0x03, 0x01, 0x7b, 0x02, 0x6f, 0x01,
+            0x70, // (local v128 externref externref funcref)
+            0x0b, // end
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        assert!(matches!(
+            module.codesec[0].locals[..],
+            [
+                Local::Vec(0),
+                Local::ExternRef(0),
+                Local::ExternRef(0),
+                Local::FuncRef(0)
+            ]
+        ));
+
+        // A local count that would overflow a running total.
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x0c, 0x01, 0x0a, // codesec
+            0x02, 0x01, 0x7f, // (local i32)
+            0xff, 0xff, 0xff, 0xff, 0x0f, 0x7f, // (local i32 * u32::MAX)
+            0x0b, // end
+        ];
+        assert!(matches!(
+            decode(&bytes, &DecodeOptions::default()).error,
+            Error::TooManyLocals(_)
+        ));
+    }
+
+    #[test]
+    fn implementation_limits() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x02, 0x08, 0x01, // importsec
+            0x01, b'm', 0x01, b'g', 0x03, 0x7f, 0x00, // (global i32)
+            0x07, 0x06, 0x01, // exportsec
+            0x02, b'a', b'b', 0x03, 0x00, // (global 0)
+        ];
+        assert!(Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).is_ok());
+
+        let options = DecodeOptions {
+            max_imports: 0,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(decode(&bytes, &options).error, Error::TooManyImports(1));
+
+        let options = DecodeOptions {
+            max_exports: 0,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(decode(&bytes, &options).error, Error::TooManyExports(1));
+
+        let options = DecodeOptions {
+            max_name_len: 1,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(decode(&bytes, &options).error, Error::NameTooLong(2));
+    }
+
+    #[test]
+    fn duplicate_exports() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x07, 0x0d, 0x03, // exportsec
+            0x01, b'a', 0x02, 0x00, // (memory 0)
+            0x01, b'b', 0x02, 0x00, // (memory 0)
+            0x01, b'a', 0x02, 0x00, // (memory 0)
+        ];
+        // Duplicates are only caught on validation by default.
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global);
+        assert!(module.is_ok());
+        #[cfg(feature = "validate")]
+        assert!(matches!(
+            module.unwrap().validate(),
+            Err(crate::validate::Error::DuplicateExportName { .. })
+        ));
+
+        let options = DecodeOptions {
+            reject_duplicate_exports: true,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(
+            decode(&bytes, &options).error,
+            Error::DuplicateExportName(2)
+        );
+
+        let mut distinct = bytes;
+        distinct[25] = b'c';
+        assert!(
+            Module::decode_bytes_with_options(
+                distinct,
+                &mut NoCustomSectionVisitor {},
+                Global,
+                &options
+            )
+            .is_ok()
+        );
+    }
+
+    // Every failure to read is surfaced as a storage error with the context of
+    // the read, except for the end of the stream at a section boundary, which
+    // ends the module.
+    #[test]
+    fn storage_faults() {
+        use crate::storage::Buffer;
+        use crate::storage::test::{Fault, FaultyStream, FaultyStreamError};
+
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+            0x00, 0x03, 0x01, b'a', 0xff, // customsec
+        ];
+        let boundaries = [8, 14, 18, 24, 29];
+        // The outermost context of a read at a given offset.
+        let outermost = |offset| match offset {
+            0..4 => "magic",
+            4..8 => "version",
+            10..14 => "typesec",
+            16..18 => "funcsec",
+            20..24 => "codesec",
+            26..28 => "name",
+            28 => "skipping bytes",
+            _ if boundaries.contains(&offset) => "section ID",
+            _ => "u32", // A section length.
+        };
+
+        for offset in 0..=bytes.len() {
+            for fault in [Fault::Eof, Fault::Error] {
+                let stream = FaultyStream::with_fault(Buffer::new(&bytes[..]), offset, fault);
+                let result = Module::decode(stream, &mut NoCustomSectionVisitor {}, Global);
+                if fault == Fault::Eof && boundaries.contains(&offset) {
+                    let module = result.unwrap();
+                    assert_eq!(module.typesec.len(), usize::from(offset >= 14));
+                    assert_eq!(module.codesec.len(), usize::from(offset >= 24));
+                    continue;
+                }
+
+                let err = result.err().unwrap();
+                assert_eq!(
+                    err.error,
+                    Error::Storage(FaultyStreamError::Injected(fault)),
+                    "{offset}"
+                );
+                let frames: std::vec::Vec<_> = err.context.iter().collect();
+                assert_eq!(frames[0].context, outermost(offset), "{offset}: {err:?}");
+                assert!(frames.iter().all(|frame| frame.offset <= offset), "{err:?}");
+            }
+        }
+    }
+
+    // A module truncated anywhere other than a section boundary fails to
+    // decode with an EOF, however it is read.
+    #[test]
+    fn truncation() {
+        fn check<'a, Storage: Stream>(
+            bytes: &'a [u8],
+            boundaries: &[usize],
+            stream: impl Fn(&'a [u8]) -> Storage,
+        ) {
+            for len in 0..=bytes.len() {
+                let result = Module::decode(
+                    stream(&bytes[..len]),
+                    &mut NoCustomSectionVisitor {},
+                    Global,
+                );
+                if boundaries.contains(&len) {
+                    assert!(result.is_ok(), "{len}");
+                    continue;
+                }
+                match result.err().unwrap().error {
+                    Error::Storage(ref err) if Storage::is_eof(err) => {}
+                    err => panic!("{len}: {err:?}"),
+                }
+            }
+        }
+
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+            0x00, 0x06, 0x01, b'a', 0xff, 0xfe, 0xfd, 0xfc, // customsec
+        ];
+        let boundaries = [8, 14, 18, 24, 32];
+
+        check(&bytes, &boundaries, crate::storage::Buffer::new);
+        #[cfg(feature = "std")]
+        {
+            use std::io::Cursor;
+
+            use crate::storage::BufferedStream;
+
+            check(&bytes, &boundaries, Cursor::new);
+            for capacity in [1, 4, 16] {
+                check(&bytes, &boundaries, |bytes| {
+                    BufferedStream::with_capacity(capacity, Cursor::new(bytes)).unwrap()
+                });
+            }
+        }
+    }
+
+    // Decoding does not recurse on its input, so arbitrarily deep nesting of
+    // blocks decodes within a small stack: 8 KiB, as for embedded targets,
+    // in optimized builds, and several times that in unoptimized ones.
+    #[test]
+    fn small_stack() {
+        const DEPTH: usize = 100_000;
+        const STACK_SIZE: usize = if cfg!(debug_assertions) {
+            64 * 1024
+        } else {
+            8 * 1024
+        };
+
+        let mut body = std::vec![0x00]; // no locals
+        body.extend([0x02, 0x40].repeat(DEPTH)); // block
+        body.extend([0x0b].repeat(DEPTH + 1)); // end
+        let mut codesec = std::vec![0x01];
+        leb128(&mut codesec, body.len() as u64);
+        codesec.extend(body);
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, // codesec
+        ];
+        leb128(&mut bytes, codesec.len() as u64);
+        bytes.extend(codesec);
+
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let module =
+                    Module::decode_bytes(&bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+                assert_eq!(module.code_stats().max_depth, DEPTH);
+                #[cfg(feature = "validate")]
+                module.validate().unwrap();
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
 }
@@ -0,0 +1,174 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! A visitor for the `name` custom section, which gives debug names to a
+//! module's entities.
+//!
+//! See <https://webassembly.github.io/spec/core/appendix/custom.html#name-section>.
+
+use crate::Allocator;
+use crate::core_compat::vec::Vec;
+use crate::storage::{MemoryEof, Stream};
+use crate::types::{CustomSection, NameAssoc, NameSection, SectionId};
+
+use super::custom::parse_payload;
+use super::{
+    ContextId, ContextStack, Contextual, CustomSectionPosition, CustomSectionVisitor, Decodable,
+    Decoder, Error, ErrorWithContext,
+};
+
+// The IDs of the understood subsections.
+const MODULE_NAME: u8 = 0;
+const FUNCTION_NAMES: u8 = 1;
+
+/// A visitor that parses the `name` custom section. Only module and function
+/// names are retained; other subsections are skipped.
+pub struct NameSectionVisitor<A: Allocator> {
+    alloc: A,
+    names: Option<Result<NameSection<A>, ErrorWithContext<MemoryEof, A>>>,
+}
+
+impl<A: Allocator> NameSectionVisitor<A> {
+    /// The name of the custom section parsed.
+    pub const SECTION_NAME: &str = "name";
+
+    /// Creates a visitor, allocating the parsed names with the given
+    /// allocator.
+    pub fn new(alloc: A) -> Self {
+        Self { alloc, names: None }
+    }
+
+    /// Returns the parsed names, or `None` if the section was not present.
+    /// These may be attached to the decoded module as [`Module::names`].
+    ///
+    /// [`Module::names`]: crate::Module::names
+    pub fn finish(self) -> Option<Result<NameSection<A>, ErrorWithContext<MemoryEof, A>>> {
+        self.names
+    }
+}
+
+impl<A: Allocator> CustomSectionVisitor<A> for NameSectionVisitor<A> {
+    fn should_visit(&self, name: &str) -> bool {
+        name == Self::SECTION_NAME
+    }
+
+    fn visit(&mut self, custom: CustomSection<A>, _: CustomSectionPosition) {
+        let alloc = &self.alloc;
+        let len = custom.bytes.len();
+        self.names = Some(parse_payload(&custom, |decoder, context| {
+            decode_names(decoder, context, alloc, len)
+        }));
+    }
+}
+
+// Decodes the subsections of a `name` section of a given length.
+fn decode_names<Storage: Stream, A: Allocator>(
+    decoder: &mut Decoder<Storage>,
+    context: &mut ContextStack<A>,
+    alloc: &A,
+    len: usize,
+) -> Result<NameSection<A>, Error<Storage::Error>> {
+    let mut names = NameSection {
+        module: None,
+        functions: Vec::new_in(alloc.clone()),
+    };
+
+    let mut ordinal = 0;
+    while decoder.offset() < len {
+        let id = ContextId::NameSubsection;
+        decoder.with_context_nth(context, id, Some(ordinal), |decoder, context| {
+            let id = decoder.read_byte_raw()?;
+            let size: u32 = decoder.read_bounded(context)?;
+            let start = decoder.offset();
+            match id {
+                MODULE_NAME => names.module = Some(decoder.read(context, alloc)?),
+                FUNCTION_NAMES => names.functions = Decodable::decode(decoder, context, alloc)?,
+                _ => decoder.skip_bytes(context, size as usize)?,
+            }
+            let actual = decoder.offset() - start;
+            if actual != size as usize {
+                return Err(Error::InvalidSectionLength {
+                    id: SectionId::Custom,
+                    expected: size,
+                    actual: u32::try_from(actual).unwrap_or(u32::MAX),
+                });
+            }
+            Ok(())
+        })?;
+        ordinal += 1;
+    }
+    // Names are looked up by binary search; the format requires them to be
+    // in index order already, but producers are not to be relied upon.
+    names.functions.sort_unstable_by_key(|assoc| assoc.index);
+    Ok(names)
+}
+
+impl<A: Allocator> Contextual for NameAssoc<A> {
+    const ID: ContextId = ContextId::NameAssoc;
+}
+
+impl<A: Allocator> Decodable<A> for NameAssoc<A> {
+    fn decode<Storage: Stream>(
+        decoder: &mut Decoder<Storage>,
+        context: &mut ContextStack<A>,
+        alloc: &A,
+    ) -> Result<Self, Error<Storage::Error>> {
+        Ok(Self {
+            index: decoder.read_bounded(context)?,
+            name: decoder.read(context, alloc)?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::types::FuncIdx;
+
+    #[test]
+    fn name_section() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x04, 0x03, 0x00, 0x00, 0x00, // funcsec
+            0x0a, 0x0a, 0x03, 0x02, 0x00, 0x0b, 0x02, 0x00, 0x0b, 0x02, 0x00, 0x0b, // codesec
+            0x00, 0x18, // customsec
+            0x04, b'n', b'a', b'm', b'e', // name
+            0x00, 0x02, 0x01, b'm', // module name
+            0x01, 0x09, 0x02, // function names
+            0x02, 0x01, b'b', // (2, "b")
+            0x00, 0x03, b'm', b'a', b'n', // (0, "man")
+            0x02, 0x02, 0x00, 0x00, // local names (skipped)
+        ];
+        let mut visitor = NameSectionVisitor::new(Global);
+        let mut module = Module::decode_bytes(bytes, &mut visitor, Global).unwrap();
+        assert!(module.names.is_none());
+
+        module.names = Some(visitor.finish().unwrap().unwrap());
+        let names = module.names.as_ref().unwrap();
+        assert_eq!(names.module.as_deref().map(|name| &**name), Some("m"));
+        assert_eq!(names.function_name(FuncIdx::new(0)), Some("man"));
+        assert_eq!(names.function_name(FuncIdx::new(1)), None);
+        assert_eq!(
+            std::format!(
+                "{} {} {}",
+                module.display_function(FuncIdx::new(0)),
+                module.display_function(FuncIdx::new(1)),
+                module.display_function(FuncIdx::new(2)),
+            ),
+            "$man 1 $b"
+        );
+        assert_eq!(
+            std::format!("{module:?}"),
+            "Module { version: V1, name: Some(\"m\"), types: 1, imports: 0, \
+             functions: [$man, 1, $b], tables: 0, memories: 0, globals: 0, exports: 0, \
+             start: None, elements: 0, data: 0, .. }"
+        );
+    }
+}
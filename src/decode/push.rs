@@ -0,0 +1,320 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Incremental decoding of modules from pushed chunks of bytes.
+
+use crate::core_compat::vec::Vec;
+use crate::storage::{Buffer, MemoryEof};
+use crate::types::{Function, SectionId};
+use crate::{Allocator, Module};
+
+use super::{
+    ContextStack, CustomSectionVisitor, Decoder, Error, ErrorWithContext, ModuleBuilder,
+    NoDataSegmentSink, SectionAllocators, check_section_length, prepare_module_for_validation,
+    read_header,
+};
+
+/// The progress of a [`PushDecoder`] over the bytes pushed so far.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Status {
+    /// The bytes end partway through the module header, a section, or a
+    /// function body, and more are needed.
+    Partial,
+    /// The bytes end at a section boundary, where the module may be finished.
+    SectionBoundary,
+}
+
+// Progress through the code section, which is decoded a function body at a
+// time rather than all at once.
+struct CodeProgress {
+    // The stream offset at which the section's payload begins.
+    start: usize,
+    // The section's declared length.
+    len: u32,
+    // The number of function bodies yet to be decoded.
+    remaining: u32,
+}
+
+/// A decoder driven by pushing chunks of bytes as they become available,
+/// rather than by pulling them from a [`Stream`](crate::storage::Stream).
+///
+/// Pushed bytes are buffered until a complete unit of the module can be
+/// decoded: the header, a whole section, or (within the code section) a whole
+/// function body. Decoding otherwise proceeds as with
+/// [`Module::decode_bytes`], with the exception that a section or function
+/// body whose contents overrun its declared length is reported as an
+/// [`Error::Storage`] at the point of the overrun.
+///
+/// The decoder should be discarded after any error.
+pub struct PushDecoder<'a, A: Allocator, CustomSecVisitor: CustomSectionVisitor<A>> {
+    customsec_visitor: &'a mut CustomSecVisitor,
+    allocators: SectionAllocators<A>,
+
+    // Bytes pushed but not yet decoded, beginning at stream offset `offset`.
+    buffer: Vec<u8, A>,
+    offset: usize,
+
+    // Present once the header has been decoded.
+    builder: Option<ModuleBuilder<A>>,
+
+    // Present while within the code section.
+    code: Option<CodeProgress>,
+}
+
+impl<'a, A, CustomSecVisitor> PushDecoder<'a, A, CustomSecVisitor>
+where
+    A: Allocator,
+    CustomSecVisitor: CustomSectionVisitor<A>,
+{
+    /// Creates a new decoder, with a given custom section visitor and
+    /// allocator.
+    pub fn new(customsec_visitor: &'a mut CustomSecVisitor, alloc: A) -> Self {
+        Self {
+            customsec_visitor,
+            buffer: Vec::new_in(alloc.clone()),
+            allocators: SectionAllocators::uniform(alloc),
+            offset: 0,
+            builder: None,
+            code: None,
+        }
+    }
+
+    /// Pushes the next chunk of the module, decoding as much as possible.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Status, ErrorWithContext<MemoryEof, A>> {
+        self.buffer
+            .try_reserve(bytes.len())
+            .map_err(|err| without_context(err.into()))?;
+        self.buffer.extend_from_slice(bytes);
+
+        let mut consumed = 0;
+        let result = loop {
+            let mut context = ContextStack::default();
+            match self.step(consumed, &mut context) {
+                Ok(Some(len)) => consumed += len,
+                Ok(None) => break Ok(()),
+                Err(error) => break Err(ErrorWithContext { error, context }),
+            }
+        };
+        self.buffer.drain(..consumed);
+        self.offset += consumed;
+        result?;
+
+        if self.builder.is_some() && self.code.is_none() && self.buffer.is_empty() {
+            Ok(Status::SectionBoundary)
+        } else {
+            Ok(Status::Partial)
+        }
+    }
+
+    /// Finishes decoding, which is only possible at a section boundary.
+    pub fn finish(self) -> Result<Module<A>, ErrorWithContext<MemoryEof, A>> {
+        let Some(builder) = self.builder else {
+            return Err(without_context(Error::Storage(MemoryEof {})));
+        };
+        if self.code.is_some() || !self.buffer.is_empty() {
+            return Err(without_context(Error::Storage(MemoryEof {})));
+        }
+        let mut module = builder.finish();
+        prepare_module_for_validation(&mut module).map_err(without_context)?;
+        Ok(module)
+    }
+
+    // Attempts to decode the next unit of the module from the buffered bytes
+    // past `pos`, returning the number of bytes consumed or else None if more
+    // are needed.
+    fn step(
+        &mut self,
+        pos: usize,
+        context: &mut ContextStack<A>,
+    ) -> Result<Option<usize>, Error<MemoryEof>> {
+        let bytes = &self.buffer[pos..];
+        let base = self.offset + pos;
+        let mut decoder = Decoder::new(Buffer::with_offset(bytes, base));
+
+        let Some(builder) = &mut self.builder else {
+            let Some(version) = within(read_header(&mut decoder, context), false)? else {
+                return Ok(None);
+            };
+            self.builder = Some(ModuleBuilder::new(version, &self.allocators));
+            return Ok(Some(decoder.offset() - base));
+        };
+
+        if let Some(code) = &mut self.code {
+            if code.remaining == 0 {
+                check_section_length(SectionId::Code, code.len, base - code.start)?;
+                self.code = None;
+                return Ok(Some(0));
+            }
+
+            // Function bodies are prefixed by their size, so we can tell when
+            // one has been completely buffered.
+            let section_rest = (code.start + code.len as usize).saturating_sub(base);
+            let window = &bytes[..bytes.len().min(section_rest)];
+            let complete = window.len() == section_rest;
+            let mut decoder = Decoder::new(Buffer::with_offset(window, base));
+            let Some(size) = within(decoder.read_bounded::<u32, _>(context), complete)? else {
+                return Ok(None);
+            };
+            let len = decoder.offset() - base + size as usize;
+            if len > section_rest {
+                return Err(Error::Storage(MemoryEof {}));
+            }
+            if len > bytes.len() {
+                return Ok(None);
+            }
+
+            let mut decoder = Decoder::new(Buffer::with_offset(&bytes[..len], base));
+            let function: Function<A> = decoder.read(context, &self.allocators.code)?;
+            builder.codesec.0.push(function);
+            code.remaining -= 1;
+            return Ok(Some(len));
+        }
+
+        let Some(id) = within(decoder.read_bounded::<SectionId, _>(context), false)? else {
+            return Ok(None);
+        };
+        let Some(len) = within(decoder.read_bounded::<u32, _>(context), false)? else {
+            return Ok(None);
+        };
+        let header_len = decoder.offset() - base;
+        let section_len = header_len + len as usize;
+        let window = &bytes[header_len..bytes.len().min(section_len)];
+        let complete = bytes.len() >= section_len;
+        let mut decoder = Decoder::new(Buffer::with_offset(window, base + header_len));
+
+        if id == SectionId::Code {
+            let Some(count) = within(decoder.read_bounded::<u32, _>(context), complete)? else {
+                return Ok(None);
+            };
+            builder.begin_section(id)?;
+            builder.codesec.0.try_reserve_exact(count as usize)?;
+            self.code = Some(CodeProgress {
+                start: base + header_len,
+                len,
+                remaining: count,
+            });
+            return Ok(Some(decoder.offset() - base));
+        }
+
+        if !complete {
+            return Ok(None);
+        }
+        builder.begin_section(id)?;
+        builder.decode_section(
+            &mut decoder,
+            context,
+            self.customsec_visitor,
+            &mut NoDataSegmentSink {},
+            &self.allocators,
+            id,
+            len,
+        )?;
+        Ok(Some(section_len))
+    }
+}
+
+// Interprets the result of decoding from a window of buffered bytes, where
+// running out of bytes only signifies an error if the window was complete;
+// otherwise, more bytes are needed.
+fn within<T>(
+    result: Result<T, Error<MemoryEof>>,
+    complete: bool,
+) -> Result<Option<T>, Error<MemoryEof>> {
+    match result {
+        Err(Error::Storage(MemoryEof {})) if !complete => Ok(None),
+        result => result.map(Some),
+    }
+}
+
+fn without_context<A: Allocator>(error: Error<MemoryEof>) -> ErrorWithContext<MemoryEof, A> {
+    ErrorWithContext {
+        error,
+        context: ContextStack::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::CustomSectionPosition;
+    use crate::types::CustomSection;
+
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x00, 0x04, 0x01, b'n', 0xaa, 0xbb, // customsec
+        0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+        0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+        0x0a, 0x0e, 0x02, // codesec
+        0x04, 0x00, 0x41, 0x01, 0x0b, // (func (i32.const 1))
+        0x07, 0x00, 0x02, 0x7f, 0x41, 0x02, 0x0b, 0x0b, // (func (block (i32.const 2)))
+    ];
+
+    #[derive(Default)]
+    struct CountingVisitor {
+        visits: usize,
+    }
+
+    impl CustomSectionVisitor<Global> for CountingVisitor {
+        fn should_visit(&self, _: &str) -> bool {
+            true
+        }
+
+        fn visit(&mut self, custom: CustomSection<Global>, _: CustomSectionPosition) {
+            assert_eq!(&**custom.name, "n");
+            assert_eq!(&*custom.bytes, &[0xaa, 0xbb]);
+            self.visits += 1;
+        }
+    }
+
+    #[test]
+    fn byte_by_byte() {
+        let mut visitor = CountingVisitor::default();
+        let mut decoder = PushDecoder::new(&mut visitor, Global);
+        let mut boundaries = std::vec::Vec::new();
+        for (i, byte) in MODULE.iter().enumerate() {
+            if decoder.push(&[*byte]).unwrap() == Status::SectionBoundary {
+                boundaries.push(i + 1);
+            }
+        }
+        assert_eq!(boundaries, [8, 14, 21, 26, 42]);
+
+        let module = decoder.finish().unwrap();
+        let expected = Module::decode_bytes(
+            MODULE,
+            &mut crate::decode::NoCustomSectionVisitor {},
+            Global,
+        )
+        .unwrap();
+        assert_eq!(module.typesec.len(), 1);
+        assert_eq!(module.funcsec.len(), 2);
+        assert_eq!(module.code_stats(), expected.code_stats());
+        assert_eq!(visitor.visits, 1);
+    }
+
+    #[test]
+    fn truncated() {
+        for len in [0, 4, 10, 30, 41] {
+            let mut visitor = CountingVisitor::default();
+            let mut decoder = PushDecoder::new(&mut visitor, Global);
+            assert_eq!(decoder.push(&MODULE[..len]).unwrap(), Status::Partial);
+            let err = decoder.finish().err().unwrap();
+            assert_eq!(err.error, Error::Storage(MemoryEof {}));
+        }
+    }
+
+    #[test]
+    fn function_overrunning_section() {
+        let mut bytes = std::vec::Vec::from(MODULE);
+        bytes[27] -= 1; // Shorten the code section by a byte.
+        bytes.pop();
+        let mut visitor = CountingVisitor::default();
+        let mut decoder = PushDecoder::new(&mut visitor, Global);
+        let err = decoder.push(&bytes).err().unwrap();
+        assert_eq!(err.error, Error::Storage(MemoryEof {}));
+    }
+}
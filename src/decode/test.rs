@@ -0,0 +1,133 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Utilities for testing the robustness of decoding.
+
+use crate::Module;
+use crate::core_compat::alloc::Global;
+use crate::core_compat::vec::Vec;
+use crate::scan::scan_bytes;
+
+use super::{ErrorKind, NoCustomSectionVisitor};
+
+// Modules up to this length have every prefix decoded; longer ones have only
+// those around section boundaries, and a sample of the rest.
+const MAX_EXHAUSTIVE_LEN: usize = 256;
+const SAMPLED_PREFIXES: usize = 64;
+
+/// Decodes the given well-formed module and prefixes of it, as a check that
+/// truncated input fails gracefully, with an error of truncation.
+///
+/// Every prefix of a module of up to 256 bytes is decoded. For a longer
+/// module, those ending within two bytes of a section boundary are, along
+/// with 64 evenly spaced others.
+///
+/// # Panics
+///
+/// Panics if the module fails to decode, or if a prefix not ending on a
+/// section boundary decodes or fails with an error other than a storage EOF
+/// or a declared length or size that the input cannot satisfy.
+pub fn assert_no_panic_on_prefixes(bytes: &[u8]) {
+    let decode = |bytes: &[u8]| {
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).map(|_| ())
+    };
+    let result = decode(bytes);
+    assert!(result.is_ok(), "module failed to decode: {result:?}");
+
+    // The ends of the header and of each section, at which a prefix is a
+    // well-formed module of its own (if not a valid one).
+    let mut boundaries = Vec::new_in(Global);
+    boundaries.push(8);
+    if let Ok(sections) = scan_bytes(bytes, Global) {
+        boundaries.extend(
+            sections
+                .iter()
+                .map(|section| section.offset + section.len as usize),
+        );
+    }
+
+    let stride = bytes.len().div_ceil(SAMPLED_PREFIXES);
+    let sampled = |len: usize| {
+        bytes.len() <= MAX_EXHAUSTIVE_LEN
+            || len.is_multiple_of(stride)
+            || boundaries.iter().any(|&end| len.abs_diff(end) <= 2)
+    };
+    for len in (0..bytes.len()).filter(|&len| sampled(len)) {
+        let result = decode(&bytes[..len]);
+        if boundaries.contains(&len) {
+            continue;
+        }
+        assert!(
+            matches!(
+                result.as_ref().map_err(|err| err.error().kind()),
+                Err(ErrorKind::Storage
+                    | ErrorKind::LengthOutOfBounds
+                    | ErrorKind::InvalidSectionLength
+                    | ErrorKind::InvalidFunctionLength)
+            ),
+            "prefix of length {len} did not fail with truncation: {result:?}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::encoding::{leb128, section};
+
+    const FUNCTIONS: u8 = 32;
+
+    #[test]
+    fn prefixes() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x00, // exportsec
+            0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x2a, 0x0b, // codesec
+            0x00, 0x03, 0x01, b'a', 0xff, // customsec
+        ];
+        assert_no_panic_on_prefixes(&bytes);
+
+        // A module long enough for its prefixes to be sampled: one with 32
+        // functions of type [] -> [], each repeating `i32.const 42; drop`.
+        let mut bytes = Vec::from(*b"\0asm\x01\0\0\0");
+        section(&mut bytes, 1, |out| out.extend([0x01, 0x60, 0x00, 0x00]));
+        section(&mut bytes, 3, |out| {
+            out.push(FUNCTIONS);
+            out.extend((0..FUNCTIONS).map(|_| 0x00));
+        });
+        section(&mut bytes, 10, |out| {
+            out.push(FUNCTIONS);
+            for _ in 0..FUNCTIONS {
+                let body = [[0x41, 0x2a, 0x1a]; 4].concat();
+                leb128(out, body.len() as u64 + 2);
+                out.push(0x00);
+                out.extend(body);
+                out.push(0x0b);
+            }
+        });
+        assert!(bytes.len() > MAX_EXHAUSTIVE_LEN);
+        assert_no_panic_on_prefixes(&bytes);
+    }
+
+    #[test]
+    #[should_panic(expected = "module failed to decode")]
+    fn malformed() {
+        // An unknown opcode within the function body.
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x05, 0x01, 0x03, 0x00, 0xff, 0x0b, // codesec
+        ];
+        assert_no_panic_on_prefixes(&bytes);
+    }
+}
@@ -0,0 +1,622 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Content digests of modules, which give stable identities to modules (e.g.,
+//! as cache keys) without re-reading or re-decoding them.
+//!
+//! The hash function is up to the caller, by way of the [`Digest`] trait.
+//! Digests may be taken of a module binary, with [`hash_module_stream`] and
+//! [`hash_module_bytes`], or of a decoded module, with [`Module::digest`].
+
+#[cfg(feature = "scan")]
+use crate::decode::{self, ContextStack, walk_module_bytes};
+#[cfg(feature = "scan")]
+use crate::storage::{self, MemoryEof, Stream};
+#[cfg(feature = "experimental")]
+use crate::types::Handler;
+use crate::types::{
+    BlockType, DataMode, ElementInit, ElementMode, ExportDescriptor, Expression, FuncIdx,
+    ImportDescriptor, Instruction, Limits, Local, Name, Opcode, Operands, Operator, SkippedSection,
+    TypeIdx, ValType,
+};
+use crate::{Allocator, Module};
+
+/// A hash function fed incrementally, like those of cryptographic hashing
+/// crates.
+///
+/// The digest itself is retrieved from the implementation, which is free to
+/// produce one of any size.
+pub trait Digest {
+    /// Feeds bytes into the hash.
+    fn update(&mut self, bytes: &[u8]);
+}
+
+impl<D: Digest + ?Sized> Digest for &mut D {
+    fn update(&mut self, bytes: &[u8]) {
+        (**self).update(bytes);
+    }
+}
+
+/// Discards its input, for when only one of the digests of
+/// [`hash_module_stream`] is wanted.
+impl Digest for () {
+    fn update(&mut self, _: &[u8]) {}
+}
+
+/// Hashes a module binary from streaming storage into two digests:
+/// * `binary`, which is fed every byte of the module as is;
+/// * `semantic`, which is fed the bytes of all but the custom sections, and so
+///   is unaffected by debug information, producer metadata, and the like.
+///
+/// Only the module header and section headers are decoded, so no checks are
+/// made of section order or contents.
+#[cfg(feature = "scan")]
+pub fn hash_module_stream<Storage: Stream, B: Digest, S: Digest>(
+    storage: Storage,
+    binary: &mut B,
+    semantic: &mut S,
+) -> Result<(), decode::ErrorWithContext<Storage::Error>> {
+    let mut context = ContextStack::default();
+    walk_module_bytes(storage, &mut context, |custom, bytes| {
+        binary.update(bytes);
+        if !custom {
+            semantic.update(bytes);
+        }
+    })
+    .map_err(|error| decode::ErrorWithContext { error, context })
+}
+
+/// Hashes a module binary directly from memory (see [`hash_module_stream`]).
+#[cfg(feature = "scan")]
+pub fn hash_module_bytes<Bytes: AsRef<[u8]>, B: Digest, S: Digest>(
+    bytes: Bytes,
+    binary: &mut B,
+    semantic: &mut S,
+) -> Result<(), decode::ErrorWithContext<MemoryEof>> {
+    hash_module_stream(storage::Buffer::new(bytes), binary, semantic)
+}
+
+impl<A: Allocator> Module<A> {
+    /// Hashes a canonical encoding of the module into a digest, such that
+    /// semantically equal modules (see
+    /// [`semantically_eq`](Self::semantically_eq)) hash alike.
+    ///
+    /// The encoding is that of the crate rather than the binary format, so the
+    /// digest differs from those of [`hash_module_stream`]; nor is it stable
+    /// across versions of the crate.
+    ///
+    /// Of a section skipped on decoding (see
+    /// [`DecodeOptions::skip_code`](crate::decode::DecodeOptions::skip_code)),
+    /// only the extent is known, and so only that is hashed: such modules hash
+    /// apart from those with the section decoded, but may hash alike if they
+    /// differ only within the section.
+    pub fn digest<D: Digest + ?Sized>(&self, digest: &mut D) {
+        let mut hashing = Hashing {
+            module: self,
+            digest,
+        };
+        hashing.module();
+    }
+}
+
+// Feeds the canonical encoding of a module into a digest. Integers are fed in
+// little-endian order, variable-length items are prefixed by their lengths,
+// and variants by tags.
+struct Hashing<'a, A: Allocator, D: Digest + ?Sized> {
+    module: &'a Module<A>,
+    digest: &'a mut D,
+}
+
+impl<A: Allocator, D: Digest + ?Sized> Hashing<'_, A, D> {
+    fn module(&mut self) {
+        let module = self.module;
+        self.u32(module.version as u32);
+
+        self.len(module.importsec.len());
+        for import in module.importsec.iter() {
+            self.name(&import.module);
+            self.name(&import.field);
+            match import.descriptor {
+                ImportDescriptor::Function(ty) => {
+                    self.u8(0);
+                    self.ty(ty);
+                }
+                ImportDescriptor::Table(table) => {
+                    self.u8(1);
+                    self.u8(table.reftype as u8);
+                    self.limits(&table.limits);
+                }
+                ImportDescriptor::Memory(mem) => {
+                    self.u8(2);
+                    self.limits(&mem);
+                }
+                ImportDescriptor::Global(global) => {
+                    self.u8(3);
+                    self.u8(global.value as u8);
+                    self.u8(global.mutability as u8);
+                }
+            }
+        }
+
+        self.len(module.funcsec.len());
+        for ty in module.funcsec.iter() {
+            self.ty(*ty);
+        }
+
+        self.len(module.tablesec.len());
+        for table in module.tablesec.iter() {
+            self.u8(table.reftype as u8);
+            self.limits(&table.limits);
+        }
+
+        self.len(module.memsec.len());
+        for mem in module.memsec.iter() {
+            self.limits(mem);
+        }
+
+        self.len(module.globalsec.len());
+        for global in module.globalsec.iter() {
+            self.u8(global.ty.value as u8);
+            self.u8(global.ty.mutability as u8);
+            self.expr(&global.init);
+        }
+
+        self.len(module.exportsec.len());
+        for export in module.exportsec.iter() {
+            self.name(&export.field);
+            let (kind, idx) = match export.descriptor {
+                ExportDescriptor::Function(idx) => (0, *idx),
+                ExportDescriptor::Table(idx) => (1, *idx),
+                ExportDescriptor::Memory(idx) => (2, *idx),
+                ExportDescriptor::Global(idx) => (3, *idx),
+            };
+            self.u8(kind);
+            self.u32(idx);
+        }
+
+        match module.startsec {
+            Some(start) => {
+                self.u8(1);
+                self.u32(**start);
+            }
+            None => self.u8(0),
+        }
+
+        self.len(module.elemsec.len());
+        for elem in module.elemsec.iter() {
+            self.u8(elem.ty as u8);
+            match &elem.mode {
+                ElementMode::Passive => self.u8(0),
+                ElementMode::Active(active) => {
+                    self.u8(1);
+                    self.u32(*active.table);
+                    self.expr(&active.offset);
+                }
+                ElementMode::Declarative => self.u8(2),
+            }
+            // Function indices are hashed as the equivalent `ref.func`
+            // expressions.
+            match &elem.init {
+                ElementInit::FunctionIndices(funcs) => {
+                    self.len(funcs.len());
+                    for funcidx in funcs {
+                        self.ref_func(*funcidx);
+                    }
+                }
+                ElementInit::Expressions(exprs) => {
+                    self.len(exprs.len());
+                    for expr in exprs {
+                        self.expr(expr);
+                    }
+                }
+            }
+        }
+
+        self.skipped(module.skipped_codesec);
+        self.len(module.codesec.len());
+        for func in module.codesec.iter() {
+            self.len(func.locals.len());
+            for local in func.locals.iter() {
+                self.local(local);
+            }
+            self.expr(&func.code);
+        }
+
+        self.skipped(module.skipped_datasec);
+        self.len(module.datasec.len());
+        for data in module.datasec.iter() {
+            self.bytes(&data.init);
+            match &data.mode {
+                DataMode::Passive() => self.u8(0),
+                DataMode::Active(active) => {
+                    self.u8(1);
+                    self.u32(*active.memory);
+                    self.expr(&active.offset);
+                }
+            }
+        }
+    }
+
+    fn skipped(&mut self, skipped: Option<SkippedSection>) {
+        match skipped {
+            Some(skipped) => {
+                self.u8(1);
+                self.u32(skipped.count);
+                self.u32(skipped.size);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    // Type indices are hashed as the function types they refer to, as they
+    // are compared.
+    fn ty(&mut self, idx: TypeIdx) {
+        self.block_type(BlockType::TypeIndex(idx));
+    }
+
+    fn block_type(&mut self, blocktype: BlockType) {
+        let (parameters, results): (&[ValType], &[ValType]) = match blocktype {
+            BlockType::Empty => (&[], &[]),
+            BlockType::Result(ref ty) => (&[], core::slice::from_ref(ty)),
+            BlockType::TypeIndex(idx) => {
+                let Some(ty) = self.module.typesec.get(*idx as usize) else {
+                    // Out-of-range indices, in unvalidated modules.
+                    self.u8(1);
+                    self.u32(*idx);
+                    return;
+                };
+                (&ty.parameters, &ty.results)
+            }
+        };
+        self.u8(0);
+        self.val_types(parameters.iter().copied(), parameters.len());
+        self.val_types(results.iter().copied(), results.len());
+    }
+
+    fn val_types(&mut self, types: impl Iterator<Item = ValType>, len: usize) {
+        self.len(len);
+        for ty in types {
+            self.u8(ty as u8);
+        }
+    }
+
+    fn limits(&mut self, limits: &Limits) {
+        self.u32(limits.min);
+        match limits.max {
+            Some(max) => {
+                self.u8(1);
+                self.u32(max);
+            }
+            None => self.u8(0),
+        }
+        self.u8(u8::from(limits.shared));
+    }
+
+    fn local(&mut self, local: &Local) {
+        match *local {
+            Local::I32(value) => {
+                self.u8(0);
+                self.digest.update(&value.to_le_bytes());
+            }
+            Local::I64(value) => {
+                self.u8(1);
+                self.digest.update(&value.to_le_bytes());
+            }
+            Local::F32(value) => {
+                self.u8(2);
+                self.u32(value.to_bits());
+            }
+            Local::F64(value) => {
+                self.u8(3);
+                self.u64(value.to_bits());
+            }
+            Local::Vec(value) => {
+                self.u8(4);
+                self.digest.update(&value.to_le_bytes());
+            }
+            Local::FuncRef(value) => {
+                self.u8(5);
+                self.u32(value);
+            }
+            Local::ExternRef(value) => {
+                self.u8(6);
+                self.u32(value);
+            }
+        }
+    }
+
+    // Expressions are hashed as their sequences of instructions, each preceded
+    // by a 1 and the whole followed by a 0.
+    fn expr(&mut self, expr: &Expression<A>) {
+        for instr in expr.instructions() {
+            self.u8(1);
+            self.instr(&instr);
+        }
+        self.u8(0);
+    }
+
+    // As expr() would hash `(ref.func funcidx) end`.
+    fn ref_func(&mut self, funcidx: FuncIdx) {
+        let instrs = [
+            (Opcode::RefFunc, Operands::Idx(*funcidx)),
+            (Opcode::End, Operands::None),
+        ];
+        for (op, operands) in instrs {
+            self.u8(1);
+            self.instr(&Instruction {
+                offset: 0,
+                op: Operator::Basic(op),
+                operands,
+            });
+        }
+        self.u8(0);
+    }
+
+    fn operator(&mut self, op: Operator) {
+        match op {
+            Operator::Basic(op) => self.u8(op as u8),
+            Operator::Bulk(op) => {
+                self.u8(0xfc);
+                self.u32(op as u32);
+            }
+            Operator::Atomic(op) => {
+                self.u8(0xfe);
+                self.u32(op as u32);
+            }
+        }
+    }
+
+    // Instructions are hashed without their offsets, which depend on the
+    // encoding.
+    fn instr(&mut self, instr: &Instruction<'_>) {
+        self.operator(instr.op);
+        match instr.operands {
+            Operands::None => self.u8(0),
+            Operands::BlockType(blocktype) => {
+                self.u8(1);
+                self.block_type(blocktype);
+            }
+            Operands::BrTable { labels, default } => {
+                self.u8(2);
+                self.len(labels.len());
+                for label in labels.iter() {
+                    self.u32(*label);
+                }
+                self.u32(*default);
+            }
+            Operands::CallIndirect(operands) => {
+                self.u8(3);
+                self.u32(*operands.table);
+                self.ty(operands.ty);
+            }
+            // Floats are hashed bitwise, as they are compared.
+            Operands::F32(value) => {
+                self.u8(4);
+                self.u32(value.to_bits());
+            }
+            Operands::F64(value) => {
+                self.u8(5);
+                self.u64(value.to_bits());
+            }
+            Operands::I32(value) => {
+                self.u8(6);
+                self.digest.update(&value.to_le_bytes());
+            }
+            Operands::I64(value) => {
+                self.u8(7);
+                self.digest.update(&value.to_le_bytes());
+            }
+            Operands::Idx(idx) => {
+                self.u8(8);
+                self.u32(idx);
+            }
+            Operands::MemArg(memarg) => {
+                self.u8(9);
+                self.u32(memarg.offset);
+                self.u32(memarg.align);
+            }
+            Operands::RefType(reftype) => {
+                self.u8(10);
+                self.u8(reftype as u8);
+            }
+            Operands::SelectT(types) => {
+                self.u8(11);
+                self.val_types(types.iter(), types.len());
+            }
+            Operands::TableCopy(operands) => {
+                self.u8(12);
+                self.u32(*operands.src);
+                self.u32(*operands.dst);
+            }
+            Operands::TableInit(operands) => {
+                self.u8(13);
+                self.u32(*operands.table);
+                self.u32(*operands.elem);
+            }
+            // Instructions of in-flight proposals hash their type indices as
+            // is.
+            #[cfg(feature = "experimental")]
+            Operands::ContBind(operands) => {
+                self.u8(14);
+                self.u32(*operands.src);
+                self.u32(*operands.dst);
+            }
+            #[cfg(feature = "experimental")]
+            Operands::Resume { ty, handlers } => {
+                self.u8(15);
+                self.u32(*ty);
+                self.handlers(handlers.iter(), handlers.len());
+            }
+            #[cfg(feature = "experimental")]
+            Operands::ResumeThrow { ty, tag, handlers } => {
+                self.u8(16);
+                self.u32(*ty);
+                self.u32(*tag);
+                self.handlers(handlers.iter(), handlers.len());
+            }
+            #[cfg(feature = "experimental")]
+            Operands::Switch(operands) => {
+                self.u8(17);
+                self.u32(*operands.ty);
+                self.u32(*operands.tag);
+            }
+        }
+    }
+
+    #[cfg(feature = "experimental")]
+    fn handlers(&mut self, handlers: impl Iterator<Item = Handler>, len: usize) {
+        self.len(len);
+        for handler in handlers {
+            match handler {
+                Handler::OnLabel { tag, label } => {
+                    self.u8(0);
+                    self.u32(*tag);
+                    self.u32(*label);
+                }
+                Handler::OnSwitch(tag) => {
+                    self.u8(1);
+                    self.u32(*tag);
+                }
+            }
+        }
+    }
+
+    // Both the (lossily) decoded name and the original bytes, as compared.
+    fn name<B: Allocator>(&mut self, name: &Name<B>) {
+        self.bytes(name.as_bytes());
+        match name.raw_bytes() {
+            Some(raw) => {
+                self.u8(1);
+                self.bytes(raw);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.digest.update(bytes);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u64(len as u64);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.digest.update(&[value]);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.digest.update(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.digest.update(&value.to_le_bytes());
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::{DecodeOptions, NoCustomSectionVisitor};
+
+    // Records what is fed to it, for comparison.
+    #[derive(Default)]
+    struct Recorder(std::vec::Vec<u8>);
+
+    impl Digest for Recorder {
+        fn update(&mut self, bytes: &[u8]) {
+            self.0.extend_from_slice(bytes);
+        }
+    }
+
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+        0x03, 0x02, 0x01, 0x00, // funcsec
+        0x00, 0x83, 0x00, 0x01, b'a', 0xff, // customsec (padded length)
+        0x0a, 0x09, 0x01, 0x07, 0x00, // codesec
+        0x02, 0x7f, // block (result i32)
+        0x41, 0x01, // i32.const 1
+        0x0b, 0x0b, // end end
+    ];
+
+    fn digest(bytes: &[u8], packed_expressions: bool) -> std::vec::Vec<u8> {
+        digest_with_options(
+            bytes,
+            &DecodeOptions {
+                packed_expressions,
+                ..DecodeOptions::default()
+            },
+        )
+    }
+
+    fn digest_with_options(bytes: &[u8], options: &DecodeOptions) -> std::vec::Vec<u8> {
+        let module = Module::decode_bytes_with_options(
+            bytes,
+            &mut NoCustomSectionVisitor {},
+            Global,
+            options,
+        )
+        .unwrap();
+        let mut digest = Recorder::default();
+        module.digest(&mut digest);
+        digest.0
+    }
+
+    #[test]
+    fn binary_digests() {
+        let mut binary = Recorder::default();
+        let mut semantic = Recorder::default();
+        hash_module_bytes(MODULE, &mut binary, &mut semantic).unwrap();
+        assert_eq!(binary.0, MODULE);
+        assert_eq!(semantic.0, [&MODULE[..19], &MODULE[25..]].concat());
+
+        let mut semantic_only = Recorder::default();
+        hash_module_bytes(MODULE, &mut (), &mut semantic_only).unwrap();
+        assert_eq!(semantic_only.0, semantic.0);
+
+        let error = hash_module_bytes(&MODULE[..30], &mut (), &mut ())
+            .err()
+            .unwrap();
+        assert_eq!(error.error, decode::Error::Storage(MemoryEof {}));
+    }
+
+    #[test]
+    fn module_digest() {
+        let duplicated = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x09, 0x02, // typesec
+            0x60, 0x00, 0x01, 0x7f, // (type (func (result i32)))
+            0x60, 0x00, 0x01, 0x7f, // (type (func (result i32)))
+            0x03, 0x02, 0x01, 0x01, // funcsec
+            0x0a, 0x0a, 0x01, 0x08, 0x00, // codesec
+            0x02, 0x01, // block (type 1)
+            0x41, 0x81, 0x00, // i32.const 1 (padded)
+            0x0b, 0x0b, // end end
+        ];
+
+        let expected = digest(MODULE, false);
+        assert_eq!(digest(MODULE, true), expected);
+        assert_eq!(digest(&duplicated, false), expected);
+
+        let mut different = MODULE.to_vec();
+        different[33] = 0x02; // i32.const 2
+        assert_ne!(digest(&different, false), expected);
+
+        // A skipped code section is hashed by its extent alone.
+        let skip_code = DecodeOptions {
+            skip_code: true,
+            ..DecodeOptions::default()
+        };
+        let skipped = digest_with_options(MODULE, &skip_code);
+        assert_ne!(skipped, expected);
+        assert_eq!(digest_with_options(&different, &skip_code), skipped);
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Helpers for writing binary encodings by hand, shared by the generators of
+//! synthetic modules (see `testing` and `bench_support`) and by tests.
+//!
+//! Encodings are canonical, with minimal LEB128 widths.
+
+use std::vec::Vec;
+
+/// Writes a section of the given ID, with the contents written by the
+/// callback.
+pub(crate) fn section(bytes: &mut Vec<u8>, id: u8, contents: impl FnOnce(&mut Vec<u8>)) {
+    let mut payload = Vec::new();
+    contents(&mut payload);
+    bytes.push(id);
+    leb128(bytes, payload.len() as u64);
+    bytes.extend_from_slice(&payload);
+}
+
+/// Writes a length-prefixed name.
+pub(crate) fn name(out: &mut Vec<u8>, name: &str) {
+    leb128(out, name.len() as u64);
+    out.extend_from_slice(name.as_bytes());
+}
+
+/// Writes an unsigned LEB128 integer.
+pub(crate) fn leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Writes a signed LEB128 integer.
+pub(crate) fn sleb128(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f).cast_unsigned() as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Evaluation of constant expressions, as needed at instantiation to compute
+//! global initial values and the offsets of active segments.
+
+use crate::Allocator;
+use crate::types::{Expression, FuncIdx, GlobalIdx, Opcode, Operands, Operator, RefType};
+
+/// The value of a constant expression or of a global.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    /// An `i32`.
+    I32(i32),
+    /// An `i64`.
+    I64(i64),
+    /// An `f32`.
+    F32(f32),
+    /// An `f64`.
+    F64(f64),
+    /// A `v128`, as only arises from imported globals.
+    V128(u128),
+    /// A function reference, or null. Functions are referenced by their index
+    /// within the module, though the values of imported globals are free to
+    /// use the embedder's own scheme.
+    FuncRef(Option<FuncIdx>),
+    /// An external reference as an opaque handle of the embedder, or null.
+    ExternRef(Option<u32>),
+}
+
+/// Provides the values of the globals that constant expressions may reference,
+/// as resolved at instantiation.
+pub trait GlobalEnv {
+    /// Returns the value of the global of a given index, if available.
+    fn global(&self, idx: GlobalIdx) -> Option<Value>;
+}
+
+impl GlobalEnv for [Value] {
+    fn global(&self, idx: GlobalIdx) -> Option<Value> {
+        self.get(*idx as usize).copied()
+    }
+}
+
+/// The maximum depth of the operand stack in evaluating a constant expression.
+///
+/// The extended constant instructions permit arbitrarily deep stacks in
+/// principle, though toolchains only ever emit an operation or two over a
+/// `global.get`.
+pub const MAX_STACK_DEPTH: usize = 16;
+
+/// Represents errors that can arise in evaluating a constant expression.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EvalError {
+    /// The expression contains an instruction that is not constant.
+    NonConstantInstruction(Operator),
+    /// The environment provides no value for a referenced global.
+    UnknownGlobal(GlobalIdx),
+    /// Operands are of the wrong type or missing, or other than a single
+    /// result remains, which is only possible for unvalidated expressions.
+    TypeMismatch,
+    /// The operand stack exceeds [`MAX_STACK_DEPTH`].
+    StackLimitExceeded,
+}
+
+/// Evaluates a constant expression, given the values of the globals it may
+/// reference.
+///
+/// In addition to the single-instruction forms of [`ConstExpr`], the integer
+/// `add`, `sub`, and `mul` instructions of the extended constant expressions
+/// proposal are supported.
+///
+/// [`ConstExpr`]: crate::types::ConstExpr
+pub fn eval_const<A: Allocator, E: GlobalEnv + ?Sized>(
+    expr: &Expression<A>,
+    env: &E,
+) -> Result<Value, EvalError> {
+    let mut stack = Stack {
+        values: [Value::I32(0); MAX_STACK_DEPTH],
+        len: 0,
+    };
+    for instr in expr.instructions() {
+        let Operator::Basic(op) = instr.op else {
+            return Err(EvalError::NonConstantInstruction(instr.op));
+        };
+        let value = match (op, instr.operands) {
+            (Opcode::End, _) => break,
+            (Opcode::I32Const, Operands::I32(value)) => Value::I32(value),
+            (Opcode::I64Const, Operands::I64(value)) => Value::I64(value),
+            (Opcode::F32Const, Operands::F32(value)) => Value::F32(value),
+            (Opcode::F64Const, Operands::F64(value)) => Value::F64(value),
+            (Opcode::GlobalGet, Operands::Idx(idx)) => {
+                let idx = GlobalIdx::new(idx);
+                env.global(idx).ok_or(EvalError::UnknownGlobal(idx))?
+            }
+            (Opcode::RefNull, Operands::RefType(ty)) => match ty {
+                RefType::Func => Value::FuncRef(None),
+                RefType::Extern => Value::ExternRef(None),
+            },
+            (Opcode::RefFunc, Operands::Idx(idx)) => Value::FuncRef(Some(FuncIdx::new(idx))),
+            (Opcode::I32Add, _) => stack.binary_i32(i32::wrapping_add)?,
+            (Opcode::I32Sub, _) => stack.binary_i32(i32::wrapping_sub)?,
+            (Opcode::I32Mul, _) => stack.binary_i32(i32::wrapping_mul)?,
+            (Opcode::I64Add, _) => stack.binary_i64(i64::wrapping_add)?,
+            (Opcode::I64Sub, _) => stack.binary_i64(i64::wrapping_sub)?,
+            (Opcode::I64Mul, _) => stack.binary_i64(i64::wrapping_mul)?,
+            _ => return Err(EvalError::NonConstantInstruction(instr.op)),
+        };
+        stack.push(value)?;
+    }
+    match stack.len {
+        1 => Ok(stack.values[0]),
+        _ => Err(EvalError::TypeMismatch),
+    }
+}
+
+struct Stack {
+    values: [Value; MAX_STACK_DEPTH],
+    len: usize,
+}
+
+impl Stack {
+    fn push(&mut self, value: Value) -> Result<(), EvalError> {
+        let slot = self
+            .values
+            .get_mut(self.len)
+            .ok_or(EvalError::StackLimitExceeded)?;
+        *slot = value;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value, EvalError> {
+        self.len = self.len.checked_sub(1).ok_or(EvalError::TypeMismatch)?;
+        Ok(self.values[self.len])
+    }
+
+    fn binary_i32(&mut self, op: fn(i32, i32) -> i32) -> Result<Value, EvalError> {
+        match (self.pop()?, self.pop()?) {
+            (Value::I32(rhs), Value::I32(lhs)) => Ok(Value::I32(op(lhs, rhs))),
+            _ => Err(EvalError::TypeMismatch),
+        }
+    }
+
+    fn binary_i64(&mut self, op: fn(i64, i64) -> i64) -> Result<Value, EvalError> {
+        match (self.pop()?, self.pop()?) {
+            (Value::I64(rhs), Value::I64(lhs)) => Ok(Value::I64(op(lhs, rhs))),
+            _ => Err(EvalError::TypeMismatch),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    #[test]
+    fn constant_expressions() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x02, 0x08, 0x01, // importsec
+            0x01, b'm', 0x01, b'g', 0x03, 0x7f, 0x00, // (import "m" "g" (global i32))
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x06, 0x13, 0x03, // globalsec
+            0x7f, 0x00, 0x23, 0x00, 0x41, 0x08, 0x6a, 0x0b, // (global.get 0) + 8
+            0x7e, 0x00, 0x42, 0x7f, 0x0b, // (i64.const -1)
+            0x70, 0x00, 0xd0, 0x70, 0x0b, // (ref.null func)
+            0x0a, 0x07, 0x01, 0x05, 0x00, 0x41, 0x01, 0x45, 0x0b, // codesec
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        let env = [Value::I32(100)];
+        let eval = |idx: usize, env: &[Value]| eval_const(&module.globalsec[idx].init, env);
+
+        assert_eq!(eval(0, &env), Ok(Value::I32(108)));
+        assert_eq!(eval(1, &env), Ok(Value::I64(-1)));
+        assert_eq!(eval(2, &env), Ok(Value::FuncRef(None)));
+
+        assert_eq!(
+            eval(0, &[]),
+            Err(EvalError::UnknownGlobal(GlobalIdx::new(0)))
+        );
+        assert_eq!(eval(0, &[Value::I64(100)]), Err(EvalError::TypeMismatch));
+        assert_eq!(
+            eval_const(&module.codesec[0].code, &env[..]),
+            Err(EvalError::NonConstantInstruction(Operator::Basic(
+                Opcode::I32Eqz
+            )))
+        );
+    }
+}
@@ -12,36 +12,113 @@
 #[cfg(nightly)]
 extern crate alloc;
 
+pub mod analysis;
+#[cfg(feature = "decode")]
+pub mod batch;
+#[cfg(feature = "bench-support")]
+pub mod bench_support;
+mod compare;
 pub mod core_compat;
+pub mod digest;
+#[cfg(any(test, feature = "proptest", feature = "bench-support"))]
+mod encoding;
+pub mod eval;
+// With the panic-free feature, explicit failure is ruled out of the decoding
+// and validation paths; what remains are unreachable!()s of internal
+// invariants (and indexing that prior checks keep in bounds).
+#[cfg(feature = "scan")]
+#[cfg_attr(
+    all(feature = "panic-free", not(test)),
+    deny(
+        clippy::expect_used,
+        clippy::panic,
+        clippy::todo,
+        clippy::unimplemented,
+        clippy::unwrap_used
+    )
+)]
 pub mod decode;
+pub mod link;
+pub mod lint;
+pub mod merge;
+pub mod prelude;
+#[cfg(feature = "scan")]
+pub mod scan;
 pub mod storage;
+#[cfg(any(test, feature = "proptest"))]
+pub mod testing;
+#[cfg(any(feature = "decode", feature = "validate"))]
+mod trace;
+#[cfg(feature = "decode")]
+pub mod transform;
 pub mod types;
+#[cfg(feature = "validate")]
+#[cfg_attr(
+    all(feature = "panic-free", not(test)),
+    deny(
+        clippy::expect_used,
+        clippy::panic,
+        clippy::todo,
+        clippy::unimplemented,
+        clippy::unwrap_used
+    )
+)]
 pub mod validate;
 
 use core::fmt;
 
-use decode::{ContextStack, CustomSectionVisitor, decode_module};
+use core_compat::alloc::Global;
+use core_compat::vec::Vec;
+#[cfg(feature = "decode")]
+use decode::{
+    ContextStack, CustomSectionVisitor, DataSegmentSink, DecodeOptions, ExpressionBuilder,
+    LenientResult, NoCustomSectionVisitor, NoDataSegmentSink, SectionAllocators, decode_module,
+    decode_module_lenient, prepare_module_for_validation,
+};
+#[cfg(feature = "decode")]
 use storage::{MemoryEof, Stream};
 use types::{
-    CodeSection, DataSection, ElementSection, ExportSection, FunctionSection, GlobalSection,
-    ImportSection, MemorySection, StartSection, TableSection, TypeSection, Version,
+    CodeSection, DataSection, ElementSection, ExportSection, ExpressionStats, FuncIdx,
+    FunctionSection, GlobalSection, ImportSection, MemorySection, NameSection, SkippedSection,
+    StartSection, TableSection, TypeSection, Version,
 };
-use validate::{prepare_module_for_validation, validate_module};
+#[cfg(feature = "validate")]
+use types::{ExportIndex, FunctionType, GlobalType, IndexSpace, MemType, TableType};
+#[cfg(feature = "validate")]
+use validate::validate_module;
+#[cfg(feature = "rayon")]
+use validate::validate_module_parallel;
 
 /// A convenience trait that captures the commonly required allocation-related
 /// trait bounds.
+///
+/// It is implemented for any allocator of the backend in use, which is
+/// `allocator-api2` unless the `nightly` feature is enabled on a nightly
+/// toolchain (see [`core_compat`]).
 pub trait Allocator: core_compat::alloc::Allocator + fmt::Debug + Clone {}
 
 impl<A> Allocator for A where A: core_compat::alloc::Allocator + fmt::Debug + Clone {}
 
+/// A module with the global allocator.
+pub type DefaultModule = Module<Global>;
+
+#[cfg(feature = "validate")]
+/// A validated module with the global allocator.
+pub type DefaultValidatedModule = ValidatedModule<Global>;
+
 /// A WebAssembly module.
 pub struct Module<A: Allocator> {
     /// Module version.
     pub version: Version,
     /// Function type declarations.
     pub typesec: TypeSection<A>,
-    /// Import declarations.
+    /// Import declarations, grouped by kind (see
+    /// [`imports_by_kind`](Self::imports_by_kind)).
     pub importsec: ImportSection<A>,
+    // The position within `importsec` of each import, in binary order; empty
+    // if `importsec` is already in binary order, and disregarded if it no
+    // longer matches `importsec` in length (see `imports_in_binary_order()`).
+    pub(crate) import_order: Vec<u32, A>,
     /// Function type indices.
     pub funcsec: FunctionSection<A>,
     /// Table declarations.
@@ -62,36 +139,684 @@ pub struct Module<A: Allocator> {
     pub codesec: CodeSection<A>,
     /// Data segments.
     pub datasec: DataSection<A>,
+    /// The extent of the code section, if skipped on decoding (see
+    /// [`DecodeOptions::skip_code`](decode::DecodeOptions::skip_code)), in
+    /// which case `codesec` is empty and the module fails validation.
+    pub skipped_codesec: Option<SkippedSection>,
+    /// The extent of the data section, if skipped on decoding (see
+    /// [`DecodeOptions::skip_data`](decode::DecodeOptions::skip_data)), in
+    /// which case `datasec` is empty and the module fails validation.
+    pub skipped_datasec: Option<SkippedSection>,
+    /// The debug names of the module's entities, if attached (e.g., as parsed
+    /// by [`NameSectionVisitor`](decode::NameSectionVisitor)), by which its
+    /// functions are displayed (see [`display_function`](Self::display_function)).
+    /// Decoding leaves these unset.
+    pub names: Option<NameSection<A>>,
 }
 
 impl<A: Allocator> Module<A> {
+    /// Creates an empty module of a given version, with each section
+    /// allocated by a given allocator. Its sections may then be filled in
+    /// directly.
+    pub fn new(version: Version, alloc: A) -> Self {
+        Self {
+            version,
+            typesec: TypeSection::new(Vec::new_in(alloc.clone())),
+            importsec: ImportSection::new(Vec::new_in(alloc.clone())),
+            import_order: Vec::new_in(alloc.clone()),
+            funcsec: FunctionSection::new(Vec::new_in(alloc.clone())),
+            tablesec: TableSection::new(Vec::new_in(alloc.clone())),
+            memsec: MemorySection::new(Vec::new_in(alloc.clone())),
+            globalsec: GlobalSection::new(Vec::new_in(alloc.clone())),
+            exportsec: ExportSection::new(Vec::new_in(alloc.clone())),
+            startsec: None,
+            elemsec: ElementSection::new(Vec::new_in(alloc.clone())),
+            datacountsec: None,
+            codesec: CodeSection::new(Vec::new_in(alloc.clone())),
+            datasec: DataSection::new(Vec::new_in(alloc)),
+            skipped_codesec: None,
+            skipped_datasec: None,
+            names: None,
+        }
+    }
+
+    #[cfg(feature = "decode")]
     /// Decodes the module from streaming storage, with a given allocator and a
     /// custom section visitor.
     pub fn decode<Storage: Stream, CustomSecVisitor: CustomSectionVisitor<A>>(
         storage: Storage,
         customsec_visitor: &mut CustomSecVisitor,
         alloc: A,
-    ) -> Result<Self, decode::ErrorWithContext<Storage::Error>> {
-        let mut context = ContextStack::default();
-        let mut module = decode_module(storage, &mut context, customsec_visitor, alloc)
-            .map_err(|error| decode::ErrorWithContext { error, context })?;
-        // Prepare now so the validation phase can take it for granted that
-        // certain internal invariants hold for any constructed Module.
-        prepare_module_for_validation(&mut module);
-        Ok(module)
+    ) -> Result<Self, decode::ErrorWithContext<Storage::Error, A>> {
+        Self::decode_with_options(storage, customsec_visitor, alloc, &DecodeOptions::default())
+    }
+
+    #[cfg(feature = "decode")]
+    /// Decodes the module from streaming storage, as with
+    /// [`decode`](Self::decode), but with the given options.
+    pub fn decode_with_options<Storage: Stream, CustomSecVisitor: CustomSectionVisitor<A>>(
+        storage: Storage,
+        customsec_visitor: &mut CustomSecVisitor,
+        alloc: A,
+        options: &DecodeOptions,
+    ) -> Result<Self, decode::ErrorWithContext<Storage::Error, A>> {
+        Self::decode_with_data_sink(
+            storage,
+            customsec_visitor,
+            &mut NoDataSegmentSink {},
+            alloc,
+            options,
+        )
+    }
+
+    #[cfg(feature = "decode")]
+    /// Decodes the module from streaming storage, as with
+    /// [`decode_with_options`](Self::decode_with_options), but streaming the
+    /// contents of data segments to the given sink as it directs.
+    pub fn decode_with_data_sink<
+        Storage: Stream,
+        CustomSecVisitor: CustomSectionVisitor<A>,
+        DataSink: DataSegmentSink<A>,
+    >(
+        storage: Storage,
+        customsec_visitor: &mut CustomSecVisitor,
+        data_sink: &mut DataSink,
+        alloc: A,
+        options: &DecodeOptions,
+    ) -> Result<Self, decode::ErrorWithContext<Storage::Error, A>> {
+        Self::decode_with_allocators(
+            storage,
+            customsec_visitor,
+            data_sink,
+            &SectionAllocators::uniform(alloc),
+            options,
+        )
+    }
+
+    #[cfg(feature = "decode")]
+    /// Decodes the module from streaming storage, as with
+    /// [`decode_with_data_sink`](Self::decode_with_data_sink), but allocating
+    /// the code and data sections with allocators of their own, apart from
+    /// the rest of the module.
+    pub fn decode_with_allocators<
+        Storage: Stream,
+        CustomSecVisitor: CustomSectionVisitor<A>,
+        DataSink: DataSegmentSink<A>,
+    >(
+        storage: Storage,
+        customsec_visitor: &mut CustomSecVisitor,
+        data_sink: &mut DataSink,
+        allocators: &SectionAllocators<A>,
+        options: &DecodeOptions,
+    ) -> Result<Self, decode::ErrorWithContext<Storage::Error, A>> {
+        Self::decode_with_scratch(
+            storage,
+            customsec_visitor,
+            data_sink,
+            allocators,
+            options,
+            &mut None,
+        )
+    }
+
+    // As with decode_with_allocators(), but transcoding expressions by way of
+    // the given scratch space, if any (see `batch`).
+    #[cfg(feature = "decode")]
+    pub(crate) fn decode_with_scratch<
+        Storage: Stream,
+        CustomSecVisitor: CustomSectionVisitor<A>,
+        DataSink: DataSegmentSink<A>,
+    >(
+        storage: Storage,
+        customsec_visitor: &mut CustomSecVisitor,
+        data_sink: &mut DataSink,
+        allocators: &SectionAllocators<A>,
+        options: &DecodeOptions,
+        expr_scratch: &mut Option<ExpressionBuilder<Global>>,
+    ) -> Result<Self, decode::ErrorWithContext<Storage::Error, A>> {
+        let mut context = ContextStack::new(options, allocators.metadata.clone());
+        decode_module(
+            storage,
+            options,
+            &mut context,
+            customsec_visitor,
+            data_sink,
+            allocators,
+            expr_scratch,
+        )
+        .and_then(|mut module| {
+            // Prepare now so the validation phase can take it for granted
+            // that certain internal invariants hold for any constructed
+            // Module.
+            prepare_module_for_validation(&mut module)?;
+            Ok(module)
+        })
+        .map_err(|error| decode::ErrorWithContext { error, context })
     }
 
+    #[cfg(feature = "decode")]
     /// Decodes a module directly from memory.
     pub fn decode_bytes<Bytes: AsRef<[u8]>, CustomSecVisitor: CustomSectionVisitor<A>>(
         bytes: Bytes,
         customsec_visitor: &mut CustomSecVisitor,
         alloc: A,
-    ) -> Result<Self, decode::ErrorWithContext<MemoryEof>> {
+    ) -> Result<Self, decode::ErrorWithContext<MemoryEof, A>> {
         Self::decode(storage::Buffer::new(bytes), customsec_visitor, alloc)
     }
 
-    /// Validates the module.
+    #[cfg(feature = "decode")]
+    /// Decodes the module from streaming storage as with
+    /// [`decode`](Self::decode), but recovering from errors within sections
+    /// where possible, returning the partially decoded module alongside all
+    /// errors encountered.
+    ///
+    /// An erroneous section is omitted from the module, with decoding resuming
+    /// at the next section per the declared length of the erroneous one.
+    /// Decoding ends early if the next section cannot be found this way (e.g.,
+    /// on a malformed section header or on running past a section's declared
+    /// length), and fails outright on an invalid module header.
+    pub fn decode_lenient<Storage: Stream, CustomSecVisitor: CustomSectionVisitor<A>>(
+        storage: Storage,
+        customsec_visitor: &mut CustomSecVisitor,
+        alloc: A,
+    ) -> LenientResult<A, Storage::Error> {
+        let (mut module, errors) =
+            decode_module_lenient(storage, &DecodeOptions::default(), customsec_visitor, alloc)?;
+        prepare_module_for_validation(&mut module).map_err(|error| decode::ErrorWithContext {
+            error,
+            context: ContextStack::default(),
+        })?;
+        Ok((module, errors))
+    }
+
+    #[cfg(feature = "decode")]
+    /// Decodes a module directly from memory, with the given options.
+    pub fn decode_bytes_with_options<
+        Bytes: AsRef<[u8]>,
+        CustomSecVisitor: CustomSectionVisitor<A>,
+    >(
+        bytes: Bytes,
+        customsec_visitor: &mut CustomSecVisitor,
+        alloc: A,
+        options: &DecodeOptions,
+    ) -> Result<Self, decode::ErrorWithContext<MemoryEof, A>> {
+        Self::decode_with_options(
+            storage::Buffer::new(bytes),
+            customsec_visitor,
+            alloc,
+            options,
+        )
+    }
+
+    #[cfg(feature = "validate")]
+    /// Validates the module against the core specification.
     pub fn validate(&self) -> Result<(), validate::Error> {
-        validate_module(self)
+        self.validate_with_features(validate::Features::default())
+    }
+
+    #[cfg(feature = "validate")]
+    /// Validates the module, additionally accepting the given proposals.
+    pub fn validate_with_features(
+        &self,
+        features: validate::Features,
+    ) -> Result<(), validate::Error> {
+        self.validate_with_options(&features.into())
+    }
+
+    #[cfg(feature = "validate")]
+    /// Validates the module with the given options, which may additionally
+    /// limit the complexity of function bodies.
+    pub fn validate_with_options(
+        &self,
+        options: &validate::ValidateOptions,
+    ) -> Result<(), validate::Error> {
+        validate_module(self, options, true)
+    }
+
+    #[cfg(feature = "validate")]
+    /// Validates the module as with
+    /// [`validate_with_features`](Self::validate_with_features), failing with
+    /// [`validate::Error::ComplexityLimitExceeded`] on any function body of
+    /// more than `max_function_instructions` instructions (counting the
+    /// terminating `end`).
+    ///
+    /// As validation of a function body takes time linear in its length,
+    /// this bounds the worst-case time spent on any one function, as may be
+    /// needed when validating untrusted modules. This is shorthand for
+    /// [`ValidateOptions::max_function_instructions`](validate::ValidateOptions::max_function_instructions).
+    pub fn validate_with_budget(
+        &self,
+        features: validate::Features,
+        max_function_instructions: usize,
+    ) -> Result<(), validate::Error> {
+        let mut options = validate::ValidateOptions::from(features);
+        options.max_function_instructions = Some(max_function_instructions);
+        self.validate_with_options(&options)
+    }
+
+    #[cfg(feature = "validate")]
+    /// Validates the structure of the module: everything but its function
+    /// bodies, which typically account for the bulk of validation time. This
+    /// makes for a cheap pre-filter ahead of full validation.
+    pub fn validate_structure(&self) -> Result<(), validate::Error> {
+        self.validate_structure_with_features(validate::Features::default())
+    }
+
+    #[cfg(feature = "validate")]
+    /// Validates the structure of the module, additionally accepting the given
+    /// proposals.
+    pub fn validate_structure_with_features(
+        &self,
+        features: validate::Features,
+    ) -> Result<(), validate::Error> {
+        validate_module(self, &features.into(), false)
+    }
+
+    #[cfg(feature = "rayon")]
+    /// Validates the module as with [`validate`](Self::validate), but with
+    /// independent sections and function bodies validated concurrently on
+    /// the rayon thread pool. The error returned for an invalid module is the
+    /// same.
+    pub fn validate_parallel(&self) -> Result<(), validate::Error>
+    where
+        A: Sync,
+    {
+        self.validate_parallel_with_features(validate::Features::default())
+    }
+
+    #[cfg(feature = "rayon")]
+    /// Validates the module concurrently, additionally accepting the given
+    /// proposals.
+    pub fn validate_parallel_with_features(
+        &self,
+        features: validate::Features,
+    ) -> Result<(), validate::Error>
+    where
+        A: Sync,
+    {
+        self.validate_parallel_with_options(&features.into())
+    }
+
+    #[cfg(feature = "rayon")]
+    /// Validates the module concurrently with the given options, as with
+    /// [`validate_with_options`](Self::validate_with_options).
+    pub fn validate_parallel_with_options(
+        &self,
+        options: &validate::ValidateOptions,
+    ) -> Result<(), validate::Error>
+    where
+        A: Sync,
+    {
+        validate_module_parallel(self, options)
+    }
+
+    /// Computes aggregate statistics over all function bodies.
+    pub fn code_stats(&self) -> ExpressionStats {
+        let mut stats = ExpressionStats::default();
+        for function in self.codesec.iter() {
+            stats.merge(&function.code.stats());
+        }
+        stats
+    }
+}
+
+// Modules are summarized rather than dumped in full, with functions referred
+// to by name where known (see `Module::names`).
+impl<A: Allocator> fmt::Debug for Module<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let function_count = self.imports_by_kind().functions.len() + self.funcsec.len();
+        let functions =
+            (0..function_count as u32).map(|idx| self.display_function(FuncIdx::new(idx)));
+        let name = self
+            .names
+            .as_ref()
+            .and_then(|names| names.module.as_deref());
+        f.debug_struct("Module")
+            .field("version", &self.version)
+            .field("name", &name.map(|name| &**name))
+            .field("types", &self.typesec.len())
+            .field("imports", &self.importsec.len())
+            .field("functions", &DebugList(functions))
+            .field("tables", &self.tablesec.len())
+            .field("memories", &self.memsec.len())
+            .field("globals", &self.globalsec.len())
+            .field("exports", &self.exportsec.len())
+            .field(
+                "start",
+                &self
+                    .startsec
+                    .as_ref()
+                    .map(|start| self.display_function(**start)),
+            )
+            .field("elements", &self.elemsec.len())
+            .field("data", &self.datasec.len())
+            .finish_non_exhaustive()
+    }
+}
+
+// Formats an iterator as a list, as with `fmt::Formatter::debug_list()`.
+struct DebugList<I>(I);
+
+impl<I: Iterator<Item = T> + Clone, T: fmt::Debug> fmt::Debug for DebugList<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.clone()).finish()
+    }
+}
+
+impl Module<Global> {
+    #[cfg(feature = "decode")]
+    /// Decodes a module directly from memory, with the global allocator and
+    /// with custom sections ignored.
+    pub fn from_bytes<Bytes: AsRef<[u8]>>(
+        bytes: Bytes,
+    ) -> Result<Self, decode::ErrorWithContext<MemoryEof>> {
+        Self::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+    }
+}
+
+#[cfg(feature = "validate")]
+/// A module that has been decoded and validated.
+pub struct ValidatedModule<A: Allocator> {
+    module: Module<A>,
+    // The slots of the export index (see types::export_index_slots()).
+    export_slots: Vec<u32, A>,
+}
+
+#[cfg(feature = "validate")]
+impl<A: Allocator> ValidatedModule<A> {
+    /// Validates a decoded module against the core specification, and indexes
+    /// its exports by name.
+    pub fn new(module: Module<A>) -> Result<Self, validate::Error> {
+        module.validate()?;
+        let alloc = module.exportsec.allocator().clone();
+        let export_slots = types::export_index_slots(&module.exportsec, alloc)
+            .map_err(|_| validate::Error::AllocError)?;
+        Ok(Self {
+            module,
+            export_slots,
+        })
+    }
+
+    /// Returns the index of the module's exports by name, which is built on
+    /// validation.
+    pub fn export_index(&self) -> ExportIndex<'_, A> {
+        ExportIndex::new(&self.module.exportsec, &self.export_slots)
+    }
+
+    /// The types of the functions of the module, by function index: imported
+    /// functions, followed by those defined by the module. As the module is
+    /// valid, every index within the bounds of the index space resolves.
+    pub fn function_types(&self) -> IndexSpace<'_, A, &FunctionType<A>> {
+        self.module.function_types()
+    }
+
+    /// The types of the tables of the module, by table index (as with
+    /// [`function_types`](Self::function_types)).
+    pub fn table_types(&self) -> IndexSpace<'_, A, TableType> {
+        self.module.table_types()
+    }
+
+    /// The types of the memories of the module, by memory index (as with
+    /// [`function_types`](Self::function_types)).
+    pub fn memory_types(&self) -> IndexSpace<'_, A, MemType> {
+        self.module.memory_types()
+    }
+
+    /// The types of the globals of the module, by global index (as with
+    /// [`function_types`](Self::function_types)).
+    pub fn global_types(&self) -> IndexSpace<'_, A, GlobalType> {
+        self.module.global_types()
+    }
+
+    /// Returns the underlying module.
+    pub fn into_inner(self) -> Module<A> {
+        self.module
+    }
+}
+
+#[cfg(feature = "validate")]
+impl<A: Allocator> core::ops::Deref for ValidatedModule<A> {
+    type Target = Module<A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.module
+    }
+}
+
+#[cfg(all(feature = "decode", feature = "validate"))]
+/// Represents errors that can arise in decoding and validating a module in a
+/// single call.
+pub enum Error<StorageError> {
+    /// The module failed to decode.
+    Decode(decode::ErrorWithContext<StorageError>),
+    /// The module decoded, but failed to validate.
+    Validate(validate::Error),
+}
+
+#[cfg(all(feature = "decode", feature = "validate"))]
+impl<StorageError: fmt::Debug> fmt::Debug for Error<StorageError> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Decode(err) => write!(f, "decoding failed: {err:?}"),
+            Error::Validate(err) => write!(f, "validation failed: {err:?}"),
+        }
+    }
+}
+
+#[cfg(all(feature = "decode", feature = "validate"))]
+impl<StorageError> From<decode::ErrorWithContext<StorageError>> for Error<StorageError> {
+    fn from(err: decode::ErrorWithContext<StorageError>) -> Self {
+        Error::Decode(err)
+    }
+}
+
+#[cfg(all(feature = "decode", feature = "validate"))]
+impl<StorageError> From<validate::Error> for Error<StorageError> {
+    fn from(err: validate::Error) -> Self {
+        Error::Validate(err)
+    }
+}
+
+#[cfg(all(feature = "decode", feature = "validate"))]
+/// Decodes and validates a module from streaming storage, with the global
+/// allocator and with custom sections ignored.
+pub fn decode_and_validate_stream<Storage: Stream>(
+    storage: Storage,
+) -> Result<ValidatedModule<Global>, Error<Storage::Error>> {
+    let module = Module::decode(storage, &mut NoCustomSectionVisitor {}, Global)?;
+    Ok(ValidatedModule::new(module)?)
+}
+
+#[cfg(all(feature = "decode", feature = "validate"))]
+/// Decodes and validates a module directly from memory, with the global
+/// allocator and with custom sections ignored.
+pub fn decode_and_validate<Bytes: AsRef<[u8]>>(
+    bytes: Bytes,
+) -> Result<ValidatedModule<Global>, Error<MemoryEof>> {
+    decode_and_validate_stream(storage::Buffer::new(bytes))
+}
+
+#[cfg(all(feature = "decode", feature = "validate"))]
+/// Decodes and validates arbitrary bytes by every available means, as an entry
+/// point for fuzzing (see `fuzz/`). Untrusted input should only ever give rise
+/// to errors, so any panic here is a bug.
+///
+/// # Panics
+///
+/// Panics if decoding from pushed chunks of the bytes and decoding them from
+/// memory disagree on whether they are well-formed.
+pub fn fuzz_entry(bytes: &[u8]) {
+    let strict = decode_and_validate(bytes);
+    if let Ok(module) = &strict {
+        module.code_stats();
+        module.import_types().for_each(drop);
+        module.export_types().for_each(drop);
+    }
+
+    // Decoding from pushed chunks should agree with decoding from memory.
+    let mut visitor = NoCustomSectionVisitor {};
+    let mut decoder = decode::PushDecoder::new(&mut visitor, Global);
+    let (front, back) = bytes.split_at(bytes.len() / 2);
+    let pushed = decoder
+        .push(front)
+        .and_then(|_| decoder.push(back))
+        .and_then(|_| decoder.finish());
+    assert_eq!(
+        pushed.is_ok(),
+        !matches!(strict, Err(Error::Decode(_))),
+        "push and pull decoding disagree"
+    );
+
+    if let Ok((module, _)) = Module::decode_lenient(
+        storage::Buffer::new(bytes),
+        &mut NoCustomSectionVisitor {},
+        Global,
+    ) {
+        let _ = module.validate_structure();
+        let _ = module.validate();
+    }
+
+    let options = DecodeOptions {
+        packed_expressions: true,
+        ..DecodeOptions::default()
+    };
+    if let Ok(module) =
+        Module::decode_bytes_with_options(bytes, &mut NoCustomSectionVisitor {}, Global, &options)
+    {
+        module.code_stats();
+        let _ = module.validate();
+    }
+
+    let _ = scan::estimate(bytes);
+}
+
+#[cfg(all(test, feature = "decode", feature = "validate"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_and_validate() {
+        let module = super::decode_and_validate([
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x08, 0x01, 0x00, // startsec
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+        ])
+        .unwrap();
+        assert_eq!(module.codesec.len(), 1);
+
+        let err = super::decode_and_validate([0x00, 0x61, 0x73, 0x6d, 0x02, 0x00, 0x00, 0x00]);
+        assert!(matches!(
+            err,
+            Err(Error::Decode(err)) if err.error == decode::Error::UnknownVersion(2)
+        ));
+
+        // The start function does not exist.
+        let err = super::decode_and_validate([
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x08, 0x01, 0x00, // startsec
+        ]);
+        assert!(matches!(err, Err(Error::Validate(_))));
+    }
+
+    #[test]
+    fn from_bytes() {
+        use crate::prelude::*;
+
+        let module = DefaultModule::from_bytes([
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+        ])
+        .unwrap();
+        assert_eq!(module.typesec.len(), 1);
+        let module: DefaultValidatedModule = ValidatedModule::new(module).unwrap();
+        assert!(module.funcsec.is_empty());
+        assert!(module.function_types().is_empty());
+    }
+
+    // A deterministic stand-in for fuzzing: every truncation of a module, and
+    // every single-bit corruption of it.
+    #[test]
+    fn fuzz_entry() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00, 0x00, // importsec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x07, 0x05, 0x01, 0x01, b'g', 0x00, 0x01, // exportsec
+            0x0a, 0x0f, 0x01, 0x0d, 0x00, // codesec
+            0x02, 0x7f, 0x41, 0x2a, 0x0b, // (block (result i32) (i32.const 42))
+            0x41, 0x00, 0x28, 0x02, 0x00, 0x6a, 0x0b, // (i32.add (i32.load (i32.const 0)))
+        ];
+        assert!(super::decode_and_validate(bytes).is_ok());
+
+        for len in 0..=bytes.len() {
+            super::fuzz_entry(&bytes[..len]);
+        }
+        for i in 0..bytes.len() {
+            for bit in 0..8 {
+                let mut corrupted = bytes;
+                corrupted[i] ^= 1 << bit;
+                super::fuzz_entry(&corrupted);
+            }
+        }
+    }
+
+    // Parallel validation agrees with sequential validation on every
+    // single-bit corruption of a module that still decodes.
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn validate_parallel() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x01, // exportsec
+            0x0a, 0x0e, 0x02, // codesec
+            0x04, 0x00, 0x41, 0x2a, 0x0b, // (i32.const 42)
+            0x07, 0x00, 0x41, 0x00, 0x28, 0x02, 0x00, 0x0b, // (i32.load (i32.const 0))
+            0x0b, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0x2a, // datasec
+        ];
+        let module = DefaultModule::from_bytes(bytes).unwrap();
+        assert!(module.validate_parallel().is_ok());
+
+        let mut invalid = 0;
+        for i in 0..bytes.len() {
+            for bit in 0..8 {
+                let mut corrupted = bytes;
+                corrupted[i] ^= 1 << bit;
+                let Ok(module) = DefaultModule::from_bytes(corrupted) else {
+                    continue;
+                };
+                let expected = module.validate();
+                invalid += usize::from(expected.is_err());
+                assert_eq!(
+                    std::format!("{:?}", module.validate_parallel()),
+                    std::format!("{expected:?}")
+                );
+            }
+        }
+        assert!(invalid > 0);
+
+        // Parallel validation observes the same options.
+        let module = DefaultModule::from_bytes(bytes).unwrap();
+        let options = validate::ValidateOptions {
+            max_function_instructions: Some(2),
+            ..validate::ValidateOptions::default()
+        };
+        let expected = module.validate_with_options(&options);
+        assert!(expected.is_err());
+        assert_eq!(
+            std::format!("{:?}", module.validate_parallel_with_options(&options)),
+            std::format!("{expected:?}")
+        );
     }
 }
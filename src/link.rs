@@ -0,0 +1,222 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Runtime-independent checks of whether a module's imports can be satisfied.
+
+use crate::types::{ExternType, GlobalType, Limits};
+use crate::{Allocator, Module};
+
+/// Describes the entities available for import, as provided by a host or by
+/// other modules.
+pub trait InterfaceDescriptor {
+    /// The allocator of the described function types.
+    type Alloc: Allocator;
+
+    /// Looks up the type of the entity of a given module and name, if any.
+    fn lookup(&self, module: &str, name: &str) -> Option<ExternType<'_, Self::Alloc>>;
+}
+
+impl<A: Allocator> InterfaceDescriptor for [(&str, &str, ExternType<'_, A>)] {
+    type Alloc = A;
+
+    fn lookup(&self, module: &str, name: &str) -> Option<ExternType<'_, A>> {
+        self.iter()
+            .find(|(m, n, _)| *m == module && *n == name)
+            .map(|(_, _, ty)| ty.clone())
+    }
+}
+
+/// Represents errors that can arise in checking a module's imports, which
+/// correspond to the spec's `assert_unlinkable` cases.
+///
+/// Imports are identified by their index in [`Module::importsec`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// No entity is provided for the import.
+    UnknownImport { import_idx: u32 },
+    /// The provided entity is of a different kind or of an incompatible type.
+    IncompatibleImportType { import_idx: u32 },
+}
+
+impl<A: Allocator> Module<A> {
+    /// Checks whether the module's imports can be satisfied by the given
+    /// entities, ahead of any instantiation.
+    ///
+    /// Per the spec's import matching rules, function signatures must be
+    /// equal, table and memory limits of the provided entities must be within
+    /// those of the imports, and global types and mutability must be equal.
+    ///
+    /// The module is expected to have been validated.
+    pub fn instantiation_precheck<I: InterfaceDescriptor + ?Sized>(
+        &self,
+        imports: &I,
+    ) -> Result<(), Error> {
+        match self.unsatisfied_imports(imports).next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks each of the module's imports against the given entities, as with
+    /// [`instantiation_precheck`](Self::instantiation_precheck), but reports
+    /// every import that cannot be satisfied rather than only the first, in
+    /// the order of [`Module::importsec`].
+    ///
+    /// This suits checking a module against a host's full description of its
+    /// interface ahead of time (e.g., in CI), where all incompatibilities are
+    /// of interest.
+    pub fn unsatisfied_imports<'a, I: InterfaceDescriptor + ?Sized>(
+        &'a self,
+        imports: &'a I,
+    ) -> impl Iterator<Item = Error> + 'a {
+        self.importsec
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, import)| {
+                let import_idx = idx as u32;
+                let Some(provided) = imports.lookup(&import.module, &import.field) else {
+                    return Some(Error::UnknownImport { import_idx });
+                };
+                let matches = self
+                    .import_type(import)
+                    .is_some_and(|required| extern_type_matches(&provided, &required));
+                (!matches).then_some(Error::IncompatibleImportType { import_idx })
+            })
+    }
+}
+
+// Whether a provided entity can satisfy an import of the required type.
+pub(crate) fn extern_type_matches<A: Allocator, B: Allocator>(
+    provided: &ExternType<'_, A>,
+    required: &ExternType<'_, B>,
+) -> bool {
+    match (provided, required) {
+        (ExternType::Func(provided), ExternType::Func(required)) => provided == required,
+        (ExternType::Table(provided), ExternType::Table(required)) => {
+            provided.reftype == required.reftype && limits_match(&provided.limits, &required.limits)
+        }
+        (ExternType::Mem(provided), ExternType::Mem(required)) => {
+            provided.shared == required.shared && limits_match(provided, required)
+        }
+        (ExternType::Global(provided), ExternType::Global(required)) => {
+            global_type_eq(*provided, *required)
+        }
+        _ => false,
+    }
+}
+
+fn global_type_eq(a: GlobalType, b: GlobalType) -> bool {
+    a.value == b.value && a.mutability == b.mutability
+}
+
+// Whether the provided limits are within the required ones.
+fn limits_match(provided: &Limits, required: &Limits) -> bool {
+    if provided.min < required.min {
+        return false;
+    }
+    match (provided.max, required.max) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(provided), Some(required)) => provided <= required,
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+    use crate::types::{GlobalTypeMutability, MemType, ValType};
+
+    #[test]
+    fn precheck() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x02, 0x16, 0x03, // importsec
+            0x01, b'm', 0x01, b'f', 0x00, 0x00, // (func (type 0))
+            0x01, b'm', 0x01, b'm', 0x02, 0x01, 0x01, 0x02, // (memory 1 2)
+            0x01, b'm', 0x01, b'g', 0x03, 0x7f, 0x01, // (global (mut i32))
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        let func = module.typesec[0].clone();
+        let global = GlobalType {
+            value: ValType::I32,
+            mutability: GlobalTypeMutability::Var,
+        };
+        let mem = |min, max| {
+            ExternType::Mem(MemType::new(Limits {
+                min,
+                max,
+                shared: false,
+            }))
+        };
+
+        let mut provided = [
+            ("m", "f", ExternType::Func(&func)),
+            ("m", "m", mem(1, Some(1))),
+            ("m", "g", ExternType::Global(global)),
+        ];
+        assert_eq!(module.instantiation_precheck(&provided[..]), Ok(()));
+
+        provided[1].2 = mem(1, None);
+        assert_eq!(
+            module.instantiation_precheck(&provided[..]),
+            Err(Error::IncompatibleImportType { import_idx: 1 })
+        );
+
+        provided[1].2 = mem(2, Some(2));
+        assert_eq!(module.instantiation_precheck(&provided[..]), Ok(()));
+
+        provided[1].2 = ExternType::Global(global);
+        assert_eq!(
+            module.instantiation_precheck(&provided[..]),
+            Err(Error::IncompatibleImportType { import_idx: 1 })
+        );
+
+        assert_eq!(
+            module.instantiation_precheck(&provided[..1]),
+            Err(Error::UnknownImport { import_idx: 1 })
+        );
+    }
+
+    #[test]
+    fn unsatisfied() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x02, 0x1d, 0x04, // importsec
+            0x01, b'm', 0x01, b'f', 0x00, 0x00, // (func (type 0))
+            0x01, b'm', 0x01, b't', 0x01, 0x70, 0x00, 0x01, // (table 1 funcref)
+            0x01, b'm', 0x01, b'g', 0x03, 0x7f, 0x00, // (global i32)
+            0x01, b'm', 0x01, b'h', 0x03, 0x7e, 0x00, // (global i64)
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        let func = module.typesec[0].clone();
+        let global = GlobalType {
+            value: ValType::I32,
+            mutability: GlobalTypeMutability::Const,
+        };
+        let provided = [
+            ("m", "f", ExternType::Func(&func)),
+            ("m", "g", ExternType::Global(global)),
+            ("m", "h", ExternType::Global(global)),
+        ];
+
+        let errors: std::vec::Vec<_> = module.unsatisfied_imports(&provided[..]).collect();
+        assert_eq!(
+            errors,
+            [
+                Error::UnknownImport { import_idx: 1 },
+                Error::IncompatibleImportType { import_idx: 3 },
+            ]
+        );
+        assert_eq!(module.unsatisfied_imports(&provided[..0]).count(), 4);
+        assert_eq!(module.instantiation_precheck(&provided[..]), Err(errors[0]));
+    }
+}
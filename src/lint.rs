@@ -0,0 +1,246 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Advisory checks of modules beyond those of validation.
+//!
+//! Lints flag constructs that are valid but likely wasteful or unintended, as
+//! may be enforced on the artifacts of a build. None is an error in the sense
+//! of the specification.
+
+use crate::analysis::reachability;
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::core_compat::vec::Vec;
+use crate::types::{
+    BlockType, DataIdx, ElemIdx, ElementInit, ExprVisitor, FuncIdx, ImportDescriptor, Instruction,
+    MemArg, Opcode, Operator, TableIdx, TypeIdx,
+};
+use crate::{Allocator, Module};
+
+/// An advisory finding about a module (see [`lint`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Lint {
+    /// A type is referenced by no function, function import, `call_indirect`,
+    /// or block type.
+    UnusedType(TypeIdx),
+    /// A function, imported or defined, is unreachable from the exports,
+    /// start function, and active segments of the module (see
+    /// [`reachability`]).
+    UnreachableFunction(FuncIdx),
+    /// An element segment has no elements.
+    EmptyElementSegment(ElemIdx),
+    /// A data segment has no bytes.
+    EmptyDataSegment(DataIdx),
+    /// A load or store has an alignment hint exceeding the natural alignment
+    /// of its access. Validation rejects these, and so this only arises in
+    /// linting unvalidated modules.
+    OverAlignedAccess {
+        /// The function containing the instruction.
+        func: FuncIdx,
+        /// The offset of the instruction within the function's body, as with
+        /// [`Instruction::offset`].
+        offset: usize,
+        /// The instruction.
+        op: Operator,
+        /// The alignment hint, as an exponent of two.
+        align: u32,
+    },
+}
+
+/// Counts the references to each type of a module: by function imports, by
+/// the function section, and by the `call_indirect` instructions and block
+/// types of function bodies.
+///
+/// Out-of-range type indices are ignored. The counts are allocated with the
+/// module's metadata allocator (see `decode::SectionAllocators::metadata`).
+pub fn type_use_counts<A: Allocator>(module: &Module<A>) -> Result<Vec<u32, A>, TryReserveError> {
+    Ok(Walker::walk(module)?.counts)
+}
+
+/// Lints a module, returning its findings grouped by kind in the order of the
+/// [`Lint`] variants, and within each kind in index (or offset) order.
+///
+/// Data segments whose bytes were streamed on decoding (see
+/// `decode::DataSegmentSink`) are left empty, and so are reported as such.
+/// The lints are allocated with the module's metadata allocator (see
+/// `decode::SectionAllocators::metadata`).
+pub fn lint<A: Allocator>(module: &Module<A>) -> Result<Vec<Lint, A>, TryReserveError> {
+    let alloc = module.typesec.allocator();
+    let mut lints = Vec::new_in(alloc.clone());
+    let mut push = |lint| -> Result<(), TryReserveError> {
+        lints.try_reserve(1)?;
+        lints.push(lint);
+        Ok(())
+    };
+
+    let walker = Walker::walk(module)?;
+    for (idx, count) in walker.counts.iter().enumerate() {
+        if *count == 0 {
+            push(Lint::UnusedType(TypeIdx::new(idx as u32)))?;
+        }
+    }
+
+    let reachable = reachability(module)?;
+    let function_count = walker.imported_functions + module.funcsec.len();
+    for idx in 0..function_count as u32 {
+        let func = FuncIdx::new(idx);
+        if !reachable.contains_function(func) {
+            push(Lint::UnreachableFunction(func))?;
+        }
+    }
+
+    for (idx, segment) in module.elemsec.iter().enumerate() {
+        let empty = match &segment.init {
+            ElementInit::FunctionIndices(funcs) => funcs.is_empty(),
+            ElementInit::Expressions(exprs) => exprs.is_empty(),
+        };
+        if empty {
+            push(Lint::EmptyElementSegment(ElemIdx::new(idx as u32)))?;
+        }
+    }
+    for (idx, segment) in module.datasec.iter().enumerate() {
+        if segment.init.is_empty() {
+            push(Lint::EmptyDataSegment(DataIdx::new(idx as u32)))?;
+        }
+    }
+
+    for lint in walker.over_aligned {
+        push(lint)?;
+    }
+    Ok(lints)
+}
+
+// Walks function bodies, counting type references and recording over-aligned
+// accesses.
+struct Walker<A: Allocator> {
+    counts: Vec<u32, A>,
+    over_aligned: Vec<Lint, A>,
+    imported_functions: usize,
+    // The function being walked and the offset of its current instruction.
+    func: FuncIdx,
+    offset: usize,
+}
+
+impl<A: Allocator> Walker<A> {
+    fn walk(module: &Module<A>) -> Result<Self, TryReserveError> {
+        let alloc = module.typesec.allocator();
+        let mut counts = Vec::new_in(alloc.clone());
+        counts.try_reserve_exact(module.typesec.len())?;
+        counts.resize(module.typesec.len(), 0);
+
+        let imports = module.imports_by_kind();
+        let mut walker = Self {
+            counts,
+            over_aligned: Vec::new_in(alloc.clone()),
+            imported_functions: imports.functions.len(),
+            func: FuncIdx::new(0),
+            offset: 0,
+        };
+        for import in imports.functions {
+            if let ImportDescriptor::Function(ty) = import.descriptor {
+                walker.count(ty);
+            }
+        }
+        for ty in module.funcsec.iter() {
+            walker.count(*ty);
+        }
+        for (idx, function) in module.codesec.iter().enumerate() {
+            walker.func = FuncIdx::new((walker.imported_functions + idx) as u32);
+            function.code.visit(&mut walker)?;
+        }
+        Ok(walker)
+    }
+
+    fn count(&mut self, ty: TypeIdx) {
+        if let Some(count) = self.counts.get_mut(*ty as usize) {
+            *count = count.saturating_add(1);
+        }
+    }
+}
+
+impl<A: Allocator> ExprVisitor for Walker<A> {
+    type Error = TryReserveError;
+
+    fn visit_instruction(&mut self, instr: &Instruction<'_>) -> Result<(), TryReserveError> {
+        self.offset = instr.offset;
+        Ok(())
+    }
+
+    fn visit_block(&mut self, _: Opcode, ty: BlockType) -> Result<(), TryReserveError> {
+        if let BlockType::TypeIndex(ty) = ty {
+            self.count(ty);
+        }
+        Ok(())
+    }
+
+    fn visit_call_indirect(&mut self, ty: TypeIdx, _: TableIdx) -> Result<(), TryReserveError> {
+        self.count(ty);
+        Ok(())
+    }
+
+    fn visit_memarg(&mut self, op: Operator, memarg: MemArg) -> Result<(), TryReserveError> {
+        if op
+            .natural_alignment()
+            .is_some_and(|natural| memarg.align > natural)
+        {
+            self.over_aligned.try_reserve(1)?;
+            self.over_aligned.push(Lint::OverAlignedAccess {
+                func: self.func,
+                offset: self.offset,
+                op,
+                align: memarg.align,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    #[test]
+    fn lints() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x0c, 0x03, // typesec
+            0x60, 0x00, 0x00, // (func)
+            0x60, 0x01, 0x7f, 0x00, // (func (param i32))
+            0x60, 0x00, 0x01, 0x7f, // (func (result i32))
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x00, // (export "f" (func 0))
+            0x09, 0x04, 0x01, 0x05, 0x70, 0x00, // (elem passive funcref)
+            0x0a, 0x0d, 0x02, // codesec
+            0x08, 0x00, 0x41, 0x00, 0x28, 0x03, 0x00, 0x1a,
+            0x0b, // (drop (i32.load align=8 (i32.const 0)))
+            0x02, 0x00, 0x0b, // (func)
+            0x0b, 0x03, 0x01, 0x01, 0x00, // (data passive "")
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        assert_eq!(type_use_counts(&module).unwrap()[..], [2, 0, 0]);
+
+        let load = module.codesec[0].code.instructions().nth(1).unwrap();
+        assert_eq!(
+            lint(&module).unwrap()[..],
+            [
+                Lint::UnusedType(TypeIdx::new(1)),
+                Lint::UnusedType(TypeIdx::new(2)),
+                Lint::UnreachableFunction(FuncIdx::new(1)),
+                Lint::EmptyElementSegment(ElemIdx::new(0)),
+                Lint::EmptyDataSegment(DataIdx::new(0)),
+                Lint::OverAlignedAccess {
+                    func: FuncIdx::new(0),
+                    offset: load.offset,
+                    op: load.op,
+                    align: 3,
+                },
+            ]
+        );
+    }
+}
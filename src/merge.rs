@@ -0,0 +1,731 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Merging of modules into one, as by a static linker.
+//!
+//! Modules are merged under the names by which they import from one another:
+//! an import naming one of the merged modules is resolved to the entity that
+//! module exports under the import's field, while all other imports remain
+//! imports of the merged module. The index spaces of the modules are
+//! concatenated in order, after those remaining imports, and indices are
+//! rewritten throughout (see [`Expression::remap_indices`]).
+//!
+//! [`Expression::remap_indices`]: crate::types::Expression::remap_indices
+
+use core::array;
+
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::core_compat::vec::Vec;
+use crate::link::extern_type_matches;
+use crate::types::{
+    DataIdx, DataMode, ElemIdx, ElementInit, ElementMode, ExportDescriptor, ExternKind, FuncIdx,
+    GlobalIdx, Import, ImportDescriptor, IndexRemapper, MemIdx, SectionId, StartSection, TableIdx,
+    TypeIdx, Version,
+};
+use crate::{Allocator, Module};
+
+/// Represents errors that can arise in merging modules.
+///
+/// Modules are identified by their position among those merged, and their
+/// imports and exports by their indices in [`Module::importsec`] and
+/// [`Module::exportsec`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// An allocation failed.
+    AllocError,
+    /// An import names one of the merged modules, which exports no entity
+    /// under the import's field.
+    UnknownImport { module: u32, import_idx: u32 },
+    /// An import is resolved to an entity of a different kind or of an
+    /// incompatible type.
+    IncompatibleImportType { module: u32, import_idx: u32 },
+    /// An import is resolved, through the exports of imported entities, back
+    /// to itself.
+    CyclicImport { module: u32, import_idx: u32 },
+    /// An export has the name of an export of an earlier module.
+    DuplicateExport { module: u32, export_idx: u32 },
+    /// More than one of the modules has a start function.
+    MultipleStartFunctions,
+    /// The merged module would have more than one memory, which memory
+    /// instructions cannot address.
+    MultipleMemories,
+    /// The payload of a section of a module was skipped on decoding (see
+    /// [`DecodeOptions::skip_code`]), so the module cannot be merged.
+    ///
+    /// [`DecodeOptions::skip_code`]: crate::decode::DecodeOptions::skip_code
+    SkippedSection { module: u32, id: SectionId },
+}
+
+impl From<TryReserveError> for Error {
+    fn from(_: TryReserveError) -> Self {
+        Self::AllocError
+    }
+}
+
+// The number of kinds of importable entities, indexed by the discriminants of
+// ImportDescriptor.
+const KINDS: usize = ExternKind::COUNT;
+const MEMORY: usize = ExternKind::Memory as usize;
+
+/// Merges modules, each given with the name under which the others import
+/// from it, into one.
+///
+/// Exports are kept under their names, which must then be distinct across
+/// the modules. At most one module may have a start function, and the merged
+/// module may have at most one memory, which the modules can share by
+/// importing it from one another.
+///
+/// The modules are expected to have been validated; indices out of bounds,
+/// which can only occur in modules that fail validation, are left as is.
+/// These include all tag indices (of the stack-switching proposal), as tags
+/// can be neither imported nor defined.
+/// Modules with skipped sections, which cannot be validated, are refused.
+pub fn merge<A: Allocator>(modules: Vec<(&str, Module<A>), A>) -> Result<Module<A>, Error> {
+    let alloc = modules.allocator().clone();
+    check_modules(&modules)?;
+    let layouts = layouts(&modules, &alloc)?;
+    let (targets, external) = resolve_imports(&modules, &layouts, &alloc)?;
+    let maps = index_maps(&layouts, &targets, external, &alloc)?;
+    let memories = layouts
+        .iter()
+        .map(|layout| layout.defined[MEMORY])
+        .sum::<u32>();
+    if external[MEMORY] + memories > 1 {
+        return Err(Error::MultipleMemories);
+    }
+
+    let data_count = layouts
+        .last()
+        .map_or(0, |layout| layout.datas + layout.data_count);
+    let mut merged = Module::new(Version::V1, alloc);
+    merged.datacountsec = modules
+        .iter()
+        .any(|(_, module)| module.datacountsec.is_some())
+        .then_some(data_count);
+    for ((_, module), layout) in modules.into_iter().zip(layouts.iter()) {
+        let map = |kind: usize| {
+            let start = layout.first[kind] as usize;
+            let end = start + (layout.imported[kind] + layout.defined[kind]) as usize;
+            &maps[kind][start..end]
+        };
+        let remapper = Remapper {
+            types: layout.types,
+            elems: layout.elems,
+            datas: layout.datas,
+            funcs: map(ExternKind::Function as usize),
+            tables: map(ExternKind::Table as usize),
+            memories: map(MEMORY),
+            globals: map(ExternKind::Global as usize),
+        };
+        append_module(&mut merged, module, layout, &targets, &remapper)?;
+    }
+
+    // Restore the invariants of decoded modules: imports grouped by kind
+    // (here, in binary order) and exports sorted by name.
+    merged
+        .importsec
+        .0
+        .sort_by_key(|import| import.descriptor.kind());
+    merged
+        .exportsec
+        .0
+        .sort_by(|a, b| a.field.as_ref().cmp(b.field.as_ref()));
+    Ok(merged)
+}
+
+// The place of a module's entities within the merged module.
+#[derive(Clone, Copy, Default)]
+struct Layout {
+    // The numbers of imported and defined entities of each kind.
+    imported: [u32; KINDS],
+    defined: [u32; KINDS],
+    // The offsets of the module's index space of each kind within the tables
+    // of all of the modules' index spaces of that kind, and of its imports of
+    // each kind within the tables of all of their imports.
+    first: [u32; KINDS],
+    first_import: [u32; KINDS],
+    // The index of the first entity of each kind defined by the module, less
+    // the number of imports of that kind of the merged module.
+    defined_base: [u32; KINDS],
+    // The indices of the module's first type, element segment, and data
+    // segment in the merged module, and its number of data segments.
+    types: u32,
+    elems: u32,
+    datas: u32,
+    data_count: u32,
+}
+
+// The resolution of an import.
+#[derive(Clone, Copy)]
+enum Target {
+    // Left as an import of the merged module, with the given index among
+    // those of its kind.
+    External(u32),
+    // Resolved to an entity of the module of the given position.
+    Entity { module: u32, idx: u32 },
+}
+
+// The targets of the modules' imports of each kind, module by module.
+type Targets<A> = [Vec<Target, A>; KINDS];
+
+// Checks that the modules can be merged, as far as can be told without
+// resolving their imports.
+fn check_modules<A: Allocator>(modules: &[(&str, Module<A>)]) -> Result<(), Error> {
+    let mut start = false;
+    for (pos, (_, module)) in modules.iter().enumerate() {
+        let skipped = [
+            (SectionId::Code, module.skipped_codesec),
+            (SectionId::Data, module.skipped_datasec),
+        ];
+        if let Some((id, _)) = skipped.iter().find(|(_, skipped)| skipped.is_some()) {
+            return Err(Error::SkippedSection {
+                module: pos as u32,
+                id: *id,
+            });
+        }
+        if module.startsec.is_some() {
+            if start {
+                return Err(Error::MultipleStartFunctions);
+            }
+            start = true;
+        }
+        for (export_idx, export) in module.exportsec.iter().enumerate() {
+            if modules[..pos]
+                .iter()
+                .any(|(_, earlier)| earlier.export(&export.field).is_some())
+            {
+                return Err(Error::DuplicateExport {
+                    module: pos as u32,
+                    export_idx: export_idx as u32,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn layouts<A: Allocator>(
+    modules: &[(&str, Module<A>)],
+    alloc: &A,
+) -> Result<Vec<Layout, A>, TryReserveError> {
+    let mut layouts = Vec::new_in(alloc.clone());
+    layouts.try_reserve_exact(modules.len())?;
+    let mut next = Layout::default();
+    for (_, module) in modules {
+        let imports = module.imports_by_kind();
+        let layout = Layout {
+            imported: [
+                imports.functions.len() as u32,
+                imports.tables.len() as u32,
+                imports.memories.len() as u32,
+                imports.globals.len() as u32,
+            ],
+            defined: [
+                module.funcsec.len() as u32,
+                module.tablesec.len() as u32,
+                module.memsec.len() as u32,
+                module.globalsec.len() as u32,
+            ],
+            data_count: module.datasec.len() as u32,
+            ..next
+        };
+        for kind in 0..KINDS {
+            next.first[kind] += layout.imported[kind] + layout.defined[kind];
+            next.first_import[kind] += layout.imported[kind];
+            next.defined_base[kind] += layout.defined[kind];
+        }
+        next.types += module.typesec.len() as u32;
+        next.elems += module.elemsec.len() as u32;
+        next.datas += layout.data_count;
+        layouts.push(layout);
+    }
+    Ok(layouts)
+}
+
+// Resolves the imports of each kind, returning their targets in the order of
+// the modules' imports along with the number left as imports of the merged
+// module.
+fn resolve_imports<A: Allocator>(
+    modules: &[(&str, Module<A>)],
+    layouts: &[Layout],
+    alloc: &A,
+) -> Result<(Targets<A>, [u32; KINDS]), Error> {
+    let mut targets = array::from_fn(|_| Vec::new_in(alloc.clone()));
+    let mut external = [0u32; KINDS];
+    for ((pos, (_, module)), layout) in modules.iter().enumerate().zip(layouts) {
+        for (of_kind, &count) in targets.iter_mut().zip(&layout.imported) {
+            of_kind.try_reserve(count as usize)?;
+        }
+        for (import_idx, import) in module.importsec.iter().enumerate() {
+            let kind = import.descriptor.kind() as usize;
+            let target = match resolve_import(modules, module, import) {
+                Ok(Some(target)) => target,
+                Ok(None) => {
+                    external[kind] += 1;
+                    Target::External(external[kind] - 1)
+                }
+                Err(error) => return Err(error(pos as u32, import_idx as u32)),
+            };
+            targets[kind].push(target);
+        }
+    }
+    Ok((targets, external))
+}
+
+// Resolves an import to the entity exported by the merged module it names, if
+// any, or else returns the constructor of the error in doing so.
+fn resolve_import<A: Allocator>(
+    modules: &[(&str, Module<A>)],
+    module: &Module<A>,
+    import: &Import<A>,
+) -> Result<Option<Target>, fn(u32, u32) -> Error> {
+    let Some(pos) = modules
+        .iter()
+        .position(|(name, _)| **name == **import.module)
+    else {
+        return Ok(None);
+    };
+    let exporter = &modules[pos].1;
+    let Some(export) = exporter.export(&import.field) else {
+        return Err(|module, import_idx| Error::UnknownImport { module, import_idx });
+    };
+    let matches = export.descriptor.kind() == import.descriptor.kind()
+        && match (
+            exporter.export_type(export.descriptor),
+            module.import_type(import),
+        ) {
+            (Some(provided), Some(required)) => extern_type_matches(&provided, &required),
+            _ => false,
+        };
+    if !matches {
+        return Err(|module, import_idx| Error::IncompatibleImportType { module, import_idx });
+    }
+    Ok(Some(Target::Entity {
+        module: pos as u32,
+        idx: export.descriptor.index(),
+    }))
+}
+
+// Computes, for each kind, the index in the merged module of each entity of
+// each module, module by module.
+fn index_maps<A: Allocator>(
+    layouts: &[Layout],
+    targets: &Targets<A>,
+    external: [u32; KINDS],
+    alloc: &A,
+) -> Result<[Vec<u32, A>; KINDS], Error> {
+    let mut maps: [Vec<u32, A>; KINDS] = array::from_fn(|_| Vec::new_in(alloc.clone()));
+    for (pos, layout) in layouts.iter().enumerate() {
+        for kind in 0..KINDS {
+            let map = &mut maps[kind];
+            map.try_reserve((layout.imported[kind] + layout.defined[kind]) as usize)?;
+            for idx in 0..layout.imported[kind] + layout.defined[kind] {
+                let merged = merged_index(layouts, &targets[kind], external[kind], kind, pos, idx)
+                    .ok_or_else(|| Error::CyclicImport {
+                        module: pos as u32,
+                        import_idx: layout.imported[..kind].iter().sum::<u32>() + idx,
+                    })?;
+                map.push(merged);
+            }
+        }
+    }
+    Ok(maps)
+}
+
+// Follows an entity through the resolved imports to its index in the merged
+// module, given the number of imports of its kind of the merged module.
+// Returns None if the imports are resolved in a cycle.
+fn merged_index(
+    layouts: &[Layout],
+    targets: &[Target],
+    external: u32,
+    kind: usize,
+    mut pos: usize,
+    mut idx: u32,
+) -> Option<u32> {
+    // Absent a cycle, each step follows a distinct import.
+    for _ in 0..=targets.len() {
+        let layout = &layouts[pos];
+        if idx >= layout.imported[kind] {
+            return Some(external + layout.defined_base[kind] + idx - layout.imported[kind]);
+        }
+        match targets[(layout.first_import[kind] + idx) as usize] {
+            Target::External(idx) => return Some(idx),
+            Target::Entity { module, idx: next } => {
+                pos = module as usize;
+                idx = next;
+            }
+        }
+    }
+    None
+}
+
+// Maps the indices of one of the modules to those of the merged module.
+struct Remapper<'a> {
+    types: u32,
+    elems: u32,
+    datas: u32,
+    funcs: &'a [u32],
+    tables: &'a [u32],
+    memories: &'a [u32],
+    globals: &'a [u32],
+}
+
+fn lookup(map: &[u32], idx: u32) -> u32 {
+    map.get(idx as usize).copied().unwrap_or(idx)
+}
+
+impl Remapper<'_> {
+    fn remap_export(&self, descriptor: ExportDescriptor) -> ExportDescriptor {
+        match descriptor {
+            ExportDescriptor::Function(idx) => ExportDescriptor::Function(self.remap_func(idx)),
+            ExportDescriptor::Table(idx) => ExportDescriptor::Table(self.remap_table(idx)),
+            ExportDescriptor::Memory(idx) => {
+                ExportDescriptor::Memory(MemIdx::new(lookup(self.memories, *idx)))
+            }
+            ExportDescriptor::Global(idx) => ExportDescriptor::Global(self.remap_global(idx)),
+        }
+    }
+}
+
+impl IndexRemapper for Remapper<'_> {
+    fn remap_type(&self, idx: TypeIdx) -> TypeIdx {
+        TypeIdx::new(idx.saturating_add(self.types))
+    }
+
+    fn remap_func(&self, idx: FuncIdx) -> FuncIdx {
+        FuncIdx::new(lookup(self.funcs, *idx))
+    }
+
+    fn remap_table(&self, idx: TableIdx) -> TableIdx {
+        TableIdx::new(lookup(self.tables, *idx))
+    }
+
+    fn remap_global(&self, idx: GlobalIdx) -> GlobalIdx {
+        GlobalIdx::new(lookup(self.globals, *idx))
+    }
+
+    fn remap_elem(&self, idx: ElemIdx) -> ElemIdx {
+        ElemIdx::new(idx.saturating_add(self.elems))
+    }
+
+    fn remap_data(&self, idx: DataIdx) -> DataIdx {
+        DataIdx::new(idx.saturating_add(self.datas))
+    }
+}
+
+// Appends the items to a vector, reserving room for them first.
+fn append<T, A: Allocator>(
+    vec: &mut Vec<T, A>,
+    items: impl ExactSizeIterator<Item = T>,
+) -> Result<(), TryReserveError> {
+    vec.try_reserve(items.len())?;
+    vec.extend(items);
+    Ok(())
+}
+
+// Appends the entities of a module to the merged module, with their indices
+// remapped, leaving out the imports resolved among the merged modules.
+fn append_module<A: Allocator>(
+    merged: &mut Module<A>,
+    module: Module<A>,
+    layout: &Layout,
+    targets: &Targets<A>,
+    remapper: &Remapper<'_>,
+) -> Result<(), TryReserveError> {
+    let Module {
+        typesec,
+        importsec,
+        funcsec,
+        tablesec,
+        memsec,
+        globalsec,
+        exportsec,
+        startsec,
+        elemsec,
+        codesec,
+        datasec,
+        ..
+    } = module;
+
+    append(&mut merged.typesec.0, typesec.0.into_iter())?;
+
+    let mut positions = layout.first_import;
+    for mut import in importsec.0 {
+        let kind = import.descriptor.kind() as usize;
+        let target = targets[kind][positions[kind] as usize];
+        positions[kind] += 1;
+        if let Target::External(_) = target {
+            if let ImportDescriptor::Function(typeidx) = &mut import.descriptor {
+                *typeidx = remapper.remap_type(*typeidx);
+            }
+            merged.importsec.0.try_reserve(1)?;
+            merged.importsec.0.push(import);
+        }
+    }
+
+    let types = funcsec.iter().map(|&typeidx| remapper.remap_type(typeidx));
+    append(&mut merged.funcsec.0, types)?;
+    append(&mut merged.tablesec.0, tablesec.0.into_iter())?;
+    append(&mut merged.memsec.0, memsec.0.into_iter())?;
+    let globals = globalsec.0.into_iter().map(|mut global| {
+        global.init.remap_indices(remapper);
+        global
+    });
+    append(&mut merged.globalsec.0, globals)?;
+
+    let exports = exportsec.0.into_iter().map(|mut export| {
+        export.descriptor = remapper.remap_export(export.descriptor);
+        export
+    });
+    append(&mut merged.exportsec.0, exports)?;
+    if let Some(start) = startsec {
+        merged.startsec = Some(StartSection::new(remapper.remap_func(*start)));
+    }
+
+    let segments = elemsec.0.into_iter().map(|mut segment| {
+        match &mut segment.init {
+            ElementInit::FunctionIndices(funcs) => {
+                for funcidx in funcs.iter_mut() {
+                    *funcidx = remapper.remap_func(*funcidx);
+                }
+            }
+            ElementInit::Expressions(exprs) => {
+                for expr in exprs.iter_mut() {
+                    expr.remap_indices(remapper);
+                }
+            }
+        }
+        if let ElementMode::Active(active) = &mut segment.mode {
+            active.table = remapper.remap_table(active.table);
+            active.offset.remap_indices(remapper);
+        }
+        segment
+    });
+    append(&mut merged.elemsec.0, segments)?;
+
+    let functions = codesec.0.into_iter().map(|mut function| {
+        function.code.remap_indices(remapper);
+        function
+    });
+    append(&mut merged.codesec.0, functions)?;
+
+    let segments = datasec.0.into_iter().map(|mut segment| {
+        if let DataMode::Active(active) = &mut segment.mode {
+            active.memory = MemIdx::new(lookup(remapper.memories, *active.memory));
+            active.offset.remap_indices(remapper);
+        }
+        segment
+    });
+    append(&mut merged.datasec.0, segments)
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::{DecodeOptions, NoCustomSectionVisitor};
+    use crate::types::{GlobalType, GlobalTypeMutability, Opcode, Operands, Operator, ValType};
+
+    // Exports (func $double (param i32) (result i32)) and a mutable i32
+    // global "counter".
+    const LIB: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x01, 0x06, 0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f, // typesec
+        0x03, 0x02, 0x01, 0x00, // funcsec
+        0x06, 0x06, 0x01, 0x7f, 0x01, 0x41, 0x07, 0x0b, // globalsec
+        0x07, 0x14, 0x02, // exportsec
+        0x06, b'd', b'o', b'u', b'b', b'l', b'e', 0x00, 0x00, // (func 0)
+        0x07, b'c', b'o', b'u', b'n', b't', b'e', b'r', 0x03, 0x00, // (global 0)
+        0x0a, 0x09, 0x01, 0x07, 0x00, // codesec
+        0x20, 0x00, 0x20, 0x00, 0x6a, 0x0b, // (i32.add (local.get 0) (local.get 0))
+    ];
+
+    // Imports "double" and "counter" from "lib" and "log" from "env", and
+    // exports a function calling the former two.
+    const MAIN: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x01, 0x0a, 0x02, // typesec
+        0x60, 0x00, 0x01, 0x7f, // [] -> [i32]
+        0x60, 0x01, 0x7f, 0x01, 0x7f, // [i32] -> [i32]
+        0x02, 0x27, 0x03, // importsec
+        0x03, b'l', b'i', b'b', 0x06, b'd', b'o', b'u', b'b', b'l', b'e', 0x00, 0x01, 0x03, b'e',
+        b'n', b'v', 0x03, b'l', b'o', b'g', 0x00, 0x01, //
+        0x03, b'l', b'i', b'b', 0x07, b'c', b'o', b'u', b'n', b't', b'e', b'r', 0x03, 0x7f, 0x01,
+        0x03, 0x02, 0x01, 0x00, // funcsec
+        0x07, 0x08, 0x01, 0x04, b'm', b'a', b'i', b'n', 0x00, 0x02, // exportsec
+        0x0a, 0x0a, 0x01, 0x08, 0x00, // codesec
+        0x23, 0x00, 0x10, 0x00, 0x10, 0x01, // (call 1 (call 0 (global.get 0)))
+        0x0b, // end
+    ];
+
+    fn decode(bytes: &[u8]) -> Module<Global> {
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap()
+    }
+
+    fn modules<const N: usize>(
+        modules: [(&'static str, Module<Global>); N],
+    ) -> Vec<(&'static str, Module<Global>), Global> {
+        let mut vec = Vec::new_in(Global);
+        vec.extend(modules);
+        vec
+    }
+
+    #[test]
+    fn merging() {
+        let merged = merge(modules([("lib", decode(LIB)), ("main", decode(MAIN))])).unwrap();
+
+        assert_eq!(merged.typesec.len(), 3);
+        let imports: std::vec::Vec<_> = merged
+            .importsec
+            .iter()
+            .map(|import| (&**import.module, &**import.field))
+            .collect();
+        assert_eq!(imports, [("env", "log")]);
+        assert!(matches!(
+            merged.importsec[0].descriptor,
+            ImportDescriptor::Function(idx) if *idx == 2
+        ));
+        assert!(merged.funcsec.iter().map(|idx| **idx).eq([0, 1]));
+        assert_eq!(merged.globalsec.len(), 1);
+
+        let exports: std::vec::Vec<_> = merged
+            .exportsec
+            .iter()
+            .map(|export| (&**export.field, export.descriptor))
+            .collect();
+        assert_eq!(
+            exports,
+            [
+                ("counter", ExportDescriptor::Global(GlobalIdx::new(0))),
+                ("double", ExportDescriptor::Function(FuncIdx::new(1))),
+                ("main", ExportDescriptor::Function(FuncIdx::new(2))),
+            ]
+        );
+
+        let instrs: std::vec::Vec<_> = merged.codesec[1]
+            .code
+            .instructions()
+            .map(|instr| match (instr.op, instr.operands) {
+                (Operator::Basic(op), Operands::Idx(idx)) => (op, Some(idx)),
+                (Operator::Basic(op), _) => (op, None),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            instrs,
+            [
+                (Opcode::GlobalGet, Some(0)),
+                (Opcode::Call, Some(1)),
+                (Opcode::Call, Some(0)),
+                (Opcode::End, None),
+            ]
+        );
+
+        #[cfg(feature = "validate")]
+        merged.validate().unwrap();
+    }
+
+    #[test]
+    fn conflicts() {
+        let error = merge(modules([("a", decode(LIB)), ("b", decode(LIB))]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            Error::DuplicateExport {
+                module: 1,
+                export_idx: 0,
+            }
+        );
+
+        // "main" imports itself in place of "lib".
+        let error = merge(modules([("lib", decode(MAIN))])).err().unwrap();
+        assert_eq!(
+            error,
+            Error::UnknownImport {
+                module: 0,
+                import_idx: 0,
+            }
+        );
+
+        let mut main = decode(MAIN);
+        main.importsec.0[2].descriptor = ImportDescriptor::Global(GlobalType {
+            value: ValType::I32,
+            mutability: GlobalTypeMutability::Const,
+        });
+        let error = merge(modules([("lib", decode(LIB)), ("main", main)]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            Error::IncompatibleImportType {
+                module: 1,
+                import_idx: 2,
+            }
+        );
+
+        // Re-exports the function it imports from itself.
+        let cyclic = decode(&[
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x07, 0x01, 0x01, b'a', 0x01, b'f', 0x00, 0x00, // importsec
+            0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x00, // exportsec
+        ]);
+        let error = merge(modules([("a", cyclic)])).err().unwrap();
+        assert_eq!(
+            error,
+            Error::CyclicImport {
+                module: 0,
+                import_idx: 0,
+            }
+        );
+
+        let options = DecodeOptions {
+            skip_code: true,
+            ..DecodeOptions::default()
+        };
+        let main = Module::decode_bytes_with_options(
+            MAIN,
+            &mut NoCustomSectionVisitor {},
+            Global,
+            &options,
+        )
+        .unwrap();
+        let error = merge(modules([("lib", decode(LIB)), ("main", main)]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            Error::SkippedSection {
+                module: 1,
+                id: SectionId::Code,
+            }
+        );
+    }
+
+    // The type indices of stack-switching instructions are remapped along
+    // with all others.
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn stack_switching() {
+        let cont = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x07, 0x01, 0x05, 0x00, // codesec
+            0xe0, 0x00, 0x1a, 0x0b, // (drop (cont.new 0))
+        ];
+        let merged = merge(modules([("lib", decode(LIB)), ("cont", decode(&cont))])).unwrap();
+        let instr = merged.codesec[1].code.instructions().next().unwrap();
+        assert_eq!(instr.op, Operator::Basic(Opcode::ContNew));
+        assert!(matches!(instr.operands, Operands::Idx(1)));
+    }
+}
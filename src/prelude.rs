@@ -0,0 +1,27 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! The items needed by most uses of the crate, for glob import:
+//!
+//! ```
+//! use wafer::prelude::*;
+//!
+//! # #[cfg(all(feature = "decode", feature = "validate"))]
+//! # {
+//! let module = DefaultModule::from_bytes(b"\0asm\x01\0\0\0").unwrap();
+//! let module = ValidatedModule::new(module).unwrap();
+//! # }
+//! ```
+
+pub use crate::core_compat::alloc::Global;
+#[cfg(feature = "scan")]
+pub use crate::decode::{DecodeOptions, NoCustomSectionVisitor};
+#[cfg(all(feature = "decode", feature = "validate"))]
+pub use crate::decode_and_validate;
+pub use crate::types::{ExternType, FunctionType, ValType};
+pub use crate::{Allocator, DefaultModule, Module};
+#[cfg(feature = "validate")]
+pub use crate::{DefaultValidatedModule, ValidatedModule};
@@ -0,0 +1,366 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Lightweight scanning of a module's section layout, without decoding
+//! section contents.
+
+use core::mem::size_of;
+
+use crate::core_compat::alloc::Global;
+use crate::core_compat::vec::Vec;
+use crate::decode::{
+    self, ContextStack, CustomSectionVisitor, DecodeOptions, count_section_entries, scan_module,
+    visit_module_custom_sections,
+};
+use crate::storage::{self, MemoryEof, Stream};
+use crate::types::{
+    DataSegment, ElementSegment, Export, Function, FunctionType, Global as GlobalEntry, Import,
+    MemType, Name, SectionId, TableType, TypeIdx,
+};
+use crate::{Allocator, Module};
+
+// The factors by which the crate's re-encoding of expressions is assumed to
+// expand the sections containing them: operands padded out to their natural
+// alignments typically take four bytes or more where LEB128 takes one or two,
+// and packed operands still take their full widths. These round up the
+// expansions of code sections measured across the synthetic modules of
+// `bench_support` and `testing`: 2.3-3.4x, and 1.7-2.4x packed.
+const EXPRESSION_EXPANSION: usize = 4;
+const PACKED_EXPRESSION_EXPANSION: usize = 3;
+
+/// The location of a section within a module.
+#[derive(Debug)]
+pub struct SectionInfo<A: Allocator> {
+    /// The section's ID.
+    pub id: SectionId,
+    /// The byte offset of the section's payload (i.e., just past the ID and
+    /// length).
+    pub offset: usize,
+    /// The length of the section's payload.
+    pub len: u32,
+    /// The name of the section, if custom.
+    pub name: Option<Name<A>>,
+}
+
+/// Walks the sections of a module from streaming storage, skipping over their
+/// payloads.
+///
+/// Only the module header, the section headers, and the names of custom
+/// sections are decoded, so no checks are made of section order or contents.
+pub fn scan_sections<Storage: Stream, A: Allocator>(
+    storage: Storage,
+    alloc: A,
+) -> Result<Vec<SectionInfo<A>, A>, decode::ErrorWithContext<Storage::Error, A>> {
+    let mut context = ContextStack::default();
+    scan_module(storage, &mut context, alloc)
+        .map_err(|error| decode::ErrorWithContext { error, context })
+}
+
+/// Walks the sections of a module directly from memory.
+pub fn scan_bytes<Bytes: AsRef<[u8]>, A: Allocator>(
+    bytes: Bytes,
+    alloc: A,
+) -> Result<Vec<SectionInfo<A>, A>, decode::ErrorWithContext<MemoryEof, A>> {
+    scan_sections(storage::Buffer::new(bytes), alloc)
+}
+
+/// Walks the sections of a module from streaming storage, passing its custom
+/// sections to a visitor (as on decoding) and skipping over the payloads of
+/// the rest.
+///
+/// This reads custom sections (e.g., with
+/// [`ProducersVisitor`](decode::ProducersVisitor) or
+/// [`ObjectMetadataVisitor`](decode::ObjectMetadataVisitor)) without decoding
+/// the module, and so is available without the `decode` feature. As with
+/// [`scan_sections`], no checks are made of section order or contents.
+pub fn visit_custom_sections<Storage: Stream, A: Allocator, V: CustomSectionVisitor<A>>(
+    storage: Storage,
+    customsec_visitor: &mut V,
+    alloc: A,
+) -> Result<(), decode::ErrorWithContext<Storage::Error, A>> {
+    let mut context = ContextStack::default();
+    visit_module_custom_sections(storage, &mut context, customsec_visitor, &alloc)
+        .map_err(|error| decode::ErrorWithContext { error, context })
+}
+
+/// Passes the custom sections of a module directly from memory to a visitor
+/// (see [`visit_custom_sections`]).
+pub fn visit_custom_section_bytes<Bytes: AsRef<[u8]>, A: Allocator, V: CustomSectionVisitor<A>>(
+    bytes: Bytes,
+    customsec_visitor: &mut V,
+    alloc: A,
+) -> Result<(), decode::ErrorWithContext<MemoryEof, A>> {
+    visit_custom_sections(storage::Buffer::new(bytes), customsec_visitor, alloc)
+}
+
+/// An estimate of the memory allocated by decoding a module, in bytes.
+///
+/// Sizes are those of the crate's types with the global allocator (or any
+/// other zero-sized allocator handle).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SizeEstimate {
+    /// The estimated size of the function bodies of the code section.
+    pub code: usize,
+    /// The estimated size of the data segments.
+    pub data: usize,
+    /// The estimated size of everything else.
+    pub other: usize,
+}
+
+impl SizeEstimate {
+    /// The estimated total size.
+    pub const fn total(&self) -> usize {
+        self.code + self.data + self.other
+    }
+
+    fn add_section(&mut self, id: SectionId, len: u32, count: u32, expansion: usize) {
+        let len = len as usize;
+        let count = count as usize;
+        let entries = |size: usize, extra: usize| count.saturating_mul(size).saturating_add(extra);
+        let expressions = len.saturating_mul(expansion);
+        let (bucket, size) = match id {
+            // Value types are encoded (and stored) as single bytes.
+            SectionId::Type => (
+                &mut self.other,
+                entries(size_of::<FunctionType<Global>>(), len),
+            ),
+            // Names are stored as they are encoded.
+            SectionId::Import => (&mut self.other, entries(size_of::<Import<Global>>(), len)),
+            SectionId::Function => (&mut self.other, entries(size_of::<TypeIdx>(), 0)),
+            SectionId::Table => (&mut self.other, entries(size_of::<TableType>(), 0)),
+            SectionId::Memory => (&mut self.other, entries(size_of::<MemType>(), 0)),
+            SectionId::Global => (
+                &mut self.other,
+                entries(size_of::<GlobalEntry<Global>>(), expressions),
+            ),
+            SectionId::Export => (&mut self.other, entries(size_of::<Export<Global>>(), len)),
+            SectionId::Element => (
+                &mut self.other,
+                entries(size_of::<ElementSegment<Global>>(), expressions),
+            ),
+            SectionId::Code => (
+                &mut self.code,
+                entries(size_of::<Function<Global>>(), expressions),
+            ),
+            SectionId::Data => (
+                &mut self.data,
+                entries(size_of::<DataSegment<Global>>(), len),
+            ),
+            SectionId::Custom | SectionId::Start | SectionId::DataCount => return,
+        };
+        *bucket = bucket.saturating_add(size);
+    }
+}
+
+/// Estimates the memory that decoding a module would allocate, from the
+/// section headers and entry counts alone.
+///
+/// This is a dry run: section contents are skipped rather than decoded, and
+/// no checks are made of section order. The estimates are heuristic rather
+/// than bounds; in particular, the sizes of re-encoded expressions and of
+/// function locals are only approximated.
+pub fn estimate<Bytes: AsRef<[u8]>>(
+    bytes: Bytes,
+) -> Result<SizeEstimate, decode::ErrorWithContext<MemoryEof>> {
+    let footprint =
+        estimate_decode_footprint(storage::Buffer::new(bytes), &DecodeOptions::default())?;
+    Ok(footprint.module)
+}
+
+/// An estimate of the peak heap usage of decoding a module, in bytes (see
+/// [`estimate_decode_footprint`]).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FootprintEstimate {
+    /// The estimated size of the decoded module.
+    pub module: SizeEstimate,
+    /// The estimated size of the allocations made in the course of decoding
+    /// and freed by its end, at their peak.
+    pub transient: usize,
+}
+
+impl FootprintEstimate {
+    /// The estimated total size, as a budget for the heap.
+    pub const fn total(&self) -> usize {
+        self.module.total().saturating_add(self.transient)
+    }
+}
+
+/// Estimates the peak heap usage of decoding a module from streaming storage
+/// with the given options, from the section headers and entry counts alone,
+/// as for rejecting modules up front on platforms with fixed heaps.
+///
+/// As with [`estimate`], this is a dry run, and the estimates are heuristic.
+/// The options are accounted for as follows:
+/// * packed expressions are assumed to take three quarters of the space;
+/// * skipped code and data sections take none;
+/// * rejecting duplicate export names takes a transient hash table.
+///
+/// Transcoding an expression takes a transient buffer as large as its
+/// re-encoding. The largest expression cannot be known from the headers, so it
+/// is assumed to be as large as its whole section.
+///
+/// Data segments streamed to a sink on decoding are still counted, while
+/// custom sections, which are left to the visitor, are not.
+pub fn estimate_decode_footprint<Storage: Stream>(
+    storage: Storage,
+    options: &DecodeOptions,
+) -> Result<FootprintEstimate, decode::ErrorWithContext<Storage::Error>> {
+    let expansion = if options.packed_expressions {
+        PACKED_EXPRESSION_EXPANSION
+    } else {
+        EXPRESSION_EXPANSION
+    };
+    let mut footprint = FootprintEstimate {
+        module: SizeEstimate {
+            other: size_of::<Module<Global>>(),
+            ..SizeEstimate::default()
+        },
+        transient: 0,
+    };
+    let mut context = ContextStack::default();
+    count_section_entries(storage, &mut context, |id, len, count| {
+        // Transient allocations are made section by section, so the peak is
+        // that of the section making the largest.
+        let transient = match id {
+            SectionId::Code if options.skip_code => return,
+            SectionId::Data if options.skip_data => return,
+            // The hash table of export names (see `find_duplicate_export`).
+            SectionId::Export if options.reject_duplicate_exports && count > 0 => {
+                let slots = (2 * count as usize).next_power_of_two();
+                slots.saturating_mul(size_of::<u32>())
+            }
+            // The buffer into which an expression is transcoded, which
+            // coexists with its reallocation as it grows. The largest
+            // expression is bounded only by its section.
+            SectionId::Code | SectionId::Global | SectionId::Element => {
+                (len as usize).saturating_mul(expansion)
+            }
+            _ => 0,
+        };
+        footprint.transient = footprint.transient.max(transient);
+        footprint.module.add_section(id, len, count, expansion);
+    })
+    .map_err(|error| decode::ErrorWithContext { error, context })?;
+    Ok(footprint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x00, 0x04, 0x01, b'n', 0xaa, 0xbb, // customsec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+        ];
+        let sections = scan_bytes(bytes, Global).unwrap();
+        assert_eq!(sections.len(), 3);
+        assert_eq!(
+            sections
+                .iter()
+                .map(|section| (section.id, section.offset, section.len))
+                .collect::<std::vec::Vec<_>>(),
+            [
+                (SectionId::Type, 10, 4),
+                (SectionId::Custom, 16, 4),
+                (SectionId::Function, 22, 2),
+            ]
+        );
+        assert!(sections[0].name.is_none());
+        let name: &str = sections[1].name.as_ref().unwrap();
+        assert_eq!(name, "n");
+    }
+
+    #[test]
+    fn truncated_section() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, // truncated typesec
+        ];
+        let err = scan_bytes(bytes, Global).unwrap_err();
+        assert_eq!(err.error, decode::Error::Storage(MemoryEof {}));
+    }
+
+    #[test]
+    fn size_estimate() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+            0x0a, 0x09, 0x02, // codesec
+            0x02, 0x00, 0x0b, // (func)
+            0x04, 0x00, 0x41, 0x00, 0x0b, // (func (i32.const 0))
+            0x0b, 0x06, 0x01, 0x01, 0x03, 0x61, 0x62, 0x63, // datasec
+        ];
+        let sizes = estimate(bytes).unwrap();
+        assert!(sizes.code >= 2 * size_of::<Function<Global>>());
+        assert!(sizes.data >= size_of::<DataSegment<Global>>() + 3);
+        assert!(sizes.other >= size_of::<Module<Global>>() + 2 * size_of::<TypeIdx>());
+        assert_eq!(sizes.total(), sizes.code + sizes.data + sizes.other);
+
+        #[cfg(feature = "decode")]
+        {
+            use crate::decode::NoCustomSectionVisitor;
+
+            let module =
+                Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+            let code_size: usize = module.codesec.iter().map(|func| func.code.len()).sum();
+            assert!(sizes.code >= code_size);
+        }
+
+        let err = estimate([0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01]);
+        assert_eq!(err.unwrap_err().error, decode::Error::Storage(MemoryEof {}));
+    }
+
+    #[test]
+    fn decode_footprint() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x07, 0x09, 0x02, // exportsec
+            0x01, b'a', 0x00, 0x00, // (export "a" (func 0))
+            0x01, b'b', 0x00, 0x00, // (export "b" (func 0))
+            0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x00, 0x0b, // codesec
+            0x0b, 0x06, 0x01, 0x01, 0x03, 0x61, 0x62, 0x63, // datasec
+        ];
+        let footprint = |options: &DecodeOptions| {
+            estimate_decode_footprint(storage::Buffer::new(bytes), options).unwrap()
+        };
+
+        let default = footprint(&DecodeOptions::default());
+        assert_eq!(default.module, estimate(bytes).unwrap());
+        // The transcoding buffer of the code section.
+        assert_eq!(default.transient, 6 * EXPRESSION_EXPANSION);
+        assert_eq!(default.total(), default.module.total() + default.transient);
+
+        let packed = footprint(&DecodeOptions {
+            packed_expressions: true,
+            ..DecodeOptions::default()
+        });
+        assert!(packed.module.code < default.module.code);
+        assert_eq!(packed.module.data, default.module.data);
+        assert_eq!(packed.transient, 6 * PACKED_EXPRESSION_EXPANSION);
+
+        let options = DecodeOptions {
+            skip_code: true,
+            skip_data: true,
+            reject_duplicate_exports: true,
+            ..DecodeOptions::default()
+        };
+        let skipped = footprint(&options);
+        assert_eq!((skipped.module.code, skipped.module.data), (0, 0));
+        assert_eq!(skipped.module.other, default.module.other);
+        assert_eq!(skipped.transient, 4 * size_of::<u32>());
+    }
+}
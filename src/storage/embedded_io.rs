@@ -0,0 +1,200 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+use embedded_io::{Read, ReadExactError, Seek, SeekFrom};
+
+use super::{Checkpoint, SeekableStream, Stream};
+
+/// Represents errors that can arise in reading from an [`EmbeddedIoStream`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EmbeddedIoError<E> {
+    /// The end of the stream was reached.
+    Eof,
+    /// A position within the stream is not representable as a `usize`.
+    OffsetOverflow,
+    /// An error of the underlying reader.
+    Io(E),
+}
+
+impl<E> From<ReadExactError<E>> for EmbeddedIoError<E> {
+    fn from(err: ReadExactError<E>) -> Self {
+        match err {
+            ReadExactError::UnexpectedEof => Self::Eof,
+            ReadExactError::Other(err) => Self::Io(err),
+        }
+    }
+}
+
+/// A [`Stream`] over an `embedded-io` reader, as for modules stored in flash.
+///
+/// Bytes are read directly from the reader without buffering beyond its own,
+/// and the offset into the stream is tracked without querying the reader.
+pub struct EmbeddedIoStream<R: Read + Seek> {
+    inner: R,
+    offset: usize,
+}
+
+impl<R: Read + Seek> EmbeddedIoStream<R> {
+    /// Wraps a reader. Reading then starts from the reader's current position.
+    pub fn new(mut inner: R) -> Result<Self, EmbeddedIoError<R::Error>> {
+        let offset = inner.stream_position().map_err(EmbeddedIoError::Io)?;
+        let offset = usize::try_from(offset).map_err(|_| EmbeddedIoError::OffsetOverflow)?;
+        Ok(Self { inner, offset })
+    }
+
+    /// Unwraps the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn seek_to(&mut self, offset: usize) -> Result<(), EmbeddedIoError<R::Error>> {
+        self.inner
+            .seek(SeekFrom::Start(offset as u64))
+            .map_err(EmbeddedIoError::Io)?;
+        self.offset = offset;
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Stream for EmbeddedIoStream<R> {
+    type Error = EmbeddedIoError<R::Error>;
+
+    fn is_eof(err: &Self::Error) -> bool {
+        matches!(err, EmbeddedIoError::Eof)
+    }
+
+    fn offset(&mut self) -> usize {
+        self.offset
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        let mut buf = [0u8; 1];
+        self.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read_exact(buf)?;
+        self.offset += buf.len();
+        Ok(())
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<(), Self::Error> {
+        // As with `std::io` types, seeking past the end is not necessarily an
+        // error, so seek to just before the last skipped byte and read it to
+        // confirm it exists.
+        let Some(count) = count.checked_sub(1) else {
+            return Ok(());
+        };
+        let offset = self
+            .offset
+            .checked_add(count)
+            .ok_or(EmbeddedIoError::OffsetOverflow)?;
+        self.seek_to(offset)?;
+        self.read_byte().map(|_| ())
+    }
+
+    fn len_hint(&mut self) -> Option<usize> {
+        let end = self.inner.seek(SeekFrom::End(0)).ok()?;
+        self.inner.seek(SeekFrom::Start(self.offset as u64)).ok()?;
+        usize::try_from(end).ok()?.checked_sub(self.offset)
+    }
+}
+
+impl<R: Read + Seek> SeekableStream for EmbeddedIoStream<R> {
+    fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), Self::Error> {
+        self.seek_to(checkpoint.offset)
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use embedded_io::{ErrorKind, ErrorType};
+
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    // A stand-in for flash-backed storage, which reads at most four bytes at
+    // a time.
+    struct Flash {
+        bytes: &'static [u8],
+        pos: usize,
+    }
+
+    impl ErrorType for Flash {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Flash {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            let remaining = self.bytes.get(self.pos..).unwrap_or_default();
+            let count = buf.len().min(remaining.len()).min(4);
+            buf[..count].copy_from_slice(&remaining[..count]);
+            self.pos += count;
+            Ok(count)
+        }
+    }
+
+    impl Seek for Flash {
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64, ErrorKind> {
+            let pos = match pos {
+                SeekFrom::Start(pos) => usize::try_from(pos).ok(),
+                SeekFrom::End(delta) => self.bytes.len().checked_add_signed(delta as isize),
+                SeekFrom::Current(delta) => self.pos.checked_add_signed(delta as isize),
+            };
+            self.pos = pos.ok_or(ErrorKind::InvalidInput)?;
+            Ok(self.pos as u64)
+        }
+    }
+
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, // magic
+        0x01, 0x00, 0x00, 0x00, // version
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+        0x03, 0x02, 0x01, 0x00, // funcsec
+        0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+        0x00, 0x03, 0x01, b'a', 0xff, // customsec
+    ];
+
+    #[test]
+    fn embedded_io_stream() {
+        let flash = Flash {
+            bytes: MODULE,
+            pos: 8,
+        };
+        let mut stream = EmbeddedIoStream::new(flash).unwrap();
+        assert_eq!(stream.offset(), 8);
+        assert_eq!(stream.len_hint(), Some(21));
+
+        let checkpoint = stream.checkpoint();
+        let mut buf = [0; 6];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, MODULE[8..14]);
+        stream.skip_bytes(10).unwrap();
+        assert_eq!(stream.offset(), 24);
+        assert_eq!(stream.read_byte().unwrap(), 0x00);
+
+        let err = stream.skip_bytes(5).unwrap_err();
+        assert!(EmbeddedIoStream::<Flash>::is_eof(&err));
+
+        stream.rewind(checkpoint).unwrap();
+        assert_eq!(stream.offset(), 8);
+        assert_eq!(stream.read_byte().unwrap(), 0x01);
+    }
+
+    #[test]
+    fn embedded_io_decoding() {
+        let flash = Flash {
+            bytes: MODULE,
+            pos: 0,
+        };
+        let stream = EmbeddedIoStream::new(flash).unwrap();
+        let module = Module::decode(stream, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        assert_eq!(module.codesec.len(), 1);
+    }
+}
@@ -7,10 +7,24 @@
 //! Storage abstraction for sequential binary data reading.
 //!
 //! Provides the [`Stream`] trait for reading binary data sequentially,
-//! with implementations for in-memory buffers and standard I/O types.
-
+//! with implementations for in-memory buffers, non-contiguous [`Segments`],
+//! standard I/O types, and (with the `embedded-io` feature) `embedded-io`
+//! types, along with the [`SeekableStream`] extension for those that can be
+//! rewound.
+
+#[cfg(feature = "embedded-io")]
+mod embedded_io;
+mod segments;
 #[cfg(feature = "std")]
 mod std;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test;
+
+#[cfg(feature = "embedded-io")]
+pub use self::embedded_io::{EmbeddedIoError, EmbeddedIoStream};
+pub use self::segments::Segments;
+#[cfg(feature = "std")]
+pub use self::std::BufferedStream;
 
 use core::fmt;
 
@@ -44,13 +58,51 @@ pub trait Stream {
 
     /// Skip the specified number of bytes in the stream.
     ///
-    /// Implementors should override for better performance.
+    /// As with reads, returns an error if EOF is reached before the bytes are
+    /// skipped. Implementors should override for better performance.
     fn skip_bytes(&mut self, count: usize) -> Result<(), Self::Error> {
         for _ in 0..count {
             self.read_byte()?;
         }
         Ok(())
     }
+
+    /// Returns an upper bound on the number of bytes remaining in the stream,
+    /// if one is cheaply known.
+    ///
+    /// Decoding consults it before allocating for a declared length, so that
+    /// a truncated stream is reported as such rather than attempting a
+    /// needlessly large allocation.
+    fn len_hint(&mut self) -> Option<usize> {
+        None
+    }
+}
+
+/// A position within a [`SeekableStream`], to which it can later be rewound.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Checkpoint {
+    offset: usize,
+}
+
+impl Checkpoint {
+    /// The byte offset into the stream of the position.
+    pub const fn offset(self) -> usize {
+        self.offset
+    }
+}
+
+/// A [`Stream`] that can return to an earlier position, as needed to recover
+/// from errors or to decode sections lazily.
+pub trait SeekableStream: Stream {
+    /// Records the current position.
+    fn checkpoint(&mut self) -> Checkpoint {
+        Checkpoint {
+            offset: self.offset(),
+        }
+    }
+
+    /// Returns to a recorded position.
+    fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), Self::Error>;
 }
 
 /// Represents attempting to read past the end of a buffer.
@@ -58,18 +110,33 @@ pub trait Stream {
 pub struct MemoryEof {}
 
 /// In-memory buffer implementation of [`Stream`].
+#[cfg(feature = "scan")]
 pub(super) struct Buffer<Bytes: AsRef<[u8]>> {
     bytes: Bytes,
     pos: usize,
+    // The offset reported for the start of `bytes`.
+    base: usize,
 }
 
+#[cfg(feature = "scan")]
 impl<Bytes: AsRef<[u8]>> Buffer<Bytes> {
     /// Create a new buffer stream from the given bytes.
     pub(super) fn new(bytes: Bytes) -> Self {
-        Self { bytes, pos: 0 }
+        Self::with_offset(bytes, 0)
+    }
+
+    /// Create a new buffer stream from the given bytes, which are understood
+    /// to begin at a given offset into a larger stream.
+    pub(super) fn with_offset(bytes: Bytes, base: usize) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            base,
+        }
     }
 }
 
+#[cfg(feature = "scan")]
 impl<Bytes: AsRef<[u8]>> Stream for Buffer<Bytes> {
     type Error = MemoryEof;
 
@@ -78,7 +145,7 @@ impl<Bytes: AsRef<[u8]>> Stream for Buffer<Bytes> {
     }
 
     fn offset(&mut self) -> usize {
-        self.pos
+        self.base + self.pos
     }
 
     fn read_byte(&mut self) -> Result<u8, Self::Error> {
@@ -114,4 +181,46 @@ impl<Bytes: AsRef<[u8]>> Stream for Buffer<Bytes> {
             Err(MemoryEof {})
         }
     }
+    fn len_hint(&mut self) -> Option<usize> {
+        Some(self.bytes.as_ref().len() - self.pos)
+    }
+}
+
+#[cfg(feature = "scan")]
+impl<Bytes: AsRef<[u8]>> SeekableStream for Buffer<Bytes> {
+    fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), Self::Error> {
+        match checkpoint.offset.checked_sub(self.base) {
+            Some(pos) if pos <= self.bytes.as_ref().len() => {
+                self.pos = pos;
+                Ok(())
+            }
+            _ => Err(MemoryEof {}),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "scan"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_checkpoints() {
+        let mut buffer = Buffer::with_offset([1, 2, 3, 4], 0x10);
+        assert_eq!(buffer.read_byte(), Ok(1));
+        let checkpoint = buffer.checkpoint();
+        assert_eq!(checkpoint.offset(), 0x11);
+        buffer.skip_bytes(3).unwrap();
+        assert_eq!(buffer.read_byte(), Err(MemoryEof {}));
+
+        buffer.rewind(checkpoint).unwrap();
+        assert_eq!(buffer.read_byte(), Ok(2));
+        assert_eq!(buffer.offset(), 0x12);
+        assert_eq!(buffer.len_hint(), Some(2));
+
+        let before = Checkpoint { offset: 0xf };
+        let after = Checkpoint { offset: 0x15 };
+        assert_eq!(buffer.rewind(before), Err(MemoryEof {}));
+        assert_eq!(buffer.rewind(after), Err(MemoryEof {}));
+        assert_eq!(buffer.read_byte(), Ok(3));
+    }
 }
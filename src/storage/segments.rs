@@ -0,0 +1,188 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+use super::{Checkpoint, MemoryEof, SeekableStream, Stream};
+
+/// A [`Stream`] over a sequence of non-contiguous byte slices, read as if
+/// they were concatenated.
+///
+/// This permits decoding directly out of scattered buffers (e.g., received
+/// network packets or the two halves of a ring buffer) without first copying
+/// them into one. Empty segments are permitted and ignored.
+#[derive(Clone, Debug)]
+pub struct Segments<'a> {
+    slices: &'a [&'a [u8]],
+    // The index of the current segment.
+    index: usize,
+    // The position within the current segment.
+    pos: usize,
+    // The overall offset into the stream.
+    offset: usize,
+}
+
+impl<'a> Segments<'a> {
+    /// Creates a stream over the concatenation of the given segments.
+    pub const fn new(segments: &'a [&'a [u8]]) -> Self {
+        Self {
+            slices: segments,
+            index: 0,
+            pos: 0,
+            offset: 0,
+        }
+    }
+
+    /// The total number of bytes across all segments.
+    pub fn len(&self) -> usize {
+        self.slices.iter().map(|segment| segment.len()).sum()
+    }
+
+    /// Whether there are no bytes across all segments.
+    pub fn is_empty(&self) -> bool {
+        self.slices.iter().all(|segment| segment.is_empty())
+    }
+
+    // The unread remainder of the current segment, advancing past any
+    // exhausted ones. Empty only at the end of the stream.
+    fn current(&mut self) -> &'a [u8] {
+        while let Some(segment) = self.slices.get(self.index) {
+            if self.pos < segment.len() {
+                return &segment[self.pos..];
+            }
+            self.index += 1;
+            self.pos = 0;
+        }
+        &[]
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.pos += count;
+        self.offset += count;
+    }
+
+    // Whether at least `count` more bytes remain, checked up front so that a
+    // failed read leaves the position unchanged, as with a contiguous buffer.
+    fn has_remaining(&self, count: usize) -> bool {
+        let mut needed = count;
+        let mut pos = self.pos;
+        for segment in self.slices.iter().skip(self.index) {
+            let available = segment.len().saturating_sub(pos);
+            if needed <= available {
+                return true;
+            }
+            needed -= available;
+            pos = 0;
+        }
+        false
+    }
+}
+
+impl Stream for Segments<'_> {
+    type Error = MemoryEof;
+
+    fn is_eof(_: &Self::Error) -> bool {
+        true
+    }
+
+    fn offset(&mut self) -> usize {
+        self.offset
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        let byte = *self.current().first().ok_or(MemoryEof {})?;
+        self.advance(1);
+        Ok(byte)
+    }
+
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), Self::Error> {
+        if !self.has_remaining(buf.len()) {
+            return Err(MemoryEof {});
+        }
+        while !buf.is_empty() {
+            let current = self.current();
+            let count = current.len().min(buf.len());
+            let (head, tail) = buf.split_at_mut(count);
+            head.copy_from_slice(&current[..count]);
+            self.advance(count);
+            buf = tail;
+        }
+        Ok(())
+    }
+
+    fn skip_bytes(&mut self, mut count: usize) -> Result<(), Self::Error> {
+        if !self.has_remaining(count) {
+            return Err(MemoryEof {});
+        }
+        while count > 0 {
+            let skipped = self.current().len().min(count);
+            self.advance(skipped);
+            count -= skipped;
+        }
+        Ok(())
+    }
+}
+
+impl SeekableStream for Segments<'_> {
+    fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), Self::Error> {
+        let mut rewound = Self::new(self.slices);
+        rewound.skip_bytes(checkpoint.offset())?;
+        *self = rewound;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segmented_reads() {
+        let segments: [&[u8]; 4] = [&[1, 2], &[], &[3], &[4, 5, 6]];
+        let mut stream = Segments::new(&segments);
+        assert_eq!(stream.len(), 6);
+
+        assert_eq!(stream.read_byte(), Ok(1));
+        let mut buf = [0; 3];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [2, 3, 4]);
+        assert_eq!(stream.offset(), 4);
+
+        // A failed read consumes nothing.
+        assert_eq!(stream.read_exact(&mut buf), Err(MemoryEof {}));
+        assert_eq!(stream.offset(), 4);
+
+        let checkpoint = stream.checkpoint();
+        stream.skip_bytes(2).unwrap();
+        assert_eq!(stream.read_byte(), Err(MemoryEof {}));
+
+        stream.rewind(checkpoint).unwrap();
+        assert_eq!(stream.read_byte(), Ok(5));
+        stream.rewind(Checkpoint { offset: 1 }).unwrap();
+        assert_eq!(stream.read_byte(), Ok(2));
+        assert_eq!(stream.rewind(Checkpoint { offset: 7 }), Err(MemoryEof {}));
+        assert_eq!(stream.read_byte(), Ok(3));
+    }
+
+    #[cfg(feature = "decode")]
+    #[test]
+    fn segmented_decoding() {
+        use crate::Module;
+        use crate::decode::NoCustomSectionVisitor;
+
+        // A module with a single function type, split mid-section.
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // Header
+            0x01, 0x05, 0x01, 0x60, 0x01, 0x7f, 0x00, // Type section
+        ];
+        let segments: [&[u8]; 3] = [&bytes[..3], &bytes[3..11], &bytes[11..]];
+        let module = Module::decode(
+            Segments::new(&segments),
+            &mut NoCustomSectionVisitor {},
+            allocator_api2::alloc::Global,
+        )
+        .unwrap();
+        assert_eq!(module.typesec.len(), 1);
+    }
+}
@@ -4,9 +4,10 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT
 
+use std::boxed::Box;
 use std::io;
 
-use super::Stream;
+use super::{Checkpoint, SeekableStream, Stream};
 
 /// Stream implementation for `std::io` types.
 impl<R: io::Read + io::Seek> Stream for R {
@@ -31,6 +32,226 @@ impl<R: io::Read + io::Seek> Stream for R {
     }
 
     fn skip_bytes(&mut self, count: usize) -> Result<(), Self::Error> {
-        io::Seek::seek_relative(self, count.try_into().unwrap())
+        // Seeking past the end of a reader is not itself an error, so seek to
+        // just before the last skipped byte and read it to confirm it exists.
+        let Some(count) = count.checked_sub(1) else {
+            return Ok(());
+        };
+        let count = count
+            .try_into()
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        io::Seek::seek_relative(self, count)?;
+        Stream::read_byte(self).map(|_| ())
+    }
+
+    fn len_hint(&mut self) -> Option<usize> {
+        let pos = self.stream_position().ok()?;
+        let end = self.seek(io::SeekFrom::End(0)).ok()?;
+        self.seek(io::SeekFrom::Start(pos)).ok()?;
+        usize::try_from(end.saturating_sub(pos)).ok()
+    }
+}
+
+impl<R: io::Read + io::Seek> SeekableStream for R {
+    fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), Self::Error> {
+        self.seek(io::SeekFrom::Start(checkpoint.offset as u64))?;
+        Ok(())
+    }
+}
+
+/// A buffered [`Stream`] over a `std::io` type, which reads ahead in chunks of
+/// a configurable size.
+///
+/// Unlike `std::io::BufReader`, the offset into the stream is tracked without
+/// querying the underlying reader, and skips and rewinds within the buffered
+/// bytes do not touch it at all.
+pub struct BufferedStream<R: io::Read + io::Seek> {
+    inner: R,
+    buf: Box<[u8]>,
+    // The position of the next byte to read within `buf`.
+    pos: usize,
+    // The number of bytes of `buf` that hold data.
+    filled: usize,
+    // The offset into the stream of `buf[0]`.
+    base: usize,
+}
+
+impl<R: io::Read + io::Seek> BufferedStream<R> {
+    /// The default read-ahead size.
+    pub const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+    /// Wraps a reader with the default read-ahead size.
+    pub fn new(inner: R) -> io::Result<Self> {
+        Self::with_capacity(Self::DEFAULT_CAPACITY, inner)
+    }
+
+    /// Wraps a reader, reading ahead `capacity` bytes at a time. Reading then
+    /// starts from the reader's current position.
+    pub fn with_capacity(capacity: usize, mut inner: R) -> io::Result<Self> {
+        let base = usize::try_from(inner.stream_position()?)
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        Ok(Self {
+            inner,
+            buf: std::vec![0; capacity.max(1)].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            base,
+        })
+    }
+
+    /// Unwraps the underlying reader, whose position is unspecified.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Discards the buffered bytes, repositioning the reader at `offset`.
+    fn reset(&mut self, offset: usize) -> io::Result<()> {
+        self.inner.seek(io::SeekFrom::Start(offset as u64))?;
+        self.base = offset;
+        self.pos = 0;
+        self.filled = 0;
+        Ok(())
+    }
+
+    // Refills the buffer from the reader, returning false at its end.
+    fn fill(&mut self) -> io::Result<bool> {
+        debug_assert_eq!(self.pos, self.filled);
+        self.base += self.filled;
+        self.pos = 0;
+        self.filled = 0;
+        loop {
+            match self.inner.read(&mut self.buf) {
+                Ok(read) => {
+                    self.filled = read;
+                    return Ok(read > 0);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl<R: io::Read + io::Seek> Stream for BufferedStream<R> {
+    type Error = io::Error;
+
+    fn is_eof(err: &Self::Error) -> bool {
+        err.kind() == io::ErrorKind::UnexpectedEof
+    }
+
+    fn offset(&mut self) -> usize {
+        self.base + self.pos
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        if self.pos == self.filled && !self.fill()? {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let byte = self.buf[self.pos];
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), Self::Error> {
+        while !buf.is_empty() {
+            if self.pos == self.filled && !self.fill()? {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let count = buf.len().min(self.filled - self.pos);
+            let (head, tail) = buf.split_at_mut(count);
+            head.copy_from_slice(&self.buf[self.pos..self.pos + count]);
+            self.pos += count;
+            buf = tail;
+        }
+        Ok(())
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<(), Self::Error> {
+        if count <= self.filled - self.pos {
+            self.pos += count;
+            return Ok(());
+        }
+        // As with the unbuffered implementation, reposition just before the
+        // last skipped byte and read it to confirm it exists.
+        let offset = self
+            .offset()
+            .checked_add(count - 1)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        self.reset(offset)?;
+        self.read_byte().map(|_| ())
+    }
+
+    // The reader is positioned just past the buffered bytes.
+    fn len_hint(&mut self) -> Option<usize> {
+        let buffered = self.filled - self.pos;
+        buffered.checked_add(self.inner.len_hint()?)
+    }
+}
+
+impl<R: io::Read + io::Seek> SeekableStream for BufferedStream<R> {
+    fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), Self::Error> {
+        match checkpoint.offset.checked_sub(self.base) {
+            Some(pos) if pos <= self.filled => {
+                self.pos = pos;
+                Ok(())
+            }
+            _ => self.reset(checkpoint.offset),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    #[test]
+    fn buffered_stream() {
+        let bytes: std::vec::Vec<u8> = (0..=255).collect();
+        let mut stream = BufferedStream::with_capacity(16, Cursor::new(&bytes)).unwrap();
+
+        assert_eq!(stream.read_byte().unwrap(), 0);
+        let checkpoint = stream.checkpoint();
+        let mut buf = [0; 40]; // Spanning several refills.
+        stream.read_exact(&mut buf).unwrap();
+        assert!(buf.iter().copied().eq(1..41));
+        stream.skip_bytes(4).unwrap(); // Within the buffer.
+        stream.skip_bytes(100).unwrap(); // Beyond it.
+        assert_eq!(stream.offset(), 145);
+        assert_eq!(stream.read_byte().unwrap(), 145);
+        assert_eq!(stream.len_hint(), Some(110));
+
+        stream.rewind(checkpoint).unwrap();
+        assert_eq!(stream.offset(), 1);
+        assert_eq!(stream.read_byte().unwrap(), 1);
+
+        stream.skip_bytes(254).unwrap(); // Up to the end.
+        let err = stream.read_byte().unwrap_err();
+        assert!(BufferedStream::<Cursor<&std::vec::Vec<u8>>>::is_eof(&err));
+
+        stream.rewind(checkpoint).unwrap();
+        let err = stream.skip_bytes(256).unwrap_err(); // Past the end.
+        assert!(BufferedStream::<Cursor<&std::vec::Vec<u8>>>::is_eof(&err));
+    }
+
+    #[test]
+    fn buffered_decoding() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+            0x00, 0x03, 0x01, b'a', 0xff, // customsec
+        ];
+        for capacity in 1..=bytes.len() {
+            let stream = BufferedStream::with_capacity(capacity, Cursor::new(&bytes)).unwrap();
+            let module = Module::decode(stream, &mut NoCustomSectionVisitor {}, Global).unwrap();
+            assert_eq!(module.codesec.len(), 1);
+        }
     }
 }
@@ -0,0 +1,122 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Utilities for testing the handling of storage failures.
+
+use super::{Checkpoint, SeekableStream, Stream};
+
+/// The kind of failure injected by a [`FaultyStream`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fault {
+    /// The stream appears to end.
+    Eof,
+    /// The stream fails with an error other than reaching its end.
+    Error,
+}
+
+/// An error reading from a [`FaultyStream`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FaultyStreamError<StreamError> {
+    /// An injected fault.
+    Injected(Fault),
+    /// An error of the wrapped stream.
+    Stream(StreamError),
+}
+
+/// A stream wrapping another, which fails upon reaching a given offset.
+///
+/// The fault is persistent: once the offset is reached, all further reads
+/// fail. A read spanning the offset first reads the bytes preceding it.
+pub struct FaultyStream<S: Stream> {
+    inner: S,
+    fault: Option<(usize, Fault)>,
+}
+
+impl<S: Stream> FaultyStream<S> {
+    /// Wraps a stream without injecting any faults.
+    pub fn new(inner: S) -> Self {
+        Self { inner, fault: None }
+    }
+
+    /// Wraps a stream, injecting a fault upon reaching the given offset.
+    pub fn with_fault(inner: S, offset: usize, fault: Fault) -> Self {
+        Self {
+            inner,
+            fault: Some((offset, fault)),
+        }
+    }
+
+    /// Unwraps the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    // Checks whether a read of `count` bytes would reach the fault, returning
+    // the number of bytes preceding it along with the fault if so.
+    fn check(&mut self, count: usize) -> Option<(usize, Fault)> {
+        let (offset, fault) = self.fault?;
+        let available = offset.saturating_sub(self.inner.offset());
+        (count > available).then_some((available, fault))
+    }
+}
+
+impl<S: Stream> Stream for FaultyStream<S> {
+    type Error = FaultyStreamError<S::Error>;
+
+    fn is_eof(err: &Self::Error) -> bool {
+        match err {
+            FaultyStreamError::Injected(fault) => *fault == Fault::Eof,
+            FaultyStreamError::Stream(err) => S::is_eof(err),
+        }
+    }
+
+    fn offset(&mut self) -> usize {
+        self.inner.offset()
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Self::Error> {
+        if let Some((_, fault)) = self.check(1) {
+            return Err(FaultyStreamError::Injected(fault));
+        }
+        self.inner.read_byte().map_err(FaultyStreamError::Stream)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        if let Some((available, fault)) = self.check(buf.len()) {
+            self.inner
+                .read_exact(&mut buf[..available])
+                .map_err(FaultyStreamError::Stream)?;
+            return Err(FaultyStreamError::Injected(fault));
+        }
+        self.inner
+            .read_exact(buf)
+            .map_err(FaultyStreamError::Stream)
+    }
+
+    fn skip_bytes(&mut self, count: usize) -> Result<(), Self::Error> {
+        if let Some((available, fault)) = self.check(count) {
+            self.inner
+                .skip_bytes(available)
+                .map_err(FaultyStreamError::Stream)?;
+            return Err(FaultyStreamError::Injected(fault));
+        }
+        self.inner
+            .skip_bytes(count)
+            .map_err(FaultyStreamError::Stream)
+    }
+
+    fn len_hint(&mut self) -> Option<usize> {
+        self.inner.len_hint()
+    }
+}
+
+impl<S: SeekableStream> SeekableStream for FaultyStream<S> {
+    fn rewind(&mut self, checkpoint: Checkpoint) -> Result<(), Self::Error> {
+        self.inner
+            .rewind(checkpoint)
+            .map_err(FaultyStreamError::Stream)
+    }
+}
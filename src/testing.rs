@@ -0,0 +1,776 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Property-based testing with `proptest`, by way of strategies generating
+//! arbitrary valid modules.
+//!
+//! The generated modules are small but cover much of the binary format:
+//! function imports and definitions of assorted types, with locals and bodies
+//! of calls, loads, constants, and nested blocks; immutable global imports and
+//! mutable global definitions; an optional memory; function and memory
+//! exports; element segments; and active and passive data segments.
+
+use std::format;
+use std::vec::Vec;
+
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::encoding::{leb128, name, section, sleb128};
+
+/// A strategy generating the binary encodings of arbitrary valid modules.
+///
+/// Encodings are canonical (e.g., with minimal LEB128 widths), and shrink
+/// towards modules with fewer and simpler entities.
+pub fn arbitrary_module() -> impl Strategy<Value = Vec<u8>> {
+    module_spec().prop_map(|spec| spec.encode())
+}
+
+// The value types of generated modules: i32, i64, f32, and f64.
+const VALTYPES: [u8; 4] = [0x7f, 0x7e, 0x7d, 0x7c];
+
+// A constant of a value type, given by index into `VALTYPES`, encoded with
+// the corresponding `*.const` instruction.
+#[derive(Clone, Copy, Debug)]
+struct Const {
+    ty: usize,
+    bits: u64,
+}
+
+impl Const {
+    fn of(ty: usize) -> Self {
+        Self { ty, bits: 0 }
+    }
+
+    fn encode(self, out: &mut Vec<u8>) {
+        match self.ty {
+            0 => {
+                out.push(0x41);
+                sleb128(out, i64::from((self.bits as u32).cast_signed()));
+            }
+            1 => {
+                out.push(0x42);
+                sleb128(out, self.bits.cast_signed());
+            }
+            2 => {
+                out.push(0x43);
+                out.extend((self.bits as u32).to_le_bytes());
+            }
+            _ => {
+                out.push(0x44);
+                out.extend(self.bits.to_le_bytes());
+            }
+        }
+    }
+}
+
+// A stack-neutral sequence of instructions.
+#[derive(Clone, Debug)]
+enum Stmt {
+    Nop,
+    // (drop (*.const))
+    Const(Const),
+    // (drop (local.get))
+    LocalGet(Index),
+    // (drop (global.get))
+    GlobalGet(Index),
+    // (global.set (*.const)) of a defined (and so mutable) global
+    GlobalSet(Index, u64),
+    // (drop (i32.load align offset (i32.const 0))), if there is a memory
+    Load { align: u32, offset: u32 },
+    // A call with constant arguments, dropping any result
+    Call(Index),
+    // (block ...) or (loop ...)
+    Block { r#loop: bool, body: Vec<Stmt> },
+}
+
+#[derive(Clone, Debug)]
+struct FuncSpec {
+    ty: Index,
+    locals: Vec<usize>,
+    body: Vec<Stmt>,
+}
+
+// The definition of an arbitrary module, in terms of which its encoding is
+// generated.
+#[derive(Clone, Debug)]
+struct ModuleSpec {
+    // Function types, each of parameters and an optional result.
+    types: Vec<(Vec<usize>, Option<usize>)>,
+    imported_funcs: Vec<Index>,
+    imported_globals: Vec<usize>,
+    funcs: Vec<FuncSpec>,
+    // The minimum and optional maximum number of pages.
+    memory: Option<(u32, Option<u32>)>,
+    globals: Vec<Const>,
+    // Whether each function is exported, as "f<funcidx>".
+    exported_funcs: Vec<bool>,
+    export_memory: bool,
+    // The functions of a passive element segment, if any.
+    elems: Option<Vec<Index>>,
+    // Data segments, each active at an offset or passive.
+    datas: Vec<(Option<u32>, Vec<u8>)>,
+}
+
+fn valtype() -> impl Strategy<Value = usize> {
+    0..VALTYPES.len()
+}
+
+fn constant() -> impl Strategy<Value = Const> {
+    (valtype(), any::<u64>()).prop_map(|(ty, bits)| Const { ty, bits })
+}
+
+fn stmt() -> impl Strategy<Value = Stmt> {
+    let leaf = prop_oneof![
+        Just(Stmt::Nop),
+        constant().prop_map(Stmt::Const),
+        any::<Index>().prop_map(Stmt::LocalGet),
+        any::<Index>().prop_map(Stmt::GlobalGet),
+        (any::<Index>(), any::<u64>()).prop_map(|(idx, bits)| Stmt::GlobalSet(idx, bits)),
+        (0..=2u32, any::<u32>()).prop_map(|(align, offset)| Stmt::Load { align, offset }),
+        any::<Index>().prop_map(Stmt::Call),
+    ];
+    leaf.prop_recursive(3, 16, 4, |inner| {
+        (any::<bool>(), vec(inner, 0..4)).prop_map(|(r#loop, body)| Stmt::Block { r#loop, body })
+    })
+}
+
+fn module_spec() -> impl Strategy<Value = ModuleSpec> {
+    let func = (any::<Index>(), vec(valtype(), 0..3), vec(stmt(), 0..6))
+        .prop_map(|(ty, locals, body)| FuncSpec { ty, locals, body });
+    (
+        vec((vec(valtype(), 0..3), option::of(valtype())), 1..4),
+        vec(any::<Index>(), 0..3),
+        vec(valtype(), 0..2),
+        vec(func, 0..6),
+        option::of((0..3u32, option::of(0..3u32))),
+        vec(constant(), 0..3),
+        vec(any::<bool>(), 9),
+        any::<bool>(),
+        option::of(vec(any::<Index>(), 0..4)),
+        vec((option::of(0..0x1_0000u32), vec(any::<u8>(), 0..16)), 0..3),
+    )
+        .prop_map(
+            |(
+                types,
+                imported_funcs,
+                imported_globals,
+                funcs,
+                memory,
+                globals,
+                exported_funcs,
+                export_memory,
+                elems,
+                datas,
+            )| {
+                let memory = memory.map(|(min, extra)| (min, extra.map(|extra| min + extra)));
+                ModuleSpec {
+                    types,
+                    imported_funcs,
+                    imported_globals,
+                    funcs,
+                    memory,
+                    globals,
+                    exported_funcs,
+                    export_memory,
+                    elems,
+                    // Active segments require a memory.
+                    datas: datas
+                        .into_iter()
+                        .filter(|(offset, _)| memory.is_some() || offset.is_none())
+                        .collect(),
+                }
+            },
+        )
+}
+
+impl ModuleSpec {
+    fn func_count(&self) -> usize {
+        self.imported_funcs.len() + self.funcs.len()
+    }
+
+    fn global_count(&self) -> usize {
+        self.imported_globals.len() + self.globals.len()
+    }
+
+    // The type index of the function of a given index.
+    fn func_type(&self, idx: usize) -> usize {
+        let ty = match idx.checked_sub(self.imported_funcs.len()) {
+            Some(idx) => self.funcs[idx].ty,
+            None => self.imported_funcs[idx],
+        };
+        ty.index(self.types.len())
+    }
+
+    // The value type of the global of a given index.
+    fn global_type(&self, idx: usize) -> usize {
+        match idx.checked_sub(self.imported_globals.len()) {
+            Some(idx) => self.globals[idx].ty,
+            None => self.imported_globals[idx],
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::from(*b"\0asm\x01\0\0\0");
+
+        section(&mut bytes, 1, |out| {
+            leb128(out, self.types.len() as u64);
+            for (params, result) in &self.types {
+                out.push(0x60);
+                leb128(out, params.len() as u64);
+                out.extend(params.iter().map(|&ty| VALTYPES[ty]));
+                leb128(out, u64::from(result.is_some()));
+                out.extend(result.iter().map(|&ty| VALTYPES[ty]));
+            }
+        });
+
+        if !self.imported_funcs.is_empty() || !self.imported_globals.is_empty() {
+            section(&mut bytes, 2, |out| {
+                leb128(
+                    out,
+                    (self.imported_funcs.len() + self.imported_globals.len()) as u64,
+                );
+                for (idx, ty) in self.imported_funcs.iter().enumerate() {
+                    name(out, "env");
+                    name(out, &format!("f{idx}"));
+                    out.push(0x00);
+                    leb128(out, ty.index(self.types.len()) as u64);
+                }
+                for (idx, &ty) in self.imported_globals.iter().enumerate() {
+                    name(out, "env");
+                    name(out, &format!("g{idx}"));
+                    out.extend([0x03, VALTYPES[ty], 0x00]);
+                }
+            });
+        }
+
+        if !self.funcs.is_empty() {
+            section(&mut bytes, 3, |out| {
+                leb128(out, self.funcs.len() as u64);
+                for func in &self.funcs {
+                    leb128(out, func.ty.index(self.types.len()) as u64);
+                }
+            });
+        }
+
+        if let Some((min, max)) = self.memory {
+            section(&mut bytes, 5, |out| {
+                out.push(0x01);
+                limits(out, min, max);
+            });
+        }
+
+        if !self.globals.is_empty() {
+            section(&mut bytes, 6, |out| {
+                leb128(out, self.globals.len() as u64);
+                for init in &self.globals {
+                    out.extend([VALTYPES[init.ty], 0x01]);
+                    init.encode(out);
+                    out.push(0x0b);
+                }
+            });
+        }
+
+        let exported_funcs: Vec<_> = (0..self.func_count())
+            .filter(|&idx| self.exported_funcs.get(idx).copied().unwrap_or_default())
+            .collect();
+        let export_memory = self.export_memory && self.memory.is_some();
+        if !exported_funcs.is_empty() || export_memory {
+            section(&mut bytes, 7, |out| {
+                leb128(
+                    out,
+                    (exported_funcs.len() + usize::from(export_memory)) as u64,
+                );
+                for idx in exported_funcs {
+                    name(out, &format!("f{idx}"));
+                    out.push(0x00);
+                    leb128(out, idx as u64);
+                }
+                if export_memory {
+                    name(out, "memory");
+                    out.extend([0x02, 0x00]);
+                }
+            });
+        }
+
+        if let Some(elems) = &self.elems
+            && self.func_count() > 0
+        {
+            section(&mut bytes, 9, |out| {
+                out.extend([0x01, 0x01, 0x00]);
+                leb128(out, elems.len() as u64);
+                for func in elems {
+                    leb128(out, func.index(self.func_count()) as u64);
+                }
+            });
+        }
+
+        if !self.datas.is_empty() {
+            section(&mut bytes, 12, |out| leb128(out, self.datas.len() as u64));
+        }
+
+        if !self.funcs.is_empty() {
+            section(&mut bytes, 10, |out| {
+                leb128(out, self.funcs.len() as u64);
+                for (idx, func) in self.funcs.iter().enumerate() {
+                    let mut body = Vec::new();
+                    self.encode_body(self.imported_funcs.len() + idx, func, &mut body);
+                    leb128(out, body.len() as u64);
+                    out.extend(body);
+                }
+            });
+        }
+
+        if !self.datas.is_empty() {
+            section(&mut bytes, 11, |out| {
+                leb128(out, self.datas.len() as u64);
+                for (offset, init) in &self.datas {
+                    match offset {
+                        Some(offset) => {
+                            out.push(0x00);
+                            Const {
+                                ty: 0,
+                                bits: u64::from(*offset),
+                            }
+                            .encode(out);
+                            out.push(0x0b);
+                        }
+                        None => out.push(0x01),
+                    }
+                    leb128(out, init.len() as u64);
+                    out.extend(init);
+                }
+            });
+        }
+
+        bytes
+    }
+
+    fn encode_body(&self, funcidx: usize, func: &FuncSpec, out: &mut Vec<u8>) {
+        let (params, result) = &self.types[self.func_type(funcidx)];
+        leb128(out, func.locals.len() as u64);
+        for &ty in &func.locals {
+            out.extend([0x01, VALTYPES[ty]]);
+        }
+        let locals: Vec<_> = params.iter().chain(&func.locals).copied().collect();
+        for stmt in &func.body {
+            self.encode_stmt(stmt, &locals, out);
+        }
+        if let Some(ty) = result {
+            Const::of(*ty).encode(out);
+        }
+        out.push(0x0b);
+    }
+
+    fn encode_stmt(&self, stmt: &Stmt, locals: &[usize], out: &mut Vec<u8>) {
+        match stmt {
+            Stmt::Const(value) => {
+                value.encode(out);
+                out.push(0x1a);
+            }
+            Stmt::LocalGet(idx) if !locals.is_empty() => {
+                out.push(0x20);
+                leb128(out, idx.index(locals.len()) as u64);
+                out.push(0x1a);
+            }
+            Stmt::GlobalGet(idx) if self.global_count() > 0 => {
+                out.push(0x23);
+                leb128(out, idx.index(self.global_count()) as u64);
+                out.push(0x1a);
+            }
+            Stmt::GlobalSet(idx, bits) if !self.globals.is_empty() => {
+                let idx = self.imported_globals.len() + idx.index(self.globals.len());
+                Const {
+                    ty: self.global_type(idx),
+                    bits: *bits,
+                }
+                .encode(out);
+                out.push(0x24);
+                leb128(out, idx as u64);
+            }
+            Stmt::Load { align, offset } if self.memory.is_some() => {
+                out.extend([0x41, 0x00, 0x28]);
+                leb128(out, u64::from(*align));
+                leb128(out, u64::from(*offset));
+                out.push(0x1a);
+            }
+            Stmt::Call(idx) if self.func_count() > 0 => {
+                let idx = idx.index(self.func_count());
+                let (params, result) = &self.types[self.func_type(idx)];
+                for &ty in params {
+                    Const::of(ty).encode(out);
+                }
+                out.push(0x10);
+                leb128(out, idx as u64);
+                if result.is_some() {
+                    out.push(0x1a);
+                }
+            }
+            Stmt::Block { r#loop, body } => {
+                out.extend([if *r#loop { 0x03 } else { 0x02 }, 0x40]);
+                for stmt in body {
+                    self.encode_stmt(stmt, locals, out);
+                }
+                out.push(0x0b);
+            }
+            // A nop, or a statement referring to entities the module lacks.
+            _ => out.push(0x01),
+        }
+    }
+}
+
+fn limits(out: &mut Vec<u8>, min: u32, max: Option<u32>) {
+    out.push(u8::from(max.is_some()));
+    leb128(out, u64::from(min));
+    if let Some(max) = max {
+        leb128(out, u64::from(max));
+    }
+}
+
+#[cfg(all(test, feature = "decode", feature = "validate"))]
+mod tests {
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::{DecodeOptions, NoCustomSectionVisitor, PushDecoder};
+    use crate::types::{
+        BlockType, ConstExpr, DataMode, ElementInit, ElementMode, ExportDescriptor, Expression,
+        GlobalTypeMutability, ImportDescriptor, Local, Operands, Operator, ValType,
+    };
+
+    fn valtypes(types: &[ValType]) -> Vec<usize> {
+        types
+            .iter()
+            .map(|&ty| VALTYPES.iter().position(|&b| b == ty as u8).unwrap())
+            .collect()
+    }
+
+    // Encodes a decoded module as `ModuleSpec::encode()` would have, for the
+    // subset of the format that it generates.
+    fn reencode(module: &Module<Global>) -> Vec<u8> {
+        let mut bytes = Vec::from(*b"\0asm\x01\0\0\0");
+
+        section(&mut bytes, 1, |out| {
+            leb128(out, module.typesec.len() as u64);
+            for ty in module.typesec.iter() {
+                out.push(0x60);
+                leb128(out, ty.parameters.len() as u64);
+                out.extend(ty.parameters.iter().map(|&ty| ty as u8));
+                leb128(out, ty.results.len() as u64);
+                out.extend(ty.results.iter().map(|&ty| ty as u8));
+            }
+        });
+
+        if !module.importsec.is_empty() {
+            section(&mut bytes, 2, |out| {
+                leb128(out, module.importsec.len() as u64);
+                for import in module.imports_in_binary_order() {
+                    name(out, &import.module);
+                    name(out, &import.field);
+                    match import.descriptor {
+                        ImportDescriptor::Function(idx) => {
+                            out.push(0x00);
+                            leb128(out, u64::from(*idx));
+                        }
+                        ImportDescriptor::Global(ty) => {
+                            out.extend([0x03, ty.value as u8, ty.mutability as u8]);
+                        }
+                        _ => panic!("unexpected import: {import:?}"),
+                    }
+                }
+            });
+        }
+
+        if !module.funcsec.is_empty() {
+            section(&mut bytes, 3, |out| {
+                leb128(out, module.funcsec.len() as u64);
+                for idx in module.funcsec.iter() {
+                    leb128(out, u64::from(**idx));
+                }
+            });
+        }
+
+        if !module.memsec.is_empty() {
+            section(&mut bytes, 5, |out| {
+                leb128(out, module.memsec.len() as u64);
+                for ty in module.memsec.iter() {
+                    limits(out, ty.min, ty.max);
+                }
+            });
+        }
+
+        if !module.globalsec.is_empty() {
+            section(&mut bytes, 6, |out| {
+                leb128(out, module.globalsec.len() as u64);
+                for global in module.globalsec.iter() {
+                    out.extend([global.ty.value as u8, global.ty.mutability as u8]);
+                    expression(out, &global.init);
+                }
+            });
+        }
+
+        if !module.exportsec.is_empty() {
+            section(&mut bytes, 7, |out| {
+                leb128(out, module.exportsec.len() as u64);
+                for export in module.exportsec.iter() {
+                    name(out, &export.field);
+                    let (kind, idx) = match export.descriptor {
+                        ExportDescriptor::Function(idx) => (0x00, *idx),
+                        ExportDescriptor::Memory(idx) => (0x02, *idx),
+                        _ => panic!("unexpected export: {export:?}"),
+                    };
+                    out.push(kind);
+                    leb128(out, u64::from(idx));
+                }
+            });
+        }
+
+        if !module.elemsec.is_empty() {
+            section(&mut bytes, 9, |out| {
+                leb128(out, module.elemsec.len() as u64);
+                for segment in module.elemsec.iter() {
+                    let (ElementMode::Passive, ElementInit::FunctionIndices(funcs)) =
+                        (&segment.mode, &segment.init)
+                    else {
+                        panic!("unexpected element segment: {segment:?}");
+                    };
+                    out.extend([0x01, 0x00]);
+                    leb128(out, funcs.len() as u64);
+                    for idx in funcs {
+                        leb128(out, u64::from(**idx));
+                    }
+                }
+            });
+        }
+
+        if let Some(count) = module.datacountsec {
+            section(&mut bytes, 12, |out| leb128(out, u64::from(count)));
+        }
+
+        if !module.codesec.is_empty() {
+            section(&mut bytes, 10, |out| {
+                leb128(out, module.codesec.len() as u64);
+                for func in module.codesec.iter() {
+                    let mut body = Vec::new();
+                    leb128(&mut body, func.locals.len() as u64);
+                    for local in func.locals.iter() {
+                        let ty = match local {
+                            Local::I32(_) => ValType::I32,
+                            Local::I64(_) => ValType::I64,
+                            Local::F32(_) => ValType::F32,
+                            Local::F64(_) => ValType::F64,
+                            _ => panic!("unexpected local: {local:?}"),
+                        };
+                        body.extend([0x01, ty as u8]);
+                    }
+                    expression(&mut body, &func.code);
+                    leb128(out, body.len() as u64);
+                    out.extend(body);
+                }
+            });
+        }
+
+        if !module.datasec.is_empty() {
+            section(&mut bytes, 11, |out| {
+                leb128(out, module.datasec.len() as u64);
+                for segment in module.datasec.iter() {
+                    match &segment.mode {
+                        DataMode::Active(active) => {
+                            out.push(0x00);
+                            expression(out, &active.offset);
+                        }
+                        DataMode::Passive() => out.push(0x01),
+                    }
+                    leb128(out, segment.init.len() as u64);
+                    out.extend(&segment.init[..]);
+                }
+            });
+        }
+
+        bytes
+    }
+
+    // Encodes an expression, through its final `end`.
+    fn expression(out: &mut Vec<u8>, expr: &Expression<Global>) {
+        for instr in expr.instructions() {
+            let Operator::Basic(op) = instr.op else {
+                panic!("unexpected instruction: {instr:?}");
+            };
+            out.push(op as u8);
+            match instr.operands {
+                Operands::None => {}
+                Operands::BlockType(BlockType::Empty) => out.push(0x40),
+                Operands::I32(value) => sleb128(out, i64::from(value)),
+                Operands::I64(value) => sleb128(out, value),
+                Operands::F32(value) => out.extend(value.to_bits().to_le_bytes()),
+                Operands::F64(value) => out.extend(value.to_bits().to_le_bytes()),
+                Operands::Idx(idx) => leb128(out, u64::from(idx)),
+                Operands::MemArg(arg) => {
+                    leb128(out, u64::from(arg.align));
+                    leb128(out, u64::from(arg.offset));
+                }
+                operands => panic!("unexpected operands: {operands:?}"),
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn generated_modules_validate(bytes in arbitrary_module()) {
+            let module = Module::decode_bytes(&bytes, &mut NoCustomSectionVisitor {}, Global)
+                .unwrap();
+            module
+                .validate()
+                .map_err(|err| TestCaseError::fail(format!("{err:?}")))?;
+        }
+
+        // The decoded module is that which was encoded.
+        #[test]
+        fn decoding_preserves_structure(spec in module_spec()) {
+            let bytes = spec.encode();
+            let module = Module::decode_bytes(&bytes, &mut NoCustomSectionVisitor {}, Global)
+                .unwrap();
+            prop_assert_eq!(module.typesec.len(), spec.types.len());
+            for (ty, (params, result)) in module.typesec.iter().zip(&spec.types) {
+                prop_assert_eq!(valtypes(&ty.parameters), params.clone());
+                prop_assert_eq!(valtypes(&ty.results), result.iter().copied().collect::<Vec<_>>());
+            }
+
+            let imports = module.imports_by_kind();
+            prop_assert_eq!(imports.functions.len(), spec.imported_funcs.len());
+            for (import, ty) in imports.functions.iter().zip(&spec.imported_funcs) {
+                let ImportDescriptor::Function(idx) = import.descriptor else {
+                    return Err(TestCaseError::fail("not a function import"));
+                };
+                prop_assert_eq!(*idx as usize, ty.index(spec.types.len()));
+            }
+            prop_assert_eq!(imports.globals.len(), spec.imported_globals.len());
+            for (import, &ty) in imports.globals.iter().zip(&spec.imported_globals) {
+                let ImportDescriptor::Global(global) = import.descriptor else {
+                    return Err(TestCaseError::fail("not a global import"));
+                };
+                prop_assert_eq!(global.value as u8, VALTYPES[ty]);
+                prop_assert_eq!(global.mutability, GlobalTypeMutability::Const);
+            }
+
+            prop_assert_eq!(module.funcsec.len(), spec.funcs.len());
+            prop_assert_eq!(module.codesec.len(), spec.funcs.len());
+            for (idx, func) in spec.funcs.iter().enumerate() {
+                prop_assert_eq!(*module.funcsec[idx] as usize, func.ty.index(spec.types.len()));
+                let locals: Vec<_> = module.codesec[idx]
+                    .locals
+                    .iter()
+                    .map(|local| match local {
+                        Local::I32(_) => 0,
+                        Local::I64(_) => 1,
+                        Local::F32(_) => 2,
+                        _ => 3,
+                    })
+                    .collect();
+                prop_assert_eq!(locals, func.locals.clone());
+            }
+
+            let memory = module.memsec.iter().next().map(|ty| (ty.min, ty.max));
+            prop_assert_eq!(memory, spec.memory);
+
+            prop_assert_eq!(module.globalsec.len(), spec.globals.len());
+            for (global, init) in module.globalsec.iter().zip(&spec.globals) {
+                prop_assert_eq!(global.ty.value as u8, VALTYPES[init.ty]);
+                prop_assert_eq!(global.ty.mutability, GlobalTypeMutability::Var);
+                // Compare bit patterns, as NaNs are not equal to themselves.
+                let bits = match global.init.as_const() {
+                    Some(ConstExpr::I32(value)) => u64::from(value.cast_unsigned()),
+                    Some(ConstExpr::I64(value)) => value.cast_unsigned(),
+                    Some(ConstExpr::F32(value)) => u64::from(value.to_bits()),
+                    Some(ConstExpr::F64(value)) => value.to_bits(),
+                    init => return Err(TestCaseError::fail(format!("{init:?}"))),
+                };
+                // 32-bit constants are of the low bits.
+                let expected = if matches!(init.ty, 0 | 2) {
+                    u64::from(init.bits as u32)
+                } else {
+                    init.bits
+                };
+                prop_assert_eq!(bits, expected);
+            }
+
+            for export in module.exportsec.iter() {
+                match export.descriptor {
+                    ExportDescriptor::Function(idx) => {
+                        let name = format!("f{}", *idx);
+                        prop_assert_eq!(&**export.field, name.as_str());
+                        prop_assert!(spec.exported_funcs[*idx as usize]);
+                    }
+                    ExportDescriptor::Memory(_) => prop_assert!(spec.export_memory),
+                    _ => return Err(TestCaseError::fail("unexpected export")),
+                }
+            }
+
+            match (&spec.elems, module.elemsec.iter().next()) {
+                (Some(elems), Some(segment)) => {
+                    prop_assert!(matches!(segment.mode, ElementMode::Passive));
+                    let ElementInit::FunctionIndices(funcs) = &segment.init else {
+                        return Err(TestCaseError::fail("not a function index segment"));
+                    };
+                    let expected: Vec<_> =
+                        elems.iter().map(|idx| idx.index(spec.func_count())).collect();
+                    let actual: Vec<_> = funcs.iter().map(|&idx| *idx as usize).collect();
+                    prop_assert_eq!(actual, expected);
+                }
+                (_, segment) => prop_assert!(segment.is_none()),
+            }
+
+            prop_assert_eq!(module.datasec.len(), spec.datas.len());
+            for (segment, (offset, init)) in module.datasec.iter().zip(&spec.datas) {
+                prop_assert_eq!(&segment.init[..], &init[..]);
+                let actual = match &segment.mode {
+                    DataMode::Active(active) => active.offset.as_const(),
+                    DataMode::Passive() => None,
+                };
+                let expected = offset.map(|offset| ConstExpr::I32(offset.cast_signed()));
+                prop_assert_eq!(actual, expected);
+            }
+        }
+
+        // Re-encoding a decoded module reproduces its (canonical) encoding,
+        // instruction for instruction.
+        #[test]
+        fn reencoding_round_trips(bytes in arbitrary_module()) {
+            let module = Module::decode_bytes(&bytes, &mut NoCustomSectionVisitor {}, Global)
+                .unwrap();
+            prop_assert_eq!(reencode(&module), bytes);
+        }
+
+        // The ways of decoding a module agree.
+        #[test]
+        fn decodings_agree(bytes in arbitrary_module(), chunk_size in 1..64usize) {
+            let module = Module::decode_bytes(&bytes, &mut NoCustomSectionVisitor {}, Global)
+                .unwrap();
+
+            let options = DecodeOptions {
+                packed_expressions: true,
+                ..DecodeOptions::default()
+            };
+            let packed = Module::decode_bytes_with_options(
+                &bytes,
+                &mut NoCustomSectionVisitor {},
+                Global,
+                &options,
+            )
+            .unwrap();
+            prop_assert!(module.semantically_eq(&packed));
+
+            let mut visitor = NoCustomSectionVisitor {};
+            let mut decoder = PushDecoder::new(&mut visitor, Global);
+            for chunk in bytes.chunks(chunk_size) {
+                decoder.push(chunk).unwrap();
+            }
+            prop_assert!(module.semantically_eq(&decoder.finish().unwrap()));
+        }
+    }
+}
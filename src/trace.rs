@@ -0,0 +1,136 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Instrumentation of decoding and validation via the `tracing` crate, which
+//! compiles away entirely unless the `tracing` feature is enabled.
+//!
+//! Spans are emitted for the decoding and validation of each module
+//! (`decode` and `validate`), for each section decoded (`section`, with its ID,
+//! declared length, and offset), and for the decoding and validation of each
+//! function body (`function`, with its offset and size or its index within the
+//! code section, respectively), so that a subscriber can attribute time spent
+//! to each. Each decoded section is followed by a DEBUG event with its actual
+//! length and number of entries, and errors are reported by DEBUG events
+//! within the span of the section or the module in which they arise.
+
+// Enters a span of the given level (e.g., DEBUG) for the remainder of the
+// enclosing scope, taking the remaining arguments of `tracing::span!`.
+macro_rules! trace_span {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::span!(::tracing::Level::$level, $($arg)*).entered();
+    };
+}
+
+// Emits an event of the given level, taking the remaining arguments of
+// `tracing::event!`.
+#[cfg(feature = "decode")]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::event!(::tracing::Level::$level, $($arg)*);
+    };
+}
+
+// Evaluates to a given result, first emitting a DEBUG event with the given
+// message for its error, if any.
+macro_rules! trace_error {
+    ($result:expr, $message:literal) => {{
+        let result = $result;
+        #[cfg(feature = "tracing")]
+        if let Err(error) = &result {
+            ::tracing::event!(::tracing::Level::DEBUG, ?error, $message);
+        }
+        result
+    }};
+}
+
+pub(crate) use trace_error;
+#[cfg(feature = "decode")]
+pub(crate) use trace_event;
+pub(crate) use trace_span;
+
+#[cfg(all(test, feature = "tracing", feature = "decode", feature = "validate"))]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::vec::Vec;
+
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Records the names of the spans created, and the number of error events.
+    #[derive(Default)]
+    struct SpanNames(Mutex<Vec<&'static str>>, AtomicUsize);
+
+    impl Subscriber for SpanNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(span.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            if event.metadata().fields().field("error").is_some() {
+                self.1.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn spans() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+            0x0a, 0x07, 0x02, 0x02, 0x00, 0x0b, 0x02, 0x00, 0x0b, // codesec
+        ];
+        let subscriber = std::sync::Arc::new(SpanNames::default());
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            crate::decode_and_validate(bytes).unwrap();
+        });
+        let names = subscriber.0.lock().unwrap().join(" ");
+        assert_eq!(
+            names,
+            "decode section section section function function validate function function"
+        );
+        assert_eq!(subscriber.1.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn error_events() {
+        let invalid_functype = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x61, 0x00, 0x00, // typesec
+        ];
+        let unknown_type = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x03, 0x02, 0x01, 0x00, // funcsec
+        ];
+        let subscriber = std::sync::Arc::new(SpanNames::default());
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            assert!(crate::decode_and_validate(invalid_functype).is_err());
+            assert_eq!(subscriber.1.load(Ordering::Relaxed), 1);
+            assert!(crate::decode_and_validate(unknown_type).is_err());
+            assert_eq!(subscriber.1.load(Ordering::Relaxed), 2);
+        });
+    }
+}
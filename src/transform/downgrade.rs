@@ -0,0 +1,524 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Downgrading of modules for engines that predate the sign-extension and
+//! bulk-memory proposals.
+//!
+//! Sign-extension instructions are rewritten in place into pairs of shifts.
+//! `memory.fill` and `memory.copy` are rewritten into calls to helper
+//! functions appended to the module, which trap up front if out of bounds (as
+//! the instructions do) and otherwise loop over the bytes. The rest of the
+//! bulk-memory proposal (passive segments, and the instructions on them and
+//! on tables) has no equivalent short of restructuring the module, and such
+//! modules are refused.
+
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::core_compat::vec::Vec;
+use crate::decode::ExpressionBuilder;
+use crate::types::{
+    BlockType, BulkOpcode, DataIdx, DataMode, ElemIdx, ElementMode, Expression, FuncIdx, Function,
+    FunctionType, Instruction, Locals, MemArg, Opcode, Operands, Operator, ResultType, TypeIdx,
+    TypeSection, ValType,
+};
+use crate::{Allocator, Module};
+
+/// An error downgrading a module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// An allocation failed.
+    AllocError,
+    /// A function uses a bulk-memory instruction other than `memory.fill` and
+    /// `memory.copy`, which has no equivalent without the proposal.
+    UnsupportedInstruction { function: FuncIdx, op: BulkOpcode },
+    /// A data segment is passive, which only the bulk-memory proposal allows.
+    PassiveDataSegment(DataIdx),
+    /// An element segment is passive, which only the bulk-memory proposal
+    /// allows.
+    PassiveElementSegment(ElemIdx),
+}
+
+impl From<TryReserveError> for Error {
+    fn from(_: TryReserveError) -> Self {
+        Self::AllocError
+    }
+}
+
+/// Rewrites the sign-extension instructions of a module's function bodies
+/// into equivalent shifts (e.g., `i32.extend8_s` into a left shift by 24
+/// followed by an arithmetic right shift by 24).
+///
+/// The module is expected to have been validated, in which case the
+/// rewritten module is valid as well.
+pub fn lower_sign_extension<A: Allocator>(
+    mut module: Module<A>,
+) -> Result<Module<A>, TryReserveError> {
+    let alloc = module.codesec.allocator().clone();
+    for function in &mut module.codesec.0 {
+        if !function
+            .code
+            .instructions()
+            .any(|instr| sign_extension(instr.op).is_some())
+        {
+            continue;
+        }
+        function.code = rewrite(
+            &function.code,
+            &alloc,
+            |builder, instr| -> Result<_, TryReserveError> {
+                let Some((shift, i64)) = sign_extension(instr.op) else {
+                    return Ok(false);
+                };
+                let (constant, shl, shr_s) = if i64 {
+                    (
+                        (Opcode::I64Const, Operands::I64(shift)),
+                        Opcode::I64Shl,
+                        Opcode::I64ShrS,
+                    )
+                } else {
+                    (
+                        (Opcode::I32Const, Operands::I32(shift as i32)),
+                        Opcode::I32Shl,
+                        Opcode::I32ShrS,
+                    )
+                };
+                emit_all(builder, &[constant, (shl, NONE), constant, (shr_s, NONE)])?;
+                Ok(true)
+            },
+        )?;
+    }
+    Ok(module)
+}
+
+/// Rewrites the `memory.fill` and `memory.copy` instructions of a module's
+/// function bodies into calls to helper functions, which are appended to the
+/// module as needed, and drops the data count section.
+///
+/// As the helpers follow all other functions, no indices are shifted. Their
+/// type, `[i32 i32 i32] -> []`, is added to the type section if not already
+/// present.
+///
+/// The module is expected to have been validated, in which case the
+/// rewritten module is valid as well.
+pub fn lower_bulk_memory<A: Allocator>(mut module: Module<A>) -> Result<Module<A>, Error> {
+    for (idx, segment) in module.datasec.iter().enumerate() {
+        if matches!(segment.mode, DataMode::Passive()) {
+            return Err(Error::PassiveDataSegment(DataIdx::new(idx as u32)));
+        }
+    }
+    for (idx, segment) in module.elemsec.iter().enumerate() {
+        if matches!(segment.mode, ElementMode::Passive) {
+            return Err(Error::PassiveElementSegment(ElemIdx::new(idx as u32)));
+        }
+    }
+
+    let alloc = module.codesec.allocator().clone();
+    let imported = module.imports_by_kind().functions.len();
+    // The function indices of the helpers, assigned as first needed.
+    let (mut fill, mut copy) = (None, None);
+    let mut next_funcidx = (imported + module.funcsec.len()) as u32;
+    for (idx, function) in module.codesec.0.iter_mut().enumerate() {
+        if !function
+            .code
+            .instructions()
+            .any(|instr| matches!(instr.op, Operator::Bulk(op) if is_bulk_memory(op)))
+        {
+            continue;
+        }
+        let funcidx = FuncIdx::new((imported + idx) as u32);
+        function.code = rewrite(&function.code, &alloc, |builder, instr| {
+            let Operator::Bulk(op) = instr.op else {
+                return Ok(false);
+            };
+            let helper = match op {
+                BulkOpcode::MemoryFill => &mut fill,
+                BulkOpcode::MemoryCopy => &mut copy,
+                _ if is_bulk_memory(op) => {
+                    return Err(Error::UnsupportedInstruction {
+                        function: funcidx,
+                        op,
+                    });
+                }
+                _ => return Ok(false),
+            };
+            let helper = *helper.get_or_insert_with(|| {
+                next_funcidx += 1;
+                next_funcidx - 1
+            });
+            emit(builder, Opcode::Call, Operands::Idx(helper))?;
+            Ok(true)
+        })?;
+    }
+
+    // The helpers are appended in the order their indices were assigned.
+    let mut helpers: [(_, WriteBody<A>); 2] = [(fill, write_fill), (copy, write_copy)];
+    helpers.sort_by_key(|&(funcidx, _)| funcidx);
+    for (funcidx, write_body) in helpers {
+        if funcidx.is_none() {
+            continue;
+        }
+        let typeidx = helper_type(&mut module.typesec, &alloc)?;
+        let mut builder = ExpressionBuilder::new(alloc.clone(), false);
+        write_body(&mut builder)?;
+        module.funcsec.0.try_reserve(1)?;
+        module.funcsec.0.push(typeidx);
+        module.codesec.0.try_reserve(1)?;
+        module.codesec.0.push(Function {
+            locals: Locals::new(Vec::new_in(alloc.clone())),
+            code: builder.finalize(),
+        });
+    }
+    module.datacountsec = None;
+    Ok(module)
+}
+
+/// Downgrades a module for engines that support neither the sign-extension
+/// nor the bulk-memory proposal, as by [`lower_sign_extension`] and then
+/// [`lower_bulk_memory`].
+pub fn downgrade<A: Allocator>(module: Module<A>) -> Result<Module<A>, Error> {
+    lower_bulk_memory(lower_sign_extension(module)?)
+}
+
+// Writes the body of a helper.
+type WriteBody<A> = fn(&mut ExpressionBuilder<A>) -> Result<(), TryReserveError>;
+
+// The shift by which a sign-extension instruction can be replaced, and whether
+// it operates on i64.
+fn sign_extension(op: Operator) -> Option<(i64, bool)> {
+    let lowered = match op {
+        Operator::Basic(Opcode::I32Extend8S) => (24, false),
+        Operator::Basic(Opcode::I32Extend16S) => (16, false),
+        Operator::Basic(Opcode::I64Extend8S) => (56, true),
+        Operator::Basic(Opcode::I64Extend16S) => (48, true),
+        Operator::Basic(Opcode::I64Extend32S) => (32, true),
+        _ => return None,
+    };
+    Some(lowered)
+}
+
+// Whether an instruction belongs to the bulk-memory proposal (as opposed to
+// the other proposals that share the prefix).
+fn is_bulk_memory(op: BulkOpcode) -> bool {
+    matches!(
+        op,
+        BulkOpcode::MemoryInit
+            | BulkOpcode::DataDrop
+            | BulkOpcode::MemoryCopy
+            | BulkOpcode::MemoryFill
+            | BulkOpcode::TableInit
+            | BulkOpcode::ElemDrop
+            | BulkOpcode::TableCopy
+    )
+}
+
+// Returns the index of the type of the helpers, adding it if need be.
+fn helper_type<A: Allocator>(
+    typesec: &mut TypeSection<A>,
+    alloc: &A,
+) -> Result<TypeIdx, TryReserveError> {
+    const PARAMETERS: [ValType; 3] = [ValType::I32; 3];
+    let idx = typesec
+        .iter()
+        .position(|ty| ty.parameters[..] == PARAMETERS && ty.results.is_empty());
+    if let Some(idx) = idx {
+        return Ok(TypeIdx::new(idx as u32));
+    }
+
+    let mut parameters = Vec::new_in(alloc.clone());
+    parameters.try_reserve_exact(PARAMETERS.len())?;
+    parameters.extend_from_slice(&PARAMETERS);
+    typesec.0.try_reserve(1)?;
+    typesec.0.push(FunctionType {
+        parameters,
+        results: ResultType::new(Vec::new_in(alloc.clone())),
+    });
+    Ok(TypeIdx::new((typesec.len() - 1) as u32))
+}
+
+// Re-encodes an expression, with each instruction for which `lower` writes a
+// replacement (returning true) replaced, and each other written as is.
+fn rewrite<A, E, F>(expr: &Expression<A>, alloc: &A, mut lower: F) -> Result<Expression<A>, E>
+where
+    A: Allocator,
+    E: From<TryReserveError>,
+    F: FnMut(&mut ExpressionBuilder<A>, &Instruction<'_>) -> Result<bool, E>,
+{
+    let mut builder = ExpressionBuilder::new(alloc.clone(), expr.is_packed());
+    for instr in expr.instructions() {
+        if !lower(&mut builder, &instr)? {
+            builder.write_instruction(&instr)?;
+        }
+    }
+    Ok(builder.finalize())
+}
+
+fn emit<A: Allocator>(
+    builder: &mut ExpressionBuilder<A>,
+    op: Opcode,
+    operands: Operands<'_>,
+) -> Result<(), TryReserveError> {
+    builder.write_instruction(&Instruction {
+        offset: 0,
+        op: Operator::Basic(op),
+        operands,
+    })
+}
+
+fn emit_all<A: Allocator>(
+    builder: &mut ExpressionBuilder<A>,
+    instrs: &[(Opcode, Operands<'_>)],
+) -> Result<(), TryReserveError> {
+    for &(op, operands) in instrs {
+        emit(builder, op, operands)?;
+    }
+    Ok(())
+}
+
+// The helpers take their operands in the order of the instructions: the
+// destination address, the value or source address, and the length.
+const DST: Operands<'static> = Operands::Idx(0);
+const SRC: Operands<'static> = Operands::Idx(1);
+const LEN: Operands<'static> = Operands::Idx(2);
+const BYTE: Operands<'static> = Operands::MemArg(MemArg {
+    offset: 0,
+    align: 0,
+});
+const EMPTY: Operands<'static> = Operands::BlockType(BlockType::Empty);
+const NONE: Operands<'static> = Operands::None;
+
+// Writes a check that the `len` bytes at the address in the given local are
+// within memory, trapping otherwise. The sum is taken in i64 to not overflow.
+fn write_bounds_check<A: Allocator>(
+    builder: &mut ExpressionBuilder<A>,
+    addr: Operands<'_>,
+) -> Result<(), TryReserveError> {
+    use Opcode::*;
+    emit_all(
+        builder,
+        &[
+            (LocalGet, addr),
+            (I64ExtendI32U, NONE),
+            (LocalGet, LEN),
+            (I64ExtendI32U, NONE),
+            (I64Add, NONE),
+            (MemorySize, NONE),
+            (I64ExtendI32U, NONE),
+            (I64Const, Operands::I64(16)),
+            (I64Shl, NONE),
+            (I64GtU, NONE),
+            (If, EMPTY),
+            (Unreachable, NONE),
+            (End, NONE),
+        ],
+    )
+}
+
+// Writes the body of the `memory.fill` helper, which stores the value byte by
+// byte upward from the destination.
+fn write_fill<A: Allocator>(builder: &mut ExpressionBuilder<A>) -> Result<(), TryReserveError> {
+    use Opcode::*;
+    write_bounds_check(builder, DST)?;
+    emit_all(
+        builder,
+        &[
+            (Block, EMPTY),
+            (Loop, EMPTY),
+            (LocalGet, LEN),
+            (I32Eqz, NONE),
+            (BrIf, Operands::Idx(1)),
+            (LocalGet, DST),
+            (LocalGet, SRC),
+            (I32Store8, BYTE),
+            (LocalGet, DST),
+            (I32Const, Operands::I32(1)),
+            (I32Add, NONE),
+            (LocalSet, DST),
+            (LocalGet, LEN),
+            (I32Const, Operands::I32(1)),
+            (I32Sub, NONE),
+            (LocalSet, LEN),
+            (Br, Operands::Idx(0)),
+            (End, NONE),
+            (End, NONE),
+            (End, NONE),
+        ],
+    )
+}
+
+// Writes the body of the `memory.copy` helper, which copies byte by byte
+// downward if the destination is above the source and upward otherwise, so
+// that overlapping ranges are copied correctly.
+fn write_copy<A: Allocator>(builder: &mut ExpressionBuilder<A>) -> Result<(), TryReserveError> {
+    use Opcode::*;
+    write_bounds_check(builder, DST)?;
+    write_bounds_check(builder, SRC)?;
+    emit_all(
+        builder,
+        &[
+            (LocalGet, DST),
+            (LocalGet, SRC),
+            (I32GtU, NONE),
+            (If, EMPTY),
+            (Loop, EMPTY),
+            (LocalGet, LEN),
+            (I32Eqz, NONE),
+            (BrIf, Operands::Idx(1)),
+            (LocalGet, LEN),
+            (I32Const, Operands::I32(1)),
+            (I32Sub, NONE),
+            (LocalSet, LEN),
+            (LocalGet, DST),
+            (LocalGet, LEN),
+            (I32Add, NONE),
+            (LocalGet, SRC),
+            (LocalGet, LEN),
+            (I32Add, NONE),
+            (I32Load8U, BYTE),
+            (I32Store8, BYTE),
+            (Br, Operands::Idx(0)),
+            (End, NONE),
+            (Else, NONE),
+            (Loop, EMPTY),
+            (LocalGet, LEN),
+            (I32Eqz, NONE),
+            (BrIf, Operands::Idx(1)),
+            (LocalGet, DST),
+            (LocalGet, SRC),
+            (I32Load8U, BYTE),
+            (I32Store8, BYTE),
+            (LocalGet, DST),
+            (I32Const, Operands::I32(1)),
+            (I32Add, NONE),
+            (LocalSet, DST),
+            (LocalGet, SRC),
+            (I32Const, Operands::I32(1)),
+            (I32Add, NONE),
+            (LocalSet, SRC),
+            (LocalGet, LEN),
+            (I32Const, Operands::I32(1)),
+            (I32Sub, NONE),
+            (LocalSet, LEN),
+            (Br, Operands::Idx(0)),
+            (End, NONE),
+            (End, NONE),
+            (End, NONE),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    fn decode(bytes: &[u8]) -> Module<Global> {
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap()
+    }
+
+    #[test]
+    fn downgrading() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x0c, 0x01, 0x00, // datacountsec
+            0x0a, 0x21, 0x01, 0x1f, 0x00, // codesec
+            0x41, 0x00, 0x41, 0x80, 0x01, 0x41, 0x04, // i32.const 0, 128, 4
+            0xfc, 0x0b, 0x00, // memory.fill
+            0x41, 0x04, 0x41, 0x00, 0x41, 0x04, // i32.const 4, 0, 4
+            0xfc, 0x0a, 0x00, 0x00, // memory.copy
+            0x41, 0x80, 0x01, 0xc0, 0x1a, // (drop (i32.extend8_s (i32.const 128)))
+            0x42, 0x00, 0xc4, 0x1a, // (drop (i64.extend32_s (i64.const 0)))
+            0x0b, // end
+        ];
+        let module = downgrade(decode(&bytes)).unwrap();
+
+        assert!(module.datacountsec.is_none());
+        assert_eq!(module.typesec.len(), 2);
+        assert_eq!(module.funcsec.len(), 3);
+        for function in module.codesec.iter() {
+            assert!(function.code.instructions().all(|instr| {
+                sign_extension(instr.op).is_none()
+                    && !matches!(instr.op, Operator::Bulk(op) if is_bulk_memory(op))
+            }));
+        }
+
+        let instrs: std::vec::Vec<_> = module.codesec[0]
+            .code
+            .instructions()
+            .map(|instr| match (instr.op, instr.operands) {
+                (Operator::Basic(op), Operands::I32(value)) => (op, i64::from(value)),
+                (Operator::Basic(op), Operands::I64(value)) => (op, value),
+                (Operator::Basic(op), Operands::Idx(idx)) => (op, i64::from(idx)),
+                (Operator::Basic(op), _) => (op, -1),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            instrs,
+            [
+                (Opcode::I32Const, 0),
+                (Opcode::I32Const, 128),
+                (Opcode::I32Const, 4),
+                (Opcode::Call, 1),
+                (Opcode::I32Const, 4),
+                (Opcode::I32Const, 0),
+                (Opcode::I32Const, 4),
+                (Opcode::Call, 2),
+                (Opcode::I32Const, 128),
+                (Opcode::I32Const, 24),
+                (Opcode::I32Shl, -1),
+                (Opcode::I32Const, 24),
+                (Opcode::I32ShrS, -1),
+                (Opcode::Drop, -1),
+                (Opcode::I64Const, 0),
+                (Opcode::I64Const, 32),
+                (Opcode::I64Shl, -1),
+                (Opcode::I64Const, 32),
+                (Opcode::I64ShrS, -1),
+                (Opcode::Drop, -1),
+                (Opcode::End, -1),
+            ]
+        );
+
+        #[cfg(feature = "validate")]
+        module.validate().unwrap();
+    }
+
+    #[test]
+    fn unsupported() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x0b, 0x03, 0x01, 0x01, 0x00, // datasec: a passive segment
+        ];
+        assert_eq!(
+            lower_bulk_memory(decode(&bytes)).err(),
+            Some(Error::PassiveDataSegment(DataIdx::new(0)))
+        );
+
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x09, 0x05, 0x01, 0x03, 0x00, 0x01, 0x00, // elemsec: (elem declare func 0)
+            0x0a, 0x07, 0x01, 0x05, 0x00, // codesec
+            0xfc, 0x0d, 0x00, // elem.drop 0
+            0x0b, // end
+        ];
+        assert_eq!(
+            lower_bulk_memory(decode(&bytes)).err(),
+            Some(Error::UnsupportedInstruction {
+                function: FuncIdx::new(0),
+                op: BulkOpcode::ElemDrop,
+            })
+        );
+    }
+}
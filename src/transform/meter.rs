@@ -0,0 +1,338 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Gas metering of function bodies.
+//!
+//! Each function body is divided into metered blocks: the runs of
+//! instructions between structured control instructions and branches, each of
+//! which executes in full once entered (unless it traps). At the start of each
+//! block, an imported gas function of type `[i64] -> []` is called with the
+//! total cost of the block's instructions, with which an embedder can charge
+//! for and bound execution.
+
+use super::name;
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::core_compat::vec::Vec;
+use crate::decode::ExpressionBuilder;
+use crate::types::{
+    ElementInit, ExportDescriptor, Expression, FuncIdx, FunctionType, Import, ImportDescriptor,
+    IndexRemapper, Instruction, Opcode, Operands, Operator, ResultType, StartSection, TypeIdx,
+    TypeSection, ValType,
+};
+use crate::{Allocator, Module};
+
+/// The imported function called with the cost of each metered block.
+#[derive(Clone, Copy, Debug)]
+pub struct GasFunction<'a> {
+    /// Name of the module to import from.
+    pub module: &'a str,
+    /// Name of the imported function.
+    pub field: &'a str,
+}
+
+/// Instruments the function bodies of a module to call the given gas function
+/// at the start of each metered block, with the cost of each instruction given
+/// by `cost`.
+///
+/// The gas function is imported after the existing function imports, so the
+/// indices of the defined functions are shifted up by one throughout the
+/// module. Its type is added to the type section if not already present.
+///
+/// The module is expected to have been validated, in which case the
+/// instrumented module is valid as well.
+pub fn meter<A: Allocator>(
+    mut module: Module<A>,
+    gas: GasFunction<'_>,
+    cost: impl Fn(&Instruction<'_>) -> u64,
+) -> Result<Module<A>, TryReserveError> {
+    let alloc = module.codesec.allocator().clone();
+    let typeidx = gas_function_type(&mut module.typesec, &alloc)?;
+    let gas_funcidx = import_gas_function(&mut module, gas, typeidx, &alloc)?;
+    let shift = Shift(gas_funcidx);
+
+    for export in &mut module.exportsec.0 {
+        if let ExportDescriptor::Function(funcidx) = &mut export.descriptor {
+            *funcidx = shift.remap_func(*funcidx);
+        }
+    }
+    if let Some(startsec) = &mut module.startsec {
+        *startsec = StartSection::new(shift.remap_func(**startsec));
+    }
+    for global in &mut module.globalsec.0 {
+        global.init.remap_indices(&shift);
+    }
+    for segment in &mut module.elemsec.0 {
+        match &mut segment.init {
+            ElementInit::FunctionIndices(funcs) => {
+                for funcidx in funcs.iter_mut() {
+                    *funcidx = shift.remap_func(*funcidx);
+                }
+            }
+            ElementInit::Expressions(exprs) => {
+                for expr in exprs.iter_mut() {
+                    expr.remap_indices(&shift);
+                }
+            }
+        }
+    }
+    for function in &mut module.codesec.0 {
+        function.code = rewrite(&function.code, gas_funcidx, &cost, &alloc)?;
+    }
+    Ok(module)
+}
+
+// Shifts up by one the indices of the functions at and after the gas function.
+struct Shift(u32);
+
+impl IndexRemapper for Shift {
+    fn remap_func(&self, idx: FuncIdx) -> FuncIdx {
+        if *idx >= self.0 {
+            FuncIdx::new(*idx + 1)
+        } else {
+            idx
+        }
+    }
+}
+
+// Returns the index of the type of the gas function, adding it if need be.
+fn gas_function_type<A: Allocator>(
+    typesec: &mut TypeSection<A>,
+    alloc: &A,
+) -> Result<TypeIdx, TryReserveError> {
+    let idx = typesec
+        .iter()
+        .position(|ty| ty.parameters[..] == [ValType::I64] && ty.results.is_empty());
+    if let Some(idx) = idx {
+        return Ok(TypeIdx::new(idx as u32));
+    }
+
+    let mut parameters = Vec::new_in(alloc.clone());
+    parameters.try_reserve_exact(1)?;
+    parameters.push(ValType::I64);
+    typesec.0.try_reserve(1)?;
+    typesec.0.push(FunctionType {
+        parameters,
+        results: ResultType::new(Vec::new_in(alloc.clone())),
+    });
+    Ok(TypeIdx::new((typesec.len() - 1) as u32))
+}
+
+// Imports the gas function, returning its function index.
+fn import_gas_function<A: Allocator>(
+    module: &mut Module<A>,
+    gas: GasFunction<'_>,
+    typeidx: TypeIdx,
+    alloc: &A,
+) -> Result<u32, TryReserveError> {
+    let import = Import {
+        module: name(gas.module, alloc)?,
+        field: name(gas.field, alloc)?,
+        descriptor: ImportDescriptor::Function(typeidx),
+    };
+
+    // Imports are grouped by kind, with functions first, so the gas function
+    // goes at the end of those. It is last in binary order, which must then
+    // be recorded unless it is last in the section too.
+    let imports = &mut module.importsec.0;
+    let pos = imports
+        .iter()
+        .take_while(|import| matches!(import.descriptor, ImportDescriptor::Function(_)))
+        .count();
+    let order = &mut module.import_order;
+    // An order that no longer matches the imports is disregarded, as by
+    // Module::import_order().
+    if order.len() != imports.len() {
+        order.clear();
+    }
+    if !order.is_empty() || pos < imports.len() {
+        order.try_reserve_exact(imports.len() + 1 - order.len())?;
+        if order.is_empty() {
+            order.extend(0..imports.len() as u32);
+        }
+        for idx in order.iter_mut() {
+            if *idx as usize >= pos {
+                *idx += 1;
+            }
+        }
+        order.push(pos as u32);
+    }
+    imports.try_reserve(1)?;
+    imports.insert(pos, import);
+    Ok(pos as u32)
+}
+
+// Whether an instruction ends a metered block: that is, whether it is a
+// structured control instruction or a branch.
+fn ends_block(op: Operator) -> bool {
+    matches!(
+        op,
+        Operator::Basic(
+            Opcode::Unreachable
+                | Opcode::Block
+                | Opcode::Loop
+                | Opcode::If
+                | Opcode::Else
+                | Opcode::End
+                | Opcode::Br
+                | Opcode::BrIf
+                | Opcode::BrTable
+                | Opcode::Return
+        )
+    )
+}
+
+// Re-encodes a function body with references to the functions at and after
+// the gas function shifted up by one, and with each metered block charged for.
+fn rewrite<A: Allocator>(
+    expr: &Expression<A>,
+    gas_funcidx: u32,
+    cost: &dyn Fn(&Instruction<'_>) -> u64,
+    alloc: &A,
+) -> Result<Expression<A>, TryReserveError> {
+    let mut builder = ExpressionBuilder::new(alloc.clone(), expr.is_packed());
+    let mut instrs = expr.instructions();
+    let mut block_start = true;
+    while let Some(mut instr) = instrs.next() {
+        if block_start {
+            let mut block_cost = cost(&instr);
+            if !ends_block(instr.op) {
+                for instr in instrs.clone() {
+                    block_cost = block_cost.saturating_add(cost(&instr));
+                    if ends_block(instr.op) {
+                        break;
+                    }
+                }
+            }
+            if block_cost > 0 {
+                charge(&mut builder, gas_funcidx, block_cost)?;
+            }
+        }
+
+        if let (Operator::Basic(Opcode::Call | Opcode::RefFunc), Operands::Idx(funcidx)) =
+            (instr.op, &mut instr.operands)
+            && *funcidx >= gas_funcidx
+        {
+            *funcidx += 1;
+        }
+        builder.write_instruction(&instr)?;
+        block_start = ends_block(instr.op);
+    }
+    Ok(builder.finalize())
+}
+
+// Writes `i64.const cost; call gas_funcidx`.
+fn charge<A: Allocator>(
+    builder: &mut ExpressionBuilder<A>,
+    gas_funcidx: u32,
+    cost: u64,
+) -> Result<(), TryReserveError> {
+    builder.write_instruction(&Instruction {
+        offset: 0,
+        op: Operator::Basic(Opcode::I64Const),
+        operands: Operands::I64(i64::try_from(cost).unwrap_or(i64::MAX)),
+    })?;
+    builder.write_instruction(&Instruction {
+        offset: 0,
+        op: Operator::Basic(Opcode::Call),
+        operands: Operands::Idx(gas_funcidx),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::{DecodeOptions, NoCustomSectionVisitor};
+
+    #[test]
+    fn metering() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x06, 0x01, 0x60, 0x01, 0x7f, 0x01, 0x7f, // typesec
+            0x02, 0x12, 0x02, // importsec
+            0x03, b'e', b'n', b'v', 0x01, b'g', 0x03, 0x7f, 0x00, // (global i32)
+            0x03, b'e', b'n', b'v', 0x01, b'f', 0x00, 0x00, // (func (type 0))
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x07, 0x05, 0x01, 0x01, b'g', 0x00, 0x01, // exportsec
+            0x0a, 0x14, 0x01, 0x12, 0x00, // codesec
+            0x03, 0x40, // loop
+            0x20, 0x00, 0x41, 0x01, 0x6b, // (i32.sub (local.get 0) (i32.const 1))
+            0x22, 0x00, 0x0d, 0x00, // local.tee 0; br_if 0
+            0x0b, // end
+            0x20, 0x00, 0x10, 0x01, // (call 1 (local.get 0))
+            0x0b, // end
+        ];
+        let gas = GasFunction {
+            module: "env",
+            field: "gas",
+        };
+
+        for packed_expressions in [false, true] {
+            let options = DecodeOptions {
+                packed_expressions,
+                ..DecodeOptions::default()
+            };
+            let module = Module::decode_bytes_with_options(
+                bytes,
+                &mut NoCustomSectionVisitor {},
+                Global,
+                &options,
+            )
+            .unwrap();
+            let module = meter(module, gas, |_| 1).unwrap();
+
+            assert_eq!(module.typesec.len(), 2);
+            let names: std::vec::Vec<_> = module
+                .imports_in_binary_order()
+                .map(|import| &**import.field)
+                .collect();
+            assert_eq!(names, ["g", "f", "gas"]);
+            assert!(matches!(
+                module.exportsec[0].descriptor,
+                ExportDescriptor::Function(idx) if *idx == 2
+            ));
+
+            let code = &module.codesec[0].code;
+            assert_eq!(code.is_packed(), packed_expressions);
+            let instrs: std::vec::Vec<_> = code
+                .instructions()
+                .map(|instr| match (instr.op, instr.operands) {
+                    (Operator::Basic(op), Operands::I64(value)) => (op, value),
+                    (Operator::Basic(op), Operands::Idx(idx)) => (op, i64::from(idx)),
+                    (Operator::Basic(op), _) => (op, -1),
+                    _ => unreachable!(),
+                })
+                .collect();
+            assert_eq!(
+                instrs,
+                [
+                    (Opcode::I64Const, 1),
+                    (Opcode::Call, 1),
+                    (Opcode::Loop, -1),
+                    (Opcode::I64Const, 5),
+                    (Opcode::Call, 1),
+                    (Opcode::LocalGet, 0),
+                    (Opcode::I32Const, -1),
+                    (Opcode::I32Sub, -1),
+                    (Opcode::LocalTee, 0),
+                    (Opcode::BrIf, 0),
+                    (Opcode::I64Const, 1),
+                    (Opcode::Call, 1),
+                    (Opcode::End, -1),
+                    (Opcode::I64Const, 3),
+                    (Opcode::Call, 1),
+                    (Opcode::LocalGet, 0),
+                    (Opcode::Call, 2),
+                    (Opcode::End, -1),
+                ]
+            );
+
+            #[cfg(feature = "validate")]
+            module.validate().unwrap();
+        }
+    }
+}
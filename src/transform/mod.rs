@@ -0,0 +1,32 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Transformations of decoded modules.
+//!
+//! Expressions are rewritten directly in the crate's re-encoding (see
+//! [`Expression`](crate::types::Expression)), rather than by a round trip
+//! through the binary format.
+
+pub mod downgrade;
+pub mod meter;
+mod rename;
+
+pub use rename::{ImportName, rename_imports};
+
+use crate::Allocator;
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::core_compat::vec::Vec;
+use crate::decode::into_boxed_str;
+use crate::types::Name;
+
+// Allocates a name.
+fn name<A: Allocator>(value: &str, alloc: &A) -> Result<Name<A>, TryReserveError> {
+    let mut bytes = Vec::new_in(alloc.clone());
+    bytes.try_reserve_exact(value.len())?;
+    bytes.extend_from_slice(value.as_bytes());
+    // Safety: The bytes are those of a str.
+    Ok(Name::new(unsafe { into_boxed_str(bytes, alloc) }))
+}
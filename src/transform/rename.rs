@@ -0,0 +1,92 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Renaming of imports.
+
+use super::name;
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::types::Import;
+use crate::{Allocator, Module};
+
+/// A new name for an import, with each part left as is if None.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImportName<'a> {
+    /// Name of the module to import from instead.
+    pub module: Option<&'a str>,
+    /// Name of the imported entity instead.
+    pub field: Option<&'a str>,
+}
+
+/// Renames the imports of a module in place, as given by `rename` for each
+/// import (or None to leave it be), returning the number renamed.
+///
+/// This can retarget imports to a different module, as when shimming one
+/// host interface with another. Only names change, so index spaces and the
+/// order of imports are preserved, though renaming may of course leave
+/// imports unsatisfied by a given host.
+pub fn rename_imports<'a, A: Allocator>(
+    module: &mut Module<A>,
+    mut rename: impl FnMut(&Import<A>) -> Option<ImportName<'a>>,
+) -> Result<usize, TryReserveError> {
+    let alloc = module.importsec.allocator().clone();
+    let mut renamed = 0;
+    for import in &mut module.importsec.0 {
+        let Some(new) = rename(import) else {
+            continue;
+        };
+        if let Some(module) = new.module {
+            import.module = name(module, &alloc)?;
+        }
+        if let Some(field) = new.field {
+            import.field = name(field, &alloc)?;
+        }
+        renamed += 1;
+    }
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    #[test]
+    fn renaming() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x1c, 0x02, // importsec
+            0x04, b'w', b'a', b's', b'i', // "wasi"
+            0x08, b'f', b'd', b'_', b'w', b'r', b'i', b't', b'e', // "fd_write"
+            0x00, 0x00, // (func (type 0))
+            0x03, b'e', b'n', b'v', 0x03, b'm', b'e', b'm', // "env" "mem"
+            0x02, 0x00, 0x01, // (memory 1)
+        ];
+        let mut module =
+            Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+
+        let renamed = rename_imports(&mut module, |import| {
+            (&**import.module == "wasi").then_some(ImportName {
+                module: Some("shim"),
+                field: (&**import.field == "fd_write").then_some("write"),
+            })
+        })
+        .unwrap();
+        assert_eq!(renamed, 1);
+
+        let names: std::vec::Vec<_> = module
+            .importsec
+            .iter()
+            .map(|import| (&**import.module, &**import.field))
+            .collect();
+        assert_eq!(names, [("shim", "write"), ("env", "mem")]);
+
+        #[cfg(feature = "validate")]
+        module.validate().unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Reconstruction of the structured control flow of expressions.
+
+use crate::Allocator;
+use crate::core_compat::alloc::collections::TryReserveError;
+use crate::core_compat::boxed::Box;
+use crate::core_compat::vec::Vec;
+
+use super::{Expression, Opcode, Operands, Operator};
+
+/// The kind of a [`Block`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockKind {
+    /// The expression as a whole, which is the implicit outermost block.
+    Body,
+    /// `block`.
+    Block,
+    /// `loop`.
+    Loop,
+    /// `if`, with or without an `else`.
+    If,
+}
+
+/// A structured control instruction of an expression, spanning the byte
+/// offsets of its opening instruction through its `end`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Block {
+    /// The kind of block.
+    pub kind: BlockKind,
+    /// The offset of the opening instruction (zero for the body).
+    pub start: usize,
+    /// The offset of the `else` of an `if`, if any.
+    pub else_offset: Option<usize>,
+    /// The offset of the `end`.
+    pub end: usize,
+    /// The index of the enclosing block within [`BlockTree::blocks`], or
+    /// `None` for the body.
+    pub parent: Option<usize>,
+}
+
+impl Block {
+    /// The offset of the instruction past which execution continues on a
+    /// branch to the block: the opening instruction of a loop, and the `end`
+    /// of any other block.
+    pub const fn branch_destination(&self) -> usize {
+        match self.kind {
+            BlockKind::Loop => self.start,
+            _ => self.end,
+        }
+    }
+}
+
+/// A branch from a `br`, `br_if`, or `br_table` instruction to its target
+/// block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Branch {
+    /// The offset of the branch instruction.
+    pub offset: usize,
+    /// The index of the innermost block enclosing the instruction.
+    pub source: usize,
+    /// The index of the targeted block.
+    pub target: usize,
+}
+
+/// The structured control flow of an expression (see
+/// [`Expression::blocks`]).
+#[derive(Debug)]
+pub struct BlockTree<A: Allocator> {
+    /// The blocks of the expression in order of their opening instructions,
+    /// which is a pre-order traversal of the tree; the body comes first.
+    pub blocks: Vec<Block, A>,
+    /// The branches of the expression in order of their instructions, with
+    /// one per label of a `br_table` (its default last).
+    pub branches: Vec<Branch, A>,
+}
+
+impl<A: Allocator> BlockTree<A> {
+    /// Iterates over the indices of the blocks immediately nested within the
+    /// block of a given index.
+    pub fn children(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
+        (idx + 1..self.blocks.len()).filter(move |&i| self.blocks[i].parent == Some(idx))
+    }
+
+    /// Iterates over the branches targeting the block of a given index.
+    pub fn branches_to(&self, idx: usize) -> impl Iterator<Item = &Branch> + '_ {
+        self.branches
+            .iter()
+            .filter(move |branch| branch.target == idx)
+    }
+}
+
+impl<A: Allocator> Expression<A> {
+    /// Reconstructs the structured control flow of the expression: its nested
+    /// blocks, and the blocks targeted by its branches.
+    ///
+    /// Byte offsets are those of the crate's re-encoding, as with
+    /// [`Instruction::offset`](super::Instruction::offset). The expression is
+    /// expected to have been validated; otherwise, branches to labels beyond
+    /// the enclosing blocks are omitted, as are the handler labels of
+    /// experimental instructions regardless.
+    pub fn blocks(&self) -> Result<BlockTree<A>, TryReserveError> {
+        let alloc = Box::allocator(&self.bytes);
+        let mut blocks = Vec::new_in(alloc.clone());
+        let mut branches = Vec::new_in(alloc.clone());
+        // The indices of the open blocks, innermost last.
+        let mut open = Vec::new_in(alloc.clone());
+
+        blocks.try_reserve(1)?;
+        blocks.push(Block {
+            kind: BlockKind::Body,
+            start: 0,
+            else_offset: None,
+            end: self.len(),
+            parent: None,
+        });
+        open.try_reserve(1)?;
+        open.push(0);
+
+        for instr in self.instructions() {
+            let Some(&current) = open.last() else {
+                break;
+            };
+            let Operator::Basic(op) = instr.op else {
+                continue;
+            };
+            let kind = match op {
+                Opcode::Block => BlockKind::Block,
+                Opcode::Loop => BlockKind::Loop,
+                Opcode::If => BlockKind::If,
+                Opcode::Else => {
+                    blocks[current].else_offset = Some(instr.offset);
+                    continue;
+                }
+                Opcode::End => {
+                    blocks[current].end = instr.offset;
+                    open.pop();
+                    continue;
+                }
+                _ => {
+                    let mut branch = |label: u32| -> Result<(), TryReserveError> {
+                        let Some(depth) = open.len().checked_sub(label as usize + 1) else {
+                            return Ok(());
+                        };
+                        branches.try_reserve(1)?;
+                        branches.push(Branch {
+                            offset: instr.offset,
+                            source: current,
+                            target: open[depth],
+                        });
+                        Ok(())
+                    };
+                    match (op, instr.operands) {
+                        (Opcode::Br | Opcode::BrIf, Operands::Idx(label)) => branch(label)?,
+                        (Opcode::BrTable, Operands::BrTable { labels, default }) => {
+                            for label in labels.iter() {
+                                branch(*label)?;
+                            }
+                            branch(*default)?;
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+            };
+            blocks.try_reserve(1)?;
+            blocks.push(Block {
+                kind,
+                start: instr.offset,
+                else_offset: None,
+                end: self.len(),
+                parent: Some(current),
+            });
+            open.try_reserve(1)?;
+            open.push(blocks.len() - 1);
+        }
+        Ok(BlockTree { blocks, branches })
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    #[test]
+    fn block_tree() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x01, 0x7f, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x1c, 0x01, 0x1a, 0x00, // codesec
+            0x02, 0x40, // block
+            0x03, 0x40, // loop
+            0x20, 0x00, 0x0d, 0x00, // (br_if 0 (local.get 0))
+            0x20, 0x00, 0x04, 0x40, // (if (local.get 0))
+            0x0c, 0x02, // br 2
+            0x05, // else
+            0x20, 0x00, 0x0e, 0x01, 0x01, 0x03, // (br_table 1 3 (local.get 0))
+            0x0b, // end (if)
+            0x0b, // end (loop)
+            0x0b, // end (block)
+            0x0b, // end
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        let code = &module.codesec[0].code;
+        let tree = code.blocks().unwrap();
+
+        let offsets: std::vec::Vec<_> = code.instructions().map(|instr| instr.offset).collect();
+        let [block, r#loop, br_if, r#if, br, r#else, br_table] =
+            [0, 1, 3, 5, 6, 7, 9].map(|i| offsets[i]);
+        let ends = &offsets[10..];
+        assert_eq!(ends.len(), 4);
+
+        let kinds: std::vec::Vec<_> = tree.blocks.iter().map(|block| block.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                BlockKind::Body,
+                BlockKind::Block,
+                BlockKind::Loop,
+                BlockKind::If
+            ]
+        );
+        assert_eq!(
+            tree.blocks[0],
+            Block {
+                kind: BlockKind::Body,
+                start: 0,
+                else_offset: None,
+                end: ends[3],
+                parent: None,
+            }
+        );
+        assert_eq!((tree.blocks[1].start, tree.blocks[1].end), (block, ends[2]));
+        assert_eq!(tree.blocks[2].branch_destination(), r#loop);
+        assert_eq!(tree.blocks[3].parent, Some(2));
+        assert_eq!(tree.blocks[3].start, r#if);
+        assert_eq!(tree.blocks[3].else_offset, Some(r#else));
+        assert_eq!(tree.blocks[3].branch_destination(), ends[0]);
+        assert!(tree.children(0).eq([1]));
+        assert!(tree.children(2).eq([3]));
+
+        let branch = |offset, source, target| Branch {
+            offset,
+            source,
+            target,
+        };
+        assert_eq!(
+            tree.branches[..],
+            [
+                branch(br_if, 2, 2),
+                branch(br, 3, 1),
+                branch(br_table, 3, 2),
+                branch(br_table, 3, 0),
+            ]
+        );
+        assert_eq!(tree.branches_to(2).count(), 2);
+    }
+}
@@ -0,0 +1,1180 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Reading back the crate-specific re-encoding of expressions. (See
+//! Expression's docstring for more detail.)
+
+use core::mem::{align_of, size_of};
+
+use crate::Allocator;
+
+use super::{
+    AtomicOpcode, BlockType, BulkOpcode, CallIndirectOperands, DataIdx, ElemIdx, Expression,
+    FuncIdx, GlobalIdx, InstructionClass, LabelIdx, MemArg, Opcode, OperandLayout, OperandType,
+    RefType, TableCopyOperands, TableIdx, TableInitOperands, TypeIdx, ValType,
+};
+#[cfg(feature = "experimental")]
+use super::{ContBindOperands, Handler, SwitchOperands, TagIdx};
+
+/// A fully-resolved instruction opcode, including the secondary opcode of
+/// prefixed instructions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Operator {
+    /// An instruction encoded by a single-byte opcode.
+    Basic(Opcode),
+    /// A bulk memory or table instruction (0xfc prefix).
+    Bulk(BulkOpcode),
+    /// An atomic memory instruction (0xfe prefix).
+    Atomic(AtomicOpcode),
+}
+
+impl Operator {
+    /// The class of the instruction, or None for a prefix opcode left
+    /// unresolved (e.g., `Operator::Basic(Opcode::BulkPrefix)`), which
+    /// decoding never produces.
+    pub const fn class(self) -> Option<InstructionClass> {
+        match self {
+            Operator::Basic(op) => op.class(),
+            Operator::Bulk(op) => Some(op.class()),
+            Operator::Atomic(op) => Some(op.class()),
+        }
+    }
+
+    /// The natural alignment of the memory access of a load, store, or
+    /// atomic instruction, as an exponent of two; `None` for instructions
+    /// without memory operands.
+    pub fn natural_alignment(self) -> Option<u32> {
+        match self {
+            Operator::Basic(op) => match op {
+                Opcode::I32Load8S
+                | Opcode::I32Load8U
+                | Opcode::I64Load8S
+                | Opcode::I64Load8U
+                | Opcode::I32Store8
+                | Opcode::I64Store8 => Some(0),
+                Opcode::I32Load16S
+                | Opcode::I32Load16U
+                | Opcode::I64Load16S
+                | Opcode::I64Load16U
+                | Opcode::I32Store16
+                | Opcode::I64Store16 => Some(1),
+                Opcode::I32Load
+                | Opcode::F32Load
+                | Opcode::I64Load32S
+                | Opcode::I64Load32U
+                | Opcode::I32Store
+                | Opcode::F32Store
+                | Opcode::I64Store32 => Some(2),
+                Opcode::I64Load | Opcode::F64Load | Opcode::I64Store | Opcode::F64Store => Some(3),
+                _ => None,
+            },
+            Operator::Bulk(_) => None,
+            Operator::Atomic(op) => match op {
+                AtomicOpcode::MemoryAtomicNotify | AtomicOpcode::MemoryAtomicWait32 => Some(2),
+                AtomicOpcode::MemoryAtomicWait64 => Some(3),
+                AtomicOpcode::AtomicFence => None,
+                // The loads, stores, and read-modify-writes each come in runs of
+                // seven accesses: of an i32, an i64, the low 8 and 16 bits of an
+                // i32, and the low 8, 16, and 32 bits of an i64.
+                _ => {
+                    const RUN: [u32; 7] = [2, 3, 0, 1, 0, 1, 2];
+                    let offset = (op as u32).checked_sub(AtomicOpcode::I32AtomicLoad as u32)?;
+                    RUN.get(offset as usize % RUN.len()).copied()
+                }
+            },
+        }
+    }
+}
+
+/// The target labels of a `br_table` instruction.
+#[derive(Clone, Copy, Debug)]
+pub struct BrTableLabels<'a> {
+    bytes: &'a [u8],
+}
+
+impl BrTableLabels<'_> {
+    /// The number of labels.
+    pub const fn len(&self) -> usize {
+        self.bytes.len() / size_of::<u32>()
+    }
+
+    /// Whether there are no labels.
+    pub const fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Iterates over the labels.
+    pub fn iter(&self) -> impl Iterator<Item = LabelIdx> + '_ {
+        self.bytes.chunks_exact(size_of::<u32>()).map(|chunk| {
+            LabelIdx::new(u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        })
+    }
+}
+
+/// The handler clauses of a `resume` or `resume_throw` instruction.
+#[cfg(feature = "experimental")]
+#[derive(Clone, Copy, Debug)]
+pub struct Handlers<'a> {
+    // Each handler is re-encoded as three u32s: the binary format's kind byte
+    // (0x00 for a label and 0x01 for a switch), the tagidx, and the labelidx
+    // (zero for a switch).
+    bytes: &'a [u8],
+}
+
+#[cfg(feature = "experimental")]
+impl Handlers<'_> {
+    pub(crate) const ENCODED_SIZE: usize = 3 * size_of::<u32>();
+
+    /// The number of handlers.
+    pub const fn len(&self) -> usize {
+        self.bytes.len() / Self::ENCODED_SIZE
+    }
+
+    /// Whether there are no handlers.
+    pub const fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Iterates over the handlers.
+    pub fn iter(&self) -> impl Iterator<Item = Handler> + '_ {
+        self.bytes.chunks_exact(Self::ENCODED_SIZE).map(|chunk| {
+            let word =
+                |i: usize| u32::from_ne_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]]);
+            let tag = TagIdx::new(word(4));
+            if word(0) == 0 {
+                Handler::OnLabel {
+                    tag,
+                    label: LabelIdx::new(word(8)),
+                }
+            } else {
+                Handler::OnSwitch(tag)
+            }
+        })
+    }
+}
+
+/// The value types of a typed `select` instruction.
+#[derive(Clone, Copy, Debug)]
+pub struct SelectTypes<'a> {
+    // Each byte is known to be a valid ValType.
+    bytes: &'a [u8],
+}
+
+impl SelectTypes<'_> {
+    /// The number of types.
+    pub const fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether there are no types.
+    pub const fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Iterates over the types.
+    pub fn iter(&self) -> impl Iterator<Item = ValType> + '_ {
+        self.bytes
+            .iter()
+            .filter_map(|&byte| ValType::try_from(byte).ok())
+    }
+}
+
+/// The immediate operands of an instruction.
+///
+/// The operands of in-flight proposals are only present with the
+/// `experimental` feature, so the enum is non-exhaustive.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum Operands<'a> {
+    /// No operands.
+    None,
+    /// The block type of `block`, `loop`, or `if`.
+    BlockType(BlockType),
+    /// The operands of `br_table`.
+    BrTable {
+        labels: BrTableLabels<'a>,
+        default: LabelIdx,
+    },
+    /// The operands of `call_indirect`.
+    CallIndirect(CallIndirectOperands),
+    /// An `f32.const` value.
+    F32(f32),
+    /// An `f64.const` value.
+    F64(f64),
+    /// An `i32.const` value.
+    I32(i32),
+    /// An `i64.const` value.
+    I64(i64),
+    /// A single index, whose index space is determined by the operator (e.g.,
+    /// a funcidx for `call` or a localidx for `local.get`).
+    Idx(u32),
+    /// The memory operands of a load or store.
+    MemArg(MemArg),
+    /// The reference type of `ref.null`.
+    RefType(RefType),
+    /// The operands of the typed `select`.
+    SelectT(SelectTypes<'a>),
+    /// The operands of `table.copy`.
+    TableCopy(TableCopyOperands),
+    /// The operands of `table.init`.
+    TableInit(TableInitOperands),
+    /// The operands of `cont.bind` (experimental).
+    #[cfg(feature = "experimental")]
+    ContBind(ContBindOperands),
+    /// The operands of `resume` (experimental).
+    #[cfg(feature = "experimental")]
+    Resume { ty: TypeIdx, handlers: Handlers<'a> },
+    /// The operands of `resume_throw` (experimental).
+    #[cfg(feature = "experimental")]
+    ResumeThrow {
+        ty: TypeIdx,
+        tag: TagIdx,
+        handlers: Handlers<'a>,
+    },
+    /// The operands of `switch` (experimental).
+    #[cfg(feature = "experimental")]
+    Switch(SwitchOperands),
+}
+
+/// A single instruction read back from an expression.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Instruction<'a> {
+    /// The byte offset of the instruction within the crate's re-encoding of
+    /// the expression.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub offset: usize,
+    /// The instruction's operator.
+    pub op: Operator,
+    /// The instruction's immediate operands.
+    pub operands: Operands<'a>,
+}
+
+/// An iterator over the instructions of an expression.
+///
+/// Iteration ends early if a malformed encoding is encountered, which can only
+/// happen for expressions that were not produced by this crate's decoder.
+#[derive(Clone)]
+pub struct Instructions<'a> {
+    // Operands are read back by value from their natural alignments rather
+    // than referenced in place, since the bytes are only guaranteed to be
+    // well-formed (and suitably aligned) when produced by our own transcoder.
+    // This also makes reading packed (unaligned) operands safe.
+    bytes: &'a [u8],
+    pos: usize,
+    packed: bool,
+}
+
+impl<'a> Instructions<'a> {
+    fn new(bytes: &'a [u8], packed: bool) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            packed,
+        }
+    }
+
+    // A no-op for packed expressions. (Within repr(C) types, the offsets of
+    // fields are the same either way, as they are relative to the start.)
+    fn align_to(&mut self, align: usize) {
+        if !self.packed {
+            self.pos = self.pos.next_multiple_of(align);
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self, align: usize) -> Option<[u8; N]> {
+        self.align_to(align);
+        self.take(N)?.try_into().ok()
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        Some(self.read_array::<1>(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Option<u32> {
+        self.read_array(align_of::<u32>()).map(u32::from_ne_bytes)
+    }
+
+    fn read_block_type(&mut self) -> Option<BlockType> {
+        // BlockType is repr(C): a C-int-sized tag followed by the payload at
+        // the payload's natural alignment. Only the bytes of the active
+        // variant are initialized.
+        self.align_to(align_of::<BlockType>());
+        let start = self.pos;
+        let tag = self.read_u32()?;
+        let block_type = match tag {
+            0 => BlockType::Empty,
+            1 => BlockType::Result(ValType::try_from(self.read_u8()?).ok()?),
+            2 => BlockType::TypeIndex(TypeIdx::new(self.read_u32()?)),
+            _ => return None,
+        };
+        self.pos = start + size_of::<BlockType>();
+        Some(block_type)
+    }
+
+    #[cfg(feature = "experimental")]
+    fn read_handlers(&mut self) -> Option<Handlers<'a>> {
+        let len = self.read_u32()? as usize;
+        let bytes = self.take(len.checked_mul(Handlers::ENCODED_SIZE)?)?;
+        Some(Handlers { bytes })
+    }
+
+    fn read_operands(&mut self, operand_type: OperandType) -> Option<Operands<'a>> {
+        let operands = match operand_type {
+            OperandType::None | OperandType::ZeroBytes(_) => Operands::None,
+            OperandType::BlockType => Operands::BlockType(self.read_block_type()?),
+            OperandType::BrTable => {
+                let len = self.read_u32()? as usize;
+                let labels = BrTableLabels {
+                    bytes: self.take(len.checked_mul(size_of::<u32>())?)?,
+                };
+                let default = LabelIdx::new(self.read_u32()?);
+                Operands::BrTable { labels, default }
+            }
+            OperandType::CallIndirect => Operands::CallIndirect(CallIndirectOperands {
+                table: TableIdx::new(self.read_u32()?),
+                ty: TypeIdx::new(self.read_u32()?),
+            }),
+            OperandType::F32 => {
+                Operands::F32(f32::from_ne_bytes(self.read_array(align_of::<f32>())?))
+            }
+            OperandType::F64 => {
+                Operands::F64(f64::from_ne_bytes(self.read_array(align_of::<f64>())?))
+            }
+            OperandType::I32 => {
+                Operands::I32(i32::from_ne_bytes(self.read_array(align_of::<i32>())?))
+            }
+            OperandType::I64 => {
+                Operands::I64(i64::from_ne_bytes(self.read_array(align_of::<i64>())?))
+            }
+            OperandType::Idx | OperandType::MemoryInit => Operands::Idx(self.read_u32()?),
+            OperandType::MemArg => Operands::MemArg(MemArg {
+                offset: self.read_u32()?,
+                align: self.read_u32()?,
+            }),
+            OperandType::RefType => Operands::RefType(RefType::try_from(self.read_u8()?).ok()?),
+            OperandType::SelectT => {
+                let len = self.read_u32()? as usize;
+                let types = self.take(len)?;
+                if types.iter().any(|&byte| ValType::try_from(byte).is_err()) {
+                    return None;
+                }
+                Operands::SelectT(SelectTypes { bytes: types })
+            }
+            OperandType::TableCopy => Operands::TableCopy(TableCopyOperands {
+                src: TableIdx::new(self.read_u32()?),
+                dst: TableIdx::new(self.read_u32()?),
+            }),
+            OperandType::TableInit => Operands::TableInit(TableInitOperands {
+                table: TableIdx::new(self.read_u32()?),
+                elem: ElemIdx::new(self.read_u32()?),
+            }),
+            #[cfg(feature = "experimental")]
+            OperandType::ContBind => Operands::ContBind(ContBindOperands {
+                src: TypeIdx::new(self.read_u32()?),
+                dst: TypeIdx::new(self.read_u32()?),
+            }),
+            #[cfg(feature = "experimental")]
+            OperandType::Resume => Operands::Resume {
+                ty: TypeIdx::new(self.read_u32()?),
+                handlers: self.read_handlers()?,
+            },
+            #[cfg(feature = "experimental")]
+            OperandType::ResumeThrow => Operands::ResumeThrow {
+                ty: TypeIdx::new(self.read_u32()?),
+                tag: TagIdx::new(self.read_u32()?),
+                handlers: self.read_handlers()?,
+            },
+            #[cfg(feature = "experimental")]
+            OperandType::Switch => Operands::Switch(SwitchOperands {
+                ty: TypeIdx::new(self.read_u32()?),
+                tag: TagIdx::new(self.read_u32()?),
+            }),
+            // Atomic and bulk prefixes are resolved by the caller, and vector
+            // instructions are not yet transcoded.
+            OperandType::AtomicPrefix | OperandType::BulkPrefix | OperandType::VectorPrefix => {
+                return None;
+            }
+        };
+        Some(operands)
+    }
+
+    fn read_instruction(&mut self) -> Option<Instruction<'a>> {
+        let offset = self.pos;
+        let op = Opcode::try_from(self.read_u8()?).ok()?;
+        let (op, operands) = match op.operand_type() {
+            OperandType::AtomicPrefix => {
+                let atomic_op = AtomicOpcode::try_from(self.read_u32()?).ok()?;
+                (
+                    Operator::Atomic(atomic_op),
+                    self.read_operands(atomic_op.operand_type())?,
+                )
+            }
+            OperandType::BulkPrefix => {
+                let bulk_op = BulkOpcode::try_from(self.read_u32()?).ok()?;
+                (
+                    Operator::Bulk(bulk_op),
+                    self.read_operands(bulk_op.operand_type())?,
+                )
+            }
+            operand_type => (Operator::Basic(op), self.read_operands(operand_type)?),
+        };
+        Some(Instruction {
+            offset,
+            op,
+            operands,
+        })
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Instruction<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.bytes.len() {
+            return None;
+        }
+        let instr = self.read_instruction();
+        if instr.is_none() {
+            self.pos = self.bytes.len();
+        }
+        instr
+    }
+}
+
+/// Maps the indices referenced by an expression to new ones, as when merging
+/// or splitting modules (see [`Expression::remap_indices`]). Indices are left
+/// unchanged by default.
+pub trait IndexRemapper {
+    /// Remaps the typeidx of a block type or of `call_indirect`.
+    fn remap_type(&self, idx: TypeIdx) -> TypeIdx {
+        idx
+    }
+
+    /// Remaps the funcidx of a `call` or `ref.func`.
+    fn remap_func(&self, idx: FuncIdx) -> FuncIdx {
+        idx
+    }
+
+    /// Remaps the tableidx of `call_indirect` or of a table instruction.
+    fn remap_table(&self, idx: TableIdx) -> TableIdx {
+        idx
+    }
+
+    /// Remaps the globalidx of a `global.get` or `global.set`.
+    fn remap_global(&self, idx: GlobalIdx) -> GlobalIdx {
+        idx
+    }
+
+    /// Remaps the elemidx of a `table.init` or `elem.drop`.
+    fn remap_elem(&self, idx: ElemIdx) -> ElemIdx {
+        idx
+    }
+
+    /// Remaps the dataidx of a `memory.init` or `data.drop`.
+    fn remap_data(&self, idx: DataIdx) -> DataIdx {
+        idx
+    }
+
+    /// Remaps the tagidx of a stack-switching instruction or handler.
+    #[cfg(feature = "experimental")]
+    fn remap_tag(&self, idx: TagIdx) -> TagIdx {
+        idx
+    }
+}
+
+/// A constant expression consisting of a single instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConstExpr {
+    /// `i32.const`.
+    I32(i32),
+    /// `i64.const`.
+    I64(i64),
+    /// `f32.const`.
+    F32(f32),
+    /// `f64.const`.
+    F64(f64),
+    /// `global.get`.
+    GlobalGet(GlobalIdx),
+    /// `ref.null`.
+    RefNull(RefType),
+    /// `ref.func`.
+    RefFunc(FuncIdx),
+}
+
+/// Size and shape statistics of an expression, or of an aggregate of
+/// expressions.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ExpressionStats {
+    /// The number of instructions, including the terminating `end`.
+    pub instructions: usize,
+    /// The maximum nesting depth of structured control instructions.
+    pub max_depth: usize,
+    /// The size in bytes of the crate's re-encoding.
+    pub byte_size: usize,
+    class_counts: [usize; InstructionClass::COUNT],
+}
+
+impl ExpressionStats {
+    /// The number of instructions of a given class.
+    pub const fn count(&self, class: InstructionClass) -> usize {
+        self.class_counts[class as usize]
+    }
+
+    /// Folds another set of statistics into this one: counts and sizes are
+    /// summed, while the maximum depth is the greater of the two.
+    pub fn merge(&mut self, other: &Self) {
+        self.instructions += other.instructions;
+        self.max_depth = self.max_depth.max(other.max_depth);
+        self.byte_size += other.byte_size;
+        for (count, other) in self.class_counts.iter_mut().zip(other.class_counts) {
+            *count += other;
+        }
+    }
+}
+
+impl<A: Allocator> Expression<A> {
+    /// Iterates over the instructions of the expression.
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(self, self.packed)
+    }
+
+    /// Returns the expression as a single constant instruction, if it is one.
+    pub fn as_const(&self) -> Option<ConstExpr> {
+        let mut instrs = self.instructions();
+        let instr = instrs.next()?;
+        let end = instrs.next()?;
+        if end.op != Operator::Basic(Opcode::End) || instrs.next().is_some() {
+            return None;
+        }
+        let Operator::Basic(op) = instr.op else {
+            return None;
+        };
+        let expr = match (op, instr.operands) {
+            (Opcode::I32Const, Operands::I32(value)) => ConstExpr::I32(value),
+            (Opcode::I64Const, Operands::I64(value)) => ConstExpr::I64(value),
+            (Opcode::F32Const, Operands::F32(value)) => ConstExpr::F32(value),
+            (Opcode::F64Const, Operands::F64(value)) => ConstExpr::F64(value),
+            (Opcode::GlobalGet, Operands::Idx(idx)) => ConstExpr::GlobalGet(GlobalIdx::new(idx)),
+            (Opcode::RefNull, Operands::RefType(ty)) => ConstExpr::RefNull(ty),
+            (Opcode::RefFunc, Operands::Idx(idx)) => ConstExpr::RefFunc(FuncIdx::new(idx)),
+            _ => return None,
+        };
+        Some(expr)
+    }
+
+    /// Rewrites the indices referenced by the expression in place.
+    ///
+    /// Indices are fixed-size in the crate's re-encoding, so no instruction
+    /// moves. As with iteration, rewriting stops at a malformed encoding.
+    pub fn remap_indices(&mut self, remapper: &impl IndexRemapper) {
+        let mut pos = 0;
+        while pos < self.bytes.len() {
+            let mut instrs = Instructions {
+                bytes: &self.bytes,
+                pos,
+                packed: self.packed,
+            };
+            let Some(instr) = instrs.read_instruction() else {
+                break;
+            };
+            pos = instrs.pos;
+            let func = |idx| *remapper.remap_func(FuncIdx::new(idx));
+            let table = |idx| *remapper.remap_table(TableIdx::new(idx));
+            #[cfg(feature = "experimental")]
+            let ty = |idx| *remapper.remap_type(idx);
+            #[cfg(feature = "experimental")]
+            let tag = |idx| *remapper.remap_tag(idx);
+            // Up to two indices, as many as given by the count, followed by
+            // the given number of trailing bytes (i.e., those of handlers).
+            let (words, count, trailing) = match (instr.op, instr.operands) {
+                (
+                    Operator::Basic(Opcode::Block | Opcode::Loop | Opcode::If),
+                    Operands::BlockType(BlockType::TypeIndex(idx)),
+                ) => ([*remapper.remap_type(idx), 0], 1, 0),
+                (Operator::Basic(Opcode::Call | Opcode::RefFunc), Operands::Idx(idx)) => {
+                    ([func(idx), 0], 1, 0)
+                }
+                (Operator::Basic(Opcode::GlobalGet | Opcode::GlobalSet), Operands::Idx(idx)) => {
+                    ([*remapper.remap_global(GlobalIdx::new(idx)), 0], 1, 0)
+                }
+                (
+                    Operator::Basic(Opcode::TableGet | Opcode::TableSet)
+                    | Operator::Bulk(
+                        BulkOpcode::TableGrow | BulkOpcode::TableSize | BulkOpcode::TableFill,
+                    ),
+                    Operands::Idx(idx),
+                ) => ([table(idx), 0], 1, 0),
+                (Operator::Bulk(BulkOpcode::ElemDrop), Operands::Idx(idx)) => {
+                    ([*remapper.remap_elem(ElemIdx::new(idx)), 0], 1, 0)
+                }
+                (
+                    Operator::Bulk(BulkOpcode::MemoryInit | BulkOpcode::DataDrop),
+                    Operands::Idx(idx),
+                ) => ([*remapper.remap_data(DataIdx::new(idx)), 0], 1, 0),
+                (_, Operands::CallIndirect(operands)) => (
+                    [table(*operands.table), *remapper.remap_type(operands.ty)],
+                    2,
+                    0,
+                ),
+                (_, Operands::TableCopy(operands)) => {
+                    ([table(*operands.src), table(*operands.dst)], 2, 0)
+                }
+                (_, Operands::TableInit(operands)) => (
+                    [table(*operands.table), *remapper.remap_elem(operands.elem)],
+                    2,
+                    0,
+                ),
+                #[cfg(feature = "experimental")]
+                (Operator::Basic(Opcode::ContNew), Operands::Idx(idx)) => {
+                    ([ty(TypeIdx::new(idx)), 0], 1, 0)
+                }
+                #[cfg(feature = "experimental")]
+                (Operator::Basic(Opcode::Suspend), Operands::Idx(idx)) => {
+                    ([tag(TagIdx::new(idx)), 0], 1, 0)
+                }
+                #[cfg(feature = "experimental")]
+                (_, Operands::ContBind(operands)) => ([ty(operands.src), ty(operands.dst)], 2, 0),
+                #[cfg(feature = "experimental")]
+                (_, Operands::Switch(operands)) => ([ty(operands.ty), tag(operands.tag)], 2, 0),
+                // The handlers follow the indices, preceded by their count.
+                #[cfg(feature = "experimental")]
+                (_, Operands::Resume { ty: idx, handlers }) => (
+                    [ty(idx), 0],
+                    1,
+                    size_of::<u32>() + handlers.len() * Handlers::ENCODED_SIZE,
+                ),
+                #[cfg(feature = "experimental")]
+                (
+                    _,
+                    Operands::ResumeThrow {
+                        ty: idx,
+                        tag: tagidx,
+                        handlers,
+                    },
+                ) => (
+                    [ty(idx), tag(tagidx)],
+                    2,
+                    size_of::<u32>() + handlers.len() * Handlers::ENCODED_SIZE,
+                ),
+                _ => continue,
+            };
+            // The indices are the last operands but for the trailing bytes,
+            // and so end the instruction.
+            let end = pos - trailing;
+            let start = end - count * size_of::<u32>();
+            let chunks = self.bytes[start..end].chunks_exact_mut(size_of::<u32>());
+            for (chunk, word) in chunks.zip(words) {
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            #[cfg(feature = "experimental")]
+            if trailing > 0 {
+                let handlers = &mut self.bytes[end + size_of::<u32>()..pos];
+                for handler in handlers.chunks_exact_mut(Handlers::ENCODED_SIZE) {
+                    // The tagidx is the second of each handler's words.
+                    let word = &mut handler[size_of::<u32>()..2 * size_of::<u32>()];
+                    let idx = u32::from_ne_bytes([word[0], word[1], word[2], word[3]]);
+                    word.copy_from_slice(&tag(TagIdx::new(idx)).to_ne_bytes());
+                }
+            }
+        }
+    }
+
+    /// Computes size and shape statistics for the expression.
+    pub fn stats(&self) -> ExpressionStats {
+        let mut stats = ExpressionStats {
+            byte_size: self.len(),
+            ..ExpressionStats::default()
+        };
+        let mut depth = 0usize;
+        for instr in self.instructions() {
+            stats.instructions += 1;
+            if let Some(class) = instr.op.class() {
+                stats.class_counts[class as usize] += 1;
+            }
+            match instr.op {
+                Operator::Basic(Opcode::Block | Opcode::Loop | Opcode::If) => {
+                    depth += 1;
+                    stats.max_depth = stats.max_depth.max(depth);
+                }
+                Operator::Basic(Opcode::End) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::{DecodeOptions, NoCustomSectionVisitor};
+
+    fn decode_body(body: &[u8]) -> Module<Global> {
+        decode_body_with_options(body, &DecodeOptions::default())
+    }
+
+    // Wraps a function body of type [] -> [i32] in a minimal module.
+    fn decode_body_with_options(body: &[u8], options: &DecodeOptions) -> Module<Global> {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+        ];
+        let code_len = u8::try_from(body.len() + 1).unwrap();
+        bytes.extend([0x0a, code_len + 2, 0x01, code_len, 0x00]);
+        bytes.extend_from_slice(body);
+        Module::decode_bytes_with_options(bytes, &mut NoCustomSectionVisitor {}, Global, options)
+            .unwrap()
+    }
+
+    #[test]
+    fn instructions() {
+        let module = decode_body(&[
+            0x02, 0x7f, // block (result i32)
+            0x41, 0x01, // i32.const 1
+            0x0b, // end
+            0x02, 0x00, // block (type 0)
+            0x0e, 0x02, 0x00, 0x01, 0x00, // br_table 0 1 0
+            0x0b, // end
+            0x1c, 0x01, 0x7f, // select (result i32)
+            0x0b, // end
+        ]);
+        let instrs: std::vec::Vec<_> = module.codesec[0].code.instructions().collect();
+        assert_eq!(instrs.len(), 8);
+        assert!(matches!(
+            instrs[0].operands,
+            Operands::BlockType(BlockType::Result(ValType::I32))
+        ));
+        assert!(matches!(instrs[1].operands, Operands::I32(1)));
+        assert!(matches!(
+            instrs[3].operands,
+            Operands::BlockType(BlockType::TypeIndex(idx)) if *idx == 0
+        ));
+        let Operands::BrTable { labels, default } = instrs[4].operands else {
+            panic!("unexpected operands: {:?}", instrs[4].operands);
+        };
+        assert!(labels.iter().map(|label| *label).eq([0, 1]));
+        assert_eq!(*default, 0);
+        let Operands::SelectT(types) = instrs[6].operands else {
+            panic!("unexpected operands: {:?}", instrs[6].operands);
+        };
+        assert!(types.iter().eq([ValType::I32]));
+    }
+
+    #[test]
+    fn remap_indices() {
+        struct Offset;
+
+        impl IndexRemapper for Offset {
+            fn remap_type(&self, idx: TypeIdx) -> TypeIdx {
+                TypeIdx::new(*idx + 0x300)
+            }
+
+            fn remap_table(&self, idx: TableIdx) -> TableIdx {
+                TableIdx::new(*idx + 0x400)
+            }
+
+            fn remap_func(&self, idx: FuncIdx) -> FuncIdx {
+                FuncIdx::new(*idx + 0x100)
+            }
+
+            fn remap_global(&self, idx: GlobalIdx) -> GlobalIdx {
+                GlobalIdx::new(*idx + 0x200)
+            }
+        }
+
+        let body = [
+            0x10, 0x00, 0x1a, // call 0; drop
+            0xd2, 0x01, 0x1a, // ref.func 1; drop
+            0x23, 0x02, 0x24, 0x03, // global.get 2; global.set 3
+            0x20, 0x04, 0x1a, // local.get 4; drop
+            0x41, 0x00, 0x11, 0x00, 0x01,
+            0x1a, // (call_indirect 1 (type 0) (i32.const 0)); drop
+            0x41, 0x05, // i32.const 5
+            0x0b, // end
+        ];
+        for packed_expressions in [false, true] {
+            let options = DecodeOptions {
+                packed_expressions,
+                ..DecodeOptions::default()
+            };
+            let module = decode_body_with_options(&body, &options);
+            let mut code = module.codesec[0].code.clone();
+            code.remap_indices(&Offset);
+            assert_eq!(code.len(), module.codesec[0].code.len());
+            let operands: std::vec::Vec<_> = code
+                .instructions()
+                .filter_map(|instr| match instr.operands {
+                    Operands::Idx(idx) => Some(idx),
+                    Operands::I32(value) => u32::try_from(value).ok(),
+                    Operands::CallIndirect(operands) => Some(*operands.table + *operands.ty),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(operands, [0x100, 0x101, 0x202, 0x203, 4, 0, 0x701, 5]);
+        }
+    }
+
+    // The immediates of each opcode per the spec's opcode table, kept
+    // independently of Opcode::operand_type().
+    fn spec_operand_type(byte: u8) -> OperandType {
+        match byte {
+            0x02..=0x04 => OperandType::BlockType,
+            0x0c | 0x0d | 0x10 | 0x20..=0x26 | 0xd2 => OperandType::Idx,
+            0x0e => OperandType::BrTable,
+            0x11 => OperandType::CallIndirect,
+            0x1c => OperandType::SelectT,
+            0x28..=0x3e => OperandType::MemArg,
+            0x3f | 0x40 => OperandType::ZeroBytes(1),
+            0x41 => OperandType::I32,
+            0x42 => OperandType::I64,
+            0x43 => OperandType::F32,
+            0x44 => OperandType::F64,
+            0xd0 => OperandType::RefType,
+            0xfc => OperandType::BulkPrefix,
+            0xfd => OperandType::VectorPrefix,
+            0xfe => OperandType::AtomicPrefix,
+            #[cfg(feature = "experimental")]
+            0xe0 | 0xe2 => OperandType::Idx,
+            #[cfg(feature = "experimental")]
+            0xe1 => OperandType::ContBind,
+            #[cfg(feature = "experimental")]
+            0xe3 => OperandType::Resume,
+            #[cfg(feature = "experimental")]
+            0xe4 => OperandType::ResumeThrow,
+            #[cfg(feature = "experimental")]
+            0xe5 => OperandType::Switch,
+            _ => OperandType::None,
+        }
+    }
+
+    // A minimal encoding of immediates of a given type.
+    fn immediates(operand_type: OperandType) -> &'static [u8] {
+        match operand_type {
+            OperandType::None => &[],
+            OperandType::BlockType => &[0x40],
+            OperandType::F32 => &[0; 4],
+            OperandType::F64 => &[0; 8],
+            OperandType::I32 | OperandType::I64 | OperandType::Idx => &[0x00],
+            OperandType::BrTable
+            | OperandType::CallIndirect
+            | OperandType::MemArg
+            | OperandType::MemoryInit
+            | OperandType::TableCopy
+            | OperandType::TableInit => &[0x00, 0x00],
+            OperandType::RefType => &[0x70],
+            OperandType::SelectT => &[0x01, 0x7f],
+            #[cfg(feature = "experimental")]
+            OperandType::ContBind | OperandType::Resume | OperandType::Switch => &[0x00, 0x00],
+            #[cfg(feature = "experimental")]
+            OperandType::ResumeThrow => &[0x00, 0x00, 0x00],
+            OperandType::ZeroBytes(count) => &[0; 2][..count as usize],
+            OperandType::AtomicPrefix | OperandType::BulkPrefix | OperandType::VectorPrefix => {
+                unreachable!()
+            }
+        }
+    }
+
+    // Checks that an instruction survives transcoding and reading back.
+    fn check_round_trip(op: Operator, encoding: &[u8]) {
+        let mut body = std::vec::Vec::from(encoding);
+        let mut expected = 2;
+        if matches!(
+            op,
+            Operator::Basic(Opcode::Block | Opcode::Loop | Opcode::If)
+        ) {
+            body.push(0x0b);
+            expected += 1;
+        }
+        if op == Operator::Basic(Opcode::End) {
+            expected -= 1;
+        } else {
+            body.push(0x0b);
+        }
+        let module = decode_body(&body);
+        let instrs: std::vec::Vec<_> = module.codesec[0].code.instructions().collect();
+        assert_eq!(instrs.len(), expected, "{op:?}");
+        assert_eq!(instrs[0].op, op);
+        assert!(op.class().is_some(), "{op:?}");
+    }
+
+    // Every opcode must have an operand table entry agreeing with the spec,
+    // and must be transcoded and read back. Vector instructions are the one
+    // known gap.
+    #[test]
+    fn opcode_coverage() {
+        for byte in 0..=u8::MAX {
+            let Ok(op) = Opcode::try_from(byte) else {
+                continue;
+            };
+            // Opcodes of in-flight proposals enabled only in wafer-types are
+            // rejected in decoding.
+            if op.is_experimental() && !cfg!(feature = "experimental") {
+                continue;
+            }
+            let operand_type = op.operand_type();
+            assert_eq!(operand_type, spec_operand_type(byte), "{op:?}");
+            match operand_type {
+                OperandType::AtomicPrefix => {
+                    for secondary in 0..=u8::MAX {
+                        let Ok(atomic_op) = AtomicOpcode::try_from(u32::from(secondary)) else {
+                            continue;
+                        };
+                        let mut encoding = std::vec![byte, secondary];
+                        encoding.extend_from_slice(immediates(atomic_op.operand_type()));
+                        check_round_trip(Operator::Atomic(atomic_op), &encoding);
+                    }
+                }
+                OperandType::BulkPrefix => {
+                    for secondary in 0..=u8::MAX {
+                        let Ok(bulk_op) = BulkOpcode::try_from(u32::from(secondary)) else {
+                            continue;
+                        };
+                        let mut encoding = std::vec![byte, secondary];
+                        encoding.extend_from_slice(immediates(bulk_op.operand_type()));
+                        check_round_trip(Operator::Bulk(bulk_op), &encoding);
+                    }
+                }
+                OperandType::VectorPrefix => {}
+                _ => {
+                    let mut encoding = std::vec![byte];
+                    encoding.extend_from_slice(immediates(operand_type));
+                    check_round_trip(Operator::Basic(op), &encoding);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn packed() {
+        let body = [
+            0x02, 0x7f, // block (result i32)
+            0x42, 0x01, // i64.const 1
+            0x1a, // drop
+            0x44, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f, // f64.const 1
+            0x1a, // drop
+            0x41, 0x02, // i32.const 2
+            0x0b, // end
+            0x0b, // end
+        ];
+        let aligned = decode_body(&body);
+        let options = DecodeOptions {
+            packed_expressions: true,
+            ..DecodeOptions::default()
+        };
+        let packed = decode_body_with_options(&body, &options);
+
+        let aligned = &aligned.codesec[0].code;
+        let packed = &packed.codesec[0].code;
+        assert!(!aligned.is_packed());
+        assert!(packed.is_packed());
+        assert!(packed.len() < aligned.len());
+        let describe = |expr: &Expression<Global>| -> std::vec::Vec<std::string::String> {
+            expr.instructions()
+                .map(|instr| std::format!("{:?} {:?}", instr.op, instr.operands))
+                .collect()
+        };
+        assert_eq!(describe(packed), describe(aligned));
+        assert_eq!(packed.stats().instructions, 8);
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn stack_switching() {
+        let body = [
+            0xe3, 0x01, 0x02, 0x00, 0x03, 0x04, 0x01, 0x05, // resume 1 (on 3 4) (on 5 switch)
+            0xe4, 0x06, 0x07, 0x00, // resume_throw 6 7
+            0xe1, 0x08, 0x09, // cont.bind 8 9
+            0xe5, 0x0a, 0x0b, // switch 10 11
+            0x41, 0x00, // i32.const 0
+            0x0b, // end
+        ];
+        let options = DecodeOptions {
+            packed_expressions: true,
+            ..DecodeOptions::default()
+        };
+        for module in [
+            decode_body(&body),
+            decode_body_with_options(&body, &options),
+        ] {
+            let instrs: std::vec::Vec<_> = module.codesec[0].code.instructions().collect();
+            assert_eq!(instrs.len(), 6);
+
+            let Operands::Resume { ty, handlers } = instrs[0].operands else {
+                panic!("{:?}", instrs[0].operands);
+            };
+            assert_eq!(*ty, 1);
+            assert_eq!(handlers.len(), 2);
+            let handlers: std::vec::Vec<_> = handlers.iter().collect();
+            assert!(matches!(
+                handlers[0],
+                Handler::OnLabel { tag, label } if *tag == 3 && *label == 4
+            ));
+            assert!(matches!(handlers[1], Handler::OnSwitch(tag) if *tag == 5));
+
+            let Operands::ResumeThrow { ty, tag, handlers } = instrs[1].operands else {
+                panic!("{:?}", instrs[1].operands);
+            };
+            assert_eq!((*ty, *tag), (6, 7));
+            assert!(handlers.is_empty());
+
+            let Operands::ContBind(operands) = instrs[2].operands else {
+                panic!("{:?}", instrs[2].operands);
+            };
+            assert_eq!((*operands.src, *operands.dst), (8, 9));
+
+            let Operands::Switch(operands) = instrs[3].operands else {
+                panic!("{:?}", instrs[3].operands);
+            };
+            assert_eq!((*operands.ty, *operands.tag), (10, 11));
+        }
+
+        // Stack-switching instructions are decoded, but not validated.
+        #[cfg(feature = "validate")]
+        {
+            let module = decode_body(&[0xe2, 0x00, 0x41, 0x00, 0x0b]); // suspend 0
+            assert!(matches!(
+                module.validate(),
+                Err(crate::validate::Error::UnsupportedInstruction(
+                    Opcode::Suspend
+                ))
+            ));
+        }
+    }
+
+    #[cfg(feature = "experimental")]
+    #[test]
+    fn remap_stack_switching_indices() {
+        struct Offset;
+
+        impl IndexRemapper for Offset {
+            fn remap_type(&self, idx: TypeIdx) -> TypeIdx {
+                TypeIdx::new(*idx + 0x100)
+            }
+
+            fn remap_tag(&self, idx: TagIdx) -> TagIdx {
+                TagIdx::new(*idx + 0x200)
+            }
+        }
+
+        let body = [
+            0xe0, 0x01, // cont.new 1
+            0xe2, 0x02, // suspend 2
+            0xe3, 0x03, 0x02, 0x00, 0x04, 0x05, 0x01, 0x06, // resume 3 (on 4 5) (on 6 switch)
+            0xe4, 0x07, 0x08, 0x01, 0x01, 0x09, // resume_throw 7 8 (on 9 switch)
+            0xe1, 0x0a, 0x0b, // cont.bind 10 11
+            0xe5, 0x0c, 0x0d, // switch 12 13
+            0x0b, // end
+        ];
+        for packed_expressions in [false, true] {
+            let options = DecodeOptions {
+                packed_expressions,
+                ..DecodeOptions::default()
+            };
+            let module = decode_body_with_options(&body, &options);
+            let mut code = module.codesec[0].code.clone();
+            code.remap_indices(&Offset);
+            let mut indices = std::vec::Vec::new();
+            for instr in code.instructions() {
+                let handlers = match instr.operands {
+                    Operands::Idx(idx) => {
+                        indices.push(idx);
+                        None
+                    }
+                    Operands::Resume { ty, handlers } => {
+                        indices.push(*ty);
+                        Some(handlers)
+                    }
+                    Operands::ResumeThrow { ty, tag, handlers } => {
+                        indices.extend([*ty, *tag]);
+                        Some(handlers)
+                    }
+                    Operands::ContBind(operands) => {
+                        indices.extend([*operands.src, *operands.dst]);
+                        None
+                    }
+                    Operands::Switch(operands) => {
+                        indices.extend([*operands.ty, *operands.tag]);
+                        None
+                    }
+                    _ => None,
+                };
+                for handler in handlers.iter().flat_map(Handlers::iter) {
+                    match handler {
+                        Handler::OnLabel { tag, label } => indices.extend([*tag, *label]),
+                        Handler::OnSwitch(tag) => indices.push(*tag),
+                    }
+                }
+            }
+            assert_eq!(
+                indices,
+                [
+                    0x101, 0x202, 0x103, 0x204, 5, 0x206, 0x107, 0x208, 0x209, 0x10a, 0x10b, 0x10c,
+                    0x20d
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn as_const() {
+        let as_const = |body: &[u8]| decode_body(body).codesec[0].code.as_const();
+        assert_eq!(as_const(&[0x41, 0x7f, 0x0b]), Some(ConstExpr::I32(-1)));
+        assert_eq!(
+            as_const(&[0x44, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f, 0x0b]),
+            Some(ConstExpr::F64(1.0))
+        );
+        assert_eq!(
+            as_const(&[0x23, 0x02, 0x0b]),
+            Some(ConstExpr::GlobalGet(GlobalIdx::new(2)))
+        );
+        assert_eq!(
+            as_const(&[0xd0, 0x70, 0x0b]),
+            Some(ConstExpr::RefNull(RefType::Func))
+        );
+        assert_eq!(as_const(&[0x41, 0x01, 0x41, 0x02, 0x6a, 0x0b]), None);
+        assert_eq!(as_const(&[0x01, 0x0b]), None);
+    }
+
+    #[test]
+    fn stats() {
+        let module = decode_body(&[
+            0x03, 0x40, // loop
+            0x02, 0x40, // block
+            0xfc, 0x09, 0x00, // data.drop 0
+            0x0b, // end
+            0x0b, // end
+            0x42, 0x7f, // i64.const -1
+            0x1a, // drop
+            0x43, 0x00, 0x00, 0x00, 0x00, // f32.const 0
+            0xfc, 0x00, // i32.trunc_sat_f32_s
+            0x0b, // end
+        ]);
+        let code = &module.codesec[0].code;
+        let stats = code.stats();
+        assert_eq!(stats.instructions, 10);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.byte_size, code.len());
+        assert_eq!(stats.count(InstructionClass::Control), 5);
+        assert_eq!(stats.count(InstructionClass::Memory), 1);
+        assert_eq!(stats.count(InstructionClass::Numeric), 3);
+        assert_eq!(stats.count(InstructionClass::Parametric), 1);
+        assert_eq!(stats.count(InstructionClass::Vector), 0);
+        assert_eq!(module.code_stats(), stats);
+    }
+}
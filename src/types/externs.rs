@@ -0,0 +1,760 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Resolution of the types of a module's imports and exports.
+
+#[cfg(any(feature = "scan", feature = "validate"))]
+use core::hash::Hasher;
+use core::ops;
+
+#[cfg(any(feature = "scan", feature = "validate"))]
+use crate::core_compat::alloc::collections::TryReserveError;
+#[cfg(any(feature = "scan", feature = "validate"))]
+use crate::core_compat::vec::Vec;
+use crate::{Allocator, Module};
+
+#[cfg(feature = "validate")]
+use super::ExportSection;
+#[cfg(any(feature = "scan", feature = "validate"))]
+use super::Fnv1a;
+use super::{
+    DataIdx, ElemIdx, Export, ExportDescriptor, ExternKind, FuncIdx, FunctionType, GlobalIdx,
+    GlobalType, Import, ImportDescriptor, MemIdx, MemType, SectionId, TableIdx, TableType, TypeIdx,
+};
+
+/// The type of an imported or exported entity.
+#[derive(Clone, Debug)]
+pub enum ExternType<'a, A: Allocator> {
+    /// A function, with its signature.
+    Func(&'a FunctionType<A>),
+    /// A table.
+    Table(TableType),
+    /// A linear memory.
+    Mem(MemType),
+    /// A global variable.
+    Global(GlobalType),
+}
+
+/// The imports of a module, grouped by kind. The position of an import within
+/// its group is its index in the corresponding index space.
+#[derive(Debug)]
+pub struct ImportsByKind<'a, A: Allocator> {
+    /// The function imports, by function index.
+    pub functions: &'a [Import<A>],
+    /// The table imports, by table index.
+    pub tables: &'a [Import<A>],
+    /// The memory imports, by memory index.
+    pub memories: &'a [Import<A>],
+    /// The global imports, by global index.
+    pub globals: &'a [Import<A>],
+}
+
+/// The types of the entities of a given kind across their index space:
+/// imported entities, followed by those defined by the module.
+///
+/// The entries of a validated module are all resolvable; those of a module
+/// that fails validation may not be, in which case they are skipped on
+/// iteration (which is why entries are yielded with their indices).
+pub struct IndexSpace<'a, A: Allocator, T> {
+    module: &'a Module<A>,
+    len: usize,
+    descriptor: fn(u32) -> ExportDescriptor,
+    project: fn(ExternType<'a, A>) -> Option<T>,
+}
+
+impl<A: Allocator, T> IndexSpace<'_, A, T> {
+    /// The number of entities in the index space.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the index space is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the type of the entity of a given index.
+    pub fn get(&self, idx: u32) -> Option<T> {
+        let ty = self.module.export_type((self.descriptor)(idx))?;
+        (self.project)(ty)
+    }
+
+    /// Iterates over the types of the entities in index order, each with its
+    /// index.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, T)> + '_ {
+        (0..self.len as u32).filter_map(|idx| Some((idx, self.get(idx)?)))
+    }
+}
+
+/// An index into one of the index spaces of a module, for code generic over
+/// them.
+///
+/// Indices are constructed unchecked (e.g., with `FuncIdx::new()`), as they
+/// are decoded; [`checked_new`](Self::checked_new) and
+/// [`Module::is_valid`] check them against the bounds of a given module.
+pub trait ModuleIndex: Copy + ops::Deref<Target = u32> {
+    /// The section defining the entities of the index space (beyond those
+    /// imported).
+    const SECTION: SectionId;
+
+    /// Wraps a raw index, without checking it.
+    fn from_raw(idx: u32) -> Self;
+
+    /// The number of entities in the index space of a module, imported ones
+    /// included.
+    fn count<A: Allocator>(module: &Module<A>) -> usize;
+
+    /// Returns the index, if within the bounds of the index space of a
+    /// module.
+    fn checked_new<A: Allocator>(module: &Module<A>, idx: u32) -> Option<Self> {
+        ((idx as usize) < Self::count(module)).then(|| Self::from_raw(idx))
+    }
+}
+
+macro_rules! impl_module_index {
+    ($idx_type:ident, $id:path, |$module:ident| $count:expr) => {
+        impl ModuleIndex for $idx_type {
+            const SECTION: SectionId = $id;
+
+            fn from_raw(idx: u32) -> Self {
+                Self::new(idx)
+            }
+
+            fn count<A: Allocator>($module: &Module<A>) -> usize {
+                $count
+            }
+        }
+    };
+}
+
+impl_module_index!(TypeIdx, SectionId::Type, |module| module.typesec.len());
+impl_module_index!(FuncIdx, SectionId::Function, |module| {
+    module.imports_of_kind(ExternKind::Function).len() + module.funcsec.len()
+});
+impl_module_index!(TableIdx, SectionId::Table, |module| {
+    module.imports_of_kind(ExternKind::Table).len() + module.tablesec.len()
+});
+impl_module_index!(MemIdx, SectionId::Memory, |module| {
+    module.imports_of_kind(ExternKind::Memory).len() + module.memsec.len()
+});
+impl_module_index!(GlobalIdx, SectionId::Global, |module| {
+    module.imports_of_kind(ExternKind::Global).len() + module.globalsec.len()
+});
+impl_module_index!(ElemIdx, SectionId::Element, |module| module.elemsec.len());
+impl_module_index!(DataIdx, SectionId::Data, |module| {
+    module
+        .skipped_datasec
+        .map_or(module.datasec.len(), |skipped| skipped.count as usize)
+});
+
+#[cfg(feature = "validate")]
+/// An index of the exports of a validated module by name, for lookup in
+/// constant time (see [`ValidatedModule::export_index`]).
+///
+/// [`ValidatedModule::export_index`]: crate::ValidatedModule::export_index
+#[derive(Clone, Copy)]
+pub struct ExportIndex<'a, A: Allocator> {
+    exports: &'a ExportSection<A>,
+    slots: &'a [u32],
+}
+
+#[cfg(feature = "validate")]
+impl<'a, A: Allocator> ExportIndex<'a, A> {
+    pub(crate) fn new(exports: &'a ExportSection<A>, slots: &'a [u32]) -> Self {
+        Self { exports, slots }
+    }
+
+    /// Returns the kind and index of the entity exported under a given name.
+    pub fn get(&self, name: &str) -> Option<ExportDescriptor> {
+        self.find(name).map(|export| export.descriptor)
+    }
+
+    /// Returns the export of a given name.
+    pub fn find(&self, name: &str) -> Option<&'a Export<A>> {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let mask = self.slots.len() - 1;
+        let mut slot = export_hash(name) as usize & mask;
+        loop {
+            let export = self
+                .exports
+                .get(self.slots[slot].checked_sub(1)? as usize)?;
+            if **export.field == *name {
+                return Some(export);
+            }
+            slot = (slot + 1) & mask;
+        }
+    }
+}
+
+// Builds the slots of an export index: an open-addressed hash table, with
+// linear probing, of the positions of the exports within the export section
+// plus one (with zero marking an empty slot). At most half of the slots are
+// filled, so that probe sequences stay short and always end.
+#[cfg(feature = "validate")]
+pub(crate) fn export_index_slots<A: Allocator>(
+    exports: &ExportSection<A>,
+    alloc: A,
+) -> Result<Vec<u32, A>, TryReserveError> {
+    Ok(hash_exports(exports, alloc, false)?.0)
+}
+
+// Returns the position of the first export with the name of an earlier one,
+// if any.
+#[cfg(feature = "scan")]
+pub(crate) fn find_duplicate_export<A: Allocator>(
+    exports: &[Export<A>],
+    alloc: A,
+) -> Result<Option<usize>, TryReserveError> {
+    Ok(hash_exports(exports, alloc, true)?.1)
+}
+
+// Builds the slots of an export index as above, stopping short at the first
+// export with a duplicate name (returned alongside) if `distinct` names are
+// required.
+#[cfg(any(feature = "scan", feature = "validate"))]
+fn hash_exports<A: Allocator>(
+    exports: &[Export<A>],
+    alloc: A,
+    distinct: bool,
+) -> Result<(Vec<u32, A>, Option<usize>), TryReserveError> {
+    let mut slots = Vec::new_in(alloc);
+    if exports.is_empty() {
+        return Ok((slots, None));
+    }
+    let len = (2 * exports.len()).next_power_of_two();
+    slots.try_reserve_exact(len)?;
+    slots.resize(len, 0);
+    let mask = len - 1;
+    for (pos, export) in exports.iter().enumerate() {
+        let mut slot = export_hash(&export.field) as usize & mask;
+        while slots[slot] != 0 {
+            if distinct && **exports[slots[slot] as usize - 1].field == **export.field {
+                return Ok((slots, Some(pos)));
+            }
+            slot = (slot + 1) & mask;
+        }
+        slots[slot] = pos as u32 + 1;
+    }
+    Ok((slots, None))
+}
+
+#[cfg(any(feature = "scan", feature = "validate"))]
+fn export_hash(name: &str) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write(name.as_bytes());
+    hasher.finish()
+}
+
+/// The execution model of a WASI module, per the entry points it exports.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EntryKind {
+    /// A command, which is run once through its `_start` function, given here.
+    Command(FuncIdx),
+    /// A reactor, which is initialized once through its `_initialize`
+    /// function (if any, given here) and then called into any number of
+    /// times.
+    Reactor(Option<FuncIdx>),
+}
+
+impl<A: Allocator> Module<A> {
+    /// Determines whether the module is a WASI command or reactor.
+    ///
+    /// Returns `None` if the module does not follow either convention: if it
+    /// does not export its memory as "memory", if it exports both `_start`
+    /// and `_initialize`, or if either is not a function of type [] -> [].
+    pub fn entry_kind(&self) -> Option<EntryKind> {
+        if !matches!(
+            self.export("memory")?.descriptor,
+            ExportDescriptor::Memory(_)
+        ) {
+            return None;
+        }
+        let start = self.entry_point("_start")?;
+        let initialize = self.entry_point("_initialize")?;
+        match (start, initialize) {
+            (Some(_), Some(_)) => None,
+            (Some(start), None) => Some(EntryKind::Command(start)),
+            (None, initialize) => Some(EntryKind::Reactor(initialize)),
+        }
+    }
+
+    // Looks up an export by name, relying on the exports being sorted by name.
+    pub(crate) fn export(&self, name: &str) -> Option<&Export<A>> {
+        let idx = self
+            .exportsec
+            .binary_search_by(|export| (**export.field).cmp(name))
+            .ok()?;
+        Some(&self.exportsec[idx])
+    }
+
+    // Returns the function exported under a given name, or Some(None) if there
+    // is no such export and None if it is not a function of type [] -> [].
+    #[allow(clippy::option_option)]
+    fn entry_point(&self, name: &str) -> Option<Option<FuncIdx>> {
+        let Some(export) = self.export(name) else {
+            return Some(None);
+        };
+        let ExportDescriptor::Function(funcidx) = export.descriptor else {
+            return None;
+        };
+        match self.export_type(export.descriptor)? {
+            ExternType::Func(ty) if ty.parameters.is_empty() && ty.results.is_empty() => {
+                Some(Some(funcidx))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether an index is within the bounds of its index space, as
+    /// `module.is_valid::<FuncIdx>(idx)`.
+    pub fn is_valid<I: ModuleIndex>(&self, idx: u32) -> bool {
+        (idx as usize) < I::count(self)
+    }
+
+    /// The types of the functions of the module, by function index.
+    pub fn function_types(&self) -> IndexSpace<'_, A, &FunctionType<A>> {
+        IndexSpace {
+            module: self,
+            len: FuncIdx::count(self),
+            descriptor: |idx| ExportDescriptor::Function(FuncIdx::new(idx)),
+            project: |ty| match ty {
+                ExternType::Func(ty) => Some(ty),
+                _ => None,
+            },
+        }
+    }
+
+    /// The types of the tables of the module, by table index. (See
+    /// [`TableType::effective_max_elements`] for how far each may grow.)
+    pub fn table_types(&self) -> IndexSpace<'_, A, TableType> {
+        IndexSpace {
+            module: self,
+            len: TableIdx::count(self),
+            descriptor: |idx| ExportDescriptor::Table(TableIdx::new(idx)),
+            project: |ty| match ty {
+                ExternType::Table(ty) => Some(ty),
+                _ => None,
+            },
+        }
+    }
+
+    /// The types of the memories of the module, by memory index. (See
+    /// [`MemType::effective_initial_bytes`] and
+    /// [`MemType::effective_max_bytes`] for their sizes in bytes.)
+    pub fn memory_types(&self) -> IndexSpace<'_, A, MemType> {
+        IndexSpace {
+            module: self,
+            len: MemIdx::count(self),
+            descriptor: |idx| ExportDescriptor::Memory(MemIdx::new(idx)),
+            project: |ty| match ty {
+                ExternType::Mem(ty) => Some(ty),
+                _ => None,
+            },
+        }
+    }
+
+    /// The types of the globals of the module, by global index.
+    pub fn global_types(&self) -> IndexSpace<'_, A, GlobalType> {
+        IndexSpace {
+            module: self,
+            len: GlobalIdx::count(self),
+            descriptor: |idx| ExportDescriptor::Global(GlobalIdx::new(idx)),
+            project: |ty| match ty {
+                ExternType::Global(ty) => Some(ty),
+                _ => None,
+            },
+        }
+    }
+
+    /// Iterates over the imports of the module in the order in which they
+    /// were declared, which is that in which import values are supplied on
+    /// instantiation.
+    ///
+    /// If [`Module::importsec`] has been replaced since decoding, the imports
+    /// are taken to be in binary order as they stand.
+    pub fn imports_in_binary_order(&self) -> impl Iterator<Item = &Import<A>> + '_ {
+        let order = self.import_order();
+        (0..self.importsec.len()).filter_map(move |idx| {
+            let idx = order.get(idx).map_or(idx, |&pos| pos as usize);
+            self.importsec.get(idx)
+        })
+    }
+
+    /// The position within [`Module::importsec`] of each import, in the order
+    /// in which they were declared; empty if the two orders agree, or if
+    /// `importsec` has been replaced since decoding.
+    pub fn import_order(&self) -> &[u32] {
+        if self.import_order.len() == self.importsec.len() {
+            &self.import_order
+        } else {
+            &[]
+        }
+    }
+
+    /// Returns the imports of the module, grouped by kind.
+    pub fn imports_by_kind(&self) -> ImportsByKind<'_, A> {
+        ImportsByKind {
+            functions: self.imports_of_kind(ExternKind::Function),
+            tables: self.imports_of_kind(ExternKind::Table),
+            memories: self.imports_of_kind(ExternKind::Memory),
+            globals: self.imports_of_kind(ExternKind::Global),
+        }
+    }
+
+    /// Iterates over the imports of the module, as (module, name, type)
+    /// triples.
+    ///
+    /// Imports are grouped by kind (functions, tables, memories, then globals)
+    /// rather than in declaration order, per the reordering done on decode.
+    /// Function imports referring to out-of-bounds type indices, which can
+    /// only occur in modules that fail validation, are skipped.
+    pub fn import_types(&self) -> impl Iterator<Item = (&str, &str, ExternType<'_, A>)> + '_ {
+        self.importsec.iter().filter_map(|import| {
+            let ty = self.import_type(import)?;
+            Some((&**import.module, &**import.field, ty))
+        })
+    }
+
+    /// Iterates over the exports of the module, as (name, type) pairs.
+    ///
+    /// Exports are ordered by name, per the reordering done on decode.
+    /// Exports referring to out-of-bounds indices, which can only occur in
+    /// modules that fail validation, are skipped.
+    pub fn export_types(&self) -> impl Iterator<Item = (&str, ExternType<'_, A>)> + '_ {
+        self.exportsec.iter().filter_map(|export| {
+            let ty = self.export_type(export.descriptor)?;
+            Some((&**export.field, ty))
+        })
+    }
+
+    pub(crate) fn export_type(&self, descriptor: ExportDescriptor) -> Option<ExternType<'_, A>> {
+        let imported = self.imports_of_kind(descriptor.kind());
+        let idx = descriptor.index() as usize;
+        if let Some(import) = imported.get(idx) {
+            return self.import_type(import);
+        }
+        let idx = idx - imported.len();
+        let ty = match descriptor {
+            ExportDescriptor::Function(_) => {
+                let typeidx = self.funcsec.get(idx)?;
+                ExternType::Func(self.typesec.get(**typeidx as usize)?)
+            }
+            ExportDescriptor::Table(_) => ExternType::Table(*self.tablesec.get(idx)?),
+            ExportDescriptor::Memory(_) => ExternType::Mem(*self.memsec.get(idx)?),
+            ExportDescriptor::Global(_) => ExternType::Global(self.globalsec.get(idx)?.ty),
+        };
+        Some(ty)
+    }
+
+    pub(crate) fn import_type(&self, import: &Import<A>) -> Option<ExternType<'_, A>> {
+        let ty = match import.descriptor {
+            ImportDescriptor::Function(typeidx) => {
+                ExternType::Func(self.typesec.get(*typeidx as usize)?)
+            }
+            ImportDescriptor::Table(ty) => ExternType::Table(ty),
+            ImportDescriptor::Memory(ty) => ExternType::Mem(ty),
+            ImportDescriptor::Global(ty) => ExternType::Global(ty),
+        };
+        Some(ty)
+    }
+
+    // Returns the imports of a given kind, which are contiguous and precede
+    // module-defined entities of that kind in its index space.
+    pub(crate) fn imports_of_kind(&self, kind: ExternKind) -> &[Import<A>] {
+        let start = self
+            .importsec
+            .partition_point(|import| import.descriptor.kind() < kind);
+        let end = self
+            .importsec
+            .partition_point(|import| import.descriptor.kind() <= kind);
+        &self.importsec[start..end]
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+    use crate::types::{RefType, ValType};
+
+    #[test]
+    fn imports_and_exports() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x0e, 0x02, // importsec
+            0x01, b'm', 0x01, b'g', 0x03, 0x7f, 0x00, // (global i32)
+            0x01, b'm', 0x01, b'f', 0x00, 0x00, // (func (type 0))
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x04, 0x04, 0x01, 0x70, 0x00, 0x01, // tablesec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x07, 0x15, 0x05, // exportsec
+            0x01, b'e', 0x00, 0x05, // (func 5)
+            0x01, b'a', 0x00, 0x01, // (func 1)
+            0x01, b'b', 0x01, 0x00, // (table 0)
+            0x01, b'c', 0x02, 0x00, // (memory 0)
+            0x01, b'd', 0x03, 0x00, // (global 0)
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+
+        let imports: std::vec::Vec<_> = module.import_types().collect();
+        assert_eq!(imports.len(), 2);
+        assert!(matches!(
+            imports[0],
+            ("m", "f", ExternType::Func(ty)) if ty.parameters.is_empty()
+        ));
+        assert!(matches!(
+            imports[1],
+            (
+                "m",
+                "g",
+                ExternType::Global(GlobalType {
+                    value: ValType::I32,
+                    ..
+                })
+            )
+        ));
+
+        let fields: std::vec::Vec<_> = module
+            .imports_in_binary_order()
+            .map(|import| &**import.field)
+            .collect();
+        assert_eq!(fields, ["g", "f"]);
+        assert_eq!(module.import_order(), [1, 0]);
+
+        // Imports replaced since decoding are taken to be in binary order.
+        let mut replaced =
+            Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        replaced.importsec.0.pop();
+        assert!(replaced.import_order().is_empty());
+        let fields: std::vec::Vec<_> = replaced
+            .imports_in_binary_order()
+            .map(|import| &**import.field)
+            .collect();
+        assert_eq!(fields, ["f"]);
+
+        let by_kind = module.imports_by_kind();
+        assert_eq!(by_kind.functions.len(), 1);
+        assert_eq!(&**by_kind.functions[0].field, "f");
+        assert!(by_kind.tables.is_empty());
+        assert!(by_kind.memories.is_empty());
+        assert_eq!(by_kind.globals.len(), 1);
+        assert_eq!(&**by_kind.globals[0].field, "g");
+
+        let funcs = module.function_types();
+        assert_eq!(funcs.len(), 2);
+        assert!(funcs.iter().all(|(_, ty)| ty.parameters.is_empty()));
+        assert!(funcs.get(2).is_none());
+        assert_eq!(module.table_types().len(), 1);
+        assert_eq!(module.memory_types().get(0).map(|ty| ty.min), Some(1));
+        let globals = module.global_types();
+        assert_eq!(globals.len(), 1);
+        assert!(matches!(
+            globals.get(0),
+            Some(GlobalType {
+                value: ValType::I32,
+                ..
+            })
+        ));
+
+        assert!(module.is_valid::<FuncIdx>(1));
+        assert!(!module.is_valid::<FuncIdx>(2));
+        assert!(module.is_valid::<GlobalIdx>(0));
+        assert!(!module.is_valid::<DataIdx>(0));
+        assert_eq!(MemIdx::checked_new(&module, 0), Some(MemIdx::new(0)));
+        assert_eq!(TableIdx::checked_new(&module, 1), None);
+        assert_eq!(TypeIdx::count(&module), 1);
+
+        // No memory is exported as "memory".
+        assert_eq!(module.entry_kind(), None);
+
+        let exports: std::vec::Vec<_> = module.export_types().collect();
+        assert_eq!(exports.len(), 4);
+        assert!(matches!(exports[0], ("a", ExternType::Func(ty)) if ty.results.is_empty()));
+        assert!(matches!(
+            exports[1],
+            (
+                "b",
+                ExternType::Table(TableType {
+                    reftype: RefType::Func,
+                    ..
+                })
+            )
+        ));
+        assert!(matches!(exports[2], ("c", ExternType::Mem(ty)) if ty.min == 1));
+        assert!(matches!(
+            exports[3],
+            (
+                "d",
+                ExternType::Global(GlobalType {
+                    value: ValType::I32,
+                    ..
+                })
+            )
+        ));
+    }
+
+    #[test]
+    fn index_space_iteration() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x02, 0x07, 0x01, // importsec
+            0x01, b'm', 0x01, b'f', 0x00, 0x05, // (func (type 5))
+            0x03, 0x02, 0x01, 0x00, // funcsec
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+
+        // The import's type does not resolve, so only the defined function is
+        // yielded, under its own index.
+        let funcs: std::vec::Vec<_> = module.function_types().iter().collect();
+        assert_eq!(funcs.len(), 1);
+        assert!(matches!(funcs[0], (1, ty) if ty.results[..] == [ValType::I32]));
+    }
+
+    // A module defining a memory and two functions, of types [] -> [] and
+    // [i32] -> [], with the given (name, descriptor) exports.
+    fn entry_kind(exports: &[(&str, [u8; 2])]) -> Option<EntryKind> {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x08, 0x02, // typesec
+            0x60, 0x00, 0x00, // [] -> []
+            0x60, 0x01, 0x7f, 0x00, // [i32] -> []
+            0x03, 0x03, 0x02, 0x00, 0x01, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+        ];
+        let mut exportsec = std::vec![u8::try_from(exports.len()).unwrap()];
+        for (name, descriptor) in exports {
+            exportsec.push(u8::try_from(name.len()).unwrap());
+            exportsec.extend_from_slice(name.as_bytes());
+            exportsec.extend_from_slice(descriptor);
+        }
+        bytes.extend([0x07, u8::try_from(exportsec.len()).unwrap()]);
+        bytes.extend(exportsec);
+        bytes.extend([
+            0x0a, 0x07, 0x02, // codesec
+            0x02, 0x00, 0x0b, // (func)
+            0x02, 0x00, 0x0b, // (func)
+        ]);
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+            .unwrap()
+            .entry_kind()
+    }
+
+    #[test]
+    fn entry_kinds() {
+        const MEMORY: (&str, [u8; 2]) = ("memory", [0x02, 0x00]);
+
+        assert_eq!(
+            entry_kind(&[MEMORY, ("_start", [0x00, 0x00])]),
+            Some(EntryKind::Command(FuncIdx::new(0)))
+        );
+        assert_eq!(entry_kind(&[MEMORY]), Some(EntryKind::Reactor(None)));
+        assert_eq!(
+            entry_kind(&[("_initialize", [0x00, 0x00]), MEMORY]),
+            Some(EntryKind::Reactor(Some(FuncIdx::new(0))))
+        );
+
+        // No memory export.
+        assert_eq!(entry_kind(&[("_start", [0x00, 0x00])]), None);
+        // Both entry points.
+        assert_eq!(
+            entry_kind(&[
+                MEMORY,
+                ("_start", [0x00, 0x00]),
+                ("_initialize", [0x00, 0x00])
+            ]),
+            None
+        );
+        // An entry point of the wrong type.
+        assert_eq!(entry_kind(&[MEMORY, ("_start", [0x00, 0x01])]), None);
+        // An entry point that is not a function.
+        assert_eq!(entry_kind(&[MEMORY, ("_start", [0x02, 0x00])]), None);
+    }
+
+    #[test]
+    fn memories_and_tables() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x02, 0x13, 0x02, // importsec
+            0x01, b'm', 0x03, b'm', b'e', b'm', 0x02, 0x01, 0x01, 0x02, // (memory 1 2)
+            0x01, b'm', 0x01, b't', 0x01, 0x70, 0x00, 0x03, // (table 3 funcref)
+            0x04, 0x05, 0x01, 0x6f, 0x01, 0x01, 0x05, // tablesec
+            0x05, 0x03, 0x01, 0x00, 0x00, // memsec
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+
+        let memories: std::vec::Vec<_> = module.memory_types().iter().collect();
+        assert_eq!(memories.len(), 2);
+        let (idx, ty) = memories[0];
+        assert_eq!(idx, 0);
+        assert_eq!(&**module.imports_by_kind().memories[0].field, "mem");
+        assert_eq!(ty.min_size_bytes(), 0x1_0000);
+        assert_eq!(ty.max_size_bytes(), Some(0x2_0000));
+        let (idx, ty) = memories[1];
+        assert_eq!(idx, 1);
+        assert_eq!(ty.min_size_bytes(), 0);
+        assert_eq!(ty.max_size_bytes(), None);
+        assert_eq!(ty.effective_initial_bytes(), 0);
+        assert_eq!(ty.effective_max_pages(), MemType::MAX_PAGES);
+        assert_eq!(ty.effective_max_bytes(), 0x1_0000_0000);
+
+        let tables: std::vec::Vec<_> = module.table_types().iter().collect();
+        assert_eq!(tables.len(), 2);
+        let (idx, ty) = tables[0];
+        assert_eq!(idx, 0);
+        assert_eq!(ty.reftype, RefType::Func);
+        assert_eq!((ty.limits.min, ty.limits.max), (3, None));
+        assert_eq!(ty.effective_max_elements(), u32::MAX);
+        let (idx, ty) = tables[1];
+        assert_eq!(idx, 1);
+        assert_eq!(ty.reftype, RefType::Extern);
+        assert_eq!((ty.limits.min, ty.limits.max), (1, Some(5)));
+        assert_eq!(ty.effective_max_elements(), 5);
+    }
+
+    #[cfg(feature = "validate")]
+    #[test]
+    fn export_index() {
+        const EXPORTS: u8 = 100;
+
+        let mut exportsec = std::vec![EXPORTS];
+        for i in 0..EXPORTS {
+            // (export "<i>" (func 0))
+            exportsec.extend([0x02, b'a' + i / 10, b'0' + i % 10, 0x00, 0x00]);
+        }
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x07, 0xf5, 0x03, // exportsec
+        ];
+        assert_eq!(exportsec.len(), 0x1f5);
+        bytes.extend(exportsec);
+        bytes.extend([0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b]); // codesec
+        let module = crate::decode_and_validate(bytes).unwrap();
+
+        let index = module.export_index();
+        for export in module.exportsec.iter() {
+            assert!(core::ptr::eq(index.find(&export.field).unwrap(), export));
+        }
+        assert!(matches!(
+            index.get("j9"),
+            Some(ExportDescriptor::Function(idx)) if *idx == 0
+        ));
+        assert!(index.get("k0").is_none());
+        assert!(index.get("").is_none());
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Conversions to and from the equivalent types of the `wasmparser` crate.
+//!
+//! Conversions into `wasmparser` types are infallible. Conversions in the other
+//! direction fail on anything beyond what this crate supports, like typed
+//! function references or 64-bit memories. The conversions of value and
+//! reference types live in `wafer-types`.
+
+use crate::Allocator;
+use crate::core_compat::vec::Vec;
+
+use super::{
+    ConversionError, FunctionType, GlobalType, GlobalTypeMutability, Limits, MemType, ResultType,
+    TableType, ValType,
+};
+
+impl<A: Allocator> From<&FunctionType<A>> for wasmparser::FuncType {
+    fn from(value: &FunctionType<A>) -> Self {
+        Self::new(
+            value.parameters.iter().copied().map(Into::into),
+            value.results.iter().copied().map(Into::into),
+        )
+    }
+}
+
+impl<A: Allocator> FunctionType<A> {
+    /// Converts from a `wasmparser` function type, with a given allocator.
+    pub fn try_from_wasmparser(
+        value: &wasmparser::FuncType,
+        alloc: A,
+    ) -> Result<Self, ConversionError> {
+        let convert = |types: &[wasmparser::ValType]| {
+            let mut converted = Vec::new_in(alloc.clone());
+            converted
+                .try_reserve_exact(types.len())
+                .map_err(|_| ConversionError::AllocError)?;
+            for ty in types {
+                converted.push(ValType::try_from(*ty)?);
+            }
+            Ok::<_, ConversionError>(converted)
+        };
+        Ok(Self {
+            parameters: convert(value.params())?,
+            results: ResultType(convert(value.results())?),
+        })
+    }
+}
+
+// wasmparser's limits are 64-bit to accommodate the memory64 proposal.
+fn limits_from_wasmparser(
+    initial: u64,
+    maximum: Option<u64>,
+    shared: bool,
+) -> Result<Limits, ConversionError> {
+    let to_u32 = |value: u64| u32::try_from(value).map_err(|_| ConversionError::Unsupported);
+    Ok(Limits {
+        min: to_u32(initial)?,
+        max: maximum.map(to_u32).transpose()?,
+        shared,
+    })
+}
+
+impl From<MemType> for wasmparser::MemoryType {
+    fn from(value: MemType) -> Self {
+        Self {
+            memory64: false,
+            shared: value.shared,
+            initial: value.min.into(),
+            maximum: value.max.map(Into::into),
+            page_size_log2: None,
+        }
+    }
+}
+
+impl TryFrom<wasmparser::MemoryType> for MemType {
+    type Error = ConversionError;
+
+    fn try_from(value: wasmparser::MemoryType) -> Result<Self, Self::Error> {
+        let page_size = MemType::PAGE_SIZE.trailing_zeros();
+        if value.memory64 || value.page_size_log2.is_some_and(|log2| log2 != page_size) {
+            return Err(ConversionError::Unsupported);
+        }
+        Ok(Self(limits_from_wasmparser(
+            value.initial,
+            value.maximum,
+            value.shared,
+        )?))
+    }
+}
+
+impl From<TableType> for wasmparser::TableType {
+    fn from(value: TableType) -> Self {
+        Self {
+            element_type: value.reftype.into(),
+            table64: false,
+            initial: value.limits.min.into(),
+            maximum: value.limits.max.map(Into::into),
+            shared: false,
+        }
+    }
+}
+
+impl TryFrom<wasmparser::TableType> for TableType {
+    type Error = ConversionError;
+
+    fn try_from(value: wasmparser::TableType) -> Result<Self, Self::Error> {
+        if value.table64 || value.shared {
+            return Err(ConversionError::Unsupported);
+        }
+        Ok(Self {
+            reftype: value.element_type.try_into()?,
+            limits: limits_from_wasmparser(value.initial, value.maximum, false)?,
+        })
+    }
+}
+
+impl From<GlobalType> for wasmparser::GlobalType {
+    fn from(value: GlobalType) -> Self {
+        Self {
+            content_type: value.value.into(),
+            mutable: value.mutability == GlobalTypeMutability::Var,
+            shared: false,
+        }
+    }
+}
+
+impl TryFrom<wasmparser::GlobalType> for GlobalType {
+    type Error = ConversionError;
+
+    fn try_from(value: wasmparser::GlobalType) -> Result<Self, Self::Error> {
+        if value.shared {
+            return Err(ConversionError::Unsupported);
+        }
+        Ok(Self {
+            value: value.content_type.try_into()?,
+            mutability: if value.mutable {
+                GlobalTypeMutability::Var
+            } else {
+                GlobalTypeMutability::Const
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::types::RefType;
+
+    #[test]
+    fn round_trips() {
+        for ty in [
+            ValType::I32,
+            ValType::I64,
+            ValType::F32,
+            ValType::F64,
+            ValType::Vec,
+            ValType::FuncRef,
+            ValType::ExternRef,
+        ] {
+            let converted = wasmparser::ValType::from(ty);
+            assert_eq!(ValType::try_from(converted), Ok(ty));
+        }
+
+        let func = wasmparser::FuncType::new(
+            [wasmparser::ValType::I32, wasmparser::ValType::FUNCREF],
+            [wasmparser::ValType::F64],
+        );
+        let converted = FunctionType::try_from_wasmparser(&func, Global).unwrap();
+        assert_eq!(*converted.parameters, [ValType::I32, ValType::FuncRef]);
+        assert_eq!(**converted.results, [ValType::F64]);
+        assert_eq!(wasmparser::FuncType::from(&converted), func);
+
+        let mem = wasmparser::MemoryType {
+            memory64: false,
+            shared: true,
+            initial: 1,
+            maximum: Some(2),
+            page_size_log2: None,
+        };
+        assert_eq!(
+            wasmparser::MemoryType::from(MemType::try_from(mem).unwrap()),
+            mem
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        let nonnull = wasmparser::RefType::FUNCREF.as_non_null();
+        assert_eq!(
+            RefType::try_from(nonnull),
+            Err(ConversionError::Unsupported)
+        );
+
+        let mem = wasmparser::MemoryType {
+            memory64: false,
+            shared: false,
+            initial: 0,
+            maximum: Some(u64::from(u32::MAX) + 1),
+            page_size_log2: None,
+        };
+        assert_eq!(
+            MemType::try_from(mem).err(),
+            Some(ConversionError::Unsupported)
+        );
+    }
+}
@@ -10,14 +10,48 @@
 //! and representing WASM modules, including value types, function signatures,
 //! imports, exports, and other WASM constructs.
 
+mod blocks;
+mod expr;
+mod externs;
 mod instr;
+#[cfg(feature = "interop-wasmparser")]
+mod interop;
+mod names;
+#[cfg(feature = "serde")]
+mod serialize;
+mod visit;
+pub use blocks::{Block, BlockKind, BlockTree, Branch};
+#[cfg(feature = "experimental")]
+pub use expr::Handlers;
+pub use expr::{
+    BrTableLabels, ConstExpr, ExpressionStats, IndexRemapper, Instruction, Instructions, Operands,
+    Operator, SelectTypes,
+};
+#[cfg(feature = "validate")]
+pub use externs::ExportIndex;
+#[cfg(feature = "validate")]
+pub(crate) use externs::export_index_slots;
+#[cfg(feature = "scan")]
+pub(crate) use externs::find_duplicate_export;
+pub use externs::{EntryKind, ExternType, ImportsByKind, IndexSpace, ModuleIndex};
 pub use instr::*;
-
-use core::cmp;
+pub use names::{DisplayFunction, NameAssoc, NameSection};
+pub use visit::ExprVisitor;
+#[cfg(feature = "interop-wasmparser")]
+pub use wafer_types::ConversionError;
+#[cfg(feature = "experimental")]
+pub use wafer_types::TagIdx;
+pub use wafer_types::{
+    DataIdx, ElemIdx, FuncIdx, GlobalIdx, InvalidValue, LabelIdx, Limits, LocalIdx, MemIdx,
+    RefType, SectionId, TableIdx, TypeIdx, ValType,
+};
+
+use core::hash::{Hash, Hasher};
 
 use num_enum::TryFromPrimitive;
 
 use crate::Allocator;
+use crate::core_compat::alloc::collections::TryReserveError;
 use crate::core_compat::boxed::Box;
 use crate::core_compat::vec::Vec;
 
@@ -73,59 +107,59 @@ macro_rules! newtype {
         }
     };
 }
-pub(crate) use newtype;
 
 /// WebAssembly module version.
 #[derive(Clone, Copy, Debug, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(u32)]
 pub enum Version {
     V1 = 1,
 }
 
-newtype!(
-    /// A name (of a module, section, or field).
-    #[derive(Debug, Eq, PartialEq)]
-    pub struct Name<A: Allocator>(Box<str, A>);
-);
-
-/// The type of a reference to an object in the runtime store.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
-#[repr(u8)]
-pub enum RefType {
-    /// Function reference type.
-    Func = 0x70,
-    /// External reference type.
-    Extern = 0x6f,
+/// A name (of a module, section, or field).
+#[derive(Debug, Eq, PartialEq)]
+pub struct Name<A: Allocator> {
+    name: Box<str, A>,
+    // The original bytes, if not valid UTF-8 and decoded lossily.
+    raw: Option<Box<[u8], A>>,
 }
 
-/// Value types classify the individual values that WebAssembly code can compute
-/// with and the values that a variable accepts.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
-#[repr(u8)]
-pub enum ValType {
-    /// 32-bit signed integer.
-    I32 = 0x7f,
-    /// 64-bit signed integer.
-    I64 = 0x7e,
-    /// 32-bit floating point number.
-    F32 = 0x7d,
-    /// 64-bit floating point number.
-    F64 = 0x7c,
-    /// 128-bit SIMD vector.
-    Vec = 0x7b,
-    /// Function reference.
-    FuncRef = RefType::Func as u8,
-    /// External reference.
-    ExternRef = RefType::Extern as u8,
-}
-
-impl From<RefType> for ValType {
-    fn from(value: RefType) -> Self {
-        match value {
-            RefType::Func => Self::FuncRef,
-            RefType::Extern => Self::ExternRef,
+impl<A: Allocator> Name<A> {
+    pub fn new(value: Box<str, A>) -> Self {
+        Self {
+            name: value,
+            raw: None,
+        }
+    }
+
+    #[cfg(feature = "scan")]
+    pub(crate) fn new_lossy(value: Box<str, A>, raw: Box<[u8], A>) -> Self {
+        Self {
+            name: value,
+            raw: Some(raw),
         }
     }
+
+    /// The original bytes of the name, if they were not valid UTF-8 and the
+    /// name was decoded lossily, with invalid sequences replaced by U+FFFD (see
+    /// [`DecodeOptions::lossy_names`](crate::decode::DecodeOptions::lossy_names)).
+    pub fn raw_bytes(&self) -> Option<&[u8]> {
+        self.raw.as_deref()
+    }
+}
+
+impl<A: Allocator> ::core::ops::Deref for Name<A> {
+    type Target = Box<str, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.name
+    }
+}
+
+impl<A: Allocator> AsRef<Box<str, A>> for Name<A> {
+    fn as_ref(&self) -> &Box<str, A> {
+        &self.name
+    }
 }
 
 newtype!(
@@ -143,19 +177,58 @@ pub struct FunctionType<A: Allocator> {
     pub results: ResultType<A>,
 }
 
-/// The size range of the resizeable storage associated with memory (# of pages)
-/// and table types (# of elements).
-#[derive(Clone, Copy, Debug)]
-pub struct Limits {
-    /// Minimum size.
-    pub min: u32,
-    /// Maximum size, with None indicating that there is no upper limit.
-    pub max: Option<u32>,
+// Equality and hashing are structural, and so independent of the allocator.
+impl<A: Allocator, B: Allocator> PartialEq<ResultType<B>> for ResultType<A> {
+    fn eq(&self, other: &ResultType<B>) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl<A: Allocator> Eq for ResultType<A> {}
+
+impl<A: Allocator> Hash for ResultType<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self[..].hash(state);
+    }
+}
+
+impl<A: Allocator, B: Allocator> PartialEq<FunctionType<B>> for FunctionType<A> {
+    fn eq(&self, other: &FunctionType<B>) -> bool {
+        self.parameters[..] == other.parameters[..] && self.results == other.results
+    }
+}
+
+impl<A: Allocator> Eq for FunctionType<A> {}
+
+impl<A: Allocator> Hash for FunctionType<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.parameters[..].hash(state);
+        self.results.hash(state);
+    }
+}
+
+impl<A: Allocator> FunctionType<A> {
+    /// The number of parameters.
+    pub fn param_arity(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// The number of results, which may exceed one per the multi-value
+    /// proposal.
+    pub fn result_arity(&self) -> usize {
+        self.results.len()
+    }
+
+    /// The numbers of parameters and of results.
+    pub fn arity(&self) -> (usize, usize) {
+        (self.param_arity(), self.result_arity())
+    }
 }
 
 newtype!(
     /// A linear memory type with its size limits.
-    #[derive(Clone, Copy, Debug)]
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct MemType(Limits);
 );
 
@@ -163,6 +236,10 @@ impl MemType {
     /// The WebAssembly page size.
     pub const PAGE_SIZE: usize = 0x1_0000; // 64 KiB
 
+    /// The maximum number of pages of a memory, which spans the 32-bit
+    /// address space.
+    pub const MAX_PAGES: u32 = 0x1_0000;
+
     /// The minimum size in bytes of the linear memory region.
     pub const fn min_size_bytes(&self) -> usize {
         (self.0.min as usize) * Self::PAGE_SIZE
@@ -172,10 +249,29 @@ impl MemType {
     pub fn max_size_bytes(&self) -> Option<usize> {
         self.0.max.map(|max| (max as usize) * Self::PAGE_SIZE)
     }
+
+    /// The number of pages to which the memory may grow: its declared
+    /// maximum, or else [`MemType::MAX_PAGES`].
+    pub fn effective_max_pages(&self) -> u32 {
+        self.0.max.unwrap_or(Self::MAX_PAGES)
+    }
+
+    /// The size in bytes of the memory on instantiation. Unlike
+    /// [`MemType::min_size_bytes`], this cannot overflow on 32-bit targets.
+    pub const fn effective_initial_bytes(&self) -> u64 {
+        (self.0.min as u64) * (Self::PAGE_SIZE as u64)
+    }
+
+    /// The size in bytes to which the memory may grow, per
+    /// [`MemType::effective_max_pages`].
+    pub fn effective_max_bytes(&self) -> u64 {
+        u64::from(self.effective_max_pages()) * (Self::PAGE_SIZE as u64)
+    }
 }
 
 /// WebAssembly table type.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TableType {
     /// Type of references stored in table.
     pub reftype: RefType,
@@ -193,10 +289,17 @@ impl TableType {
     pub const fn max_elements(&self) -> Option<u32> {
         self.limits.max
     }
+
+    /// The number of elements to which the table may grow: its declared
+    /// maximum, or else `u32::MAX`.
+    pub fn effective_max_elements(&self) -> u32 {
+        self.limits.max.unwrap_or(u32::MAX)
+    }
 }
 
 /// The mutability of a global variable.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, TryFromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(u8)]
 pub enum GlobalTypeMutability {
     /// Immutable.
@@ -206,7 +309,8 @@ pub enum GlobalTypeMutability {
 }
 
 /// Represents a global variable.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GlobalType {
     /// The type of the global.
     pub value: ValType,
@@ -215,129 +319,62 @@ pub struct GlobalType {
     pub mutability: GlobalTypeMutability,
 }
 
-newtype!(
-    /// An index into the type section.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct TypeIdx(u32);
-);
-
-newtype!(
-    /// An index into the function section.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct FuncIdx(u32);
-);
-
-newtype!(
-    /// An index into the table section.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct TableIdx(u32);
-);
-
-newtype!(
-    /// An index into the memory section.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct MemIdx(u32);
-);
-
-newtype!(
-    /// An index into the global section.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct GlobalIdx(u32);
-);
+/// Represents a WebAssembly bytecode expression, but re-encoded in a way
+/// specific to the crate:
+/// * opcodes remain unchanged;
+/// * fixed-size operands are encoded in their repr(C) representations in
+///   this module, along natural alignments (padded out with zeroes); in
+///   particular, integers are encoded as little endian and not LEB128;
+/// * vector operands remain encoded as a u32 count followed by the sequence
+///   of elements, but the count and elements are encoded per the previous
+///   point;
+/// * reserved zero bytes are stripped
+///
+///  The re-encodings along natural alignments are meant to make the
+///  execution of this code more efficient. Alternatively, expressions may be
+///  packed, with operands stored unaligned and without padding, trading
+///  some speed for footprint (see
+///  [`DecodeOptions::packed_expressions`](crate::decode::DecodeOptions::packed_expressions)).
+#[derive(Clone, Debug)]
+pub struct Expression<A: Allocator> {
+    bytes: Box<[u8], A>,
+    packed: bool,
+}
 
-newtype!(
-    /// An index into the element section.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct ElemIdx(u32);
-);
+impl<A: Allocator> Expression<A> {
+    pub fn new(value: Box<[u8], A>) -> Self {
+        Self {
+            bytes: value,
+            packed: false,
+        }
+    }
 
-newtype!(
-    /// An index into the data section.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct DataIdx(u32);
-);
+    #[cfg(feature = "decode")]
+    pub(crate) fn new_packed(value: Box<[u8], A>) -> Self {
+        Self {
+            bytes: value,
+            packed: true,
+        }
+    }
 
-newtype!(
-    /// An index into a function's local variables.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct LocalIdx(u32);
-);
+    /// Whether the operands of the expression are packed, rather than
+    /// encoded along their natural alignments.
+    pub const fn is_packed(&self) -> bool {
+        self.packed
+    }
+}
 
-newtype!(
-    /// An index referencing structured control instructions inside an
-    /// instruction sequence.
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub struct LabelIdx(u32);
-);
+impl<A: Allocator> ::core::ops::Deref for Expression<A> {
+    type Target = Box<[u8], A>;
 
-newtype!(
-    /// Represents a WebAssembly bytecode expression, but re-encoded in a way
-    /// specific to the crate:
-    /// * opcodes remain unchanged;
-    /// * fixed-size operands are encoded in their repr(C) representations in
-    ///   this module, along natural alignments (padded out with zeroes); in
-    ///   particular, integers are encoded as little endian and not LEB128;
-    /// * vector operands remain encoded as a u32 count followed by the sequence
-    ///   of elements, but the count and elements are encoded per the previous
-    ///   point;
-    /// * reserved zero bytes are stripped
-    ///
-    ///  The re-encodings along natural alignments are meant to make the
-    ///  execution of this code more efficient.
-    #[derive(Clone, Debug)]
-    pub struct Expression<A: Allocator>(Box<[u8], A>);
-);
+    fn deref(&self) -> &Self::Target {
+        &self.bytes
+    }
+}
 
-/// Section identifier within a module.
-///
-/// `PartialOrd` is implemented so that, for non-custom section IDs, an ID is
-/// less than another precisely when the former has must appear in a module
-/// before the latter in a module when both are present.
-#[derive(Clone, Copy, Debug, Eq, TryFromPrimitive, PartialEq)]
-#[repr(u8)]
-pub enum SectionId {
-    /// Custom section with arbitrary data.
-    Custom = 0,
-    /// Function type declarations.
-    Type = 1,
-    /// Import declarations.
-    Import = 2,
-    /// Function type indices for module functions.
-    Function = 3,
-    /// Table declarations.
-    Table = 4,
-    /// Memory declarations.
-    Memory = 5,
-    /// Global declarations.
-    Global = 6,
-    /// Export declarations.
-    Export = 7,
-    /// Start function index.
-    Start = 8,
-    /// Element segments for table initialization.
-    Element = 9,
-    /// Function bodies.
-    Code = 10,
-    /// Data segments for memory initialization.
-    Data = 11,
-    /// Data segment count (for bulk memory operations).
-    DataCount = 12,
-}
-
-// The logical order, as documented above.
-impl PartialOrd for SectionId {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        use SectionId::{Code, Data, DataCount};
-
-        // Handle the special case where DataCount can appear before Code
-        match (self, other) {
-            // DataCount comes before Code and Data in the logical ordering
-            (DataCount, Code | Data) => Some(cmp::Ordering::Less),
-            (Code | Data, DataCount) => Some(cmp::Ordering::Greater),
-
-            // For all other cases, use the numeric ordering
-            _ => (*self as u8).partial_cmp(&(*other as u8)),
-        }
+impl<A: Allocator> AsRef<Box<[u8], A>> for Expression<A> {
+    fn as_ref(&self) -> &Box<[u8], A> {
+        &self.bytes
     }
 }
 
@@ -355,8 +392,75 @@ newtype!(
     pub struct TypeSection<A: Allocator>(Vec<FunctionType<A>, A>);
 );
 
+impl<A: Allocator> TypeSection<A> {
+    /// Maps each type index to its canonical one: that of the first type in
+    /// the section structurally equal to it.
+    ///
+    /// Two function references have the same type precisely when their type
+    /// indices map to the same canonical index, so this serves to intern
+    /// signatures (e.g., for `call_indirect` checks). Canonical indices map to
+    /// themselves.
+    pub fn dedup_map(&self) -> Result<Vec<TypeIdx, A>, TryReserveError> {
+        let alloc = self.0.allocator();
+        let mut map = Vec::new_in(alloc.clone());
+        map.try_reserve_exact(self.len())?;
+        map.extend((0..self.len() as u32).map(TypeIdx::new));
+
+        // The indices of the types sorted by hash and then by index, so that
+        // each run of equal hashes lists its types in index order.
+        let mut order: Vec<(u64, u32), A> = Vec::new_in(alloc.clone());
+        order.try_reserve_exact(self.len())?;
+        order.extend(self.iter().enumerate().map(|(idx, ty)| {
+            let mut hasher = Fnv1a::default();
+            ty.hash(&mut hasher);
+            (hasher.finish(), idx as u32)
+        }));
+        order.sort_unstable();
+
+        for run in order.chunk_by(|a, b| a.0 == b.0) {
+            for (i, &(_, idx)) in run.iter().enumerate() {
+                // Only the canonical types earlier in the run need comparing.
+                let canonical = run[..i]
+                    .iter()
+                    .map(|&(_, earlier)| earlier)
+                    .find(|&earlier| {
+                        *map[earlier as usize] == earlier
+                            && self[earlier as usize] == self[idx as usize]
+                    });
+                if let Some(canonical) = canonical {
+                    map[idx as usize] = TypeIdx::new(canonical);
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+// FNV-1a, the hash of both types and export names. As these are chosen by
+// the module itself, a randomized hasher would be of no benefit.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
 /// Import descriptor types.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ImportDescriptor {
     /// Import a function with the given type index.
     Function(TypeIdx),
@@ -368,15 +472,31 @@ pub enum ImportDescriptor {
     Global(GlobalType),
 }
 
+// The kinds of importable and exportable entities, in the order in which
+// imports are grouped after decoding (see `imports_by_kind()`). Per-kind
+// counts and tables are arrays indexed by kind.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum ExternKind {
+    Function,
+    Table,
+    Memory,
+    Global,
+}
+
+impl ExternKind {
+    // The number of kinds.
+    pub(crate) const COUNT: usize = 4;
+}
+
 impl ImportDescriptor {
     // It serves to stably reorder the imports after decoding by type for O(1)
     // look-up by funcidx/tableidx/memidx/globalidx.
-    pub(crate) const fn discriminant(&self) -> usize {
+    pub(crate) const fn kind(&self) -> ExternKind {
         match self {
-            ImportDescriptor::Function(_) => 0,
-            ImportDescriptor::Table(_) => 1,
-            ImportDescriptor::Memory(_) => 2,
-            ImportDescriptor::Global(_) => 3,
+            ImportDescriptor::Function(_) => ExternKind::Function,
+            ImportDescriptor::Table(_) => ExternKind::Table,
+            ImportDescriptor::Memory(_) => ExternKind::Memory,
+            ImportDescriptor::Global(_) => ExternKind::Global,
         }
     }
 }
@@ -432,7 +552,8 @@ newtype!(
 );
 
 /// Describes what kind of entity is being exported.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ExportDescriptor {
     /// Export a function with the given index.
     Function(FuncIdx),
@@ -444,6 +565,27 @@ pub enum ExportDescriptor {
     Global(GlobalIdx),
 }
 
+impl ExportDescriptor {
+    pub(crate) const fn kind(self) -> ExternKind {
+        match self {
+            ExportDescriptor::Function(_) => ExternKind::Function,
+            ExportDescriptor::Table(_) => ExternKind::Table,
+            ExportDescriptor::Memory(_) => ExternKind::Memory,
+            ExportDescriptor::Global(_) => ExternKind::Global,
+        }
+    }
+
+    // The index of the exported entity within its index space.
+    pub(crate) fn index(self) -> u32 {
+        match self {
+            ExportDescriptor::Function(idx) => *idx,
+            ExportDescriptor::Table(idx) => *idx,
+            ExportDescriptor::Memory(idx) => *idx,
+            ExportDescriptor::Global(idx) => *idx,
+        }
+    }
+}
+
 /// An export declaration.
 #[derive(Debug)]
 pub struct Export<A: Allocator> {
@@ -462,6 +604,7 @@ newtype!(
 newtype!(
     /// Holds the index of the start function.
     #[derive(Clone, Copy, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct StartSection(FuncIdx);
 );
 
@@ -513,6 +656,7 @@ pub enum ElementMode<A: Allocator> {
 
 /// A local variable with its type and initial value.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Local {
     /// 32-bit integer local variable.
     I32(i32),
@@ -522,9 +666,12 @@ pub enum Local {
     F32(f32),
     /// 64-bit floating point local variable.
     F64(f64),
+    /// 128-bit SIMD vector local variable.
+    Vec(u128),
     /// Function reference local variable.
     FuncRef(u32),
-    // TODO: Vec, ExternRef
+    /// External reference local variable.
+    ExternRef(u32),
 }
 
 newtype!(
@@ -551,7 +698,8 @@ newtype!(
 /// A data segment for initializing linear memory.
 #[derive(Debug)]
 pub struct DataSegment<A: Allocator> {
-    /// The initial data bytes for this segment.
+    /// The initial data bytes for this segment, which are left empty if
+    /// streamed on decoding (see `decode::DataSegmentSink`).
     pub init: Vec<u8, A>,
     /// How this data segment should be placed (active or passive).
     pub mode: DataMode<A>,
@@ -580,3 +728,74 @@ newtype!(
     #[derive(Debug)]
     pub struct DataSection<A: Allocator>(Vec<DataSegment<A>, A>);
 );
+
+/// The extent of a section whose payload was skipped on decoding (see
+/// [`DecodeOptions::skip_code`](crate::decode::DecodeOptions::skip_code)).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SkippedSection {
+    /// The number of entries, per the count leading the payload.
+    pub count: u32,
+    /// The byte length of the payload.
+    pub size: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+
+    fn function_type(parameters: &[ValType], results: &[ValType]) -> FunctionType<Global> {
+        let mut params = Vec::new_in(Global);
+        params.extend_from_slice(parameters);
+        let mut res = Vec::new_in(Global);
+        res.extend_from_slice(results);
+        FunctionType {
+            parameters: params,
+            results: ResultType(res),
+        }
+    }
+
+    #[test]
+    fn type_dedup_map() {
+        let mut types = Vec::new_in(Global);
+        types.extend([
+            function_type(&[ValType::I32], &[]),
+            function_type(&[], &[ValType::I32]),
+            function_type(&[ValType::I32], &[]),
+            function_type(&[], &[]),
+            function_type(&[], &[ValType::I32]),
+            function_type(&[ValType::I32], &[]),
+        ]);
+        assert_eq!(types[0], types[2]);
+        assert_ne!(types[0], types[1]);
+
+        let map = TypeSection(types).dedup_map().unwrap();
+        let map: std::vec::Vec<u32> = map.iter().map(|idx| **idx).collect();
+        assert_eq!(map, [0, 1, 0, 3, 1, 0]);
+
+        let empty = TypeSection(Vec::new_in(Global));
+        assert!(empty.dedup_map().unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_effective_limits() {
+        let ty = MemType(Limits {
+            min: MemType::MAX_PAGES,
+            max: Some(MemType::MAX_PAGES),
+            shared: false,
+        });
+        assert_eq!(ty.effective_initial_bytes(), 0x1_0000_0000);
+        assert_eq!(ty.effective_max_pages(), MemType::MAX_PAGES);
+        assert_eq!(ty.effective_max_bytes(), 0x1_0000_0000);
+
+        let ty = MemType(Limits {
+            min: 1,
+            max: None,
+            shared: false,
+        });
+        assert_eq!(ty.effective_initial_bytes(), 0x1_0000);
+        assert_eq!(ty.effective_max_pages(), MemType::MAX_PAGES);
+        assert_eq!(ty.effective_max_bytes(), 0x1_0000_0000);
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! The debug names of a module's entities, and their display.
+
+use core::fmt;
+
+use crate::core_compat::vec::Vec;
+use crate::{Allocator, Module};
+
+use super::{FuncIdx, Name};
+
+/// The association of a name with an index, as within the `name` section.
+#[derive(Debug)]
+pub struct NameAssoc<A: Allocator> {
+    /// The index named, within the index space of the subsection.
+    pub index: u32,
+    /// The name given.
+    pub name: Name<A>,
+}
+
+/// The debug names of a module, from its `name` custom section (see
+/// `decode::NameSectionVisitor`).
+///
+/// Attached to a module as [`Module::names`], these are used in place of
+/// indices in displaying the module's functions (see
+/// [`Module::display_function`]), as in its `Debug` output and in validation
+/// errors (see `validate::Error::display`).
+#[derive(Debug)]
+pub struct NameSection<A: Allocator> {
+    /// The name of the module itself.
+    pub module: Option<Name<A>>,
+    /// The names of functions, imported or defined, in index order.
+    pub functions: Vec<NameAssoc<A>, A>,
+}
+
+impl<A: Allocator> NameSection<A> {
+    /// Returns the name of a function, if it has one.
+    pub fn function_name(&self, idx: FuncIdx) -> Option<&str> {
+        let i = self
+            .functions
+            .binary_search_by_key(&*idx, |assoc| assoc.index)
+            .ok()?;
+        Some(&self.functions[i].name)
+    }
+}
+
+/// A function displayed by name as `$name` if it has one, and otherwise by
+/// index (see [`Module::display_function`]).
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct DisplayFunction<'a> {
+    /// The index of the function.
+    pub idx: FuncIdx,
+    /// The name of the function, if it has one.
+    pub name: Option<&'a str>,
+}
+
+impl fmt::Display for DisplayFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "${name}"),
+            None => write!(f, "{}", *self.idx),
+        }
+    }
+}
+
+impl fmt::Debug for DisplayFunction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<A: Allocator> Module<A> {
+    /// Returns a function for display, by the name given it by
+    /// [`names`](Self::names) if any.
+    pub fn display_function(&self, idx: FuncIdx) -> DisplayFunction<'_> {
+        DisplayFunction {
+            idx,
+            name: self
+                .names
+                .as_ref()
+                .and_then(|names| names.function_name(idx)),
+        }
+    }
+}
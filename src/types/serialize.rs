@@ -0,0 +1,269 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Serialization of decoded modules.
+//!
+//! Types without allocations derive their implementations. Those generic over
+//! an allocator are implemented by hand here, as serde only supports vectors
+//! and boxes in the global allocator; these are serialized as sequences (or
+//! strings), as if they were. Expressions are serialized as their sequences of
+//! instructions.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{Allocator, Module};
+
+use super::*;
+
+macro_rules! impl_serialize_as_seq {
+    ($($type:ident),* $(,)?) => {
+        $(
+            impl<A: Allocator> Serialize for $type<A> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_seq(self.iter())
+                }
+            }
+        )*
+    };
+}
+
+impl_serialize_as_seq!(
+    CodeSection,
+    DataSection,
+    ElementSection,
+    ExportSection,
+    FunctionSection,
+    GlobalSection,
+    ImportSection,
+    Locals,
+    MemorySection,
+    ResultType,
+    TableSection,
+    TypeSection,
+);
+
+impl<A: Allocator> Serialize for Name<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+impl<A: Allocator> Serialize for Expression<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.instructions())
+    }
+}
+
+impl Serialize for BrTableLabels<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "experimental")]
+impl Serialize for Handlers<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl Serialize for SelectTypes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<A: Allocator> Serialize for FunctionType<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("FunctionType", 2)?;
+        state.serialize_field("parameters", &self.parameters[..])?;
+        state.serialize_field("results", &self.results)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for Import<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Import", 3)?;
+        state.serialize_field("module", &self.module)?;
+        state.serialize_field("field", &self.field)?;
+        state.serialize_field("descriptor", &self.descriptor)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for Global<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Global", 2)?;
+        state.serialize_field("ty", &self.ty)?;
+        state.serialize_field("init", &self.init)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for Export<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Export", 2)?;
+        state.serialize_field("field", &self.field)?;
+        state.serialize_field("descriptor", &self.descriptor)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for ElementSegment<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ElementSegment", 3)?;
+        state.serialize_field("ty", &self.ty)?;
+        state.serialize_field("init", &self.init)?;
+        state.serialize_field("mode", &self.mode)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for ElementInit<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ElementInit::FunctionIndices(funcs) => serializer.serialize_newtype_variant(
+                "ElementInit",
+                0,
+                "FunctionIndices",
+                &funcs[..],
+            ),
+            ElementInit::Expressions(exprs) => {
+                serializer.serialize_newtype_variant("ElementInit", 1, "Expressions", &exprs[..])
+            }
+        }
+    }
+}
+
+impl<A: Allocator> Serialize for ElementModeActive<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ElementModeActive", 2)?;
+        state.serialize_field("table", &self.table)?;
+        state.serialize_field("offset", &self.offset)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for ElementMode<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ElementMode::Passive => serializer.serialize_unit_variant("ElementMode", 0, "Passive"),
+            ElementMode::Active(active) => {
+                serializer.serialize_newtype_variant("ElementMode", 1, "Active", active)
+            }
+            ElementMode::Declarative => {
+                serializer.serialize_unit_variant("ElementMode", 2, "Declarative")
+            }
+        }
+    }
+}
+
+impl<A: Allocator> Serialize for Function<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Function", 2)?;
+        state.serialize_field("locals", &self.locals)?;
+        state.serialize_field("code", &self.code)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for DataSegment<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("DataSegment", 2)?;
+        state.serialize_field("init", &self.init[..])?;
+        state.serialize_field("mode", &self.mode)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for DataMode<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DataMode::Passive() => serializer.serialize_unit_variant("DataMode", 0, "Passive"),
+            DataMode::Active(active) => {
+                serializer.serialize_newtype_variant("DataMode", 1, "Active", active)
+            }
+        }
+    }
+}
+
+impl<A: Allocator> Serialize for DataModeActive<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("DataModeActive", 2)?;
+        state.serialize_field("memory", &self.memory)?;
+        state.serialize_field("offset", &self.offset)?;
+        state.end()
+    }
+}
+
+impl<A: Allocator> Serialize for Module<A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Module", 16)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("typesec", &self.typesec)?;
+        state.serialize_field("importsec", &self.importsec)?;
+        state.serialize_field("import_order", &*self.import_order)?;
+        state.serialize_field("funcsec", &self.funcsec)?;
+        state.serialize_field("tablesec", &self.tablesec)?;
+        state.serialize_field("memsec", &self.memsec)?;
+        state.serialize_field("globalsec", &self.globalsec)?;
+        state.serialize_field("exportsec", &self.exportsec)?;
+        state.serialize_field("startsec", &self.startsec)?;
+        state.serialize_field("elemsec", &self.elemsec)?;
+        state.serialize_field("datacountsec", &self.datacountsec)?;
+        state.serialize_field("codesec", &self.codesec)?;
+        state.serialize_field("datasec", &self.datasec)?;
+        state.serialize_field("skipped_codesec", &self.skipped_codesec)?;
+        state.serialize_field("skipped_datasec", &self.skipped_datasec)?;
+        state.end()
+    }
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    #[test]
+    fn json() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00, 0x00, // importsec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x07, 0x05, 0x01, 0x01, b'g', 0x00, 0x01, // exportsec
+            0x0a, 0x09, 0x01, 0x07, 0x00, // codesec
+            0x02, 0x7f, 0x41, 0x2a, 0x0b, 0x0b, // (block (result i32) (i32.const 42))
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        let json = serde_json::to_value(&module).unwrap();
+        assert_eq!(
+            json["typesec"],
+            serde_json::json!([{"parameters": [], "results": ["I32"]}])
+        );
+        assert_eq!(
+            json["importsec"],
+            serde_json::json!([{"module": "m", "field": "f", "descriptor": {"Function": 0}}])
+        );
+        assert_eq!(
+            json["exportsec"],
+            serde_json::json!([{"field": "g", "descriptor": {"Function": 1}}])
+        );
+        assert_eq!(
+            json["codesec"][0]["code"],
+            serde_json::json!([
+                {"op": {"Basic": "Block"}, "operands": {"BlockType": {"Result": "I32"}}},
+                {"op": {"Basic": "I32Const"}, "operands": {"I32": 42}},
+                {"op": {"Basic": "End"}, "operands": "None"},
+                {"op": {"Basic": "End"}, "operands": "None"},
+            ])
+        );
+        assert_eq!(json["startsec"], serde_json::Value::Null);
+    }
+}
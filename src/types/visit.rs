@@ -0,0 +1,368 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Visitation of the instructions of expressions, by the entities their
+//! operands refer to.
+
+use crate::Allocator;
+
+use super::{
+    BlockType, DataIdx, ElemIdx, Expression, FuncIdx, GlobalIdx, IndexKind, Instruction,
+    InstructionClass, LabelIdx, LocalIdx, MemArg, MemIdx, Opcode, OperandKind, Operands, Operator,
+    TableIdx, TypeIdx,
+};
+#[cfg(feature = "experimental")]
+use super::{Handler, Handlers, TagIdx};
+
+/// A visitor over the instructions of an expression (see
+/// [`Expression::visit`]), with a callback for each kind of entity that
+/// immediate operands refer to.
+///
+/// For each instruction, [`visit_instruction`](Self::visit_instruction) is
+/// called first, followed by the callbacks for its operands in their order in
+/// the binary format. Every callback defaults to doing nothing, and an error
+/// returned from any ends visitation.
+pub trait ExprVisitor {
+    /// The error with which a callback may end visitation.
+    type Error;
+
+    /// Visits an instruction, ahead of its operands.
+    fn visit_instruction(&mut self, _instr: &Instruction<'_>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the block type of `block`, `loop`, or `if`.
+    fn visit_block(&mut self, _op: Opcode, _ty: BlockType) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits a branch target: that of `br` or `br_if`, each of those of
+    /// `br_table` (ending with its default), or that of a handler of the
+    /// stack-switching proposal.
+    fn visit_label(&mut self, _op: Opcode, _label: LabelIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the callee of `call`.
+    fn visit_call(&mut self, _func: FuncIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the operands of `call_indirect`.
+    fn visit_call_indirect(&mut self, _ty: TypeIdx, _table: TableIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the function referenced by `ref.func`.
+    fn visit_ref_func(&mut self, _func: FuncIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the local accessed by `local.get`, `local.set`, or `local.tee`.
+    fn visit_local(&mut self, _op: Opcode, _local: LocalIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the global accessed by `global.get` or `global.set`.
+    fn visit_global(&mut self, _op: Opcode, _global: GlobalIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the memory operands of a load, store, or atomic instruction, all
+    /// of which access memory 0.
+    fn visit_memarg(&mut self, _op: Operator, _memarg: MemArg) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits a memory accessed by an instruction without memory operands:
+    /// `memory.size`, `memory.grow`, `memory.fill`, `memory.copy` (the
+    /// destination and then the source), or `memory.init`.
+    fn visit_memory(&mut self, _op: Operator, _memory: MemIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits a table accessed by an instruction: the destination and then
+    /// the source in the case of `table.copy`.
+    fn visit_table(&mut self, _op: Operator, _table: TableIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the element segment of `table.init` or `elem.drop`.
+    fn visit_elem(&mut self, _op: Operator, _elem: ElemIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits the data segment of `memory.init` or `data.drop`.
+    fn visit_data(&mut self, _op: Operator, _data: DataIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits a type referenced by an instruction of the stack-switching
+    /// proposal.
+    #[cfg(feature = "experimental")]
+    fn visit_type(&mut self, _op: Opcode, _ty: TypeIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Visits a tag referenced by an instruction of the stack-switching
+    /// proposal.
+    #[cfg(feature = "experimental")]
+    fn visit_tag(&mut self, _op: Opcode, _tag: TagIdx) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<A: Allocator> Expression<A> {
+    /// Visits the instructions of the expression in order.
+    pub fn visit<V: ExprVisitor>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        for instr in self.instructions() {
+            visit_instruction(visitor, &instr)?;
+        }
+        Ok(())
+    }
+}
+
+// Dispatches on the operand kind of each instruction per the opcode tables
+// (see `Opcode::operand_kind()` and `Opcode::index_kind()`), so that
+// instructions are visited as they are encoded.
+fn visit_instruction<V: ExprVisitor>(
+    visitor: &mut V,
+    instr: &Instruction<'_>,
+) -> Result<(), V::Error> {
+    visitor.visit_instruction(instr)?;
+    let op = instr.op;
+    let (kind, index_kind) = match op {
+        Operator::Basic(op) => (op.operand_kind(), op.index_kind()),
+        Operator::Bulk(op) => (op.operand_kind(), op.index_kind()),
+        Operator::Atomic(op) => (op.operand_kind(), None),
+    };
+    match (kind, op, instr.operands) {
+        (OperandKind::Idx, _, Operands::Idx(idx)) => match index_kind {
+            Some(index_kind) => visit_index(visitor, op, index_kind, idx),
+            None => Ok(()),
+        },
+        (OperandKind::BlockType, Operator::Basic(op), Operands::BlockType(ty)) => {
+            visitor.visit_block(op, ty)
+        }
+        (OperandKind::BrTable, Operator::Basic(op), Operands::BrTable { labels, default }) => {
+            for label in labels.iter() {
+                visitor.visit_label(op, label)?;
+            }
+            visitor.visit_label(op, default)
+        }
+        (OperandKind::CallIndirect, _, Operands::CallIndirect(operands)) => {
+            visitor.visit_call_indirect(operands.ty, operands.table)
+        }
+        (OperandKind::MemArg, _, Operands::MemArg(memarg)) => visitor.visit_memarg(op, memarg),
+        (OperandKind::MemoryInit, _, Operands::Idx(idx)) => {
+            visitor.visit_data(op, DataIdx::new(idx))?;
+            visitor.visit_memory(op, MemIdx::new(0))
+        }
+        // The reserved bytes of memory instructions are the indices of the
+        // memories accessed, which are always 0.
+        (OperandKind::ZeroBytes(count), _, _)
+            if matches!(op.class(), Some(InstructionClass::Memory)) =>
+        {
+            for _ in 0..count {
+                visitor.visit_memory(op, MemIdx::new(0))?;
+            }
+            Ok(())
+        }
+        (OperandKind::TableCopy, _, Operands::TableCopy(operands)) => {
+            visitor.visit_table(op, operands.dst)?;
+            visitor.visit_table(op, operands.src)
+        }
+        (OperandKind::TableInit, _, Operands::TableInit(operands)) => {
+            visitor.visit_elem(op, operands.elem)?;
+            visitor.visit_table(op, operands.table)
+        }
+        #[cfg(feature = "experimental")]
+        (OperandKind::ContBind, Operator::Basic(op), Operands::ContBind(operands)) => {
+            visitor.visit_type(op, operands.src)?;
+            visitor.visit_type(op, operands.dst)
+        }
+        #[cfg(feature = "experimental")]
+        (OperandKind::Resume, Operator::Basic(op), Operands::Resume { ty, handlers }) => {
+            visitor.visit_type(op, ty)?;
+            visit_handlers(visitor, op, handlers)
+        }
+        #[cfg(feature = "experimental")]
+        (
+            OperandKind::ResumeThrow,
+            Operator::Basic(op),
+            Operands::ResumeThrow { ty, tag, handlers },
+        ) => {
+            visitor.visit_type(op, ty)?;
+            visitor.visit_tag(op, tag)?;
+            visit_handlers(visitor, op, handlers)
+        }
+        #[cfg(feature = "experimental")]
+        (OperandKind::Switch, Operator::Basic(op), Operands::Switch(operands)) => {
+            visitor.visit_type(op, operands.ty)?;
+            visitor.visit_tag(op, operands.tag)
+        }
+        _ => Ok(()),
+    }
+}
+
+// Visits an index immediate, per the index space into which it points.
+fn visit_index<V: ExprVisitor>(
+    visitor: &mut V,
+    op: Operator,
+    kind: IndexKind,
+    idx: u32,
+) -> Result<(), V::Error> {
+    match (kind, op) {
+        (IndexKind::Label, Operator::Basic(op)) => visitor.visit_label(op, LabelIdx::new(idx)),
+        (IndexKind::Function, Operator::Basic(Opcode::Call)) => {
+            visitor.visit_call(FuncIdx::new(idx))
+        }
+        (IndexKind::Function, _) => visitor.visit_ref_func(FuncIdx::new(idx)),
+        (IndexKind::Local, Operator::Basic(op)) => visitor.visit_local(op, LocalIdx::new(idx)),
+        (IndexKind::Global, Operator::Basic(op)) => visitor.visit_global(op, GlobalIdx::new(idx)),
+        (IndexKind::Table, _) => visitor.visit_table(op, TableIdx::new(idx)),
+        (IndexKind::Element, _) => visitor.visit_elem(op, ElemIdx::new(idx)),
+        (IndexKind::Data, _) => visitor.visit_data(op, DataIdx::new(idx)),
+        #[cfg(feature = "experimental")]
+        (IndexKind::Type, Operator::Basic(op)) => visitor.visit_type(op, TypeIdx::new(idx)),
+        #[cfg(feature = "experimental")]
+        (IndexKind::Tag, Operator::Basic(op)) => visitor.visit_tag(op, TagIdx::new(idx)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(feature = "experimental")]
+fn visit_handlers<V: ExprVisitor>(
+    visitor: &mut V,
+    op: Opcode,
+    handlers: Handlers<'_>,
+) -> Result<(), V::Error> {
+    for handler in handlers.iter() {
+        match handler {
+            Handler::OnLabel { tag, label } => {
+                visitor.visit_tag(op, tag)?;
+                visitor.visit_label(op, label)?;
+            }
+            Handler::OnSwitch(tag) => visitor.visit_tag(op, tag)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use core::convert::Infallible;
+
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+
+    // Records the callbacks made, as the operator and index visited.
+    #[derive(Default)]
+    struct Recorder {
+        instructions: usize,
+        visits: std::vec::Vec<(&'static str, u32)>,
+    }
+
+    impl ExprVisitor for Recorder {
+        type Error = Infallible;
+
+        fn visit_instruction(&mut self, _: &Instruction<'_>) -> Result<(), Infallible> {
+            self.instructions += 1;
+            Ok(())
+        }
+
+        fn visit_block(&mut self, _: Opcode, _: BlockType) -> Result<(), Infallible> {
+            self.visits.push(("block", 0));
+            Ok(())
+        }
+
+        fn visit_label(&mut self, _: Opcode, label: LabelIdx) -> Result<(), Infallible> {
+            self.visits.push(("label", *label));
+            Ok(())
+        }
+
+        fn visit_call(&mut self, func: FuncIdx) -> Result<(), Infallible> {
+            self.visits.push(("call", *func));
+            Ok(())
+        }
+
+        fn visit_local(&mut self, _: Opcode, local: LocalIdx) -> Result<(), Infallible> {
+            self.visits.push(("local", *local));
+            Ok(())
+        }
+
+        fn visit_global(&mut self, _: Opcode, global: GlobalIdx) -> Result<(), Infallible> {
+            self.visits.push(("global", *global));
+            Ok(())
+        }
+
+        fn visit_memarg(&mut self, _: Operator, memarg: MemArg) -> Result<(), Infallible> {
+            self.visits.push(("memarg", memarg.offset));
+            Ok(())
+        }
+
+        fn visit_memory(&mut self, _: Operator, memory: MemIdx) -> Result<(), Infallible> {
+            self.visits.push(("memory", *memory));
+            Ok(())
+        }
+
+        fn visit_data(&mut self, _: Operator, data: DataIdx) -> Result<(), Infallible> {
+            self.visits.push(("data", *data));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn visitation() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x01, 0x7f, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x06, 0x06, 0x01, 0x7f, 0x01, 0x41, 0x00, 0x0b, // globalsec
+            0x0c, 0x01, 0x01, // datacountsec
+            0x0a, 0x24, 0x01, 0x22, 0x00, // codesec
+            0x02, 0x40, // block
+            0x20, 0x00, // local.get 0
+            0x0e, 0x01, 0x00, 0x01, // br_table 0 1
+            0x0b, // end
+            0x23, 0x00, 0x28, 0x02, 0x04, // (i32.load offset=4 (global.get 0))
+            0x10, 0x00, // call 0
+            0x41, 0x00, 0x41, 0x00, 0x41, 0x00, // i32.const 0 (x3)
+            0xfc, 0x08, 0x00, 0x00, // memory.init 0
+            0x3f, 0x00, 0x1a, // (drop (memory.size))
+            0xfc, 0x09, 0x00, // data.drop 0
+            0x0b, // end
+            0x0b, 0x04, 0x01, 0x01, 0x01, 0xaa, // datasec
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        #[cfg(feature = "validate")]
+        module.validate().unwrap();
+
+        let mut recorder = Recorder::default();
+        let Ok(()) = module.codesec[0].code.visit(&mut recorder);
+        assert_eq!(recorder.instructions, 15);
+        assert_eq!(
+            recorder.visits,
+            [
+                ("block", 0),
+                ("local", 0),
+                ("label", 0),
+                ("label", 1),
+                ("global", 0),
+                ("memarg", 4),
+                ("call", 0),
+                ("data", 0),
+                ("memory", 0),
+                ("memory", 0),
+                ("data", 0),
+            ]
+        );
+    }
+}
@@ -5,23 +5,373 @@
 // https://opensource.org/licenses/MIT
 
 use crate::Allocator;
-use crate::types::{Expression, FunctionType, ValType};
+use crate::types::{
+    Expression, FuncIdx, FunctionType, GlobalIdx, GlobalTypeMutability, Instruction, MemArg,
+    Opcode, Operands, Operator, ValType,
+};
 
-use super::{Error, Validator};
+use super::{ComplexityLimit, Error, ValidateOptions, Validator};
 
 #[allow(unused)]
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum ExpressionValidationContext<'module, A: Allocator> {
-    Function(&'module FunctionType<A>),
-    Constant(ValType),
+    // The body of the function of the given index and type.
+    Function(FuncIdx, &'module FunctionType<A>),
+    // A constant expression of type `ty`, in which `global.get` may only
+    // reference immutable globals with indices below `global_bound` (i.e.,
+    // the imported ones).
+    Constant { ty: ValType, global_bound: u32 },
 }
 
-#[allow(clippy::needless_pass_by_value, clippy::unnecessary_wraps, unused)]
+#[allow(clippy::needless_pass_by_value)]
 pub(crate) fn validate_expression<A: Allocator>(
-    validator: &mut Validator<A>,
+    validator: &Validator<A>,
     expr: &Expression<A>,
     context: ExpressionValidationContext<A>,
 ) -> Result<(), Error> {
-    // TODO: implement me.
+    let mut complexity = match context {
+        ExpressionValidationContext::Function(funcidx, _) => {
+            Some(Complexity::new(&validator.options, funcidx))
+        }
+        ExpressionValidationContext::Constant { .. } => None,
+    };
+    for instr in expr.instructions() {
+        if let Some(complexity) = &mut complexity {
+            complexity.add(&instr)?;
+        }
+        match (instr.op, instr.operands) {
+            (Operator::Basic(Opcode::GlobalGet), Operands::Idx(idx)) => {
+                if let ExpressionValidationContext::Constant { global_bound, .. } = context {
+                    validate_constant_global_reference(
+                        validator,
+                        GlobalIdx::new(idx),
+                        global_bound,
+                    )?;
+                }
+            }
+            (Operator::Basic(Opcode::RefFunc), Operands::Idx(idx)) => {
+                let funcidx = FuncIdx::new(idx);
+                validator.validate(&funcidx)?;
+                if matches!(context, ExpressionValidationContext::Function(..))
+                    && !validator.is_function_declared(funcidx)
+                {
+                    return Err(Error::UndeclaredFunctionReference(funcidx));
+                }
+            }
+            (_, Operands::BlockType(blocktype)) => validator.validate(&blocktype)?,
+            (_, Operands::SelectT(types)) if types.len() != 1 => {
+                return Err(Error::InvalidResultArity(types.len()));
+            }
+            (Operator::Atomic(_), _) if !validator.options.features.threads => {
+                return Err(Error::ThreadsNotEnabled);
+            }
+            (op, Operands::MemArg(memarg)) => validate_alignment(op, memarg)?,
+            #[cfg(feature = "experimental")]
+            (
+                Operator::Basic(
+                    op @ (Opcode::ContNew
+                    | Opcode::ContBind
+                    | Opcode::Suspend
+                    | Opcode::Resume
+                    | Opcode::ResumeThrow
+                    | Opcode::Switch),
+                ),
+                _,
+            ) => return Err(Error::UnsupportedInstruction(op)),
+            _ => {}
+        }
+    }
+    // TODO: implement the rest of me.
     Ok(())
 }
+
+// The running complexity of a function body, as checked against the limits of
+// the validation options.
+struct Complexity<'a> {
+    options: &'a ValidateOptions,
+    function: FuncIdx,
+    instructions: usize,
+    depth: usize,
+}
+
+impl<'a> Complexity<'a> {
+    fn new(options: &'a ValidateOptions, function: FuncIdx) -> Self {
+        Self {
+            options,
+            function,
+            instructions: 0,
+            depth: 0,
+        }
+    }
+
+    fn add(&mut self, instr: &Instruction) -> Result<(), Error> {
+        self.instructions += 1;
+        self.check(
+            self.instructions,
+            self.options.max_function_instructions,
+            ComplexityLimit::FunctionInstructions,
+        )?;
+        match (instr.op, instr.operands) {
+            (Operator::Basic(Opcode::Block | Opcode::Loop | Opcode::If), _) => {
+                self.depth += 1;
+                self.check(
+                    self.depth,
+                    self.options.max_nesting_depth,
+                    ComplexityLimit::NestingDepth,
+                )
+            }
+            (Operator::Basic(Opcode::End), _) => {
+                self.depth = self.depth.saturating_sub(1);
+                Ok(())
+            }
+            (_, Operands::BrTable { labels, .. }) => self.check(
+                labels.len(),
+                self.options.max_br_table_targets,
+                ComplexityLimit::BrTableTargets,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    fn check(&self, value: usize, max: Option<usize>, limit: ComplexityLimit) -> Result<(), Error> {
+        if max.is_some_and(|max| value > max) {
+            return Err(Error::ComplexityLimitExceeded {
+                function: self.function,
+                limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Alignments may not exceed the natural alignment of the access, save for
+// atomic accesses, which must be naturally aligned.
+fn validate_alignment(op: Operator, memarg: MemArg) -> Result<(), Error> {
+    let valid = match op.natural_alignment() {
+        Some(natural) if matches!(op, Operator::Atomic(_)) => memarg.align == natural,
+        Some(natural) => memarg.align <= natural,
+        None => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidAlignment {
+            opcode: op,
+            align: memarg.align,
+        })
+    }
+}
+
+fn validate_constant_global_reference<A: Allocator>(
+    validator: &Validator<A>,
+    globalidx: GlobalIdx,
+    global_bound: u32,
+) -> Result<(), Error> {
+    validator.validate(&globalidx)?;
+    if *globalidx >= global_bound
+        || validator.global_type(globalidx).mutability != GlobalTypeMutability::Const
+    {
+        return Err(Error::InvalidConstantGlobalReference(globalidx));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::Module;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::NoCustomSectionVisitor;
+    use crate::types::{AtomicOpcode, BlockType, TypeIdx};
+
+    // A module importing an immutable and a mutable i32 global, and defining a
+    // third initialized by `global.get $globalidx`.
+    fn validate_global_init(globalidx: u8) -> Result<(), Error> {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x02, 0x0f, 0x02, // importsec
+            0x01, b'm', 0x01, b'a', 0x03, 0x7f, 0x00, // (global i32)
+            0x01, b'm', 0x01, b'b', 0x03, 0x7f, 0x01, // (global (mut i32))
+            0x06, 0x06, 0x01, // globalsec
+            0x7f, 0x00, 0x23, globalidx, 0x0b, // (global i32 (global.get $globalidx))
+        ];
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+            .unwrap()
+            .validate()
+    }
+
+    #[test]
+    fn constant_global_references() {
+        assert!(validate_global_init(0).is_ok());
+        assert!(matches!(
+            validate_global_init(1),
+            Err(Error::InvalidConstantGlobalReference(idx)) if *idx == 1
+        ));
+        assert!(matches!(
+            validate_global_init(2),
+            Err(Error::InvalidConstantGlobalReference(idx)) if *idx == 2
+        ));
+        assert!(matches!(
+            validate_global_init(3),
+            Err(Error::IndexOutOfBounds { index: 3, .. })
+        ));
+    }
+
+    // A module with a single function containing a block of the given s33
+    // block type.
+    fn validate_block_type(blocktype: &[u8]) -> Result<(), Error> {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+        ];
+        let code_len = u8::try_from(blocktype.len() + 4).unwrap();
+        bytes.extend([0x0a, code_len + 2, 0x01, code_len, 0x00, 0x02]);
+        bytes.extend_from_slice(blocktype);
+        bytes.extend([0x0b, 0x0b]);
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+            .unwrap()
+            .validate()
+    }
+
+    #[test]
+    fn block_type_indices() {
+        assert!(validate_block_type(&[0x40]).is_ok());
+        assert!(validate_block_type(&[0x00]).is_ok());
+        assert!(matches!(
+            validate_block_type(&[0x01]),
+            Err(Error::IndexOutOfBounds { index: 1, .. })
+        ));
+        // Type indices beyond the range of an i32.
+        assert!(matches!(
+            validate_block_type(&[0x80, 0x80, 0x80, 0x80, 0x08]),
+            Err(Error::IndexOutOfBounds {
+                index: 0x8000_0000,
+                ..
+            })
+        ));
+        assert!(matches!(
+            validate_block_type(&[0xff, 0xff, 0xff, 0xff, 0x0f]),
+            Err(Error::IndexOutOfBounds {
+                index: u32::MAX,
+                ..
+            })
+        ));
+    }
+
+    // A module with multi-value function and block types, after the spec's
+    // tests of blocks.
+    #[test]
+    fn multi_value() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x0c, 0x02, // typesec
+            0x60, 0x00, 0x02, 0x7f, 0x7e, // (func (result i32 i64))
+            0x60, 0x01, 0x7f, 0x02, 0x7f, 0x7f, // (func (param i32) (result i32 i32))
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x14, 0x01, 0x12, 0x00, // codesec
+            0x41, 0x01, // i32.const 1
+            0x02, 0x01, 0x41, 0x02, 0x0b, // (block (type 1) (i32.const 2))
+            0x03, 0x01, 0x0b, // (loop (type 1))
+            0x1a, 0x1a, // drop drop
+            0x41, 0x03, 0x42, 0x04, // (i32.const 3) (i64.const 4)
+            0x0b, // end
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        assert!(module.validate().is_ok());
+
+        let types = &module.typesec;
+        assert_eq!(types[0].arity(), (0, 2));
+        assert_eq!(types[1].arity(), (1, 2));
+        let blocktypes: std::vec::Vec<_> = module.codesec[0]
+            .code
+            .instructions()
+            .filter_map(|instr| match instr.operands {
+                Operands::BlockType(ty) => ty.arity(types),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(blocktypes, [(1, 2), (1, 2)]);
+        assert_eq!(BlockType::Empty.arity(types), Some((0, 0)));
+        assert_eq!(BlockType::Result(ValType::F32).arity(types), Some((0, 1)));
+        assert_eq!(BlockType::TypeIndex(TypeIdx::new(2)).arity(types), None);
+    }
+
+    // A module with a single function selecting between i32s with a typed
+    // `select` of the given value types.
+    fn validate_typed_select(types: &[u8]) -> Result<(), Error> {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+        ];
+        let code_len = u8::try_from(types.len() + 11).unwrap();
+        bytes.extend([0x0a, code_len + 2, 0x01, code_len, 0x00]);
+        bytes.extend([0x41, 0x01, 0x41, 0x02, 0x41, 0x00, 0x1c]);
+        bytes.push(u8::try_from(types.len()).unwrap());
+        bytes.extend_from_slice(types);
+        bytes.extend([0x1a, 0x0b]);
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+            .unwrap()
+            .validate()
+    }
+
+    #[test]
+    fn typed_select_arity() {
+        assert!(validate_typed_select(&[0x7f]).is_ok());
+        assert!(matches!(
+            validate_typed_select(&[]),
+            Err(Error::InvalidResultArity(0))
+        ));
+        assert!(matches!(
+            validate_typed_select(&[0x7f, 0x7f]),
+            Err(Error::InvalidResultArity(2))
+        ));
+    }
+
+    // A module with a memory and a single function loading an i32 with the
+    // given alignment exponent.
+    fn validate_load_alignment(align: u8) -> Result<(), Error> {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x0a, 0x0a, 0x01, 0x08, 0x00, // codesec
+            0x41, 0x00, // i32.const 0
+            0x28, align, 0x00, // i32.load align=2**align
+            0x1a, 0x0b, // drop end
+        ];
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+            .unwrap()
+            .validate()
+    }
+
+    #[test]
+    fn memarg_alignment() {
+        assert!(validate_load_alignment(0).is_ok());
+        assert!(validate_load_alignment(2).is_ok());
+        assert!(matches!(
+            validate_load_alignment(3),
+            Err(Error::InvalidAlignment {
+                opcode: Operator::Basic(Opcode::I32Load),
+                align: 3,
+            })
+        ));
+        assert!(matches!(
+            validate_load_alignment(0x20),
+            Err(Error::InvalidAlignment { align: 0x20, .. })
+        ));
+
+        let natural = |op| Operator::Atomic(op).natural_alignment();
+        assert_eq!(natural(AtomicOpcode::I64AtomicLoad32U), Some(2));
+        assert_eq!(natural(AtomicOpcode::I32AtomicRmw16XchgU), Some(1));
+        assert_eq!(natural(AtomicOpcode::I64AtomicRmwCmpxchg), Some(3));
+        assert_eq!(natural(AtomicOpcode::I64AtomicRmw32CmpxchgU), Some(2));
+    }
+}
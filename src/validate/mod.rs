@@ -7,14 +7,36 @@
 mod expr;
 mod validate_impls;
 
-use crate::types::{FuncIdx, FunctionType, ImportDescriptor, Limits, SectionId, TypeIdx};
+use core::fmt;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::core_compat::vec::Vec;
+use crate::trace::{trace_error, trace_span};
+use crate::types::{
+    DisplayFunction, ElementInit, ElementMode, ExportDescriptor, Expression, FuncIdx, FunctionType,
+    GlobalIdx, GlobalType, ImportDescriptor, Limits, Opcode, Operands, Operator, RefType,
+    SectionId, TypeIdx, ValType,
+};
 use crate::{Allocator, Module};
 
 pub(crate) use expr::{ExpressionValidationContext, validate_expression};
 
 /// Represents errors that can arise during module validation.
+///
+/// Variants may be added in any release; for matching that is stable across
+/// releases, see [`Error::kind`].
 #[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
 pub enum Error {
+    AllocError,
+    // A function body exceeds one of the complexity limits of the validation
+    // options.
+    ComplexityLimitExceeded {
+        function: FuncIdx,
+        limit: ComplexityLimit,
+    },
     DataCountMismatch {
         expected: usize,
         actual: usize,
@@ -31,31 +53,288 @@ pub enum Error {
         index: u32,
         capacity: u32,
     },
+    // The alignment of a memory access exceeds its natural alignment (or, for
+    // atomic accesses, differs from it), both given as exponents of two.
+    InvalidAlignment {
+        opcode: Operator,
+        align: u32,
+    },
+    InvalidConstantGlobalReference(GlobalIdx),
+    // An element segment initializer is not a constant of the segment's
+    // reference type.
+    InvalidElementInit(RefType),
     InvalidMemType(Limits),
+    // A typed `select` instruction lists other than exactly one value type,
+    // given as the number listed.
+    InvalidResultArity(usize),
     InvalidStartFunction(FuncIdx),
     InvalidTableLimits(Limits),
+    // More than one memory was imported or defined without the multi-memory
+    // proposal being enabled.
+    MultipleMemories,
+    // The payload of the given section was skipped on decoding (see
+    // `DecodeOptions::skip_code`), so the module cannot be validated.
+    SkippedSection(SectionId),
+    // Shared memories or atomic instructions were used without the threads
+    // proposal being enabled.
+    ThreadsNotEnabled,
+    // An instruction of an in-flight proposal, which can be decoded but whose
+    // validation is not yet supported.
+    #[cfg(feature = "experimental")]
+    UnsupportedInstruction(Opcode),
+    // A `ref.func` within a function body references a function that is not
+    // referenced elsewhere in the module (e.g., in an export or an element
+    // segment).
+    UndeclaredFunctionReference(FuncIdx),
+    // The imports are not grouped by kind, as decoding leaves them (see
+    // `Module::importsec`), and so cannot be indexed.
+    UngroupedImports,
+}
+
+/// The kind of an [`Error`], with a variant for each of its own, as for
+/// matching on errors in a way that is stable across releases.
+///
+/// Each kind has a stable code, its discriminant, which is never reassigned
+/// (see [`code`](Self::code)).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+#[repr(u16)]
+pub enum ErrorKind {
+    /// See [`Error::AllocError`].
+    AllocError = 1,
+    /// See [`Error::ComplexityLimitExceeded`].
+    ComplexityLimitExceeded = 2,
+    /// See [`Error::DataCountMismatch`].
+    DataCountMismatch = 3,
+    /// See [`Error::DuplicateExportName`].
+    DuplicateExportName = 4,
+    /// See [`Error::FunctionAndCodeSectionMismatch`].
+    FunctionAndCodeSectionMismatch = 5,
+    /// See [`Error::IndexOutOfBounds`].
+    IndexOutOfBounds = 6,
+    /// See [`Error::InvalidAlignment`].
+    InvalidAlignment = 7,
+    /// See [`Error::InvalidConstantGlobalReference`].
+    InvalidConstantGlobalReference = 8,
+    /// See [`Error::InvalidElementInit`].
+    InvalidElementInit = 9,
+    /// See [`Error::InvalidMemType`].
+    InvalidMemType = 10,
+    /// See [`Error::InvalidResultArity`].
+    InvalidResultArity = 11,
+    /// See [`Error::InvalidStartFunction`].
+    InvalidStartFunction = 12,
+    /// See [`Error::InvalidTableLimits`].
+    InvalidTableLimits = 13,
+    /// See [`Error::MultipleMemories`].
+    MultipleMemories = 14,
+    /// See [`Error::SkippedSection`].
+    SkippedSection = 18,
+    /// See [`Error::ThreadsNotEnabled`].
+    ThreadsNotEnabled = 15,
+    /// See `Error::UnsupportedInstruction`, which only arises with the
+    /// `experimental` feature.
+    UnsupportedInstruction = 16,
+    /// See [`Error::UndeclaredFunctionReference`].
+    UndeclaredFunctionReference = 17,
+    /// See [`Error::UngroupedImports`].
+    UngroupedImports = 19,
+}
+
+impl ErrorKind {
+    /// The stable code of the kind.
+    pub const fn code(self) -> u16 {
+        self as u16
+    }
+}
+
+impl Error {
+    /// Returns the kind of the error.
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Error::AllocError => ErrorKind::AllocError,
+            Error::ComplexityLimitExceeded { .. } => ErrorKind::ComplexityLimitExceeded,
+            Error::DataCountMismatch { .. } => ErrorKind::DataCountMismatch,
+            Error::DuplicateExportName { .. } => ErrorKind::DuplicateExportName,
+            Error::FunctionAndCodeSectionMismatch { .. } => {
+                ErrorKind::FunctionAndCodeSectionMismatch
+            }
+            Error::IndexOutOfBounds { .. } => ErrorKind::IndexOutOfBounds,
+            Error::InvalidAlignment { .. } => ErrorKind::InvalidAlignment,
+            Error::InvalidConstantGlobalReference(_) => ErrorKind::InvalidConstantGlobalReference,
+            Error::InvalidElementInit(_) => ErrorKind::InvalidElementInit,
+            Error::InvalidMemType(_) => ErrorKind::InvalidMemType,
+            Error::InvalidResultArity(_) => ErrorKind::InvalidResultArity,
+            Error::InvalidStartFunction(_) => ErrorKind::InvalidStartFunction,
+            Error::InvalidTableLimits(_) => ErrorKind::InvalidTableLimits,
+            Error::MultipleMemories => ErrorKind::MultipleMemories,
+            Error::SkippedSection(_) => ErrorKind::SkippedSection,
+            Error::ThreadsNotEnabled => ErrorKind::ThreadsNotEnabled,
+            #[cfg(feature = "experimental")]
+            Error::UnsupportedInstruction(_) => ErrorKind::UnsupportedInstruction,
+            Error::UndeclaredFunctionReference(_) => ErrorKind::UndeclaredFunctionReference,
+            Error::UngroupedImports => ErrorKind::UngroupedImports,
+        }
+    }
+
+    /// Displays the error as with its `Display` implementation, but with the
+    /// functions it refers to displayed by the names given them by a module
+    /// (see [`Module::display_function`]).
+    pub fn display<'a, A: Allocator>(&'a self, module: &'a Module<A>) -> impl fmt::Display + 'a {
+        DisplayError {
+            error: self,
+            module,
+        }
+    }
+
+    fn fmt_with<'a>(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        function: impl Fn(FuncIdx) -> DisplayFunction<'a>,
+    ) -> fmt::Result {
+        match self {
+            Error::AllocError => write!(f, "allocation failure"),
+            Error::ComplexityLimitExceeded {
+                function: idx,
+                limit,
+            } => {
+                write!(
+                    f,
+                    "complexity limit exceeded ({limit:?}) in {}",
+                    function(*idx)
+                )
+            }
+            Error::DataCountMismatch { expected, actual } => {
+                write!(f, "data count mismatch: expected {expected}; got {actual}")
+            }
+            Error::DuplicateExportName { exportsec_idx } => {
+                write!(f, "duplicate export name (export {exportsec_idx})")
+            }
+            Error::FunctionAndCodeSectionMismatch {
+                funcsec_size,
+                codesec_size,
+            } => write!(
+                f,
+                "function and code section sizes differ: {funcsec_size} vs. {codesec_size}"
+            ),
+            Error::IndexOutOfBounds {
+                id,
+                index,
+                capacity,
+            } => write!(f, "index out of bounds ({id:?}): {index} of {capacity}"),
+            Error::InvalidAlignment { opcode, align } => {
+                write!(f, "invalid alignment of {opcode:?}: 2^{align}")
+            }
+            Error::InvalidConstantGlobalReference(idx) => {
+                write!(
+                    f,
+                    "invalid reference to global {} in constant expression",
+                    **idx
+                )
+            }
+            Error::InvalidElementInit(ty) => write!(f, "invalid element init of type {ty:?}"),
+            Error::InvalidMemType(limits) => write!(f, "invalid memory type ({limits:?})"),
+            Error::InvalidResultArity(arity) => write!(f, "invalid result arity ({arity})"),
+            Error::InvalidStartFunction(idx) => {
+                write!(f, "invalid start function ({})", function(*idx))
+            }
+            Error::InvalidTableLimits(limits) => write!(f, "invalid table limits ({limits:?})"),
+            Error::MultipleMemories => write!(f, "multiple memories"),
+            Error::SkippedSection(id) => write!(f, "skipped section ({id:?})"),
+            Error::ThreadsNotEnabled => write!(f, "threads proposal not enabled"),
+            #[cfg(feature = "experimental")]
+            Error::UnsupportedInstruction(op) => write!(f, "unsupported instruction ({op:?})"),
+            Error::UndeclaredFunctionReference(idx) => {
+                write!(f, "undeclared function reference ({})", function(*idx))
+            }
+            Error::UngroupedImports => write!(f, "imports not grouped by kind"),
+        }
+    }
 }
 
-// Called at the end of Module::decode() to reorder the import and export
-// sections in a way convenient for validation:
-// * imports are *stably* reordered by type, since logical grouping makes for
-//   O(1) access by funcidx/tableidx/memidx/globalidx, easier determination of
-//   the number of imports by type, and easier separaton later on.
-// * exports are reordered by field name, making it easier to determine whether
-//   they are all unique.
-pub(crate) fn prepare_module_for_validation<A: Allocator>(module: &mut Module<A>) {
-    module
-        .importsec
-        .0
-        .sort_by_key(|import| import.descriptor.discriminant());
-    module
-        .exportsec
-        .0
-        .sort_by(|a, b| a.field.as_ref().cmp(b.field.as_ref()));
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with(f, |idx| DisplayFunction { idx, name: None })
+    }
+}
+
+// An error displayed with the function names of a module (see
+// `Error::display()`).
+struct DisplayError<'a, A: Allocator> {
+    error: &'a Error,
+    module: &'a Module<A>,
+}
+
+impl<A: Allocator> fmt::Display for DisplayError<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error
+            .fmt_with(f, |idx| self.module.display_function(idx))
+    }
+}
+
+/// WebAssembly proposals beyond the core specification that validation may
+/// accept, all of which are disabled by default.
+///
+/// Proposals may be added in any release, so these are constructed by
+/// enabling them on the defaults:
+///
+/// ```
+/// let mut features = wafer::validate::Features::default();
+/// features.threads = true;
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+#[non_exhaustive]
+pub struct Features {
+    /// The threads proposal: shared memories and atomic memory instructions.
+    pub threads: bool,
+    /// The multi-memory proposal: more than one memory per module.
+    pub multi_memory: bool,
+}
+
+/// Options controlling module validation.
+///
+/// Options may be added in any release, so these are constructed by updating
+/// the defaults (or by conversion from [`Features`]).
+#[derive(Clone, Copy, Debug, Default)]
+#[non_exhaustive]
+pub struct ValidateOptions {
+    /// The proposals accepted beyond the core specification.
+    pub features: Features,
+    /// The maximum number of instructions in a function body, including the
+    /// terminating `end`, beyond which validation fails with
+    /// [`Error::ComplexityLimitExceeded`]. Defaults to None, for no limit.
+    pub max_function_instructions: Option<usize>,
+    /// The maximum nesting depth of blocks, loops, and ifs within a function
+    /// body. Defaults to None, for no limit.
+    pub max_nesting_depth: Option<usize>,
+    /// The maximum number of target labels of a `br_table` instruction,
+    /// excluding the default. Defaults to None, for no limit.
+    pub max_br_table_targets: Option<usize>,
+}
+
+impl From<Features> for ValidateOptions {
+    fn from(features: Features) -> Self {
+        Self {
+            features,
+            ..Self::default()
+        }
+    }
+}
+
+/// The complexity limits of [`ValidateOptions`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ComplexityLimit {
+    /// [`ValidateOptions::max_function_instructions`].
+    FunctionInstructions,
+    /// [`ValidateOptions::max_nesting_depth`].
+    NestingDepth,
+    /// [`ValidateOptions::max_br_table_targets`].
+    BrTableTargets,
 }
 
 pub(crate) struct Validator<'module, A: Allocator> {
     module: &'module Module<A>,
+    options: ValidateOptions,
 
     // The exclusive ending index within the import section of the functions, or
     // `import_tableidx_end` if there are none.
@@ -68,12 +347,32 @@ pub(crate) struct Validator<'module, A: Allocator> {
     // The exclusive ending index within the import section of the memories, or
     // the end index of the whole section if there are none.
     import_memidx_end: usize,
+
+    // Whether function bodies are validated, as opposed to only the structure
+    // of the module (i.e., everything else).
+    function_bodies: bool,
+
+    // A bitset of the functions that `ref.func` may reference within function
+    // bodies: those referenced elsewhere in the module. Populated by
+    // declare_function_references().
+    declared_funcs: Vec<u64, A>,
 }
 
 impl<'module, A: Allocator> Validator<'module, A> {
-    fn new(module: &'module Module<A>) -> Self {
+    fn new(
+        module: &'module Module<A>,
+        options: &ValidateOptions,
+        function_bodies: bool,
+    ) -> Result<Self, Error> {
         // Recall that the import section was stably sorted by type in
-        // prepare_module_for_validation().
+        // prepare_module_for_validation(), though a module built or modified
+        // otherwise may not be.
+        if !module
+            .importsec
+            .is_sorted_by_key(|import| import.descriptor.kind())
+        {
+            return Err(Error::UngroupedImports);
+        }
         let mut import_tableidx_start = None;
         let mut import_memidx_start = None;
         let mut import_globalidx_start = None;
@@ -100,12 +399,15 @@ impl<'module, A: Allocator> Validator<'module, A> {
         let import_memidx_end = import_globalidx_start.unwrap_or(module.importsec.len());
         let import_tableidx_end = import_memidx_start.unwrap_or(import_memidx_end);
         let import_funcidx_end = import_tableidx_start.unwrap_or(import_tableidx_end);
-        Self {
+        Ok(Self {
             module,
+            options: *options,
             import_funcidx_end,
             import_tableidx_end,
             import_memidx_end,
-        }
+            function_bodies,
+            declared_funcs: Vec::new_in(module.funcsec.allocator().clone()),
+        })
     }
 
     fn data_count(&self) -> usize {
@@ -121,7 +423,11 @@ impl<'module, A: Allocator> Validator<'module, A> {
     }
 
     fn global_count(&self) -> usize {
-        self.module.globalsec.len() + (self.module.importsec.len() - self.import_memidx_end)
+        self.module.globalsec.len() + self.imported_global_count()
+    }
+
+    fn imported_global_count(&self) -> usize {
+        self.module.importsec.len() - self.import_memidx_end
     }
 
     fn memory_count(&self) -> usize {
@@ -144,6 +450,7 @@ impl<'module, A: Allocator> Validator<'module, A> {
         let idx = *funcidx as usize;
         let typeidx = if idx < self.import_funcidx_end {
             let import = &self.module.importsec[idx];
+            // The imports were checked to be grouped by kind in new().
             let ImportDescriptor::Function(typeidx) = &import.descriptor else {
                 unreachable!();
             };
@@ -156,32 +463,208 @@ impl<'module, A: Allocator> Validator<'module, A> {
         self.function_type(typeidx)
     }
 
-    fn validate<T: Validate<A>>(&mut self, value: &T) -> Result<(), Error> {
+    fn global_type(&self, globalidx: GlobalIdx) -> GlobalType {
+        let idx = *globalidx as usize;
+        let imported = self.imported_global_count();
+        if idx < imported {
+            let import = &self.module.importsec[self.import_memidx_end + idx];
+            // The imports were checked to be grouped by kind in new().
+            let ImportDescriptor::Global(ty) = &import.descriptor else {
+                unreachable!();
+            };
+            *ty
+        } else {
+            let idx = idx - imported;
+            debug_assert!(idx < self.module.globalsec.len());
+            self.module.globalsec[idx].ty
+        }
+    }
+
+    // The context for validating a constant expression of a given type, which
+    // may only reference imported globals.
+    fn constant_context(&self, ty: ValType) -> ExpressionValidationContext<'module, A> {
+        ExpressionValidationContext::Constant {
+            ty,
+            global_bound: self.imported_global_count() as u32,
+        }
+    }
+
+    // Records the functions referenced outside of function bodies, which are
+    // expected to have already been validated.
+    fn declare_function_references(&mut self) -> Result<(), Error> {
+        let words = self.function_count().div_ceil(u64::BITS as usize);
+        self.declared_funcs
+            .try_reserve_exact(words)
+            .map_err(|_| Error::AllocError)?;
+        self.declared_funcs.resize(words, 0);
+
+        let module = self.module;
+        for export in module.exportsec.iter() {
+            if let ExportDescriptor::Function(funcidx) = export.descriptor {
+                self.declare_function(funcidx);
+            }
+        }
+        for global in module.globalsec.iter() {
+            self.declare_functions_in(&global.init);
+        }
+        for segment in module.elemsec.iter() {
+            match &segment.init {
+                ElementInit::FunctionIndices(funcs) => {
+                    for funcidx in funcs {
+                        self.declare_function(*funcidx);
+                    }
+                }
+                ElementInit::Expressions(exprs) => {
+                    for expr in exprs {
+                        self.declare_functions_in(expr);
+                    }
+                }
+            }
+            if let ElementMode::Active(active) = &segment.mode {
+                self.declare_functions_in(&active.offset);
+            }
+        }
+        Ok(())
+    }
+
+    fn declare_functions_in(&mut self, expr: &Expression<A>) {
+        for instr in expr.instructions() {
+            if let (Operator::Basic(Opcode::RefFunc), Operands::Idx(idx)) =
+                (instr.op, instr.operands)
+            {
+                self.declare_function(FuncIdx::new(idx));
+            }
+        }
+    }
+
+    fn declare_function(&mut self, funcidx: FuncIdx) {
+        let idx = *funcidx as usize;
+        let bits = u64::BITS as usize;
+        if let Some(word) = self.declared_funcs.get_mut(idx / bits) {
+            *word |= 1 << (idx % bits);
+        }
+    }
+
+    fn is_function_declared(&self, funcidx: FuncIdx) -> bool {
+        let idx = *funcidx as usize;
+        let bits = u64::BITS as usize;
+        self.declared_funcs
+            .get(idx / bits)
+            .is_some_and(|word| word & (1 << (idx % bits)) != 0)
+    }
+
+    fn validate<T: Validate<A>>(&self, value: &T) -> Result<(), Error> {
         value.validate(self)
     }
 }
 
 trait Validate<A: Allocator> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error>;
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error>;
 }
 
-pub(crate) fn validate_module<A: Allocator>(module: &Module<A>) -> Result<(), Error> {
-    let mut validator = Validator::new(module);
+// Validates a module, skipping the validation of function bodies if
+// `function_bodies` is false.
+pub(crate) fn validate_module<A: Allocator>(
+    module: &Module<A>,
+    options: &ValidateOptions,
+    function_bodies: bool,
+) -> Result<(), Error> {
+    trace_span!(DEBUG, "validate", function_bodies);
+    trace_error!(
+        validate_sections(module, options, function_bodies),
+        "validation failed"
+    )
+}
 
-    // The type section is always valid.
-    validator.validate(&module.importsec)?;
-    validator.validate(&module.funcsec)?;
-    validator.validate(&module.tablesec)?;
-    validator.validate(&module.memsec)?;
-    validator.validate(&module.globalsec)?;
-    validator.validate(&module.exportsec)?;
-    if let Some(startsec) = &module.startsec {
-        validator.validate(startsec)?;
+// Does the work of validate_module(), within its span.
+fn validate_sections<A: Allocator>(
+    module: &Module<A>,
+    options: &ValidateOptions,
+    function_bodies: bool,
+) -> Result<(), Error> {
+    let mut validator = Validator::new(module, options, function_bodies)?;
+    for step in LEADING_STEPS {
+        validator.validate_step(step)?;
+    }
+    if function_bodies {
+        validator.declare_function_references()?;
     }
-    validator.validate(&module.elemsec)?;
     validator.validate(&module.codesec)?;
     validator.validate(&module.datasec)?;
 
+    validate_data_count(module)
+}
+
+// A step of validation ahead of that of the code section, each independent of
+// the others.
+#[derive(Clone, Copy)]
+enum Step {
+    SkippedSections,
+    Imports,
+    Functions,
+    Tables,
+    Memories,
+    MemoryCount,
+    Globals,
+    Exports,
+    Start,
+    Elements,
+}
+
+// The steps of validation ahead of that of the code section, in the order in
+// which they are taken (and in which their errors take precedence). The
+// type section is always valid.
+const LEADING_STEPS: [Step; 10] = [
+    Step::SkippedSections,
+    Step::Imports,
+    Step::Functions,
+    Step::Tables,
+    Step::Memories,
+    Step::MemoryCount,
+    Step::Globals,
+    Step::Exports,
+    Step::Start,
+    Step::Elements,
+];
+
+impl<A: Allocator> Validator<'_, A> {
+    fn validate_step(&self, step: Step) -> Result<(), Error> {
+        let module = self.module;
+        match step {
+            // A module with a skipped section is missing part of its
+            // contents, which would otherwise be mistaken for their absence.
+            Step::SkippedSections => {
+                if module.skipped_codesec.is_some() {
+                    return Err(Error::SkippedSection(SectionId::Code));
+                }
+                if module.skipped_datasec.is_some() {
+                    return Err(Error::SkippedSection(SectionId::Data));
+                }
+                Ok(())
+            }
+            Step::Imports => self.validate(&module.importsec),
+            Step::Functions => self.validate(&module.funcsec),
+            Step::Tables => self.validate(&module.tablesec),
+            Step::Memories => self.validate(&module.memsec),
+            Step::MemoryCount => {
+                if self.memory_count() > 1 && !self.options.features.multi_memory {
+                    return Err(Error::MultipleMemories);
+                }
+                Ok(())
+            }
+            Step::Globals => self.validate(&module.globalsec),
+            Step::Exports => self.validate(&module.exportsec),
+            Step::Start => match &module.startsec {
+                Some(startsec) => self.validate(startsec),
+                None => Ok(()),
+            },
+            Step::Elements => self.validate(&module.elemsec),
+        }
+    }
+}
+
+// Checks the data count section against the data section.
+fn validate_data_count<A: Allocator>(module: &Module<A>) -> Result<(), Error> {
     if let Some(count) = module.datacountsec
         && (count as usize) != module.datasec.len()
     {
@@ -190,6 +673,438 @@ pub(crate) fn validate_module<A: Allocator>(module: &Module<A>) -> Result<(), Er
             actual: module.datasec.len(),
         });
     }
-
     Ok(())
 }
+
+// Validates a module in full as with validate_module(), but concurrently: the
+// steps validating the sections ahead of the code section are taken
+// independently of one another, followed by the function bodies (each
+// independently) alongside the data section. All share the one validator,
+// which is read-only throughout. The error returned is the one that
+// sequential validation would return.
+#[cfg(feature = "rayon")]
+pub(crate) fn validate_module_parallel<A: Allocator + Sync>(
+    module: &Module<A>,
+    options: &ValidateOptions,
+) -> Result<(), Error> {
+    trace_span!(DEBUG, "validate", parallel = true);
+    trace_error!(
+        validate_sections_parallel(module, options),
+        "validation failed"
+    )
+}
+
+// Does the work of validate_module_parallel(), within its span.
+#[cfg(feature = "rayon")]
+fn validate_sections_parallel<A: Allocator + Sync>(
+    module: &Module<A>,
+    options: &ValidateOptions,
+) -> Result<(), Error> {
+    let mut validator = Validator::new(module, options, true)?;
+    let shared = &validator;
+    first_error(
+        LEADING_STEPS
+            .par_iter()
+            .map(|step| shared.validate_step(*step)),
+    )?;
+    validator.declare_function_references()?;
+
+    let validator = &validator;
+    let (code, data) = rayon::join(
+        || {
+            validate_impls::validate_function_count(validator, &module.codesec)?;
+            let bodies = module.funcsec.par_iter().zip(module.codesec.par_iter());
+            first_error(bodies.enumerate().map(|(index, (typeidx, function))| {
+                validate_impls::validate_function_body(validator, index, *typeidx, function)
+            }))
+        },
+        || validator.validate(&module.datasec),
+    );
+    code?;
+    data?;
+
+    validate_data_count(module)
+}
+
+// Returns the first error in the order of the results, as opposed to the first
+// to be found.
+#[cfg(feature = "rayon")]
+fn first_error(
+    results: impl IndexedParallelIterator<Item = Result<(), Error>>,
+) -> Result<(), Error> {
+    results.find_map_first(Result::err).map_or(Ok(()), Err)
+}
+
+#[cfg(all(test, feature = "decode"))]
+mod tests {
+    use super::*;
+    use crate::core_compat::alloc::Global;
+    use crate::decode::{DecodeOptions, NameSectionVisitor, NoCustomSectionVisitor};
+    use crate::types::{AtomicOpcode, DataIdx, ModuleIndex, Operator};
+
+    // A module with a memory of the given limits and a function performing an
+    // atomic compare-exchange.
+    fn threads_module(limits: &[u8]) -> Module<Global> {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+        ];
+        let memsec_len = u8::try_from(limits.len() + 1).unwrap();
+        bytes.extend([0x05, memsec_len, 0x01]);
+        bytes.extend_from_slice(limits);
+        bytes.extend([
+            0x0a, 0x11, 0x01, 0x0f, 0x00, // codesec
+            0x41, 0x00, 0x41, 0x00, 0x41, 0x01, // i32.const 0 0 1
+            0xfe, 0x48, 0x02, 0x00, // i32.atomic.rmw.cmpxchg
+            0xfe, 0x03, 0x00, // atomic.fence
+            0x0b, // end
+        ]);
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap()
+    }
+
+    #[test]
+    fn threads() {
+        let threads = Features {
+            threads: true,
+            ..Features::default()
+        };
+
+        let module = threads_module(&[0x03, 0x01, 0x01]);
+        assert!(module.memsec[0].shared);
+        let ops: std::vec::Vec<_> = module.codesec[0]
+            .code
+            .instructions()
+            .map(|instr| instr.op)
+            .collect();
+        assert_eq!(
+            ops[3..5],
+            [
+                Operator::Atomic(AtomicOpcode::I32AtomicRmwCmpxchg),
+                Operator::Atomic(AtomicOpcode::AtomicFence),
+            ]
+        );
+        assert!(matches!(module.validate(), Err(Error::ThreadsNotEnabled)));
+        assert!(module.validate_with_features(threads).is_ok());
+
+        // Shared memories require a maximum.
+        let module = threads_module(&[0x02, 0x01]);
+        assert!(matches!(
+            module.validate_with_features(threads),
+            Err(Error::InvalidMemType(_))
+        ));
+
+        // Atomic instructions require the feature, even without a shared
+        // memory.
+        let module = threads_module(&[0x01, 0x01, 0x01]);
+        assert!(matches!(module.validate(), Err(Error::ThreadsNotEnabled)));
+        assert!(module.validate_with_features(threads).is_ok());
+    }
+
+    #[test]
+    fn instruction_budget() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00, 0x00, // importsec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x07, 0x01, 0x05, 0x00, // codesec
+            0x01, 0x01, 0x01, 0x0b, // nop nop nop end
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        let features = Features::default();
+        assert!(module.validate_with_budget(features, 4).is_ok());
+        assert!(matches!(
+            module.validate_with_budget(features, 3),
+            Err(Error::ComplexityLimitExceeded {
+                function,
+                limit: ComplexityLimit::FunctionInstructions,
+            }) if *function == 1
+        ));
+    }
+
+    #[test]
+    fn skipped_sections() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x05, 0x03, 0x01, 0x00, 0x01, // memsec
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b, // codesec
+            0x0b, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0x2a, // datasec
+        ];
+        let decode = |skip_code, skip_data| {
+            let options = DecodeOptions {
+                skip_code,
+                skip_data,
+                ..DecodeOptions::default()
+            };
+            Module::decode_bytes_with_options(
+                bytes,
+                &mut NoCustomSectionVisitor {},
+                Global,
+                &options,
+            )
+            .unwrap()
+        };
+
+        assert!(decode(false, false).validate().is_ok());
+        for (module, id) in [
+            (decode(true, false), SectionId::Code),
+            (decode(false, true), SectionId::Data),
+            (decode(true, true), SectionId::Code),
+        ] {
+            for result in [module.validate(), module.validate_structure()] {
+                assert!(matches!(result, Err(Error::SkippedSection(skipped)) if skipped == id));
+            }
+            assert!(!module.semantically_eq(&module));
+        }
+
+        // The data segments still count toward the index space.
+        let module = decode(false, true);
+        assert!(module.datasec.is_empty());
+        assert_eq!(DataIdx::count(&module), 1);
+    }
+
+    #[test]
+    fn structure_only() {
+        // Function bodies are not validated...
+        let module = threads_module(&[0x01, 0x01, 0x01]);
+        assert!(matches!(module.validate(), Err(Error::ThreadsNotEnabled)));
+        assert!(module.validate_structure().is_ok());
+
+        // ...but everything else is.
+        let module = threads_module(&[0x03, 0x01, 0x01]);
+        assert!(matches!(
+            module.validate_structure(),
+            Err(Error::ThreadsNotEnabled)
+        ));
+        assert!(
+            module
+                .validate_structure_with_features(Features {
+                    threads: true,
+                    ..Features::default()
+                })
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn complexity_limits() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x02, 0x01, 0x00, // funcsec
+            0x0a, 0x11, 0x01, 0x0f, 0x00, // codesec
+            0x02, 0x40, 0x02, 0x40, // (block (block
+            0x41, 0x00, 0x0e, 0x02, 0x00, 0x01, 0x01, // (br_table 0 1 1 (i32.const 0))
+            0x0b, 0x0b, 0x0b, // ))
+        ];
+        let module = Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        let validate = |options: ValidateOptions| match module.validate_with_options(&options) {
+            Err(Error::ComplexityLimitExceeded { function, limit }) => {
+                assert_eq!(*function, 0);
+                Some(limit)
+            }
+            result => {
+                assert!(result.is_ok());
+                None
+            }
+        };
+
+        let at_limits = ValidateOptions {
+            max_function_instructions: Some(7),
+            max_nesting_depth: Some(2),
+            max_br_table_targets: Some(2),
+            ..ValidateOptions::default()
+        };
+        assert_eq!(validate(at_limits), None);
+        assert_eq!(
+            validate(ValidateOptions {
+                max_function_instructions: Some(6),
+                ..at_limits
+            }),
+            Some(ComplexityLimit::FunctionInstructions)
+        );
+        assert_eq!(
+            validate(ValidateOptions {
+                max_nesting_depth: Some(1),
+                ..at_limits
+            }),
+            Some(ComplexityLimit::NestingDepth)
+        );
+        assert_eq!(
+            validate(ValidateOptions {
+                max_br_table_targets: Some(1),
+                ..at_limits
+            }),
+            Some(ComplexityLimit::BrTableTargets)
+        );
+    }
+
+    // A module with the given import and memory sections.
+    fn validate_memories(sections: &[u8], features: Features) -> Result<(), Error> {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+        ];
+        bytes.extend_from_slice(sections);
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+            .unwrap()
+            .validate_with_features(features)
+    }
+
+    #[test]
+    fn memories() {
+        let multi_memory = Features {
+            multi_memory: true,
+            ..Features::default()
+        };
+
+        // (import "m" "m" (memory 1))
+        let import = [0x02, 0x08, 0x01, 0x01, b'm', 0x01, b'm', 0x02, 0x00, 0x01];
+        // (memory 1)
+        let memory = [0x05, 0x03, 0x01, 0x00, 0x01];
+        // (memory 1) (memory 1)
+        let memories = [0x05, 0x05, 0x02, 0x00, 0x01, 0x00, 0x01];
+
+        assert!(validate_memories(&import, Features::default()).is_ok());
+        assert!(validate_memories(&memory, Features::default()).is_ok());
+        for sections in [[&import[..], &memory[..]].concat(), memories.to_vec()] {
+            assert!(matches!(
+                validate_memories(&sections, Features::default()),
+                Err(Error::MultipleMemories)
+            ));
+            assert!(validate_memories(&sections, multi_memory).is_ok());
+        }
+
+        // (import "m" "m" (memory 2 1))
+        assert!(matches!(
+            validate_memories(
+                &[
+                    0x02, 0x09, 0x01, 0x01, b'm', 0x01, b'm', 0x02, 0x01, 0x02, 0x01
+                ],
+                Features::default()
+            ),
+            Err(Error::InvalidMemType(_))
+        ));
+
+        // (import "m" "m" (table 2 1 funcref))
+        assert!(matches!(
+            validate_memories(
+                &[
+                    0x02, 0x0a, 0x01, 0x01, b'm', 0x01, b'm', 0x01, 0x70, 0x01, 0x02, 0x01
+                ],
+                Features::default()
+            ),
+            Err(Error::InvalidTableLimits(_))
+        ));
+    }
+
+    // A module with two functions, the first of which evaluates
+    // `ref.func 1`, and with the given export or element section.
+    fn validate_function_reference(section: &[u8]) -> Result<(), Error> {
+        let mut bytes = std::vec![
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+        ];
+        bytes.extend_from_slice(section);
+        bytes.extend([
+            0x0a, 0x0a, 0x02, // codesec
+            0x05, 0x00, 0xd2, 0x01, 0x1a, 0x0b, // (drop (ref.func 1))
+            0x02, 0x00, 0x0b, // (nop)
+        ]);
+        Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global)
+            .unwrap()
+            .validate()
+    }
+
+    #[test]
+    fn function_references() {
+        assert!(matches!(
+            validate_function_reference(&[]),
+            Err(Error::UndeclaredFunctionReference(idx)) if *idx == 1
+        ));
+
+        // (export "f" (func 1))
+        assert!(validate_function_reference(&[0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x01]).is_ok());
+
+        // (elem declare func 1)
+        assert!(validate_function_reference(&[0x09, 0x05, 0x01, 0x03, 0x00, 0x01, 0x01]).is_ok());
+
+        // (elem declare funcref (ref.func 1))
+        assert!(
+            validate_function_reference(&[0x09, 0x07, 0x01, 0x07, 0x70, 0x01, 0xd2, 0x01, 0x0b])
+                .is_ok()
+        );
+
+        // (elem funcref (ref.null extern))
+        assert!(matches!(
+            validate_function_reference(&[0x09, 0x07, 0x01, 0x05, 0x70, 0x01, 0xd0, 0x6f, 0x0b]),
+            Err(Error::InvalidElementInit(RefType::Func))
+        ));
+
+        // (elem funcref (i32.const 0))
+        assert!(matches!(
+            validate_function_reference(&[0x09, 0x07, 0x01, 0x05, 0x70, 0x01, 0x41, 0x00, 0x0b]),
+            Err(Error::InvalidElementInit(RefType::Func))
+        ));
+    }
+
+    #[test]
+    fn ungrouped_imports() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x02, 0x0e, 0x02, // importsec
+            0x01, b'm', 0x01, b'f', 0x00, 0x00, // (import "m" "f" (func (type 0)))
+            0x01, b'm', 0x01, b'g', 0x03, 0x7f, 0x00, // (import "m" "g" (global i32))
+        ];
+        let mut module =
+            Module::decode_bytes(bytes, &mut NoCustomSectionVisitor {}, Global).unwrap();
+        assert!(module.validate().is_ok());
+
+        // Globals must follow functions.
+        module.importsec.0.reverse();
+        assert!(matches!(module.validate(), Err(Error::UngroupedImports)));
+    }
+
+    #[test]
+    fn error_display() {
+        let bytes = [
+            0x00, 0x61, 0x73, 0x6d, // magic
+            0x01, 0x00, 0x00, 0x00, // version
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // typesec
+            0x03, 0x03, 0x02, 0x00, 0x00, // funcsec
+            0x0a, 0x0a, 0x02, // codesec
+            0x05, 0x00, 0xd2, 0x01, 0x1a, 0x0b, // (drop (ref.func 1))
+            0x02, 0x00, 0x0b, // (nop)
+            0x00, 0x0f, // customsec
+            0x04, b'n', b'a', b'm', b'e', // name
+            0x01, 0x08, 0x01, 0x01, 0x05, b'i', b'n', b'n', b'e', b'r', // (1, "inner")
+        ];
+        let mut visitor = NameSectionVisitor::new(Global);
+        let mut module = Module::decode_bytes(bytes, &mut visitor, Global).unwrap();
+        let err = module.validate().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UndeclaredFunctionReference);
+        assert_eq!(err.kind().code(), 17);
+        assert_eq!(std::format!("{err}"), "undeclared function reference (1)");
+        assert_eq!(
+            std::format!("{}", err.display(&module)),
+            "undeclared function reference (1)"
+        );
+
+        module.names = visitor.finish().transpose().unwrap();
+        assert_eq!(
+            std::format!("{}", err.display(&module)),
+            "undeclared function reference ($inner)"
+        );
+    }
+}
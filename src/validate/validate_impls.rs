@@ -8,19 +8,22 @@ use core::ops::Deref as _;
 
 use crate::Allocator;
 use crate::core_compat::vec::Vec;
+use crate::trace::trace_span;
 use crate::types::*;
 
 use super::{Error, ExpressionValidationContext, Validate, Validator, validate_expression};
 
+// The validator keeps its own counts of the index spaces, which are cheaper
+// to consult than ModuleIndex::count().
 macro_rules! impl_validate_for_idx {
-    ($idx_type:ty, $id:path, $count_method:ident) => {
+    ($idx_type:ty, $count_method:ident) => {
         impl<A: Allocator> Validate<A> for $idx_type {
-            fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+            fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
                 let index: u32 = **self;
                 let capacity = validator.$count_method() as u32;
                 if index >= capacity {
                     Err(Error::IndexOutOfBounds {
-                        id: $id,
+                        id: <$idx_type as ModuleIndex>::SECTION,
                         index,
                         capacity,
                     })
@@ -35,14 +38,14 @@ macro_rules! impl_validate_for_idx {
 macro_rules! impl_validate_for_newtype {
     ($type:ident<A>) => {
         impl<A: Allocator> Validate<A> for $type<A> {
-            fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+            fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
                 validator.validate(self.deref())
             }
         }
     };
     ($type:ty) => {
         impl<A: Allocator> Validate<A> for $type {
-            fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+            fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
                 validator.validate(self.deref())
             }
         }
@@ -50,7 +53,7 @@ macro_rules! impl_validate_for_newtype {
 }
 
 impl<T: Validate<A>, A: Allocator> Validate<A> for Vec<T, A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         for elem in self {
             validator.validate(elem)?;
         }
@@ -58,13 +61,13 @@ impl<T: Validate<A>, A: Allocator> Validate<A> for Vec<T, A> {
     }
 }
 
-impl_validate_for_idx!(DataIdx, SectionId::Data, data_count);
-impl_validate_for_idx!(ElemIdx, SectionId::Element, element_count);
-impl_validate_for_idx!(FuncIdx, SectionId::Function, function_count);
-impl_validate_for_idx!(GlobalIdx, SectionId::Global, global_count);
-impl_validate_for_idx!(MemIdx, SectionId::Memory, memory_count);
-impl_validate_for_idx!(TableIdx, SectionId::Table, table_count);
-impl_validate_for_idx!(TypeIdx, SectionId::Type, type_count);
+impl_validate_for_idx!(DataIdx, data_count);
+impl_validate_for_idx!(ElemIdx, element_count);
+impl_validate_for_idx!(FuncIdx, function_count);
+impl_validate_for_idx!(GlobalIdx, global_count);
+impl_validate_for_idx!(MemIdx, memory_count);
+impl_validate_for_idx!(TableIdx, table_count);
+impl_validate_for_idx!(TypeIdx, type_count);
 
 impl_validate_for_newtype!(DataSection<A>);
 impl_validate_for_newtype!(ElementSection<A>);
@@ -75,7 +78,7 @@ impl_validate_for_newtype!(MemorySection<A>);
 impl_validate_for_newtype!(TableSection<A>);
 
 impl<A: Allocator> Validate<A> for BlockType {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         if let Self::TypeIndex(idx) = self {
             validator.validate(idx)
         } else {
@@ -85,70 +88,98 @@ impl<A: Allocator> Validate<A> for BlockType {
 }
 
 impl<A: Allocator> Validate<A> for CodeSection<A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
-        let funcsec = &validator.module.funcsec;
-        if funcsec.len() != self.len() {
-            return Err(Error::FunctionAndCodeSectionMismatch {
-                funcsec_size: funcsec.len() as u32,
-                codesec_size: self.len() as u32,
-            });
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
+        validate_function_count(validator, self)?;
+        if !validator.function_bodies {
+            return Ok(());
         }
 
-        for (typeidx, function) in funcsec.iter().copied().zip(self.iter()) {
-            let func_type = validator.function_type(typeidx);
-            validate_expression(
-                validator,
-                &function.code,
-                ExpressionValidationContext::Function(func_type),
-            )?;
+        let funcsec = &validator.module.funcsec;
+        for (index, (typeidx, function)) in funcsec.iter().copied().zip(self.iter()).enumerate() {
+            validate_function_body(validator, index, typeidx, function)?;
         }
         Ok(())
     }
 }
 
+// Checks that there is a body for each function of the function section.
+pub(super) fn validate_function_count<A: Allocator>(
+    validator: &Validator<A>,
+    codesec: &CodeSection<A>,
+) -> Result<(), Error> {
+    let funcsec = &validator.module.funcsec;
+    if funcsec.len() != codesec.len() {
+        return Err(Error::FunctionAndCodeSectionMismatch {
+            funcsec_size: funcsec.len() as u32,
+            codesec_size: codesec.len() as u32,
+        });
+    }
+    Ok(())
+}
+
+// Validates the body of the function of the given index within the code
+// section.
+pub(super) fn validate_function_body<A: Allocator>(
+    validator: &Validator<A>,
+    index: usize,
+    typeidx: TypeIdx,
+    function: &Function<A>,
+) -> Result<(), Error> {
+    trace_span!(TRACE, "function", index);
+    let funcidx = FuncIdx::new((validator.import_funcidx_end + index) as u32);
+    let func_type = validator.function_type(typeidx);
+    validate_expression(
+        validator,
+        &function.code,
+        ExpressionValidationContext::Function(funcidx, func_type),
+    )
+}
+
 impl<A: Allocator> Validate<A> for DataSegment<A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         let DataMode::Active(active) = &self.mode else {
             return Ok(());
         };
         validator.validate(&active.memory)?;
-        validate_expression(
-            validator,
-            &active.offset,
-            ExpressionValidationContext::Constant(ValType::I32),
-        )
+        let context = validator.constant_context(ValType::I32);
+        validate_expression(validator, &active.offset, context)
     }
 }
 
 impl<A: Allocator> Validate<A> for ElementSegment<A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         match &self.init {
             ElementInit::FunctionIndices(funcs) => validator.validate(funcs),
             ElementInit::Expressions(exprs) => {
                 for expr in exprs {
-                    validate_expression(
-                        validator,
-                        expr,
-                        ExpressionValidationContext::Constant(self.ty.into()),
-                    )?;
+                    let context = validator.constant_context(self.ty.into());
+                    validate_expression(validator, expr, context)?;
+                    let ty = match expr.as_const() {
+                        Some(ConstExpr::RefNull(ty)) => Some(ty.into()),
+                        Some(ConstExpr::RefFunc(_)) => Some(ValType::FuncRef),
+                        Some(ConstExpr::GlobalGet(globalidx)) => {
+                            Some(validator.global_type(globalidx).value)
+                        }
+                        _ => None,
+                    };
+                    if ty != Some(self.ty.into()) {
+                        return Err(Error::InvalidElementInit(self.ty));
+                    }
                 }
                 Ok(())
             }
         }?;
         if let ElementMode::Active(active) = &self.mode {
             validator.validate(&active.table)?;
-            validate_expression(
-                validator,
-                &active.offset,
-                ExpressionValidationContext::Constant(ValType::I32),
-            )?;
+            let context = validator.constant_context(ValType::I32);
+            validate_expression(validator, &active.offset, context)?;
         }
         Ok(())
     }
 }
 
 impl<A: Allocator> Validate<A> for Export<A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         match &self.descriptor {
             ExportDescriptor::Function(funcidx) => validator.validate(funcidx),
             ExportDescriptor::Table(tableidx) => validator.validate(tableidx),
@@ -159,7 +190,7 @@ impl<A: Allocator> Validate<A> for Export<A> {
 }
 
 impl<A: Allocator> Validate<A> for ExportSection<A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         // Export names must be distinct. Since we ordered by name in
         // prepare_module_for_validation(), we can just iterate through with
         // pairwise comparison to determine this.
@@ -176,24 +207,15 @@ impl<A: Allocator> Validate<A> for ExportSection<A> {
     }
 }
 
-impl<A: Allocator> Validate<A> for Expression<A> {
-    fn validate(&self, _validator: &mut Validator<A>) -> Result<(), Error> {
-        todo!()
-    }
-}
-
 impl<A: Allocator> Validate<A> for Global<A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
-        validate_expression(
-            validator,
-            &self.init,
-            ExpressionValidationContext::Constant(self.ty.value),
-        )
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
+        let context = validator.constant_context(self.ty.value);
+        validate_expression(validator, &self.init, context)
     }
 }
 
 impl<A: Allocator> Validate<A> for Import<A> {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         match &self.descriptor {
             ImportDescriptor::Function(typeidx) => validator.validate(typeidx),
             ImportDescriptor::Table(table) => validator.validate(table),
@@ -204,8 +226,15 @@ impl<A: Allocator> Validate<A> for Import<A> {
 }
 
 impl<A: Allocator> Validate<A> for MemType {
-    fn validate(&self, _validator: &mut Validator<A>) -> Result<(), Error> {
-        const BOUND: u32 = (u16::MAX as u32) + 1;
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
+        const BOUND: u32 = MemType::MAX_PAGES;
+        if self.shared && !validator.options.features.threads {
+            return Err(Error::ThreadsNotEnabled);
+        }
+        // Shared memories must declare a maximum size.
+        if self.shared && self.max.is_none() {
+            return Err(Error::InvalidMemType(**self));
+        }
         let max = self.max.unwrap_or(BOUND);
         if self.min > BOUND || self.min > max || max > BOUND {
             Err(Error::InvalidMemType(**self))
@@ -216,7 +245,7 @@ impl<A: Allocator> Validate<A> for MemType {
 }
 
 impl<A: Allocator> Validate<A> for StartSection {
-    fn validate(&self, validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, validator: &Validator<A>) -> Result<(), Error> {
         let funcidx = **self;
         validator.validate(&funcidx)?;
         let func = validator.function_signature(funcidx);
@@ -228,7 +257,11 @@ impl<A: Allocator> Validate<A> for StartSection {
 }
 
 impl<A: Allocator> Validate<A> for TableType {
-    fn validate(&self, _validator: &mut Validator<A>) -> Result<(), Error> {
+    fn validate(&self, _validator: &Validator<A>) -> Result<(), Error> {
+        // Only memories may be shared.
+        if self.limits.shared {
+            return Err(Error::InvalidTableLimits(self.limits));
+        }
         if let Some(max) = self.limits.max
             && self.limits.min > max
         {
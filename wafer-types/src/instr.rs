@@ -0,0 +1,1317 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! WebAssembly instruction opcodes.
+//!
+//! Defines the opcodes for all WebAssembly instructions as specified in the
+//! WebAssembly specification section 5.4.
+
+use crate::ValType;
+
+// [wasm]: 5.4.1 Control Instructions
+//
+primitive_enum! {
+    /// WebAssembly instruction opcode.
+    ///
+    /// Opcodes of in-flight proposals are only present with the
+    /// `experimental` feature, so matches from other crates must account for
+    /// variants they do not name.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    #[non_exhaustive]
+    pub enum Opcode: u8 {
+        Unreachable = 0x00,
+        Nop = 0x01,
+        Block = 0x02,
+        Loop = 0x03,
+        If = 0x04,
+        Else = 0x05,
+        End = 0x0b,
+        Br = 0x0c,
+        BrIf = 0x0d,
+        BrTable = 0x0e,
+        Return = 0x0f,
+        Call = 0x10,
+        CallIndirect = 0x11,
+
+        // The stack-switching proposal (experimental).
+        #[cfg(feature = "experimental")]
+        ContNew = 0xe0,
+        #[cfg(feature = "experimental")]
+        ContBind = 0xe1,
+        #[cfg(feature = "experimental")]
+        Suspend = 0xe2,
+        #[cfg(feature = "experimental")]
+        Resume = 0xe3,
+        #[cfg(feature = "experimental")]
+        ResumeThrow = 0xe4,
+        #[cfg(feature = "experimental")]
+        Switch = 0xe5,
+
+        // [wasm]: 5.4.2 Reference Instructions
+        RefNull = 0xd0,
+        RefIsNull = 0xd1,
+        RefFunc = 0xd2,
+
+        // [wasm]: 5.4.3 Parametric Instructions
+        Drop = 0x1a,
+        Select = 0x1b,
+        SelectT = 0x1c,
+
+        // [wasm]: 5.4.4 Variable Instructions
+        LocalGet = 0x20,
+        LocalSet = 0x21,
+        LocalTee = 0x22,
+        GlobalGet = 0x23,
+        GlobalSet = 0x24,
+
+        // [wasm]: 5.4.5 Table Instructions
+        TableGet = 0x25,
+        TableSet = 0x26,
+
+        // [wasm]: 5.4.6 Memory Instructions
+        I32Load = 0x28,
+        I64Load = 0x29,
+        F32Load = 0x2a,
+        F64Load = 0x2b,
+        I32Load8S = 0x2c,
+        I32Load8U = 0x2d,
+        I32Load16S = 0x2e,
+        I32Load16U = 0x2f,
+        I64Load8S = 0x30,
+        I64Load8U = 0x31,
+        I64Load16S = 0x32,
+        I64Load16U = 0x33,
+        I64Load32S = 0x34,
+        I64Load32U = 0x35,
+        I32Store = 0x36,
+        I64Store = 0x37,
+        F32Store = 0x38,
+        F64Store = 0x39,
+        I32Store8 = 0x3a,
+        I32Store16 = 0x3b,
+        I64Store8 = 0x3c,
+        I64Store16 = 0x3d,
+        I64Store32 = 0x3e,
+        MemorySize = 0x3f,
+        MemoryGrow = 0x40,
+
+        // [wasm]: 5.4.7 Numeric Instructions
+        I32Const = 0x41,
+        I64Const = 0x42,
+        F32Const = 0x43,
+        F64Const = 0x44,
+        I32Eqz = 0x45,
+        I32Eq = 0x46,
+        I32Ne = 0x47,
+        I32LtS = 0x48,
+        I32LtU = 0x49,
+        I32GtS = 0x4a,
+        I32GtU = 0x4b,
+        I32LeS = 0x4c,
+        I32LeU = 0x4d,
+        I32GeS = 0x4e,
+        I32GeU = 0x4f,
+        I64Eqz = 0x50,
+        I64Eq = 0x51,
+        I64Ne = 0x52,
+        I64LtS = 0x53,
+        I64LtU = 0x54,
+        I64GtS = 0x55,
+        I64GtU = 0x56,
+        I64LeS = 0x57,
+        I64LeU = 0x58,
+        I64GeS = 0x59,
+        I64GeU = 0x5a,
+        F32Eq = 0x5b,
+        F32Ne = 0x5c,
+        F32Lt = 0x5d,
+        F32Gt = 0x5e,
+        F32Le = 0x5f,
+        F32Ge = 0x60,
+        F64Eq = 0x61,
+        F64Ne = 0x62,
+        F64Lt = 0x63,
+        F64Gt = 0x64,
+        F64Le = 0x65,
+        F64Ge = 0x66,
+        I32Clz = 0x67,
+        I32Ctz = 0x68,
+        I32Popcnt = 0x69,
+        I32Add = 0x6a,
+        I32Sub = 0x6b,
+        I32Mul = 0x6c,
+        I32DivS = 0x6d,
+        I32DivU = 0x6e,
+        I32RemS = 0x6f,
+        I32RemU = 0x70,
+        I32And = 0x71,
+        I32Or = 0x72,
+        I32Xor = 0x73,
+        I32Shl = 0x74,
+        I32ShrS = 0x75,
+        I32ShrU = 0x76,
+        I32Rotl = 0x77,
+        I32Rotr = 0x78,
+        I64Clz = 0x79,
+        I64Ctz = 0x7a,
+        I64Popcnt = 0x7b,
+        I64Add = 0x7c,
+        I64Sub = 0x7d,
+        I64Mul = 0x7e,
+        I64DivS = 0x7f,
+        I64DivU = 0x80,
+        I64RemS = 0x81,
+        I64RemU = 0x82,
+        I64And = 0x83,
+        I64Or = 0x84,
+        I64Xor = 0x85,
+        I64Shl = 0x86,
+        I64ShrS = 0x87,
+        I64ShrU = 0x88,
+        I64Rotl = 0x89,
+        I64Rotr = 0x8a,
+        F32Abs = 0x8b,
+        F32Neg = 0x8c,
+        F32Ceil = 0x8d,
+        F32Floor = 0x8e,
+        F32Trunc = 0x8f,
+        F32Nearest = 0x90,
+        F32Sqrt = 0x91,
+        F32Add = 0x92,
+        F32Sub = 0x93,
+        F32Mul = 0x94,
+        F32Div = 0x95,
+        F32Min = 0x96,
+        F32Max = 0x97,
+        F32Copysign = 0x98,
+        F64Abs = 0x99,
+        F64Neg = 0x9a,
+        F64Ceil = 0x9b,
+        F64Floor = 0x9c,
+        F64Trunc = 0x9d,
+        F64Nearest = 0x9e,
+        F64Sqrt = 0x9f,
+        F64Add = 0xa0,
+        F64Sub = 0xa1,
+        F64Mul = 0xa2,
+        F64Div = 0xa3,
+        F64Min = 0xa4,
+        F64Max = 0xa5,
+        F64Copysign = 0xa6,
+        I32WrapI64 = 0xa7,
+        I32TruncF32S = 0xa8,
+        I32TruncF32U = 0xa9,
+        I32TruncF64S = 0xaa,
+        I32TruncF64U = 0xab,
+        I64ExtendI32S = 0xac,
+        I64ExtendI32U = 0xad,
+        I64TruncF32S = 0xae,
+        I64TruncF32U = 0xaf,
+        I64TruncF64S = 0xb0,
+        I64TruncF64U = 0xb1,
+        F32ConvertI32S = 0xb2,
+        F32ConvertI32U = 0xb3,
+        F32ConvertI64S = 0xb4,
+        F32ConvertI64U = 0xb5,
+        F32DemoteF64 = 0xb6,
+        F64ConvertI32S = 0xb7,
+        F64ConvertI32U = 0xb8,
+        F64ConvertI64S = 0xb9,
+        F64ConvertI64U = 0xba,
+        F64PromoteF32 = 0xbb,
+        I32ReinterpretF32 = 0xbc,
+        I64ReinterpretF64 = 0xbd,
+        F32ReinterpretI32 = 0xbe,
+        F64ReinterpretI64 = 0xbf,
+        I32Extend8S = 0xc0,
+        I32Extend16S = 0xc1,
+        I64Extend8S = 0xc2,
+        I64Extend16S = 0xc3,
+        I64Extend32S = 0xc4,
+
+        // [wasm]: 5.4.7 Numeric Instructions
+        // [wasm]: 5.4.5 Table Instructions
+        //
+        // Prefix for the The bulk memory and table instruction.
+        BulkPrefix = 0xfc,
+
+        // [wasm]: 5.4.8 Vector Instructions
+        VectorPrefix = 0xfd,
+
+        // Prefix for the atomic memory instructions of the threads proposal.
+        AtomicPrefix = 0xfe,
+    }
+}
+
+primitive_enum! {
+    /// Bulk memory and table instruction opcodes (0xfc prefix).
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub enum BulkOpcode: u32 {
+        // [wasm]: 5.4.5 Table Instructions
+        TableInit = 12,
+        ElemDrop = 13,
+        TableCopy = 14,
+        TableGrow = 15,
+        TableSize = 16,
+        TableFill = 17,
+
+        // [wasm]: 5.4.6 Memory Instructions
+        MemoryInit = 8,
+        DataDrop = 9,
+        MemoryCopy = 10,
+        MemoryFill = 11,
+
+        // [wasm]: 5.4.7 Numeric Instructions
+        I32TruncSatF32S = 0,
+        I32TruncSatF32U = 1,
+        I32TruncSatF64S = 2,
+        I32TruncSatF64U = 3,
+        I64TruncSatF32S = 4,
+        I64TruncSatF32U = 5,
+        I64TruncSatF64S = 6,
+        I64TruncSatF64U = 7,
+    }
+}
+
+primitive_enum! {
+    /// Atomic memory instruction opcodes of the threads proposal (0xfe prefix).
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub enum AtomicOpcode: u32 {
+        MemoryAtomicNotify = 0x00,
+        MemoryAtomicWait32 = 0x01,
+        MemoryAtomicWait64 = 0x02,
+        AtomicFence = 0x03,
+        I32AtomicLoad = 0x10,
+        I64AtomicLoad = 0x11,
+        I32AtomicLoad8U = 0x12,
+        I32AtomicLoad16U = 0x13,
+        I64AtomicLoad8U = 0x14,
+        I64AtomicLoad16U = 0x15,
+        I64AtomicLoad32U = 0x16,
+        I32AtomicStore = 0x17,
+        I64AtomicStore = 0x18,
+        I32AtomicStore8 = 0x19,
+        I32AtomicStore16 = 0x1a,
+        I64AtomicStore8 = 0x1b,
+        I64AtomicStore16 = 0x1c,
+        I64AtomicStore32 = 0x1d,
+        I32AtomicRmwAdd = 0x1e,
+        I64AtomicRmwAdd = 0x1f,
+        I32AtomicRmw8AddU = 0x20,
+        I32AtomicRmw16AddU = 0x21,
+        I64AtomicRmw8AddU = 0x22,
+        I64AtomicRmw16AddU = 0x23,
+        I64AtomicRmw32AddU = 0x24,
+        I32AtomicRmwSub = 0x25,
+        I64AtomicRmwSub = 0x26,
+        I32AtomicRmw8SubU = 0x27,
+        I32AtomicRmw16SubU = 0x28,
+        I64AtomicRmw8SubU = 0x29,
+        I64AtomicRmw16SubU = 0x2a,
+        I64AtomicRmw32SubU = 0x2b,
+        I32AtomicRmwAnd = 0x2c,
+        I64AtomicRmwAnd = 0x2d,
+        I32AtomicRmw8AndU = 0x2e,
+        I32AtomicRmw16AndU = 0x2f,
+        I64AtomicRmw8AndU = 0x30,
+        I64AtomicRmw16AndU = 0x31,
+        I64AtomicRmw32AndU = 0x32,
+        I32AtomicRmwOr = 0x33,
+        I64AtomicRmwOr = 0x34,
+        I32AtomicRmw8OrU = 0x35,
+        I32AtomicRmw16OrU = 0x36,
+        I64AtomicRmw8OrU = 0x37,
+        I64AtomicRmw16OrU = 0x38,
+        I64AtomicRmw32OrU = 0x39,
+        I32AtomicRmwXor = 0x3a,
+        I64AtomicRmwXor = 0x3b,
+        I32AtomicRmw8XorU = 0x3c,
+        I32AtomicRmw16XorU = 0x3d,
+        I64AtomicRmw8XorU = 0x3e,
+        I64AtomicRmw16XorU = 0x3f,
+        I64AtomicRmw32XorU = 0x40,
+        I32AtomicRmwXchg = 0x41,
+        I64AtomicRmwXchg = 0x42,
+        I32AtomicRmw8XchgU = 0x43,
+        I32AtomicRmw16XchgU = 0x44,
+        I64AtomicRmw8XchgU = 0x45,
+        I64AtomicRmw16XchgU = 0x46,
+        I64AtomicRmw32XchgU = 0x47,
+        I32AtomicRmwCmpxchg = 0x48,
+        I64AtomicRmwCmpxchg = 0x49,
+        I32AtomicRmw8CmpxchgU = 0x4a,
+        I32AtomicRmw16CmpxchgU = 0x4b,
+        I64AtomicRmw8CmpxchgU = 0x4c,
+        I64AtomicRmw16CmpxchgU = 0x4d,
+        I64AtomicRmw32CmpxchgU = 0x4e,
+    }
+}
+
+/// The classes of instructions, per the subsections of the spec's
+/// instruction reference.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InstructionClass {
+    Control,
+    Reference,
+    Parametric,
+    Variable,
+    Table,
+    Memory,
+    Numeric,
+    Vector,
+}
+
+impl InstructionClass {
+    /// The number of instruction classes.
+    pub const COUNT: usize = 8;
+}
+
+/// The immediate operands that follow an opcode in the binary format.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum OperandKind {
+    /// No immediates.
+    None,
+    /// A secondary atomic opcode (with its own immediates) follows.
+    AtomicPrefix,
+    /// A block type.
+    BlockType,
+    /// A vector of label indices followed by the default label index.
+    BrTable,
+    /// A secondary bulk opcode (with its own immediates) follows.
+    BulkPrefix,
+    /// A type index followed by a table index.
+    CallIndirect,
+    /// A 32-bit float constant.
+    F32,
+    /// A 64-bit float constant.
+    F64,
+    /// A signed 32-bit LEB128 constant.
+    I32,
+    /// A signed 64-bit LEB128 constant.
+    I64,
+    /// A single u32 index, into the index space of the instruction.
+    Idx,
+    /// An alignment and offset.
+    MemArg,
+    /// A data index followed by a reserved zero byte.
+    MemoryInit,
+    /// A reference type.
+    RefType,
+    /// A vector of value types.
+    SelectT,
+    /// Destination and source table indices.
+    TableCopy,
+    /// An element index followed by a table index.
+    TableInit,
+    /// A secondary vector opcode (with its own immediates) follows.
+    VectorPrefix,
+    /// The given number of reserved zero bytes.
+    ZeroBytes(u8),
+    /// A pair of type indices.
+    #[cfg(feature = "experimental")]
+    ContBind,
+    /// A type index followed by a vector of handlers.
+    #[cfg(feature = "experimental")]
+    Resume,
+    /// A type index and tag index followed by a vector of handlers.
+    #[cfg(feature = "experimental")]
+    ResumeThrow,
+    /// A type index followed by a tag index.
+    #[cfg(feature = "experimental")]
+    Switch,
+}
+
+/// The index space into which an index immediate ([`OperandKind::Idx`] or the
+/// data index of [`OperandKind::MemoryInit`]) points.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum IndexKind {
+    /// A label of an enclosing block.
+    Label,
+    /// A function.
+    Function,
+    /// A local of the enclosing function.
+    Local,
+    /// A global.
+    Global,
+    /// A table.
+    Table,
+    /// An element segment.
+    Element,
+    /// A data segment.
+    Data,
+    /// A type.
+    Type,
+    /// A tag (of the stack-switching proposal).
+    Tag,
+}
+
+/// The operand stack effect of an instruction with a fixed type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StackEffect {
+    /// The types of the operands popped, in stack order (so the topmost
+    /// last).
+    pub pops: &'static [ValType],
+    /// The types of the results pushed, in stack order (so the topmost last).
+    pub pushes: &'static [ValType],
+}
+
+impl Opcode {
+    /// Whether the opcode belongs to an in-flight proposal (currently the
+    /// stack-switching proposal), as gated by the `experimental` feature.
+    pub const fn is_experimental(self) -> bool {
+        #[cfg(feature = "experimental")]
+        if matches!(
+            self,
+            Opcode::ContNew
+                | Opcode::ContBind
+                | Opcode::Suspend
+                | Opcode::Resume
+                | Opcode::ResumeThrow
+                | Opcode::Switch
+        ) {
+            return true;
+        }
+        false
+    }
+
+    /// The class of the instruction, or None for prefixes, whose class is
+    /// determined by the secondary opcode.
+    pub const fn class(self) -> Option<InstructionClass> {
+        use Opcode::*;
+        let class = match self {
+            Unreachable | Nop | Block | Loop | If | Else | End | Br | BrIf | BrTable | Return
+            | Call | CallIndirect => InstructionClass::Control,
+            #[cfg(feature = "experimental")]
+            ContNew | ContBind | Suspend | Resume | ResumeThrow | Switch => {
+                InstructionClass::Control
+            }
+            RefNull | RefIsNull | RefFunc => InstructionClass::Reference,
+            Drop | Select | SelectT => InstructionClass::Parametric,
+            LocalGet | LocalSet | LocalTee | GlobalGet | GlobalSet => InstructionClass::Variable,
+            TableGet | TableSet => InstructionClass::Table,
+            I32Load | I64Load | F32Load | F64Load | I32Load8S | I32Load8U | I32Load16S
+            | I32Load16U | I64Load8S | I64Load8U | I64Load16S | I64Load16U | I64Load32S
+            | I64Load32U | I32Store | I64Store | F32Store | F64Store | I32Store8 | I32Store16
+            | I64Store8 | I64Store16 | I64Store32 | MemorySize | MemoryGrow => {
+                InstructionClass::Memory
+            }
+            AtomicPrefix | BulkPrefix | VectorPrefix => return None,
+            _ => InstructionClass::Numeric,
+        };
+        Some(class)
+    }
+
+    /// The mnemonic of the instruction in the text format (e.g.,
+    /// `"i32.load8_s"`). Prefixes, which are not instructions in themselves,
+    /// are named by their byte (e.g., `"0xfc"`).
+    pub const fn name(self) -> &'static str {
+        use Opcode::*;
+        match self {
+            Unreachable => "unreachable",
+            Nop => "nop",
+            Block => "block",
+            Loop => "loop",
+            If => "if",
+            Else => "else",
+            End => "end",
+            Br => "br",
+            BrIf => "br_if",
+            BrTable => "br_table",
+            Return => "return",
+            Call => "call",
+            CallIndirect => "call_indirect",
+            #[cfg(feature = "experimental")]
+            ContNew => "cont.new",
+            #[cfg(feature = "experimental")]
+            ContBind => "cont.bind",
+            #[cfg(feature = "experimental")]
+            Suspend => "suspend",
+            #[cfg(feature = "experimental")]
+            Resume => "resume",
+            #[cfg(feature = "experimental")]
+            ResumeThrow => "resume_throw",
+            #[cfg(feature = "experimental")]
+            Switch => "switch",
+            RefNull => "ref.null",
+            RefIsNull => "ref.is_null",
+            RefFunc => "ref.func",
+            Drop => "drop",
+            Select => "select",
+            SelectT => "select",
+            LocalGet => "local.get",
+            LocalSet => "local.set",
+            LocalTee => "local.tee",
+            GlobalGet => "global.get",
+            GlobalSet => "global.set",
+            TableGet => "table.get",
+            TableSet => "table.set",
+            I32Load => "i32.load",
+            I64Load => "i64.load",
+            F32Load => "f32.load",
+            F64Load => "f64.load",
+            I32Load8S => "i32.load8_s",
+            I32Load8U => "i32.load8_u",
+            I32Load16S => "i32.load16_s",
+            I32Load16U => "i32.load16_u",
+            I64Load8S => "i64.load8_s",
+            I64Load8U => "i64.load8_u",
+            I64Load16S => "i64.load16_s",
+            I64Load16U => "i64.load16_u",
+            I64Load32S => "i64.load32_s",
+            I64Load32U => "i64.load32_u",
+            I32Store => "i32.store",
+            I64Store => "i64.store",
+            F32Store => "f32.store",
+            F64Store => "f64.store",
+            I32Store8 => "i32.store8",
+            I32Store16 => "i32.store16",
+            I64Store8 => "i64.store8",
+            I64Store16 => "i64.store16",
+            I64Store32 => "i64.store32",
+            MemorySize => "memory.size",
+            MemoryGrow => "memory.grow",
+            I32Const => "i32.const",
+            I64Const => "i64.const",
+            F32Const => "f32.const",
+            F64Const => "f64.const",
+            I32Eqz => "i32.eqz",
+            I32Eq => "i32.eq",
+            I32Ne => "i32.ne",
+            I32LtS => "i32.lt_s",
+            I32LtU => "i32.lt_u",
+            I32GtS => "i32.gt_s",
+            I32GtU => "i32.gt_u",
+            I32LeS => "i32.le_s",
+            I32LeU => "i32.le_u",
+            I32GeS => "i32.ge_s",
+            I32GeU => "i32.ge_u",
+            I64Eqz => "i64.eqz",
+            I64Eq => "i64.eq",
+            I64Ne => "i64.ne",
+            I64LtS => "i64.lt_s",
+            I64LtU => "i64.lt_u",
+            I64GtS => "i64.gt_s",
+            I64GtU => "i64.gt_u",
+            I64LeS => "i64.le_s",
+            I64LeU => "i64.le_u",
+            I64GeS => "i64.ge_s",
+            I64GeU => "i64.ge_u",
+            F32Eq => "f32.eq",
+            F32Ne => "f32.ne",
+            F32Lt => "f32.lt",
+            F32Gt => "f32.gt",
+            F32Le => "f32.le",
+            F32Ge => "f32.ge",
+            F64Eq => "f64.eq",
+            F64Ne => "f64.ne",
+            F64Lt => "f64.lt",
+            F64Gt => "f64.gt",
+            F64Le => "f64.le",
+            F64Ge => "f64.ge",
+            I32Clz => "i32.clz",
+            I32Ctz => "i32.ctz",
+            I32Popcnt => "i32.popcnt",
+            I32Add => "i32.add",
+            I32Sub => "i32.sub",
+            I32Mul => "i32.mul",
+            I32DivS => "i32.div_s",
+            I32DivU => "i32.div_u",
+            I32RemS => "i32.rem_s",
+            I32RemU => "i32.rem_u",
+            I32And => "i32.and",
+            I32Or => "i32.or",
+            I32Xor => "i32.xor",
+            I32Shl => "i32.shl",
+            I32ShrS => "i32.shr_s",
+            I32ShrU => "i32.shr_u",
+            I32Rotl => "i32.rotl",
+            I32Rotr => "i32.rotr",
+            I64Clz => "i64.clz",
+            I64Ctz => "i64.ctz",
+            I64Popcnt => "i64.popcnt",
+            I64Add => "i64.add",
+            I64Sub => "i64.sub",
+            I64Mul => "i64.mul",
+            I64DivS => "i64.div_s",
+            I64DivU => "i64.div_u",
+            I64RemS => "i64.rem_s",
+            I64RemU => "i64.rem_u",
+            I64And => "i64.and",
+            I64Or => "i64.or",
+            I64Xor => "i64.xor",
+            I64Shl => "i64.shl",
+            I64ShrS => "i64.shr_s",
+            I64ShrU => "i64.shr_u",
+            I64Rotl => "i64.rotl",
+            I64Rotr => "i64.rotr",
+            F32Abs => "f32.abs",
+            F32Neg => "f32.neg",
+            F32Ceil => "f32.ceil",
+            F32Floor => "f32.floor",
+            F32Trunc => "f32.trunc",
+            F32Nearest => "f32.nearest",
+            F32Sqrt => "f32.sqrt",
+            F32Add => "f32.add",
+            F32Sub => "f32.sub",
+            F32Mul => "f32.mul",
+            F32Div => "f32.div",
+            F32Min => "f32.min",
+            F32Max => "f32.max",
+            F32Copysign => "f32.copysign",
+            F64Abs => "f64.abs",
+            F64Neg => "f64.neg",
+            F64Ceil => "f64.ceil",
+            F64Floor => "f64.floor",
+            F64Trunc => "f64.trunc",
+            F64Nearest => "f64.nearest",
+            F64Sqrt => "f64.sqrt",
+            F64Add => "f64.add",
+            F64Sub => "f64.sub",
+            F64Mul => "f64.mul",
+            F64Div => "f64.div",
+            F64Min => "f64.min",
+            F64Max => "f64.max",
+            F64Copysign => "f64.copysign",
+            I32WrapI64 => "i32.wrap_i64",
+            I32TruncF32S => "i32.trunc_f32_s",
+            I32TruncF32U => "i32.trunc_f32_u",
+            I32TruncF64S => "i32.trunc_f64_s",
+            I32TruncF64U => "i32.trunc_f64_u",
+            I64ExtendI32S => "i64.extend_i32_s",
+            I64ExtendI32U => "i64.extend_i32_u",
+            I64TruncF32S => "i64.trunc_f32_s",
+            I64TruncF32U => "i64.trunc_f32_u",
+            I64TruncF64S => "i64.trunc_f64_s",
+            I64TruncF64U => "i64.trunc_f64_u",
+            F32ConvertI32S => "f32.convert_i32_s",
+            F32ConvertI32U => "f32.convert_i32_u",
+            F32ConvertI64S => "f32.convert_i64_s",
+            F32ConvertI64U => "f32.convert_i64_u",
+            F32DemoteF64 => "f32.demote_f64",
+            F64ConvertI32S => "f64.convert_i32_s",
+            F64ConvertI32U => "f64.convert_i32_u",
+            F64ConvertI64S => "f64.convert_i64_s",
+            F64ConvertI64U => "f64.convert_i64_u",
+            F64PromoteF32 => "f64.promote_f32",
+            I32ReinterpretF32 => "i32.reinterpret_f32",
+            I64ReinterpretF64 => "i64.reinterpret_f64",
+            F32ReinterpretI32 => "f32.reinterpret_i32",
+            F64ReinterpretI64 => "f64.reinterpret_i64",
+            I32Extend8S => "i32.extend8_s",
+            I32Extend16S => "i32.extend16_s",
+            I64Extend8S => "i64.extend8_s",
+            I64Extend16S => "i64.extend16_s",
+            I64Extend32S => "i64.extend32_s",
+            BulkPrefix => "0xfc",
+            VectorPrefix => "0xfd",
+            AtomicPrefix => "0xfe",
+        }
+    }
+
+    /// The immediate operands that follow the opcode.
+    pub const fn operand_kind(self) -> OperandKind {
+        use Opcode::*;
+        match self {
+            AtomicPrefix => OperandKind::AtomicPrefix,
+            Block | Loop | If => OperandKind::BlockType,
+            Br | BrIf | Call | GlobalGet | GlobalSet | LocalGet | LocalSet | LocalTee | RefFunc
+            | TableGet | TableSet => OperandKind::Idx,
+            BrTable => OperandKind::BrTable,
+            BulkPrefix => OperandKind::BulkPrefix,
+            CallIndirect => OperandKind::CallIndirect,
+            F32Const => OperandKind::F32,
+            F64Const => OperandKind::F64,
+            I32Const => OperandKind::I32,
+            I64Const => OperandKind::I64,
+            I32Load | I64Load | F32Load | F64Load | I32Load8S | I32Load8U | I32Load16S
+            | I32Load16U | I64Load8S | I64Load8U | I64Load16S | I64Load16U | I64Load32S
+            | I64Load32U | I32Store | I64Store | F32Store | F64Store | I32Store8 | I32Store16
+            | I64Store8 | I64Store16 | I64Store32 => OperandKind::MemArg,
+            MemorySize | MemoryGrow => OperandKind::ZeroBytes(1),
+            RefNull => OperandKind::RefType,
+            SelectT => OperandKind::SelectT,
+            VectorPrefix => OperandKind::VectorPrefix,
+            #[cfg(feature = "experimental")]
+            ContNew | Suspend => OperandKind::Idx,
+            #[cfg(feature = "experimental")]
+            ContBind => OperandKind::ContBind,
+            #[cfg(feature = "experimental")]
+            Resume => OperandKind::Resume,
+            #[cfg(feature = "experimental")]
+            ResumeThrow => OperandKind::ResumeThrow,
+            #[cfg(feature = "experimental")]
+            Switch => OperandKind::Switch,
+            _ => OperandKind::None,
+        }
+    }
+
+    /// The index space of the instruction's index immediate, if it has one.
+    pub const fn index_kind(self) -> Option<IndexKind> {
+        use Opcode::*;
+        let kind = match self {
+            Br | BrIf => IndexKind::Label,
+            Call | RefFunc => IndexKind::Function,
+            LocalGet | LocalSet | LocalTee => IndexKind::Local,
+            GlobalGet | GlobalSet => IndexKind::Global,
+            TableGet | TableSet => IndexKind::Table,
+            #[cfg(feature = "experimental")]
+            ContNew => IndexKind::Type,
+            #[cfg(feature = "experimental")]
+            Suspend => IndexKind::Tag,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// The stack effect of the instruction, or None if its operand and
+    /// result types are not fixed (e.g., as they depend on its immediates, on
+    /// the operand stack, or on the enclosing function) or if it is a prefix.
+    pub const fn stack_effect(self) -> Option<StackEffect> {
+        use Opcode::*;
+        use ValType::{F32, F64, FuncRef, I32, I64};
+        let (pops, pushes): (&[ValType], &[ValType]) = match self {
+            Nop => (&[], &[]),
+            RefFunc => (&[], &[FuncRef]),
+            I32Load | I32Load8S | I32Load8U | I32Load16S | I32Load16U | MemoryGrow | I32Eqz
+            | I32Clz | I32Ctz | I32Popcnt | I32Extend8S | I32Extend16S => (&[I32], &[I32]),
+            I64Load | I64Load8S | I64Load8U | I64Load16S | I64Load16U | I64Load32S | I64Load32U
+            | I64ExtendI32S | I64ExtendI32U => (&[I32], &[I64]),
+            F32Load | F32ConvertI32S | F32ConvertI32U | F32ReinterpretI32 => (&[I32], &[F32]),
+            F64Load | F64ConvertI32S | F64ConvertI32U => (&[I32], &[F64]),
+            I32Store | I32Store8 | I32Store16 => (&[I32, I32], &[]),
+            I64Store | I64Store8 | I64Store16 | I64Store32 => (&[I32, I64], &[]),
+            F32Store => (&[I32, F32], &[]),
+            F64Store => (&[I32, F64], &[]),
+            MemorySize | I32Const => (&[], &[I32]),
+            I64Const => (&[], &[I64]),
+            F32Const => (&[], &[F32]),
+            F64Const => (&[], &[F64]),
+            I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS
+            | I32GeU | I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU
+            | I32And | I32Or | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr => {
+                (&[I32, I32], &[I32])
+            }
+            I64Eqz | I32WrapI64 => (&[I64], &[I32]),
+            I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS
+            | I64GeU => (&[I64, I64], &[I32]),
+            F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge => (&[F32, F32], &[I32]),
+            F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge => (&[F64, F64], &[I32]),
+            I64Clz | I64Ctz | I64Popcnt | I64Extend8S | I64Extend16S | I64Extend32S => {
+                (&[I64], &[I64])
+            }
+            I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or
+            | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr => (&[I64, I64], &[I64]),
+            F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => {
+                (&[F32], &[F32])
+            }
+            F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => {
+                (&[F32, F32], &[F32])
+            }
+            F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => {
+                (&[F64], &[F64])
+            }
+            F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => {
+                (&[F64, F64], &[F64])
+            }
+            I32TruncF32S | I32TruncF32U | I32ReinterpretF32 => (&[F32], &[I32]),
+            I32TruncF64S | I32TruncF64U => (&[F64], &[I32]),
+            I64TruncF32S | I64TruncF32U => (&[F32], &[I64]),
+            I64TruncF64S | I64TruncF64U | I64ReinterpretF64 => (&[F64], &[I64]),
+            F32ConvertI64S | F32ConvertI64U => (&[I64], &[F32]),
+            F32DemoteF64 => (&[F64], &[F32]),
+            F64ConvertI64S | F64ConvertI64U | F64ReinterpretI64 => (&[I64], &[F64]),
+            F64PromoteF32 => (&[F32], &[F64]),
+            _ => return None,
+        };
+        Some(StackEffect { pops, pushes })
+    }
+}
+
+impl BulkOpcode {
+    /// The class of the instruction.
+    pub const fn class(self) -> InstructionClass {
+        use BulkOpcode::*;
+        match self {
+            TableInit | ElemDrop | TableCopy | TableGrow | TableSize | TableFill => {
+                InstructionClass::Table
+            }
+            MemoryInit | DataDrop | MemoryCopy | MemoryFill => InstructionClass::Memory,
+            I32TruncSatF32S | I32TruncSatF32U | I32TruncSatF64S | I32TruncSatF64U
+            | I64TruncSatF32S | I64TruncSatF32U | I64TruncSatF64S | I64TruncSatF64U => {
+                InstructionClass::Numeric
+            }
+        }
+    }
+
+    /// The mnemonic of the instruction in the text format.
+    pub const fn name(self) -> &'static str {
+        use BulkOpcode::*;
+        match self {
+            TableInit => "table.init",
+            ElemDrop => "elem.drop",
+            TableCopy => "table.copy",
+            TableGrow => "table.grow",
+            TableSize => "table.size",
+            TableFill => "table.fill",
+            MemoryInit => "memory.init",
+            DataDrop => "data.drop",
+            MemoryCopy => "memory.copy",
+            MemoryFill => "memory.fill",
+            I32TruncSatF32S => "i32.trunc_sat_f32_s",
+            I32TruncSatF32U => "i32.trunc_sat_f32_u",
+            I32TruncSatF64S => "i32.trunc_sat_f64_s",
+            I32TruncSatF64U => "i32.trunc_sat_f64_u",
+            I64TruncSatF32S => "i64.trunc_sat_f32_s",
+            I64TruncSatF32U => "i64.trunc_sat_f32_u",
+            I64TruncSatF64S => "i64.trunc_sat_f64_s",
+            I64TruncSatF64U => "i64.trunc_sat_f64_u",
+        }
+    }
+
+    /// The immediate operands that follow the opcode.
+    pub const fn operand_kind(self) -> OperandKind {
+        use BulkOpcode::*;
+        match self {
+            DataDrop | ElemDrop | TableFill | TableGrow | TableSize => OperandKind::Idx,
+            MemoryCopy => OperandKind::ZeroBytes(2),
+            MemoryFill => OperandKind::ZeroBytes(1),
+            MemoryInit => OperandKind::MemoryInit,
+            TableCopy => OperandKind::TableCopy,
+            TableInit => OperandKind::TableInit,
+            I32TruncSatF32S | I32TruncSatF32U | I32TruncSatF64S | I32TruncSatF64U
+            | I64TruncSatF32S | I64TruncSatF32U | I64TruncSatF64S | I64TruncSatF64U => {
+                OperandKind::None
+            }
+        }
+    }
+
+    /// The index space of the instruction's index immediate, if it has one.
+    pub const fn index_kind(self) -> Option<IndexKind> {
+        use BulkOpcode::*;
+        match self {
+            DataDrop | MemoryInit => Some(IndexKind::Data),
+            ElemDrop => Some(IndexKind::Element),
+            TableFill | TableGrow | TableSize => Some(IndexKind::Table),
+            _ => None,
+        }
+    }
+
+    /// The stack effect of the instruction, or None if it depends on the
+    /// type of the table operated on.
+    pub const fn stack_effect(self) -> Option<StackEffect> {
+        use BulkOpcode::*;
+        use ValType::{F32, F64, I32, I64};
+        let (pops, pushes): (&[ValType], &[ValType]) = match self {
+            TableInit | TableCopy | MemoryInit | MemoryCopy | MemoryFill => (&[I32, I32, I32], &[]),
+            ElemDrop | DataDrop => (&[], &[]),
+            TableSize => (&[], &[I32]),
+            I32TruncSatF32S | I32TruncSatF32U => (&[F32], &[I32]),
+            I32TruncSatF64S | I32TruncSatF64U => (&[F64], &[I32]),
+            I64TruncSatF32S | I64TruncSatF32U => (&[F32], &[I64]),
+            I64TruncSatF64S | I64TruncSatF64U => (&[F64], &[I64]),
+            TableGrow | TableFill => return None,
+        };
+        Some(StackEffect { pops, pushes })
+    }
+}
+
+impl AtomicOpcode {
+    /// The class of the instruction.
+    #[allow(clippy::unused_self)]
+    pub const fn class(self) -> InstructionClass {
+        InstructionClass::Memory
+    }
+
+    /// The mnemonic of the instruction in the text format.
+    pub const fn name(self) -> &'static str {
+        use AtomicOpcode::*;
+        match self {
+            MemoryAtomicNotify => "memory.atomic.notify",
+            MemoryAtomicWait32 => "memory.atomic.wait32",
+            MemoryAtomicWait64 => "memory.atomic.wait64",
+            AtomicFence => "atomic.fence",
+            I32AtomicLoad => "i32.atomic.load",
+            I64AtomicLoad => "i64.atomic.load",
+            I32AtomicLoad8U => "i32.atomic.load8_u",
+            I32AtomicLoad16U => "i32.atomic.load16_u",
+            I64AtomicLoad8U => "i64.atomic.load8_u",
+            I64AtomicLoad16U => "i64.atomic.load16_u",
+            I64AtomicLoad32U => "i64.atomic.load32_u",
+            I32AtomicStore => "i32.atomic.store",
+            I64AtomicStore => "i64.atomic.store",
+            I32AtomicStore8 => "i32.atomic.store8",
+            I32AtomicStore16 => "i32.atomic.store16",
+            I64AtomicStore8 => "i64.atomic.store8",
+            I64AtomicStore16 => "i64.atomic.store16",
+            I64AtomicStore32 => "i64.atomic.store32",
+            I32AtomicRmwAdd => "i32.atomic.rmw.add",
+            I64AtomicRmwAdd => "i64.atomic.rmw.add",
+            I32AtomicRmw8AddU => "i32.atomic.rmw8.add_u",
+            I32AtomicRmw16AddU => "i32.atomic.rmw16.add_u",
+            I64AtomicRmw8AddU => "i64.atomic.rmw8.add_u",
+            I64AtomicRmw16AddU => "i64.atomic.rmw16.add_u",
+            I64AtomicRmw32AddU => "i64.atomic.rmw32.add_u",
+            I32AtomicRmwSub => "i32.atomic.rmw.sub",
+            I64AtomicRmwSub => "i64.atomic.rmw.sub",
+            I32AtomicRmw8SubU => "i32.atomic.rmw8.sub_u",
+            I32AtomicRmw16SubU => "i32.atomic.rmw16.sub_u",
+            I64AtomicRmw8SubU => "i64.atomic.rmw8.sub_u",
+            I64AtomicRmw16SubU => "i64.atomic.rmw16.sub_u",
+            I64AtomicRmw32SubU => "i64.atomic.rmw32.sub_u",
+            I32AtomicRmwAnd => "i32.atomic.rmw.and",
+            I64AtomicRmwAnd => "i64.atomic.rmw.and",
+            I32AtomicRmw8AndU => "i32.atomic.rmw8.and_u",
+            I32AtomicRmw16AndU => "i32.atomic.rmw16.and_u",
+            I64AtomicRmw8AndU => "i64.atomic.rmw8.and_u",
+            I64AtomicRmw16AndU => "i64.atomic.rmw16.and_u",
+            I64AtomicRmw32AndU => "i64.atomic.rmw32.and_u",
+            I32AtomicRmwOr => "i32.atomic.rmw.or",
+            I64AtomicRmwOr => "i64.atomic.rmw.or",
+            I32AtomicRmw8OrU => "i32.atomic.rmw8.or_u",
+            I32AtomicRmw16OrU => "i32.atomic.rmw16.or_u",
+            I64AtomicRmw8OrU => "i64.atomic.rmw8.or_u",
+            I64AtomicRmw16OrU => "i64.atomic.rmw16.or_u",
+            I64AtomicRmw32OrU => "i64.atomic.rmw32.or_u",
+            I32AtomicRmwXor => "i32.atomic.rmw.xor",
+            I64AtomicRmwXor => "i64.atomic.rmw.xor",
+            I32AtomicRmw8XorU => "i32.atomic.rmw8.xor_u",
+            I32AtomicRmw16XorU => "i32.atomic.rmw16.xor_u",
+            I64AtomicRmw8XorU => "i64.atomic.rmw8.xor_u",
+            I64AtomicRmw16XorU => "i64.atomic.rmw16.xor_u",
+            I64AtomicRmw32XorU => "i64.atomic.rmw32.xor_u",
+            I32AtomicRmwXchg => "i32.atomic.rmw.xchg",
+            I64AtomicRmwXchg => "i64.atomic.rmw.xchg",
+            I32AtomicRmw8XchgU => "i32.atomic.rmw8.xchg_u",
+            I32AtomicRmw16XchgU => "i32.atomic.rmw16.xchg_u",
+            I64AtomicRmw8XchgU => "i64.atomic.rmw8.xchg_u",
+            I64AtomicRmw16XchgU => "i64.atomic.rmw16.xchg_u",
+            I64AtomicRmw32XchgU => "i64.atomic.rmw32.xchg_u",
+            I32AtomicRmwCmpxchg => "i32.atomic.rmw.cmpxchg",
+            I64AtomicRmwCmpxchg => "i64.atomic.rmw.cmpxchg",
+            I32AtomicRmw8CmpxchgU => "i32.atomic.rmw8.cmpxchg_u",
+            I32AtomicRmw16CmpxchgU => "i32.atomic.rmw16.cmpxchg_u",
+            I64AtomicRmw8CmpxchgU => "i64.atomic.rmw8.cmpxchg_u",
+            I64AtomicRmw16CmpxchgU => "i64.atomic.rmw16.cmpxchg_u",
+            I64AtomicRmw32CmpxchgU => "i64.atomic.rmw32.cmpxchg_u",
+        }
+    }
+
+    /// The immediate operands that follow the opcode.
+    pub const fn operand_kind(self) -> OperandKind {
+        match self {
+            AtomicOpcode::AtomicFence => OperandKind::ZeroBytes(1),
+            _ => OperandKind::MemArg,
+        }
+    }
+
+    /// The stack effect of the instruction, which is always fixed.
+    pub const fn stack_effect(self) -> StackEffect {
+        use AtomicOpcode::*;
+        use ValType::{I32, I64};
+        let (pops, pushes): (&[ValType], &[ValType]) = match self {
+            MemoryAtomicNotify | I32AtomicRmwAdd | I32AtomicRmw8AddU | I32AtomicRmw16AddU
+            | I32AtomicRmwSub | I32AtomicRmw8SubU | I32AtomicRmw16SubU | I32AtomicRmwAnd
+            | I32AtomicRmw8AndU | I32AtomicRmw16AndU | I32AtomicRmwOr | I32AtomicRmw8OrU
+            | I32AtomicRmw16OrU | I32AtomicRmwXor | I32AtomicRmw8XorU | I32AtomicRmw16XorU
+            | I32AtomicRmwXchg | I32AtomicRmw8XchgU | I32AtomicRmw16XchgU => (&[I32, I32], &[I32]),
+            MemoryAtomicWait32 => (&[I32, I32, I64], &[I32]),
+            MemoryAtomicWait64 => (&[I32, I64, I64], &[I32]),
+            AtomicFence => (&[], &[]),
+            I32AtomicLoad | I32AtomicLoad8U | I32AtomicLoad16U => (&[I32], &[I32]),
+            I64AtomicLoad | I64AtomicLoad8U | I64AtomicLoad16U | I64AtomicLoad32U => {
+                (&[I32], &[I64])
+            }
+            I32AtomicStore | I32AtomicStore8 | I32AtomicStore16 => (&[I32, I32], &[]),
+            I64AtomicStore | I64AtomicStore8 | I64AtomicStore16 | I64AtomicStore32 => {
+                (&[I32, I64], &[])
+            }
+            I64AtomicRmwAdd | I64AtomicRmw8AddU | I64AtomicRmw16AddU | I64AtomicRmw32AddU
+            | I64AtomicRmwSub | I64AtomicRmw8SubU | I64AtomicRmw16SubU | I64AtomicRmw32SubU
+            | I64AtomicRmwAnd | I64AtomicRmw8AndU | I64AtomicRmw16AndU | I64AtomicRmw32AndU
+            | I64AtomicRmwOr | I64AtomicRmw8OrU | I64AtomicRmw16OrU | I64AtomicRmw32OrU
+            | I64AtomicRmwXor | I64AtomicRmw8XorU | I64AtomicRmw16XorU | I64AtomicRmw32XorU
+            | I64AtomicRmwXchg | I64AtomicRmw8XchgU | I64AtomicRmw16XchgU | I64AtomicRmw32XchgU => {
+                (&[I32, I64], &[I64])
+            }
+            I32AtomicRmwCmpxchg | I32AtomicRmw8CmpxchgU | I32AtomicRmw16CmpxchgU => {
+                (&[I32, I32, I32], &[I32])
+            }
+            I64AtomicRmwCmpxchg
+            | I64AtomicRmw8CmpxchgU
+            | I64AtomicRmw16CmpxchgU
+            | I64AtomicRmw32CmpxchgU => (&[I32, I64, I64], &[I64]),
+        };
+        StackEffect { pops, pushes }
+    }
+}
+
+primitive_enum! {
+    /// SIMD vector instruction opcodes (0xfd prefix).
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum VectorOpcode: u32 {
+        V128Load = 0,
+        V128Load8x8S = 1,
+        V128Load8x8U = 2,
+        V128Load16x4S = 3,
+        V128Load16x4U = 4,
+        V128Load32x2S = 5,
+        V128Load32x2U = 6,
+        V128Load8Splat = 7,
+        V128Load16Splat = 8,
+        V128Load32Splat = 9,
+        V128Load64Splat = 10,
+        V128Store = 11,
+        V128Load32Zero = 92,
+        V128Load64Zero = 93,
+
+        V128Load8Lane = 84,
+        V128Load16Lane = 85,
+        V128Load32Lane = 86,
+        V128Load64Lane = 87,
+        V128Store8Lane = 88,
+        V128Store16Lane = 89,
+        V128Store32Lane = 90,
+        V128Store64Lane = 91,
+
+        V128Const = 12,
+
+        I8x16Shuffle = 13,
+
+        I8x16Swizzle = 14,
+
+        I8x16Splat = 15,
+        I16x8Splat = 16,
+        I32x4Splat = 17,
+        I64x2Splat = 18,
+        F32x4Splat = 19,
+        F64x2Splat = 20,
+
+        I8x16ExtractLaneS = 21,
+        I8x16ExtractLaneU = 22,
+        I8x16ReplaceLane = 23,
+        I16x8ExtractLaneS = 24,
+        I16x8ExtractLaneU = 25,
+        I16x8ReplaceLane = 26,
+        I32x4ExtractLane = 27,
+        I32x4ReplaceLane = 28,
+        I64x2ExtractLane = 29,
+        I64x2ReplaceLane = 30,
+        F32x4ExtractLane = 31,
+        F32x4ReplaceLane = 32,
+        F64x2ExtractLane = 33,
+        F64x2ReplaceLane = 34,
+
+        I8x16Eq = 35,
+        I8x16Ne = 36,
+        I8x16LtS = 37,
+        I8x16LtU = 38,
+        I8x16GtS = 39,
+        I8x16GtU = 40,
+        I8x16LeS = 41,
+        I8x16LeU = 42,
+        I8x16GeS = 43,
+        I8x16GeU = 44,
+
+        I16x8Eq = 45,
+        I16x8Ne = 46,
+        I16x8LtS = 47,
+        I16x8LtU = 48,
+        I16x8GtS = 49,
+        I16x8GtU = 50,
+        I16x8LeS = 51,
+        I16x8LeU = 52,
+        I16x8GeS = 53,
+        I16x8GeU = 54,
+
+        I32x4Eq = 55,
+        I32x4Ne = 56,
+        I32x4LtS = 57,
+        I32x4LtU = 58,
+        I32x4GtS = 59,
+        I32x4GtU = 60,
+        I32x4LeS = 61,
+        I32x4LeU = 62,
+        I32x4GeS = 63,
+        I32x4GeU = 64,
+
+        I64x2Eq = 214,
+        I64x2Ne = 215,
+        I64x2LtS = 216,
+        I64x2GtS = 217,
+        I64x2LeS = 218,
+        I64x2GeS = 219,
+
+        F32x4Eq = 65,
+        F32x4Ne = 66,
+        F32x4Lt = 67,
+        F32x4Gt = 68,
+        F32x4Le = 69,
+        F32x4Ge = 70,
+
+        F64x2Eq = 71,
+        F64x2Ne = 72,
+        F64x2Lt = 73,
+        F64x2Gt = 74,
+        F64x2Le = 75,
+        F64x2Ge = 76,
+
+        V128Not = 77,
+        V128And = 78,
+        V128Andnot = 79,
+        V128Or = 80,
+        V128Xor = 81,
+        V128Bitselect = 82,
+        V128AnyTrue = 83,
+
+        I8x16Abs = 96,
+        I8x16Neg = 97,
+        I8x16Popcnt = 98,
+        I8x16AllTrue = 99,
+        I8x16Bitmask = 100,
+        I8x16NarrowI16x8S = 101,
+        I8x16NarrowI16x8U = 102,
+        I8x16Shl = 107,
+        I8x16ShrS = 108,
+        I8x16ShrU = 109,
+        I8x16Add = 110,
+        I8x16AddSatS = 111,
+        I8x16AddSatU = 112,
+        I8x16Sub = 113,
+        I8x16SubSatS = 114,
+        I8x16SubSatU = 115,
+        I8x16MinS = 118,
+        I8x16MinU = 119,
+        I8x16MaxS = 120,
+        I8x16MaxU = 121,
+        I8x16AvgrU = 123,
+
+        I16x8ExtaddPairwiseI8x16S = 124,
+        I16x8ExtaddPairwiseI8x16U = 125,
+        I16x8Abs = 128,
+        I16x8Neg = 129,
+        I16x8Q15mulrSatS = 130,
+        I16x8AllTrue = 131,
+        I16x8Bitmask = 132,
+        I16x8NarrowI32x4S = 133,
+        I16x8NarrowI32x4U = 134,
+        I16x8ExtendLowI8x16S = 135,
+        I16x8ExtendHighI8x16S = 136,
+        I16x8ExtendLowI8x16U = 137,
+        I16x8ExtendHighI8x16U = 138,
+        I16x8Shl = 139,
+        I16x8ShrS = 140,
+        I16x8ShrU = 141,
+        I16x8Add = 142,
+        I16x8AddSatS = 143,
+        I16x8AddSatU = 144,
+        I16x8Sub = 145,
+        I16x8SubSatS = 146,
+        I16x8SubSatU = 147,
+        I16x8Mul = 149,
+        I16x8MinS = 150,
+        I16x8MinU = 151,
+        I16x8MaxS = 152,
+        I16x8MaxU = 153,
+        I16x8AvgrU = 155,
+        I16x8ExtmulLowI8x16S = 156,
+        I16x8ExtmulHighI8x16S = 157,
+        I16x8ExtmulLowI8x16U = 158,
+        I16x8ExtmulHighI8x16U = 159,
+
+        I32x4ExtaddPairwiseI16x8S = 126,
+        I32x4ExtaddPairwiseI16x8U = 127,
+        I32x4Abs = 160,
+        I32x4Neg = 161,
+        I32x4AllTrue = 163,
+        I32x4Bitmask = 164,
+        I32x4ExtendLowI16x8S = 167,
+        I32x4ExtendHighI16x8S = 168,
+        I32x4ExtendLowI16x8U = 169,
+        I32x4ExtendHighI16x8U = 170,
+        I32x4Shl = 171,
+        I32x4ShrS = 172,
+        I32x4ShrU = 173,
+        I32x4Add = 174,
+        I32x4Sub = 177,
+        I32x4Mul = 181,
+        I32x4MinS = 182,
+        I32x4MinU = 183,
+        I32x4MaxS = 184,
+        I32x4MaxU = 185,
+        I32x4DotI16x8S = 186,
+        I32x4ExtmulLowI16x8S = 188,
+        I32x4ExtmulHighI16x8S = 189,
+        I32x4ExtmulLowI16x8U = 190,
+        I32x4ExtmulHighI16x8U = 191,
+
+        I64x2Abs = 192,
+        I64x2Neg = 193,
+        I64x2AllTrue = 195,
+        I64x2Bitmask = 196,
+        I64x2ExtendLowI32x4S = 199,
+        I64x2ExtendHighI32x4S = 200,
+        I64x2ExtendLowI32x4U = 201,
+        I64x2ExtendHighI32x4U = 202,
+        I64x2Shl = 203,
+        I64x2ShrS = 204,
+        I64x2ShrU = 205,
+        I64x2Add = 206,
+        I64x2Sub = 209,
+        I64x2Mul = 213,
+        I64x2ExtmulLowI32x4S = 220,
+        I64x2ExtmulHighI32x4S = 221,
+        I64x2ExtmulLowI32x4U = 222,
+        I64x2ExtmulHighI32x4U = 223,
+
+        F32x4Ceil = 103,
+        F32x4Floor = 104,
+        F32x4Trunc = 105,
+        F32x4Nearest = 106,
+        F32x4Abs = 224,
+        F32x4Neg = 225,
+        F32x4Sqrt = 227,
+        F32x4Add = 228,
+        F32x4Sub = 229,
+        F32x4Mul = 230,
+        F32x4Div = 231,
+        F32x4Min = 232,
+        F32x4Max = 233,
+        F32x4Pmin = 234,
+        F32x4Pmax = 235,
+
+        F64x2Ceil = 116,
+        F64x2Floor = 117,
+        F64x2Trunc = 122,
+        F64x2Nearest = 148,
+        F64x2Abs = 236,
+        F64x2Neg = 237,
+        F64x2Sqrt = 239,
+        F64x2Add = 240,
+        F64x2Sub = 241,
+        F64x2Mul = 242,
+        F64x2Div = 243,
+        F64x2Min = 244,
+        F64x2Max = 245,
+        F64x2Pmin = 246,
+        F64x2Pmax = 247,
+
+        I32x4TruncSatF32x4S = 248,
+        I32x4TruncSatF32x4U = 249,
+        F32x4ConvertI32x4S = 250,
+        F32x4ConvertI32x4U = 251,
+        I32x4TruncSatF64x2SZero = 252,
+        I32x4TruncSatF64x2UZero = 253,
+        F64x2ConvertLowI32x4S = 254,
+        F64x2ConvertLowI32x4U = 255,
+        F32x4DemoteF64x2Zero = 94,
+        F64x2PromoteLowF32x4 = 95,
+    }
+}
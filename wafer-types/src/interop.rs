@@ -0,0 +1,104 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Conversions to and from the equivalent types of the `wasmparser` crate.
+//!
+//! Conversions into `wasmparser` types are infallible. Conversions in the other
+//! direction fail on anything beyond what this crate supports, like typed
+//! function references.
+
+use super::{RefType, ValType};
+
+/// Represents errors that can arise when converting from a `wasmparser` type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConversionError {
+    /// Failed memory allocation.
+    AllocError,
+    /// The type has no equivalent in this crate.
+    Unsupported,
+}
+
+impl From<RefType> for wasmparser::RefType {
+    fn from(value: RefType) -> Self {
+        match value {
+            RefType::Func => Self::FUNCREF,
+            RefType::Extern => Self::EXTERNREF,
+        }
+    }
+}
+
+impl TryFrom<wasmparser::RefType> for RefType {
+    type Error = ConversionError;
+
+    fn try_from(value: wasmparser::RefType) -> Result<Self, Self::Error> {
+        if value == wasmparser::RefType::FUNCREF {
+            Ok(Self::Func)
+        } else if value == wasmparser::RefType::EXTERNREF {
+            Ok(Self::Extern)
+        } else {
+            Err(ConversionError::Unsupported)
+        }
+    }
+}
+
+impl From<ValType> for wasmparser::ValType {
+    fn from(value: ValType) -> Self {
+        match value {
+            ValType::I32 => Self::I32,
+            ValType::I64 => Self::I64,
+            ValType::F32 => Self::F32,
+            ValType::F64 => Self::F64,
+            ValType::Vec => Self::V128,
+            ValType::FuncRef => Self::FUNCREF,
+            ValType::ExternRef => Self::EXTERNREF,
+        }
+    }
+}
+
+impl TryFrom<wasmparser::ValType> for ValType {
+    type Error = ConversionError;
+
+    fn try_from(value: wasmparser::ValType) -> Result<Self, Self::Error> {
+        Ok(match value {
+            wasmparser::ValType::I32 => Self::I32,
+            wasmparser::ValType::I64 => Self::I64,
+            wasmparser::ValType::F32 => Self::F32,
+            wasmparser::ValType::F64 => Self::F64,
+            wasmparser::ValType::V128 => Self::Vec,
+            wasmparser::ValType::Ref(ty) => RefType::try_from(ty)?.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        for ty in [
+            ValType::I32,
+            ValType::I64,
+            ValType::F32,
+            ValType::F64,
+            ValType::Vec,
+            ValType::FuncRef,
+            ValType::ExternRef,
+        ] {
+            let converted = wasmparser::ValType::from(ty);
+            assert_eq!(ValType::try_from(converted), Ok(ty));
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        let nonnull = wasmparser::RefType::FUNCREF.as_non_null();
+        assert_eq!(
+            RefType::try_from(nonnull),
+            Err(ConversionError::Unsupported)
+        );
+    }
+}
@@ -0,0 +1,409 @@
+// Copyright (c) 2025 Joshua Seaton
+//
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT
+
+//! Core WebAssembly type definitions shared by wafer and other tools.
+//!
+//! This crate holds the plain data vocabulary of the binary format - opcodes,
+//! value types, limits, section IDs, and indices - without any allocation or
+//! decoding machinery, so that assemblers, code generators, and other runtimes
+//! can speak the same types as wafer without depending on it. The wafer crate
+//! re-exports everything here from `wafer::types`.
+//!
+//! The `experimental` feature gates the opcodes of in-flight proposals (see
+//! [`Opcode::is_experimental`]), and is enabled by wafer's feature of the same
+//! name. As features are unified, it may also be enabled here by another
+//! dependent while disabled in wafer, which then still rejects such opcodes.
+
+#![no_std]
+
+// Defines a fieldless enum with the given primitive representation, along with
+// a TryFrom implementation from that primitive, which fails on any value that is
+// not a discriminant.
+macro_rules! primitive_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $type:ident: $repr:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[cfg($cfg:meta)])?
+                $variant:ident = $value:expr,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr($repr)]
+        pub enum $type {
+            $(
+                $(#[doc = $doc])*
+                $(#[cfg($cfg)])?
+                $variant = $value,
+            )*
+        }
+
+        impl TryFrom<$repr> for $type {
+            type Error = $crate::InvalidValue<$repr>;
+
+            #[allow(non_upper_case_globals)]
+            fn try_from(value: $repr) -> Result<Self, Self::Error> {
+                $(
+                    $(#[cfg($cfg)])?
+                    const $variant: $repr = $type::$variant as $repr;
+                )*
+                match value {
+                    $(
+                        $(#[cfg($cfg)])?
+                        $variant => Ok(Self::$variant),
+                    )*
+                    _ => Err($crate::InvalidValue(value)),
+                }
+            }
+        }
+    };
+}
+
+// Defines a public newtype without public mutable access to the underlying
+// type, along with some convenience trait implementations like Deref and AsRef.
+macro_rules! newtype {
+    (
+        $(#[$meta:meta])*
+        pub struct $type:ident($underlying:ty);
+    ) => {
+        $(#[$meta])*
+        pub struct $type($underlying);
+
+        impl $type {
+            pub fn new(value: $underlying) -> Self {
+                Self(value)
+            }
+        }
+
+        impl ::core::ops::Deref for $type {
+            type Target = $underlying;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl AsRef<$underlying> for $type {
+            fn as_ref(&self) -> &$underlying {
+                &self.0
+            }
+        }
+    };
+}
+
+mod instr;
+#[cfg(feature = "wasmparser")]
+mod interop;
+pub use instr::*;
+#[cfg(feature = "wasmparser")]
+pub use interop::ConversionError;
+
+use core::cmp;
+
+/// The error of converting a primitive into an enum of which it is not a
+/// discriminant, holding the primitive.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InvalidValue<T>(pub T);
+
+primitive_enum! {
+    /// The type of a reference to an object in the runtime store.
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub enum RefType: u8 {
+        /// Function reference type.
+        Func = 0x70,
+        /// External reference type.
+        Extern = 0x6f,
+    }
+}
+
+primitive_enum! {
+    /// Value types classify the individual values that WebAssembly code can
+    /// compute with and the values that a variable accepts.
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub enum ValType: u8 {
+        /// 32-bit signed integer.
+        I32 = 0x7f,
+        /// 64-bit signed integer.
+        I64 = 0x7e,
+        /// 32-bit floating point number.
+        F32 = 0x7d,
+        /// 64-bit floating point number.
+        F64 = 0x7c,
+        /// 128-bit SIMD vector.
+        Vec = 0x7b,
+        /// Function reference.
+        FuncRef = RefType::Func as u8,
+        /// External reference.
+        ExternRef = RefType::Extern as u8,
+    }
+}
+
+impl From<RefType> for ValType {
+    fn from(value: RefType) -> Self {
+        match value {
+            RefType::Func => Self::FuncRef,
+            RefType::Extern => Self::ExternRef,
+        }
+    }
+}
+
+/// The size range of the resizeable storage associated with memory (# of pages)
+/// and table types (# of elements).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Limits {
+    /// Minimum size.
+    pub min: u32,
+    /// Maximum size, with None indicating that there is no upper limit.
+    pub max: Option<u32>,
+    /// Whether the storage is shared between threads (per the threads
+    /// proposal), which is only meaningful for memories.
+    pub shared: bool,
+}
+
+primitive_enum! {
+    /// Section identifier within a module.
+    ///
+    /// `PartialOrd` is implemented so that, for non-custom section IDs, an ID is
+    /// less than another precisely when the former has must appear in a module
+    /// before the latter in a module when both are present.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum SectionId: u8 {
+        /// Custom section with arbitrary data.
+        Custom = 0,
+        /// Function type declarations.
+        Type = 1,
+        /// Import declarations.
+        Import = 2,
+        /// Function type indices for module functions.
+        Function = 3,
+        /// Table declarations.
+        Table = 4,
+        /// Memory declarations.
+        Memory = 5,
+        /// Global declarations.
+        Global = 6,
+        /// Export declarations.
+        Export = 7,
+        /// Start function index.
+        Start = 8,
+        /// Element segments for table initialization.
+        Element = 9,
+        /// Function bodies.
+        Code = 10,
+        /// Data segments for memory initialization.
+        Data = 11,
+        /// Data segment count (for bulk memory operations).
+        DataCount = 12,
+    }
+}
+
+// The logical order, as documented above.
+impl PartialOrd for SectionId {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        use SectionId::{Code, Data, DataCount};
+
+        // Handle the special case where DataCount can appear before Code
+        match (self, other) {
+            // DataCount comes before Code and Data in the logical ordering
+            (DataCount, Code | Data) => Some(cmp::Ordering::Less),
+            (Code | Data, DataCount) => Some(cmp::Ordering::Greater),
+
+            // For all other cases, use the numeric ordering
+            _ => (*self as u8).partial_cmp(&(*other as u8)),
+        }
+    }
+}
+
+newtype!(
+    /// An index into the type section.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct TypeIdx(u32);
+);
+
+newtype!(
+    /// An index into the function section.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct FuncIdx(u32);
+);
+
+newtype!(
+    /// An index into the table section.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct TableIdx(u32);
+);
+
+newtype!(
+    /// An index into the memory section.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct MemIdx(u32);
+);
+
+newtype!(
+    /// An index into the global section.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct GlobalIdx(u32);
+);
+
+newtype!(
+    /// An index into the element section.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct ElemIdx(u32);
+);
+
+newtype!(
+    /// An index into the data section.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct DataIdx(u32);
+);
+
+#[cfg(feature = "experimental")]
+newtype!(
+    /// An index into the tag index space of the exception-handling and
+    /// stack-switching proposals. Tags themselves are not yet decoded.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct TagIdx(u32);
+);
+
+newtype!(
+    /// An index into a function's local variables.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct LocalIdx(u32);
+);
+
+newtype!(
+    /// An index referencing structured control instructions inside an
+    /// instruction sequence.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct LabelIdx(u32);
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitive_conversions() {
+        assert_eq!(Opcode::try_from(0x0b), Ok(Opcode::End));
+        assert_eq!(Opcode::try_from(0x06), Err(InvalidValue(0x06)));
+        assert_eq!(BulkOpcode::try_from(17), Ok(BulkOpcode::TableFill));
+        assert_eq!(BulkOpcode::try_from(18), Err(InvalidValue(18)));
+        assert_eq!(
+            VectorOpcode::try_from(255),
+            Ok(VectorOpcode::F64x2ConvertLowI32x4U)
+        );
+        assert_eq!(ValType::try_from(0x70), Ok(ValType::FuncRef));
+        assert_eq!(ValType::try_from(0x40), Err(InvalidValue(0x40)));
+        assert_eq!(RefType::try_from(0x6f), Ok(RefType::Extern));
+        assert_eq!(SectionId::try_from(12), Ok(SectionId::DataCount));
+        assert_eq!(SectionId::try_from(13), Err(InvalidValue(13)));
+
+        // Opcodes of in-flight proposals are only known when enabled.
+        assert_eq!(
+            Opcode::try_from(0xe0).is_ok(),
+            cfg!(feature = "experimental")
+        );
+    }
+
+    #[test]
+    fn section_order() {
+        assert!(SectionId::Type < SectionId::Import);
+        assert!(SectionId::Element < SectionId::DataCount);
+        assert!(SectionId::DataCount < SectionId::Code);
+        assert!(SectionId::DataCount < SectionId::Data);
+        assert!(SectionId::Code < SectionId::Data);
+    }
+
+    #[test]
+    fn opcode_metadata() {
+        assert_eq!(Opcode::I32Load8S.name(), "i32.load8_s");
+        assert_eq!(Opcode::RefIsNull.name(), "ref.is_null");
+        assert_eq!(Opcode::F64PromoteF32.name(), "f64.promote_f32");
+        assert_eq!(BulkOpcode::I64TruncSatF32U.name(), "i64.trunc_sat_f32_u");
+        assert_eq!(BulkOpcode::ElemDrop.name(), "elem.drop");
+        assert_eq!(
+            AtomicOpcode::I64AtomicRmw32CmpxchgU.name(),
+            "i64.atomic.rmw32.cmpxchg_u"
+        );
+        assert_eq!(
+            AtomicOpcode::MemoryAtomicWait64.name(),
+            "memory.atomic.wait64"
+        );
+
+        assert_eq!(Opcode::BrTable.operand_kind(), OperandKind::BrTable);
+        assert_eq!(Opcode::MemoryGrow.operand_kind(), OperandKind::ZeroBytes(1));
+        assert_eq!(Opcode::I32Add.operand_kind(), OperandKind::None);
+        assert_eq!(
+            BulkOpcode::MemoryCopy.operand_kind(),
+            OperandKind::ZeroBytes(2)
+        );
+        assert_eq!(
+            AtomicOpcode::I32AtomicLoad.operand_kind(),
+            OperandKind::MemArg
+        );
+
+        // Exactly the instructions with index immediates have index kinds.
+        for byte in 0..=u8::MAX {
+            if let Ok(op) = Opcode::try_from(byte) {
+                let has_idx = op.operand_kind() == OperandKind::Idx;
+                assert_eq!(op.index_kind().is_some(), has_idx, "{op:?}");
+            }
+        }
+        for value in 0..=u32::from(u8::MAX) {
+            if let Ok(op) = BulkOpcode::try_from(value) {
+                let has_idx = matches!(
+                    op.operand_kind(),
+                    OperandKind::Idx | OperandKind::MemoryInit
+                );
+                assert_eq!(op.index_kind().is_some(), has_idx, "{op:?}");
+            }
+        }
+        assert_eq!(Opcode::LocalTee.index_kind(), Some(IndexKind::Local));
+        assert_eq!(BulkOpcode::MemoryInit.index_kind(), Some(IndexKind::Data));
+
+        let effect = |pops, pushes| Some(StackEffect { pops, pushes });
+        assert_eq!(
+            Opcode::F32Store.stack_effect(),
+            effect(&[ValType::I32, ValType::F32], &[])
+        );
+        assert_eq!(
+            Opcode::I64LtU.stack_effect(),
+            effect(&[ValType::I64, ValType::I64], &[ValType::I32])
+        );
+        assert_eq!(
+            Opcode::I32WrapI64.stack_effect(),
+            effect(&[ValType::I64], &[ValType::I32])
+        );
+        assert_eq!(Opcode::Drop.stack_effect(), None);
+        assert_eq!(Opcode::LocalGet.stack_effect(), None);
+        assert_eq!(BulkOpcode::TableFill.stack_effect(), None);
+        assert_eq!(
+            BulkOpcode::MemoryFill.stack_effect(),
+            effect(&[ValType::I32; 3], &[])
+        );
+        assert_eq!(
+            AtomicOpcode::I64AtomicRmw8CmpxchgU.stack_effect(),
+            StackEffect {
+                pops: &[ValType::I32, ValType::I64, ValType::I64],
+                pushes: &[ValType::I64],
+            }
+        );
+    }
+}